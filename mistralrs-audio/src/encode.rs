@@ -0,0 +1,405 @@
+//! Encoding synthesized audio for delivery.
+//!
+//! WAV, raw PCM and FLAC are written natively. MP3, Opus (in Ogg) and AAC (in ADTS) are
+//! encoded by piping PCM through the `ffmpeg` binary, which must be on `$PATH`. It is a
+//! runtime dependency only: check [`ffmpeg_available`] at startup to warn early instead of
+//! failing the first request.
+
+use std::{
+    io::{Read, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{mpsc, OnceLock},
+    thread::JoinHandle,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::flac::FlacEncoder;
+
+const FFMPEG_INSTALL_HELP: &str = "\
+FFmpeg is required for mp3, opus and aac output. Install it (apt install ffmpeg, brew install \
+ffmpeg or https://ffmpeg.org/download.html), or request wav, flac or pcm.";
+
+/// Whether an `ffmpeg` binary can be run, needed for mp3, opus and aac output. The probe runs
+/// once and is cached.
+pub fn ffmpeg_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        Command::new("ffmpeg")
+            .arg("-version")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    })
+}
+
+/// Output format for synthesized audio.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioFormat {
+    /// 16-bit PCM WAV.
+    Wav,
+    /// Raw 16-bit little-endian PCM, no header.
+    Pcm,
+    Flac,
+    Mp3,
+    /// Opus in an Ogg container.
+    Opus,
+    /// AAC in an ADTS stream.
+    Aac,
+}
+
+impl AudioFormat {
+    /// MIME type of the encoded bytes.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Pcm => "audio/pcm",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::Mp3 => "audio/mpeg",
+            AudioFormat::Opus => "audio/ogg; codecs=opus",
+            AudioFormat::Aac => "audio/aac",
+        }
+    }
+
+    /// FFmpeg encoder and muxer arguments, for the formats not encoded natively.
+    fn ffmpeg_args(self) -> Option<&'static [&'static str]> {
+        match self {
+            AudioFormat::Mp3 => Some(&["-c:a", "libmp3lame", "-b:a", "128k", "-f", "mp3"]),
+            AudioFormat::Opus => Some(&["-c:a", "libopus", "-b:a", "64k", "-f", "ogg"]),
+            AudioFormat::Aac => Some(&["-c:a", "aac", "-b:a", "128k", "-f", "adts"]),
+            AudioFormat::Wav | AudioFormat::Pcm | AudioFormat::Flac => None,
+        }
+    }
+}
+
+/// Encode a complete clip of interleaved samples in [-1, 1].
+pub fn encode_audio(
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    format: AudioFormat,
+) -> Result<Vec<u8>> {
+    match format {
+        AudioFormat::Wav => {
+            let mut out = wav_header(sample_rate, channels, Some(samples.len() as u32 * 2));
+            out.extend(pcm16(samples));
+            Ok(out)
+        }
+        AudioFormat::Flac => {
+            let mut flac = FlacEncoder::new(channels);
+            let frames = samples.len() as u64 / channels.max(1) as u64;
+            let mut out = flac.header(sample_rate, frames);
+            out.extend(flac.push(samples));
+            out.extend(flac.finish());
+            Ok(out)
+        }
+        _ => {
+            let mut encoder = AudioEncoder::new(format, sample_rate, channels)?;
+            let mut out = encoder.push(samples)?;
+            out.extend(encoder.finish()?);
+            Ok(out)
+        }
+    }
+}
+
+/// Incremental encoder for streaming: push samples as they are generated and send whatever
+/// bytes come back. Concatenating every returned buffer gives a playable stream.
+///
+/// Streamed WAV and FLAC headers cannot know the final length, so they mark it as unknown;
+/// use [`encode_audio`] when the whole clip is available.
+pub struct AudioEncoder {
+    inner: Inner,
+}
+
+enum Inner {
+    Pcm,
+    Wav {
+        sample_rate: u32,
+        channels: u16,
+        started: bool,
+    },
+    Flac {
+        encoder: FlacEncoder,
+        sample_rate: u32,
+        started: bool,
+    },
+    Ffmpeg(FfmpegEncoder),
+}
+
+impl AudioEncoder {
+    /// Fails for mp3, opus and aac if `ffmpeg` cannot be started.
+    pub fn new(format: AudioFormat, sample_rate: u32, channels: u16) -> Result<Self> {
+        let inner = match format {
+            AudioFormat::Pcm => Inner::Pcm,
+            AudioFormat::Wav => Inner::Wav {
+                sample_rate,
+                channels,
+                started: false,
+            },
+            AudioFormat::Flac => Inner::Flac {
+                encoder: FlacEncoder::new(channels),
+                sample_rate,
+                started: false,
+            },
+            _ => Inner::Ffmpeg(FfmpegEncoder::spawn(format, sample_rate, channels)?),
+        };
+        Ok(Self { inner })
+    }
+
+    /// Encode interleaved samples in [-1, 1]. May return nothing while the codec buffers.
+    pub fn push(&mut self, samples: &[f32]) -> Result<Vec<u8>> {
+        match &mut self.inner {
+            Inner::Pcm => Ok(pcm16(samples)),
+            Inner::Wav {
+                sample_rate,
+                channels,
+                started,
+            } => {
+                let mut out = Vec::new();
+                if !*started {
+                    out = wav_header(*sample_rate, *channels, None);
+                    *started = true;
+                }
+                out.extend(pcm16(samples));
+                Ok(out)
+            }
+            Inner::Flac {
+                encoder,
+                sample_rate,
+                started,
+            } => {
+                let mut out = Vec::new();
+                if !*started {
+                    out = encoder.header(*sample_rate, 0);
+                    *started = true;
+                }
+                out.extend(encoder.push(samples));
+                Ok(out)
+            }
+            Inner::Ffmpeg(ffmpeg) => ffmpeg.push(samples),
+        }
+    }
+
+    /// Flush the codec and return the remaining bytes.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        match self.inner {
+            Inner::Pcm => Ok(Vec::new()),
+            Inner::Wav {
+                sample_rate,
+                channels,
+                started,
+            } => Ok(if started {
+                Vec::new()
+            } else {
+                wav_header(sample_rate, channels, Some(0))
+            }),
+            Inner::Flac {
+                mut encoder,
+                sample_rate,
+                started,
+            } => {
+                let mut out = Vec::new();
+                if !started {
+                    out = encoder.header(sample_rate, 0);
+                }
+                out.extend(encoder.finish());
+                Ok(out)
+            }
+            Inner::Ffmpeg(ffmpeg) => ffmpeg.finish(),
+        }
+    }
+}
+
+fn pcm16(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|s| ((s.clamp(-1.0, 1.0) * 32767.0) as i16).to_le_bytes())
+        .collect()
+}
+
+/// 44-byte header for 16-bit PCM. An unknown `data_len` is written as `0xFFFFFFFF`, which
+/// streaming players read as "until the end of the stream".
+fn wav_header(sample_rate: u32, channels: u16, data_len: Option<u32>) -> Vec<u8> {
+    let block_align = 2 * channels;
+    let riff_len = data_len.map_or(u32::MAX, |len| len.saturating_add(36));
+    let mut out = Vec::with_capacity(44);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&riff_len.to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    out.extend_from_slice(&channels.to_le_bytes());
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    out.extend_from_slice(&block_align.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.unwrap_or(u32::MAX).to_le_bytes());
+    out
+}
+
+/// An `ffmpeg` child reading f32 PCM on stdin. Threads drain its stdout and stderr so writes
+/// to stdin never block on a full pipe.
+struct FfmpegEncoder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    output: mpsc::Receiver<Vec<u8>>,
+    reader: JoinHandle<std::io::Result<()>>,
+    errors: JoinHandle<String>,
+}
+
+impl FfmpegEncoder {
+    fn spawn(format: AudioFormat, sample_rate: u32, channels: u16) -> Result<Self> {
+        let codec_args = format
+            .ffmpeg_args()
+            .expect("natively encoded formats do not use ffmpeg");
+        let mut child = Command::new("ffmpeg")
+            .args(["-hide_banner", "-loglevel", "error", "-f", "f32le", "-ar"])
+            .arg(sample_rate.to_string())
+            .arg("-ac")
+            .arg(channels.to_string())
+            .args(["-i", "pipe:0"])
+            .args(codec_args)
+            .args(["-flush_packets", "1", "pipe:1"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => anyhow::anyhow!(FFMPEG_INSTALL_HELP),
+                _ => anyhow::Error::new(e).context("Failed to run ffmpeg"),
+            })?;
+        let stdin = child.stdin.take();
+        let mut stdout = child.stdout.take().context("ffmpeg stdout is not piped")?;
+        let mut stderr = child.stderr.take().context("ffmpeg stderr is not piped")?;
+        let errors = std::thread::spawn(move || {
+            let mut text = String::new();
+            stderr.read_to_string(&mut text).ok();
+            text
+        });
+        let (tx, output) = mpsc::channel();
+        let reader = std::thread::spawn(move || {
+            let mut buf = vec![0u8; 16 * 1024];
+            loop {
+                let n = stdout.read(&mut buf)?;
+                if n == 0 || tx.send(buf[..n].to_vec()).is_err() {
+                    return Ok(());
+                }
+            }
+        });
+        Ok(Self {
+            child,
+            stdin,
+            output,
+            reader,
+            errors,
+        })
+    }
+
+    fn push(&mut self, samples: &[f32]) -> Result<Vec<u8>> {
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        self.stdin
+            .as_mut()
+            .expect("stdin is open until finish")
+            .write_all(&bytes)
+            .context("ffmpeg stopped accepting audio")?;
+        Ok(self.output.try_iter().flatten().collect())
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>> {
+        // Closing stdin makes ffmpeg flush and exit.
+        drop(self.stdin.take());
+        let mut out: Vec<u8> = Vec::new();
+        for chunk in self.output.iter() {
+            out.extend(chunk);
+        }
+        self.reader
+            .join()
+            .map_err(|_| anyhow::anyhow!("ffmpeg reader thread panicked"))??;
+        let status = self.child.wait()?;
+        let stderr = self.errors.join().unwrap_or_default();
+        if !status.success() {
+            bail!("ffmpeg failed ({status}): {}", stderr.trim());
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_audio, ffmpeg_available, AudioEncoder, AudioFormat};
+    use crate::AudioInput;
+
+    fn tone(frames: usize, channels: u16) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let s = (i as f32 * 0.05).sin() * 0.5;
+                (0..channels).map(move |c| if c == 0 { s } else { -s })
+            })
+            .collect()
+    }
+
+    /// Decoding our output with symphonia must give the input back, to 16-bit precision.
+    fn assert_round_trip(bytes: &[u8], expected: &[f32], sample_rate: u32, channels: u16) {
+        let decoded = AudioInput::from_bytes(bytes).unwrap();
+        assert_eq!(decoded.sample_rate, sample_rate);
+        assert_eq!(decoded.channels, channels);
+        assert_eq!(decoded.samples.len(), expected.len());
+        for (a, b) in decoded.samples.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-3, "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn flac_round_trips() {
+        // More than one block, with a short final frame.
+        let samples = tone(10_000, 2);
+        let bytes = encode_audio(&samples, 44100, 2, AudioFormat::Flac).unwrap();
+        assert_round_trip(&bytes, &samples, 44100, 2);
+    }
+
+    #[test]
+    fn streamed_flac_round_trips() {
+        let samples = tone(9_000, 1);
+        let mut encoder = AudioEncoder::new(AudioFormat::Flac, 24000, 1).unwrap();
+        let mut bytes = Vec::new();
+        for chunk in samples.chunks(1_000) {
+            bytes.extend(encoder.push(chunk).unwrap());
+        }
+        bytes.extend(encoder.finish().unwrap());
+        assert_round_trip(&bytes, &samples, 24000, 1);
+    }
+
+    #[test]
+    fn wav_round_trips() {
+        let samples = tone(500, 1);
+        let bytes = encode_audio(&samples, 16000, 1, AudioFormat::Wav).unwrap();
+        assert_round_trip(&bytes, &samples, 16000, 1);
+    }
+
+    #[test]
+    fn mp3_through_ffmpeg() {
+        if !ffmpeg_available() {
+            eprintln!("skipping: ffmpeg is not on PATH");
+            return;
+        }
+        let samples = tone(24_000, 1);
+        let whole = encode_audio(&samples, 24000, 1, AudioFormat::Mp3).unwrap();
+        let mut encoder = AudioEncoder::new(AudioFormat::Mp3, 24000, 1).unwrap();
+        let mut streamed = Vec::new();
+        for chunk in samples.chunks(4_000) {
+            streamed.extend(encoder.push(chunk).unwrap());
+        }
+        streamed.extend(encoder.finish().unwrap());
+        for bytes in [whole, streamed] {
+            // An ID3 tag or a bare MPEG frame sync.
+            assert!(
+                bytes.starts_with(b"ID3") || (bytes.len() > 1 && bytes[0] == 0xFF),
+                "not an mp3 stream"
+            );
+            assert!(bytes.len() > 1_000);
+        }
+    }
+}
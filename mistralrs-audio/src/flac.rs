@@ -0,0 +1,282 @@
+//! 16-bit FLAC encoding with fixed predictors and Rice-coded residuals.
+//!
+//! Compression is a little behind libFLAC (no LPC, a single Rice partition) but every frame
+//! is independent, so the stream can be sent as it is produced.
+
+/// Samples per channel in every frame but the last.
+pub(crate) const BLOCK_SIZE: usize = 4096;
+
+/// Largest Rice parameter before the escape code.
+const MAX_RICE_PARAM: u32 = 14;
+
+pub(crate) struct FlacEncoder {
+    channels: usize,
+    /// Interleaved samples not yet written as a frame.
+    pending: Vec<i32>,
+    frame_number: u64,
+}
+
+impl FlacEncoder {
+    pub(crate) fn new(channels: u16) -> Self {
+        Self {
+            channels: channels.max(1) as usize,
+            pending: Vec::new(),
+            frame_number: 0,
+        }
+    }
+
+    /// The `fLaC` marker and STREAMINFO block. `total_frames` is 0 when unknown.
+    pub(crate) fn header(&self, sample_rate: u32, total_frames: u64) -> Vec<u8> {
+        let mut out = b"fLaC".to_vec();
+        // Last metadata block, type 0 (STREAMINFO), 34 bytes long.
+        out.extend_from_slice(&[0x80, 0, 0, 34]);
+        let mut w = BitWriter::default();
+        let block_size = if total_frames > 0 {
+            (total_frames as usize).min(BLOCK_SIZE)
+        } else {
+            BLOCK_SIZE
+        };
+        w.write(block_size as u64, 16);
+        w.write(block_size as u64, 16);
+        // Minimum and maximum frame sizes are unknown.
+        w.write(0, 24);
+        w.write(0, 24);
+        w.write(sample_rate as u64, 20);
+        w.write(self.channels as u64 - 1, 3);
+        w.write(15, 5); // 16 bits per sample
+        w.write(total_frames, 36);
+        out.extend(w.finish());
+        // No MD5 signature.
+        out.extend_from_slice(&[0; 16]);
+        out
+    }
+
+    /// Queue interleaved samples and return any complete frames.
+    pub(crate) fn push(&mut self, samples: &[f32]) -> Vec<u8> {
+        self.pending.extend(
+            samples
+                .iter()
+                .map(|s| (s.clamp(-1.0, 1.0) * 32767.0) as i32),
+        );
+        let mut out = Vec::new();
+        let frame_len = BLOCK_SIZE * self.channels;
+        while self.pending.len() >= frame_len {
+            let block: Vec<i32> = self.pending.drain(..frame_len).collect();
+            out.extend(self.frame(&block));
+        }
+        out
+    }
+
+    /// Write whatever is left as a final, shorter frame.
+    pub(crate) fn finish(&mut self) -> Vec<u8> {
+        let whole = self.pending.len() / self.channels * self.channels;
+        if whole == 0 {
+            return Vec::new();
+        }
+        let block: Vec<i32> = self.pending.drain(..whole).collect();
+        self.frame(&block)
+    }
+
+    fn frame(&mut self, interleaved: &[i32]) -> Vec<u8> {
+        let block_size = interleaved.len() / self.channels;
+        let mut w = BitWriter::default();
+        w.write(0b11_1111_1111_1110, 14); // sync code
+        w.write(0, 1);
+        w.write(0, 1); // fixed block size stream
+        w.write(0b0111, 4); // block size - 1 follows as 16 bits
+        w.write(0, 4); // sample rate from STREAMINFO
+        w.write(self.channels as u64 - 1, 4); // independent channels
+        w.write(0b100, 3); // 16 bits per sample
+        w.write(0, 1);
+        for byte in utf8_number(self.frame_number) {
+            w.write(byte as u64, 8);
+        }
+        w.write(block_size as u64 - 1, 16);
+        let header = w.bytes_so_far();
+        w.write(crc8(&header) as u64, 8);
+
+        for channel in 0..self.channels {
+            let samples: Vec<i64> = interleaved
+                .iter()
+                .skip(channel)
+                .step_by(self.channels)
+                .map(|&s| s as i64)
+                .collect();
+            write_subframe(&mut w, &samples);
+        }
+
+        let mut frame = w.finish();
+        let crc = crc16(&frame);
+        frame.extend_from_slice(&crc.to_be_bytes());
+        self.frame_number += 1;
+        frame
+    }
+}
+
+/// A FIXED subframe using whichever predictor order 0..=4 codes smallest.
+fn write_subframe(w: &mut BitWriter, samples: &[i64]) {
+    let max_order = samples.len().saturating_sub(1).min(4);
+    let (order, residuals, param) = (0..=max_order)
+        .map(|order| {
+            let residuals = fixed_residuals(samples, order);
+            let (param, bits) = best_rice_param(&residuals);
+            (bits, order, residuals, param)
+        })
+        .min_by_key(|(bits, ..)| *bits)
+        .map(|(_, order, residuals, param)| (order, residuals, param))
+        .expect("at least one predictor order");
+
+    w.write(0, 1);
+    w.write(0b001000 | order as u64, 6);
+    w.write(0, 1); // no wasted bits
+    for &warmup in &samples[..order] {
+        w.write_signed(warmup, 16);
+    }
+    w.write(0b00, 2); // Rice coding with 4-bit parameters
+    w.write(0, 4); // one partition
+    w.write(param as u64, 4);
+    for &r in &residuals {
+        let u = zigzag(r);
+        w.write_unary(u >> param);
+        w.write(u & ((1 << param) - 1), param);
+    }
+}
+
+fn fixed_residuals(samples: &[i64], order: usize) -> Vec<i64> {
+    (order..samples.len())
+        .map(|i| {
+            let x = |back: usize| samples[i - back];
+            match order {
+                0 => x(0),
+                1 => x(0) - x(1),
+                2 => x(0) - 2 * x(1) + x(2),
+                3 => x(0) - 3 * x(1) + 3 * x(2) - x(3),
+                _ => x(0) - 4 * x(1) + 6 * x(2) - 4 * x(3) + x(4),
+            }
+        })
+        .collect()
+}
+
+/// The Rice parameter giving the fewest bits for `residuals`, and that bit count.
+fn best_rice_param(residuals: &[i64]) -> (u32, u64) {
+    (0..=MAX_RICE_PARAM)
+        .map(|k| {
+            let bits = residuals
+                .iter()
+                .map(|&r| (zigzag(r) >> k) + 1 + k as u64)
+                .sum::<u64>();
+            (k, bits)
+        })
+        .min_by_key(|&(_, bits)| bits)
+        .expect("non-empty parameter range")
+}
+
+fn zigzag(r: i64) -> u64 {
+    ((r << 1) ^ (r >> 63)) as u64
+}
+
+/// Frame numbers use the UTF-8 style variable-length coding.
+fn utf8_number(n: u64) -> Vec<u8> {
+    if n < 0x80 {
+        return vec![n as u8];
+    }
+    let mut continuation = Vec::new();
+    let mut rest = n;
+    // Each continuation byte carries 6 bits; the lead byte holds what is left.
+    loop {
+        continuation.push(0x80 | (rest & 0x3f) as u8);
+        rest >>= 6;
+        let len = continuation.len() + 1;
+        if rest < (1 << (7 - len)) {
+            let lead = (0xff00u16 >> len) as u8 | rest as u8;
+            continuation.push(lead);
+            continuation.reverse();
+            return continuation;
+        }
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    n_bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, bits: u32) {
+        for i in (0..bits).rev() {
+            self.acc = (self.acc << 1) | ((value >> i) & 1);
+            self.n_bits += 1;
+            if self.n_bits == 8 {
+                self.bytes.push(self.acc as u8);
+                self.acc = 0;
+                self.n_bits = 0;
+            }
+        }
+    }
+
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write(value as u64 & ((1 << bits) - 1), bits);
+    }
+
+    fn write_unary(&mut self, zeros: u64) {
+        for _ in 0..zeros {
+            self.write(0, 1);
+        }
+        self.write(1, 1);
+    }
+
+    /// Bytes completed so far. Only meaningful on a byte boundary.
+    fn bytes_so_far(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    /// Pad with zeros to a byte boundary and return everything written.
+    fn finish(mut self) -> Vec<u8> {
+        if self.n_bits > 0 {
+            self.write(0, 8 - self.n_bits);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::utf8_number;
+
+    #[test]
+    fn frame_numbers_are_utf8_coded() {
+        assert_eq!(utf8_number(0x7f), [0x7f]);
+        assert_eq!(utf8_number(0x80), [0xc2, 0x80]);
+        assert_eq!(utf8_number(0x800), [0xe0, 0xa0, 0x80]);
+    }
+}
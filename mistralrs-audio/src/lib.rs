@@ -4,12 +4,19 @@
 //! functionality such as reading audio data, resampling and computing
 //! mel spectrogram features.
 
+use std::ops::Range;
+
 use anyhow::Result;
 use symphonia::core::{
     audio::SampleBuffer, codecs::DecoderOptions, formats::FormatOptions, io::MediaSourceStream,
     meta::MetadataOptions, probe::Hint,
 };
 
+mod encode;
+mod flac;
+
+pub use encode::{encode_audio, ffmpeg_available, AudioEncoder, AudioFormat};
+
 /// Raw audio input consisting of PCM samples and a sample rate.
 #[derive(Clone, Debug, PartialEq)]
 pub struct AudioInput {
@@ -104,6 +111,92 @@ impl AudioInput {
         mono
     }
 
+    /// Duration in seconds.
+    pub fn duration_secs(&self) -> f32 {
+        let frames = self.samples.len() / self.channels.max(1) as usize;
+        frames as f32 / self.sample_rate as f32
+    }
+
+    /// Split into consecutive frame ranges of at most `max_secs` each. Every cut is placed
+    /// at the quietest ~100 ms window within the last `search_secs` of the chunk, so long
+    /// recordings are not split mid-word when there is a pause nearby.
+    pub fn chunk_ranges(&self, max_secs: f32, search_secs: f32) -> Vec<Range<usize>> {
+        let mono = self.to_mono();
+        let total = mono.len();
+        let max_frames = ((max_secs * self.sample_rate as f32) as usize).max(1);
+        let search = ((search_secs * self.sample_rate as f32) as usize).min(max_frames);
+        let window = (self.sample_rate as usize / 10).max(1);
+        let hop = (window / 2).max(1);
+
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        while total - start > max_frames {
+            let hard_end = start + max_frames;
+            let mut cut = hard_end;
+            let mut quietest = f32::INFINITY;
+            let mut pos = hard_end - search;
+            while pos + window <= hard_end {
+                let energy: f32 = mono[pos..pos + window].iter().map(|s| s * s).sum();
+                if energy < quietest {
+                    quietest = energy;
+                    cut = pos + window / 2;
+                }
+                pos += hop;
+            }
+            if cut <= start {
+                cut = hard_end;
+            }
+            ranges.push(start..cut);
+            start = cut;
+        }
+        ranges.push(start..total);
+        ranges
+    }
+
+    /// Copy of the frames in `range`, keeping every channel.
+    pub fn slice_frames(&self, range: Range<usize>) -> Self {
+        let channels = self.channels.max(1) as usize;
+        Self {
+            samples: self.samples[range.start * channels..range.end * channels].to_vec(),
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+        }
+    }
+
+    /// Resample to `sample_rate` by linear interpolation, keeping every channel. Good enough
+    /// for speech; no anti-aliasing filter is applied when downsampling.
+    pub fn resample(&self, sample_rate: u32) -> Self {
+        if sample_rate == self.sample_rate || self.samples.is_empty() {
+            return Self {
+                samples: self.samples.clone(),
+                sample_rate,
+                channels: self.channels,
+            };
+        }
+        let channels = self.channels.max(1) as usize;
+        let frames = self.samples.len() / channels;
+        let out_frames =
+            ((frames as u64 * sample_rate as u64) / self.sample_rate as u64).max(1) as usize;
+        let step = self.sample_rate as f64 / sample_rate as f64;
+        let mut samples = Vec::with_capacity(out_frames * channels);
+        for i in 0..out_frames {
+            let pos = i as f64 * step;
+            let left = (pos as usize).min(frames - 1);
+            let right = (left + 1).min(frames - 1);
+            let t = (pos - left as f64) as f32;
+            for c in 0..channels {
+                let a = self.samples[left * channels + c];
+                let b = self.samples[right * channels + c];
+                samples.push(a + (b - a) * t);
+            }
+        }
+        Self {
+            samples,
+            sample_rate,
+            channels: self.channels,
+        }
+    }
+
     /// Normalize audio to prevent clipping
     pub fn normalize(&mut self) -> &mut Self {
         let max_amplitude = self.samples.iter().map(|s| s.abs()).fold(0.0f32, f32::max);
@@ -116,7 +209,9 @@ impl AudioInput {
         self
     }
 
-    /// Apply fade in/out to reduce audio artifacts
+    /// Apply fade in/out to reduce audio artifacts. Both are linear, and the fade-out is the
+    /// complement of a fade-in of the same length, so overlap-adding a faded-out tail and a
+    /// faded-in head crossfades at constant gain.
     pub fn apply_fade(&mut self, fade_in_samples: usize, fade_out_samples: usize) -> &mut Self {
         let len = self.samples.len();
         // Fade in
@@ -126,7 +221,7 @@ impl AudioInput {
         }
         // Fade out
         for i in 0..fade_out_samples.min(len) {
-            let factor = (fade_out_samples - i) as f32 / fade_out_samples as f32;
+            let factor = (i + 1) as f32 / fade_out_samples as f32;
             self.samples[len - 1 - i] *= factor;
         }
         self
@@ -222,6 +317,20 @@ mod tests {
         assert!((max - 1.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_apply_fade() {
+        let mut input = AudioInput {
+            samples: vec![1.0; 8],
+            sample_rate: 16000,
+            channels: 1,
+        };
+        input.apply_fade(4, 4);
+        assert_eq!(
+            input.samples,
+            vec![0.0, 0.25, 0.5, 0.75, 1.0, 0.75, 0.5, 0.25]
+        );
+    }
+
     #[test]
     fn test_remove_dc_offset() {
         let mut input = AudioInput {
@@ -234,4 +343,59 @@ mod tests {
             assert!((s - 0.0).abs() < 1e-6);
         }
     }
+
+    #[test]
+    fn chunk_ranges_cut_at_silence() {
+        // 10 s of tone at 100 Hz with a silent gap at 7.0-7.5 s.
+        let sample_rate = 100;
+        let samples: Vec<f32> = (0..1000)
+            .map(|i| if (700..750).contains(&i) { 0.0 } else { 0.5 })
+            .collect();
+        let input = AudioInput {
+            samples,
+            sample_rate,
+            channels: 1,
+        };
+        let ranges = input.chunk_ranges(8.0, 2.0);
+        assert_eq!(ranges.len(), 2);
+        assert!((700..750).contains(&ranges[0].end));
+        assert_eq!(ranges[1].start, ranges[0].end);
+        assert_eq!(ranges[1].end, 1000);
+
+        let first = input.slice_frames(ranges[0].clone());
+        assert_eq!(first.samples.len(), ranges[0].len());
+    }
+
+    #[test]
+    fn resample_interpolates() {
+        let input = AudioInput {
+            samples: vec![0.0, 1.0, 0.0, -1.0],
+            sample_rate: 8000,
+            channels: 1,
+        };
+        let up = input.resample(16000);
+        assert_eq!(up.sample_rate, 16000);
+        assert_eq!(up.samples, vec![0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -1.0]);
+
+        let down = up.resample(8000);
+        assert_eq!(down.samples, input.samples);
+
+        let stereo = AudioInput {
+            samples: vec![0.0, 1.0, 1.0, 0.0],
+            sample_rate: 1,
+            channels: 2,
+        };
+        assert_eq!(stereo.resample(2).samples.len(), 8);
+    }
+
+    #[test]
+    fn chunk_ranges_short_input() {
+        let input = AudioInput {
+            samples: vec![0.1; 320],
+            sample_rate: 16000,
+            channels: 2,
+        };
+        assert_eq!(input.chunk_ranges(30.0, 5.0), vec![0..160]);
+        assert!((input.duration_secs() - 0.01).abs() < 1e-6);
+    }
 }
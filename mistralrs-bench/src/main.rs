@@ -4,9 +4,10 @@ use cli_table::{format::Justify, print_stdout, Cell, CellStruct, Style, Table};
 use mistralrs_core::{
     get_auto_device_map_params, get_model_dtype, initialize_logging, paged_attn_supported,
     parse_isq_value, Constraint, DefaultSchedulerMethod, DeviceLayerMapMetadata, DeviceMapMetadata,
-    DeviceMapSetting, DrySamplingParams, Loader, LoaderBuilder, MemoryGpuConfig, MistralRs,
-    MistralRsBuilder, ModelSelected, NormalRequest, PagedAttentionConfig, PagedCacheType, Request,
-    RequestMessage, Response, SamplingParams, SchedulerConfig, TokenSource, Usage,
+    DeviceMapSetting, DrySamplingParams, FimMode, Loader, LoaderBuilder, MemoryGpuConfig,
+    MistralRs, MistralRsBuilder, ModelSelected, NormalRequest, PagedAttentionConfig,
+    PagedCacheType, Request, RequestMessage, RequestPriority, Response, SamplingParams,
+    SchedulerConfig, TokenSource, Usage,
 };
 use std::fmt::Display;
 use std::sync::Arc;
@@ -67,6 +68,7 @@ async fn run_bench(
         logits_bias: None,
         n_choices: 1,
         dry_params: Some(DrySamplingParams::default()),
+        seed: None,
     };
     let sender = mistralrs.get_sender(None).unwrap();
     let (tx, mut rx) = channel(10_000);
@@ -97,6 +99,13 @@ async fn run_bench(
         truncate_sequence: false,
         session_id: None,
         files: None,
+        input_files: None,
+        priority: RequestPriority::Normal,
+        timeout: None,
+        trace_context: None,
+        user: None,
+        prediction: None,
+        agentic_permit: None,
     }));
 
     let mut usages = Vec::new();
@@ -135,6 +144,7 @@ async fn run_bench(
                             Response::CompletionChunk(_) => unreachable!(),
                             Response::ImageGeneration(_) => unreachable!(),
                             Response::Speech { .. } => unreachable!(),
+                            Response::SpeechChunk { .. } => unreachable!(),
                             Response::Raw { .. } => unreachable!(),
                             Response::Embeddings { .. } => unreachable!(),
                             Response::AgenticToolCallProgress { .. } => unreachable!(),
@@ -259,6 +269,7 @@ async fn warmup_run(mistralrs: Arc<MistralRs>) {
             text: "Hello!".to_string(),
             echo_prompt: false,
             best_of: None,
+            fim_mode: FimMode::Psm,
         },
         sampling_params: sampling_params.clone(),
         response: tx,
@@ -283,6 +294,13 @@ async fn warmup_run(mistralrs: Arc<MistralRs>) {
         truncate_sequence: false,
         session_id: None,
         files: None,
+        input_files: None,
+        priority: RequestPriority::Normal,
+        timeout: None,
+        trace_context: None,
+        user: None,
+        prediction: None,
+        agentic_permit: None,
     }));
 
     if sender.send(req.clone()).await.is_err() {
@@ -578,6 +596,7 @@ async fn main() -> anyhow::Result<()> {
                     text: "Rust".to_string(),
                     echo_prompt: false,
                     best_of: None,
+                    fim_mode: FimMode::Psm,
                 },
                 args.n_gen - 1,
                 *concurrency,
@@ -1,5 +1,7 @@
 //! Server configuration options
 
+use std::path::PathBuf;
+
 use clap::Args;
 use serde::Deserialize;
 
@@ -32,6 +34,101 @@ pub struct ServerOptions {
     #[arg(long)]
     #[serde(default)]
     pub tool_dispatch_url: Option<String>,
+
+    /// JSON file of API keys. When set, every route except `/` and `/health` requires
+    /// `Authorization: Bearer <key>`, with per-key scopes, model allow-lists and quotas.
+    #[arg(long)]
+    #[serde(default)]
+    pub api_keys_file: Option<PathBuf>,
+
+    /// Also serve the Ollama-compatible API (`/api/chat`, `/api/generate`, `/api/tags`, ...).
+    #[arg(long)]
+    #[serde(default)]
+    pub ollama_api: bool,
+
+    /// Store Responses API objects (`store: true`) in this directory instead of memory, so
+    /// `previous_response_id` chains survive restarts.
+    #[arg(long)]
+    #[serde(default)]
+    pub responses_dir: Option<PathBuf>,
+
+    /// Seconds a stored response is kept after it was last written. Kept forever if unset.
+    #[arg(long, requires = "responses_dir")]
+    #[serde(default)]
+    pub responses_ttl: Option<u64>,
+
+    /// Maximum number of stored responses. The least recently written are removed first.
+    #[arg(long, requires = "responses_dir")]
+    #[serde(default)]
+    pub responses_max_entries: Option<usize>,
+
+    /// Persist agentic sessions (`/v1/sessions`) in this directory so they survive restarts and
+    /// model reloads.
+    #[arg(long)]
+    #[serde(default)]
+    pub sessions_dir: Option<PathBuf>,
+
+    /// Seconds a stored session is kept after it was last saved. Kept forever if unset.
+    #[arg(long, requires = "sessions_dir")]
+    #[serde(default)]
+    pub sessions_retention: Option<u64>,
+
+    /// Reject new requests with 429 once this many sequences are waiting for a model.
+    #[arg(long)]
+    #[serde(default)]
+    pub max_waiting_sequences: Option<usize>,
+
+    /// Reject new requests with 429 once this many prompt tokens are waiting for a model.
+    #[arg(long)]
+    #[serde(default)]
+    pub max_queued_prompt_tokens: Option<usize>,
+
+    /// Reject new agentic requests with 429 once this many are admitted and unfinished for a model.
+    #[arg(long)]
+    #[serde(default)]
+    pub max_agentic_loops: Option<usize>,
+
+    /// Export request traces to this OpenTelemetry collector over OTLP/HTTP, e.g.
+    /// `http://localhost:4318`. Incoming `traceparent` headers are honored.
+    #[arg(long)]
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+
+    /// `service.name` reported with exported traces. Defaults to `mistralrs`.
+    #[arg(long, requires = "otlp_endpoint")]
+    #[serde(default)]
+    pub otlp_service_name: Option<String>,
+
+    /// Append a JSONL audit record of every request, completion, agent tool call and approval
+    /// decision to this file.
+    #[arg(long)]
+    #[serde(default)]
+    pub audit_log: Option<PathBuf>,
+
+    /// Rotate the audit log once it reaches this many bytes. Never rotated if unset.
+    #[arg(long, requires = "audit_log")]
+    #[serde(default)]
+    pub audit_log_max_bytes: Option<u64>,
+
+    /// Rotated audit log files to keep. Defaults to 5.
+    #[arg(long, requires = "audit_log")]
+    #[serde(default)]
+    pub audit_log_max_files: Option<usize>,
+
+    /// Leave message contents and prompts out of audit records.
+    #[arg(long, requires = "audit_log")]
+    #[serde(default)]
+    pub audit_redact_contents: bool,
+
+    /// Leave tool call arguments out of audit records.
+    #[arg(long, requires = "audit_log")]
+    #[serde(default)]
+    pub audit_redact_tool_arguments: bool,
+
+    /// Replace matches of this regex in audit records, e.g. email addresses. Repeatable.
+    #[arg(long = "audit-redact-pattern", requires = "audit_log")]
+    #[serde(default)]
+    pub audit_redact_patterns: Vec<String>,
 }
 
 impl Default for ServerOptions {
@@ -42,6 +139,24 @@ impl Default for ServerOptions {
             no_ui: false,
             max_tool_rounds: None,
             tool_dispatch_url: None,
+            api_keys_file: None,
+            ollama_api: false,
+            responses_dir: None,
+            responses_ttl: None,
+            responses_max_entries: None,
+            sessions_dir: None,
+            sessions_retention: None,
+            max_waiting_sequences: None,
+            max_queued_prompt_tokens: None,
+            max_agentic_loops: None,
+            otlp_endpoint: None,
+            otlp_service_name: None,
+            audit_log: None,
+            audit_log_max_bytes: None,
+            audit_log_max_files: None,
+            audit_redact_contents: false,
+            audit_redact_tool_arguments: false,
+            audit_redact_patterns: Vec::new(),
         }
     }
 }
@@ -4,7 +4,7 @@ use anyhow::Result;
 use comfy_table::{presets::UTF8_FULL, Cell, Color, ContentArrangement, Table};
 use mistralrs_core::{
     initialize_logging, Constraint, DrySamplingParams, NormalRequest, Request, RequestMessage,
-    Response, SamplingParams, Usage,
+    RequestPriority, Response, SamplingParams, Usage,
 };
 use mistralrs_server_core::mistralrs_for_server_builder::MistralRsForServerBuilder;
 use std::sync::Arc;
@@ -241,6 +241,7 @@ async fn run_single_bench(
         logits_bias: None,
         n_choices: 1,
         dry_params: Some(DrySamplingParams::default()),
+        seed: None,
     };
 
     let sender = mistralrs.get_sender(None).unwrap();
@@ -275,6 +276,13 @@ async fn run_single_bench(
         model_id: None,
         truncate_sequence: false,
         files: None,
+        input_files: None,
+        priority: RequestPriority::Normal,
+        timeout: None,
+        trace_context: None,
+        user: None,
+        prediction: None,
+        agentic_permit: None,
     }));
 
     sender.send(req).await?;
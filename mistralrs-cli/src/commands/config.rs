@@ -5,6 +5,7 @@ use tracing::info;
 
 use mistralrs_core::initialize_logging;
 use mistralrs_server_core::{
+    auth::ApiKeyAuth,
     mistralrs_for_server_builder::{MistralRsForServerBuilder, ModelConfig},
     mistralrs_server_router_builder::MistralRsServerRouterBuilder,
 };
@@ -14,8 +15,9 @@ use crate::commands::run::interactive_mode;
 #[cfg(feature = "code-execution")]
 use crate::commands::serve::build_code_exec_config;
 use crate::commands::serve::{
-    apply_agent_mode, convert_to_model_selected, extract_sandbox_settings, load_mcp_config,
-    log_agent_runtime, validate_agent_options,
+    admission_limits, apply_agent_mode, build_audit_log, build_response_cache,
+    build_session_storage, convert_to_model_selected, extract_sandbox_settings, init_tracing,
+    load_mcp_config, log_agent_runtime, validate_agent_options,
 };
 use crate::config::{load_cli_config, CliConfig};
 use crate::ui::build_ui_router;
@@ -47,6 +49,7 @@ async fn run_serve_config(cfg: crate::config::ServeConfig) -> Result<()> {
     apply_agent_mode(&mut runtime);
     validate_agent_options(&runtime)?;
     log_agent_runtime(&runtime, server.max_tool_rounds);
+    init_tracing(&server)?;
 
     let (
         paged_attn,
@@ -114,13 +117,29 @@ async fn run_serve_config(cfg: crate::config::ServeConfig) -> Result<()> {
     #[cfg(not(feature = "code-execution"))]
     let _ = sandbox_policy;
 
+    builder = builder.with_session_storage_optional(build_session_storage(&server)?);
+    builder = builder.with_admission_limits(admission_limits(&server));
+    builder = builder.with_audit_log_optional(build_audit_log(&server)?);
+
     let mistralrs = builder.build().await?;
     let mistralrs_for_ui = mistralrs.clone();
 
+    let api_key_auth = server
+        .api_keys_file
+        .as_deref()
+        .map(ApiKeyAuth::from_file)
+        .transpose()?;
+    if let Some(path) = &server.api_keys_file {
+        info!("API key authentication enabled ({})", path.display());
+    }
+
     let mut app = MistralRsServerRouterBuilder::new()
         .with_mistralrs(mistralrs)
         .with_max_tool_rounds_optional(server.max_tool_rounds)
         .with_tool_dispatch_url_optional(server.tool_dispatch_url.clone())
+        .with_api_key_auth_optional(api_key_auth)
+        .with_ollama_routes(server.ollama_api)
+        .with_response_cache_optional(build_response_cache(&server)?)
         .build()
         .await?;
 
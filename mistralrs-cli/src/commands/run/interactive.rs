@@ -8,8 +8,8 @@ use indexmap::IndexMap;
 use mistralrs_core::{
     speech_utils, AgentPermission, AgentToolKind, Constraint, DiffusionGenerationParams,
     DrySamplingParams, ImageGenerationResponseFormat, MessageContent, MistralRs, ModelCategory,
-    NormalRequest, Request, RequestMessage, Response, ResponseOk, SamplingParams, Usage,
-    WebSearchOptions, TERMINATE_ALL_NEXT_STEP,
+    NormalRequest, Request, RequestMessage, RequestPriority, Response, ResponseOk, SamplingParams,
+    Usage, WebSearchOptions, TERMINATE_ALL_NEXT_STEP,
 };
 use regex::Regex;
 use rustyline::{error::ReadlineError, history::History, DefaultEditor, Editor, Helper};
@@ -224,6 +224,13 @@ async fn oneshot_text(
         model_id: None,
         truncate_sequence: false,
         files: None,
+        input_files: None,
+        priority: RequestPriority::Normal,
+        timeout: None,
+        trace_context: None,
+        user: None,
+        prediction: None,
+        agentic_permit: None,
     }));
     sender.send(req).await.unwrap();
     let start_ttft = Instant::now();
@@ -396,6 +403,13 @@ async fn oneshot_multimodal(
         model_id: None,
         truncate_sequence: false,
         files: None,
+        input_files: None,
+        priority: RequestPriority::Normal,
+        timeout: None,
+        trace_context: None,
+        user: None,
+        prediction: None,
+        agentic_permit: None,
     }));
     sender.send(req).await.unwrap();
     let start_ttft = Instant::now();
@@ -595,6 +609,7 @@ fn interactive_fallback_sample_parameters() -> SamplingParams {
         logits_bias: None,
         n_choices: 1,
         dry_params: Some(DrySamplingParams::default()),
+        seed: None,
     }
 }
 
@@ -802,6 +817,13 @@ async fn text_interactive_mode(
             model_id: None,
             truncate_sequence: false,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
         sender.send(req).await.unwrap();
         let start_ttft = Instant::now();
@@ -1192,6 +1214,7 @@ async fn stream_assistant_response(
             Response::CompletionChunk(_) => unreachable!(),
             Response::ImageGeneration(_) => unreachable!(),
             Response::Speech { .. } => unreachable!(),
+            Response::SpeechChunk { .. } => unreachable!(),
             Response::Raw { .. } => unreachable!(),
             Response::Embeddings { .. } => unreachable!(),
         }
@@ -1453,6 +1476,13 @@ async fn multimodal_interactive_mode(
             model_id: None,
             truncate_sequence: false,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
         sender.send(req).await.unwrap();
         let start_ttft = Instant::now();
@@ -1612,6 +1642,13 @@ async fn diffusion_interactive_mode(
             model_id: None,
             truncate_sequence: false,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
 
         let start = Instant::now();
@@ -1694,6 +1731,7 @@ async fn speech_interactive_mode(
             id: 0,
             messages: RequestMessage::SpeechGeneration {
                 prompt: prompt.to_string(),
+                reference: None,
             },
             sampling_params: SamplingParams::deterministic(),
             response: tx,
@@ -1724,6 +1762,13 @@ async fn speech_interactive_mode(
             model_id: None,
             truncate_sequence: false,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
 
         let start = Instant::now();
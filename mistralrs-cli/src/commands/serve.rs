@@ -2,14 +2,20 @@
 
 use anyhow::{Context, Result};
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::info;
 
 use mistralrs_core::{
-    initialize_logging, DiffusionLoaderType, McpClientConfig, ModelSelected, PagedCacheType,
-    SpeechLoaderType,
+    init_otlp_exporter, initialize_logging, AuditLog, AuditLogConfig, AuditRedaction,
+    DiffusionLoaderType, FileSessionStorage, McpClientConfig, ModelSelected, OtlpExporterConfig,
+    PagedCacheType, SessionStorage, SpeechLoaderType,
 };
 use mistralrs_server_core::{
-    approvals::ApprovalBroker, mistralrs_for_server_builder::MistralRsForServerBuilder,
+    approvals::ApprovalBroker,
+    auth::ApiKeyAuth,
+    cached_responses::{FileResponseCache, ResponseCache},
+    mistralrs_for_server_builder::MistralRsForServerBuilder,
     mistralrs_server_router_builder::MistralRsServerRouterBuilder,
 };
 
@@ -36,6 +42,7 @@ pub async fn run_server(
     apply_agent_mode(&mut runtime);
     validate_agent_options(&runtime)?;
     log_agent_runtime(&runtime, server.max_tool_rounds);
+    init_tracing(&server)?;
 
     // Convert our clean args to ModelSelected for the existing loader infrastructure
     let matformer = runtime.matformer_selection();
@@ -115,14 +122,30 @@ pub async fn run_server(
     #[cfg(not(feature = "code-execution"))]
     let _ = sandbox_policy;
 
+    builder = builder.with_session_storage_optional(build_session_storage(&server)?);
+    builder = builder.with_admission_limits(admission_limits(&server));
+    builder = builder.with_audit_log_optional(build_audit_log(&server)?);
+
     let mistralrs = builder.build().await?;
     let mistralrs_for_ui = mistralrs.clone();
 
     // Build and run the server
+    let api_key_auth = server
+        .api_keys_file
+        .as_deref()
+        .map(ApiKeyAuth::from_file)
+        .transpose()?;
+    if let Some(path) = &server.api_keys_file {
+        info!("API key authentication enabled ({})", path.display());
+    }
+
     let mut app = MistralRsServerRouterBuilder::new()
         .with_mistralrs(mistralrs)
         .with_max_tool_rounds_optional(server.max_tool_rounds)
         .with_tool_dispatch_url_optional(server.tool_dispatch_url.clone())
+        .with_api_key_auth_optional(api_key_auth)
+        .with_ollama_routes(server.ollama_api)
+        .with_response_cache_optional(build_response_cache(&server)?)
         .with_agent_permission(runtime.code_exec_permission.into())
         .with_approval_broker(approval_broker.clone())
         .build()
@@ -708,6 +731,81 @@ pub(crate) fn load_mcp_config(path: Option<&Path>) -> Result<Option<McpClientCon
     Ok(Some(config))
 }
 
+/// Open the on-disk Responses API cache from `--responses-dir`. Returns `None` to keep the
+/// default in-memory cache.
+pub(crate) fn build_response_cache(
+    server: &ServerOptions,
+) -> Result<Option<Arc<dyn ResponseCache>>> {
+    let Some(dir) = &server.responses_dir else {
+        return Ok(None);
+    };
+    let cache = FileResponseCache::new(
+        dir,
+        server.responses_ttl.map(Duration::from_secs),
+        server.responses_max_entries,
+    )?;
+    info!("Storing Responses API objects in {}", dir.display());
+    Ok(Some(Arc::new(cache)))
+}
+
+/// Open the on-disk agentic session store from `--sessions-dir`. Returns `None` to keep sessions
+/// in memory only.
+pub(crate) fn build_session_storage(
+    server: &ServerOptions,
+) -> Result<Option<Arc<dyn SessionStorage>>> {
+    let Some(dir) = &server.sessions_dir else {
+        return Ok(None);
+    };
+    let storage = FileSessionStorage::new(dir, server.sessions_retention.map(Duration::from_secs))?;
+    info!("Storing agentic sessions in {}", dir.display());
+    Ok(Some(Arc::new(storage)))
+}
+
+/// Open the audit log at `--audit-log`, if set.
+pub(crate) fn build_audit_log(server: &ServerOptions) -> Result<Option<Arc<AuditLog>>> {
+    let Some(path) = &server.audit_log else {
+        return Ok(None);
+    };
+    let mut config = AuditLogConfig::new(path);
+    config.max_file_bytes = server.audit_log_max_bytes;
+    if let Some(max_files) = server.audit_log_max_files {
+        config.max_files = max_files;
+    }
+    config.redaction = AuditRedaction {
+        message_contents: server.audit_redact_contents,
+        tool_arguments: server.audit_redact_tool_arguments,
+        patterns: server.audit_redact_patterns.clone(),
+    };
+    let audit_log = AuditLog::open(config)
+        .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+    info!("Writing audit log to {}", path.display());
+    Ok(Some(Arc::new(audit_log)))
+}
+
+/// Start exporting request traces to `--otlp-endpoint`, if set.
+pub(crate) fn init_tracing(server: &ServerOptions) -> Result<()> {
+    let Some(endpoint) = &server.otlp_endpoint else {
+        return Ok(());
+    };
+    let mut config = OtlpExporterConfig::new(endpoint.clone());
+    if let Some(service_name) = &server.otlp_service_name {
+        config.service_name = service_name.clone();
+    }
+    init_otlp_exporter(config)?;
+    info!("Exporting traces to {endpoint}");
+    Ok(())
+}
+
+/// Per-model queue bounds from `--max-waiting-sequences`, `--max-queued-prompt-tokens` and
+/// `--max-agentic-loops`.
+pub(crate) fn admission_limits(server: &ServerOptions) -> AdmissionLimits {
+    AdmissionLimits {
+        max_waiting_sequences: server.max_waiting_sequences,
+        max_queued_prompt_tokens: server.max_queued_prompt_tokens,
+        max_agentic_loops: server.max_agentic_loops,
+    }
+}
+
 /// Build a `CodeExecutionConfig` from runtime options. Returns `None` when code execution is off.
 #[cfg(feature = "code-execution")]
 pub(crate) fn build_code_exec_config(
@@ -487,7 +487,12 @@ impl CodeExecutionManager {
                         let session_arc = ctx.session_handle(&sessions, &session_id).await?;
 
                         let mut session = session_arc.lock().await;
-                        let result = session.execute_with_outputs(&code, &outputs).await;
+                        if let Err(e) = session.stage_inputs(&tc.input_files) {
+                            tracing::warn!("code execution: {e:#}");
+                        }
+                        let result = session
+                            .execute_with_outputs(&code, &outputs, &tc.cancellation)
+                            .await;
                         let files: Vec<ToolFile> =
                             result.files.iter().map(execute_file_to_tool_file).collect();
 
@@ -33,6 +33,23 @@ impl CodeExecResult {
         }
     }
 
+    /// The request was abandoned mid-execution.
+    pub fn canceled(interrupted: bool, work_dir: &str) -> Self {
+        let text = serde_json::json!({
+            "status": "canceled",
+            "working_directory": work_dir,
+            "interrupted": interrupted,
+            "killed": !interrupted,
+        })
+        .to_string();
+        Self {
+            text,
+            images: vec![],
+            video_frames: vec![],
+            files: vec![],
+        }
+    }
+
     pub fn error(msg: &str, work_dir: &str) -> Self {
         let text = serde_json::json!({
             "status": "error",
@@ -4,6 +4,8 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use anyhow::Context;
+use base64::Engine;
+use mistralrs_mcp::{ToolCancellation, ToolFile};
 use mistralrs_sandbox::{Sandbox, SandboxPolicy};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
 use tokio::process::{Child, ChildStdin, ChildStdout, Command};
@@ -195,10 +197,42 @@ impl PythonSession {
         self.work_dir.display().to_string()
     }
 
+    /// Write request attachments into the working directory. Existing files are left alone so
+    /// edits made by earlier rounds survive. Returns the names that were written.
+    pub fn stage_inputs(&self, files: &[ToolFile]) -> anyhow::Result<Vec<String>> {
+        let mut staged = Vec::new();
+        for f in files {
+            if f.is_error() {
+                continue;
+            }
+            // Only the final path component, so an attachment cannot escape the workdir.
+            let Some(name) = Path::new(&f.name).file_name() else {
+                continue;
+            };
+            let path = self.work_dir.join(name);
+            if path.exists() {
+                continue;
+            }
+            let bytes = match (&f.text, &f.data_base64) {
+                (Some(text), _) => text.as_bytes().to_vec(),
+                (None, Some(b64)) => base64::engine::general_purpose::STANDARD
+                    .decode(b64)
+                    .with_context(|| format!("attachment '{}' is not valid base64", f.name))?,
+                (None, None) => continue,
+            };
+            std::fs::write(&path, bytes)
+                .with_context(|| format!("failed to stage attachment '{}'", f.name))?;
+            staged.push(name.to_string_lossy().into_owned());
+        }
+        Ok(staged)
+    }
+
+    /// Run `code`, interrupting it on timeout or when `cancellation` fires.
     pub async fn execute_with_outputs(
         &mut self,
         code: &str,
         outputs: &[ExecuteOutputSpec],
+        cancellation: &ToolCancellation,
     ) -> CodeExecResult {
         self.last_active = Instant::now();
         if !self.alive {
@@ -222,7 +256,14 @@ impl PythonSession {
             );
         }
 
-        match tokio::time::timeout(self.timeout, self.read_response::<ExecuteResponse>()).await {
+        let timeout = self.timeout;
+        let outcome = tokio::select! {
+            outcome = tokio::time::timeout(timeout, self.read_response::<ExecuteResponse>()) => {
+                outcome.map_err(|_| false)
+            }
+            () = cancellation.canceled() => Err(true),
+        };
+        match outcome {
             Ok(Ok(response)) => CodeExecResult::from_response(response, &self.work_dir_str()),
             Ok(Err(e)) => {
                 self.alive = false;
@@ -231,15 +272,23 @@ impl PythonSession {
                     &self.work_dir_str(),
                 )
             }
-            Err(_) => {
-                // Timeout: try SIGINT first.
+            Err(canceled) => {
+                // Timeout or cancellation: try SIGINT first.
                 let interrupted = self.try_interrupt().await;
                 if !interrupted {
                     // SIGKILL as last resort.
                     let _ = self.child.kill().await;
                     self.alive = false;
                 }
-                CodeExecResult::timeout(self.timeout.as_secs(), interrupted, &self.work_dir_str())
+                if canceled {
+                    CodeExecResult::canceled(interrupted, &self.work_dir_str())
+                } else {
+                    CodeExecResult::timeout(
+                        self.timeout.as_secs(),
+                        interrupted,
+                        &self.work_dir_str(),
+                    )
+                }
             }
         }
     }
@@ -54,6 +54,9 @@ fn exec_json_with_permission(
         code_execution_approval_notifier: None,
         agent_permission: None,
         agent_approval_notifier: None,
+        input_files: Vec::new(),
+        cancellation: Default::default(),
+        traceparent: None,
     };
 
     let result = match &exec.callback {
@@ -87,6 +90,9 @@ fn reset_json(
         code_execution_approval_notifier: None,
         agent_permission: None,
         agent_approval_notifier: None,
+        input_files: Vec::new(),
+        cancellation: Default::default(),
+        traceparent: None,
     };
 
     match &reset.callback {
@@ -0,0 +1,511 @@
+//! Structured audit log of engine activity.
+//!
+//! Every line of the log is one JSON object with a `timestamp`, an `event` (`request`,
+//! `completion`, `tool_call` or `approval`) and the request it belongs to: `request_id`, `model`,
+//! `session_id` and `user`. Message contents, tool arguments and anything matching a configured
+//! pattern can be redacted before a record is written, and the file is rotated by size.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use either::Either;
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    request::NormalRequest,
+    sequence::{Sequence, SequenceState},
+    MessageContent, RequestMessage,
+};
+
+const REDACTED: &str = "[redacted]";
+
+/// Message fields kept when message contents are redacted.
+const MESSAGE_METADATA_KEYS: &[&str] = &["role", "name", "tool_call_id"];
+
+/// What to strip from records before they are written.
+#[derive(Clone, Debug, Default)]
+pub struct AuditRedaction {
+    /// Replace every message field other than `role`, `name` and `tool_call_id`, and the prompt
+    /// of non-chat requests. Tool calls made in earlier turns count as message contents.
+    pub message_contents: bool,
+    /// Replace the arguments of tool calls and approval requests.
+    pub tool_arguments: bool,
+    /// Regular expressions whose matches are replaced in every string of a record, e.g. email
+    /// addresses or card numbers.
+    pub patterns: Vec<String>,
+}
+
+/// Where the audit log is written and what it keeps.
+#[derive(Clone, Debug)]
+pub struct AuditLogConfig {
+    /// File records are appended to. Rotated files get a numeric suffix, `.1` being the newest.
+    pub path: PathBuf,
+    /// Rotate once a record would take the file past this many bytes. Never rotated when `None`.
+    pub max_file_bytes: Option<u64>,
+    /// Rotated files to keep; older ones are deleted.
+    pub max_files: usize,
+    pub redaction: AuditRedaction,
+}
+
+impl AuditLogConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_file_bytes: None,
+            max_files: 5,
+            redaction: AuditRedaction::default(),
+        }
+    }
+}
+
+/// Append-only JSONL audit log. One log can be shared by several models, see
+/// [`crate::MistralRsBuilder::with_audit_log`].
+pub struct AuditLog {
+    config: AuditLogConfig,
+    patterns: Vec<Regex>,
+    file: Mutex<AuditFile>,
+}
+
+struct AuditFile {
+    /// Closed while rotating, reopened by the next write.
+    handle: Option<File>,
+    len: u64,
+}
+
+impl AuditLog {
+    /// Open the log at `config.path`, creating it and its directory if needed. Fails if a
+    /// redaction pattern is not a valid regex.
+    pub fn open(config: AuditLogConfig) -> anyhow::Result<Self> {
+        let patterns = config
+            .redaction
+            .patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| {
+                    anyhow::anyhow!("Invalid audit redaction pattern `{pattern}`: {e}")
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if let Some(dir) = config
+            .path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+        {
+            fs::create_dir_all(dir)?;
+        }
+        let handle = open_append(&config.path)?;
+        let len = handle.metadata()?.len();
+        Ok(Self {
+            config,
+            patterns,
+            file: Mutex::new(AuditFile {
+                handle: Some(handle),
+                len,
+            }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.config.path
+    }
+
+    fn write(&self, mut record: Value) {
+        self.redact(&mut record);
+        let mut line = match serde_json::to_vec(&record) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!("Failed to serialize audit record: {e}");
+                return;
+            }
+        };
+        line.push(b'\n');
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+        if let Err(e) = self.append(&mut file, &line) {
+            tracing::warn!(
+                "Failed to write audit record to {}: {e}",
+                self.config.path.display()
+            );
+        }
+    }
+
+    fn append(&self, file: &mut AuditFile, line: &[u8]) -> std::io::Result<()> {
+        let len = line.len() as u64;
+        if self
+            .config
+            .max_file_bytes
+            .is_some_and(|max| file.len > 0 && file.len + len > max)
+        {
+            file.handle = None;
+            self.rotate()?;
+            file.len = 0;
+        }
+        let handle = match file.handle.take() {
+            Some(handle) => handle,
+            None => open_append(&self.config.path)?,
+        };
+        let handle = file.handle.insert(handle);
+        handle.write_all(line)?;
+        file.len += len;
+        Ok(())
+    }
+
+    /// Shift each `path.N` to `path.N+1`, dropping the oldest, and move the live file to `path.1`.
+    fn rotate(&self) -> std::io::Result<()> {
+        let path = &self.config.path;
+        if self.config.max_files == 0 {
+            return fs::remove_file(path);
+        }
+        let rotated = |n: usize| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(format!(".{n}"));
+            PathBuf::from(name)
+        };
+        let oldest = rotated(self.config.max_files);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for n in (1..self.config.max_files).rev() {
+            let from = rotated(n);
+            if from.exists() {
+                fs::rename(from, rotated(n + 1))?;
+            }
+        }
+        fs::rename(path, rotated(1))
+    }
+
+    fn redact(&self, record: &mut Value) {
+        let redaction = &self.config.redaction;
+        if redaction.message_contents {
+            if let Some(messages) = record.get_mut("messages").and_then(Value::as_array_mut) {
+                for message in messages.iter_mut().filter_map(Value::as_object_mut) {
+                    for (key, value) in message.iter_mut() {
+                        if !MESSAGE_METADATA_KEYS.contains(&key.as_str()) {
+                            *value = REDACTED.into();
+                        }
+                    }
+                }
+            }
+            if let Some(prompt) = record.get_mut("prompt") {
+                *prompt = REDACTED.into();
+            }
+        }
+        if redaction.tool_arguments {
+            if let Some(arguments) = record.get_mut("arguments") {
+                *arguments = REDACTED.into();
+            }
+        }
+        if !self.patterns.is_empty() {
+            redact_patterns(record, &self.patterns);
+        }
+    }
+}
+
+fn open_append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn redact_patterns(value: &mut Value, patterns: &[Regex]) {
+    match value {
+        Value::String(text) => {
+            for pattern in patterns {
+                if pattern.is_match(text) {
+                    *text = pattern.replace_all(text, REDACTED).into_owned();
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                redact_patterns(item, patterns);
+            }
+        }
+        Value::Object(fields) => {
+            for field in fields.values_mut() {
+                redact_patterns(field, patterns);
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct AuditIdentity {
+    request_id: usize,
+    model: String,
+    session_id: Option<String>,
+    user: Option<String>,
+}
+
+/// Writes the records of one request.
+#[derive(Clone)]
+pub(crate) struct AuditScope {
+    log: Arc<AuditLog>,
+    identity: Arc<AuditIdentity>,
+}
+
+impl AuditScope {
+    pub(crate) fn new(log: Arc<AuditLog>, request: &NormalRequest, model: String) -> Self {
+        Self {
+            log,
+            identity: Arc::new(AuditIdentity {
+                request_id: request.id,
+                model,
+                session_id: request.session_id.clone(),
+                user: request.user.clone(),
+            }),
+        }
+    }
+
+    fn record(&self, event: &str, fields: Value) {
+        let mut record = Map::new();
+        record.insert(
+            "timestamp".to_string(),
+            chrono::Utc::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+                .into(),
+        );
+        record.insert("event".to_string(), event.into());
+        if let Ok(Value::Object(identity)) = serde_json::to_value(&*self.identity) {
+            record.extend(identity);
+        }
+        if let Value::Object(fields) = fields {
+            record.extend(fields);
+        }
+        self.log.write(Value::Object(record));
+    }
+
+    /// A request as received, before any tool rounds.
+    pub(crate) fn request(&self, request: &NormalRequest) {
+        let mut fields = json!({
+            "stream": request.is_streaming,
+            "tools": request
+                .tools
+                .iter()
+                .flatten()
+                .map(|tool| tool.function.name.as_str())
+                .collect::<Vec<_>>(),
+        });
+        match &request.messages {
+            RequestMessage::Chat { messages, .. }
+            | RequestMessage::MultimodalChat { messages, .. } => {
+                fields["kind"] = "chat".into();
+                fields["messages"] = messages.iter().map(message_value).collect();
+            }
+            RequestMessage::Completion { text, .. } => {
+                fields["kind"] = "completion".into();
+                fields["prompt"] = text.as_str().into();
+            }
+            RequestMessage::CompletionTokens(tokens) => {
+                fields["kind"] = "completion".into();
+                fields["prompt_tokens"] = tokens.len().into();
+            }
+            RequestMessage::ImageGeneration { prompt, .. } => {
+                fields["kind"] = "image_generation".into();
+                fields["prompt"] = prompt.as_str().into();
+            }
+            RequestMessage::SpeechGeneration { prompt, .. } => {
+                fields["kind"] = "speech_generation".into();
+                fields["prompt"] = prompt.as_str().into();
+            }
+            RequestMessage::Embedding { prompt } => {
+                fields["kind"] = "embedding".into();
+                fields["prompt"] = prompt.as_str().into();
+            }
+            RequestMessage::EmbeddingTokens { prompt } => {
+                fields["kind"] = "embedding".into();
+                fields["prompt_tokens"] = prompt.len().into();
+            }
+        }
+        self.record("request", fields);
+    }
+
+    /// A sequence that finished, with its token counts and timings.
+    fn completion(&self, seq: &Sequence, finish_reason: &str) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        self.record(
+            "completion",
+            json!({
+                "choice": seq.get_response_index(),
+                "finish_reason": finish_reason,
+                "usage": {
+                    "prompt_tokens": seq.prompt_tokens(),
+                    "completion_tokens": seq.len().saturating_sub(seq.prompt_tokens()),
+                },
+                "timings": {
+                    "time_to_first_token_ms": seq
+                        .prompt_timestamp()
+                        .map(|first_token| first_token.saturating_sub(seq.timestamp())),
+                    "prompt_ms": seq.total_prompt_time,
+                    "total_ms": now.saturating_sub(seq.timestamp()),
+                },
+            }),
+        );
+    }
+
+    /// A tool call the agentic loop ran. `outcome` is `completed`, or `unhandled` when no
+    /// dispatcher knew the tool.
+    pub(crate) fn tool_call(
+        &self,
+        round: usize,
+        tool: &str,
+        arguments: &str,
+        outcome: &str,
+        duration: Duration,
+    ) {
+        self.record(
+            "tool_call",
+            json!({
+                "round": round,
+                "tool": tool,
+                "arguments": arguments,
+                "outcome": outcome,
+                "duration_ms": duration.as_millis(),
+            }),
+        );
+    }
+
+    /// Whether a tool call was allowed to run under the request's agent permission.
+    pub(crate) fn approval(
+        &self,
+        round: usize,
+        tool: &str,
+        arguments: &str,
+        approved: bool,
+        message: Option<&str>,
+    ) {
+        self.record(
+            "approval",
+            json!({
+                "round": round,
+                "tool": tool,
+                "arguments": arguments,
+                "approved": approved,
+                "message": message,
+            }),
+        );
+    }
+}
+
+fn message_value(message: &IndexMap<String, MessageContent>) -> Value {
+    message
+        .iter()
+        .map(|(key, content)| {
+            let value = match content {
+                Either::Left(text) => Value::String(text.clone()),
+                Either::Right(parts) => serde_json::to_value(parts).unwrap_or_default(),
+            };
+            (key.clone(), value)
+        })
+        .collect::<Map<_, _>>()
+        .into()
+}
+
+/// Audit state of one sequence, so its completion is recorded once.
+pub(crate) struct SequenceAudit {
+    scope: AuditScope,
+    recorded: AtomicBool,
+}
+
+impl SequenceAudit {
+    pub(crate) fn new(scope: AuditScope) -> Self {
+        Self {
+            scope,
+            recorded: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Record a `completion` for each sequence that finished in the last step.
+pub(crate) fn record_step<'a>(seqs: impl IntoIterator<Item = &'a Sequence>) {
+    for seq in seqs {
+        let Some(audit) = seq.audit() else {
+            continue;
+        };
+        let reason = match seq.getstate() {
+            SequenceState::Done(reason) => reason.to_string(),
+            SequenceState::FinishedAborted => "aborted".to_string(),
+            _ => continue,
+        };
+        if !audit.recorded.swap(true, Ordering::Relaxed) {
+            audit.scope.completion(seq, &reason);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log(name: &str, config: impl FnOnce(&mut AuditLogConfig)) -> AuditLog {
+        let dir =
+            std::env::temp_dir().join(format!("mistralrs-audit-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut audit_config = AuditLogConfig::new(dir.join("audit.jsonl"));
+        config(&mut audit_config);
+        AuditLog::open(audit_config).unwrap()
+    }
+
+    fn read_records(path: &Path) -> Vec<Value> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn redacts_contents_arguments_and_patterns() {
+        let log = temp_log("redact", |config| {
+            config.redaction = AuditRedaction {
+                message_contents: true,
+                tool_arguments: true,
+                patterns: vec![r"[\w.]+@[\w.]+".to_string()],
+            };
+        });
+        log.write(json!({
+            "event": "request",
+            "user": "ops@example.com",
+            "messages": [{ "role": "user", "content": "mail me at a@b.c" }],
+        }));
+        log.write(json!({ "event": "tool_call", "tool": "search", "arguments": "{}" }));
+
+        let records = read_records(log.path());
+        assert_eq!(records[0]["user"], REDACTED);
+        assert_eq!(records[0]["messages"][0]["role"], "user");
+        assert_eq!(records[0]["messages"][0]["content"], REDACTED);
+        assert_eq!(records[1]["tool"], "search");
+        assert_eq!(records[1]["arguments"], REDACTED);
+    }
+
+    #[test]
+    fn rotates_by_size() {
+        let log = temp_log("rotate", |config| {
+            config.max_file_bytes = Some(64);
+            config.max_files = 2;
+        });
+        for i in 0..4 {
+            log.write(json!({ "event": "request", "padding": "x".repeat(40), "i": i }));
+        }
+
+        let rotated = |n: usize| PathBuf::from(format!("{}.{n}", log.path().display()));
+        assert_eq!(read_records(log.path())[0]["i"], 3);
+        assert_eq!(read_records(&rotated(1))[0]["i"], 2);
+        assert_eq!(read_records(&rotated(2))[0]["i"], 1);
+        assert!(!rotated(3).exists());
+    }
+}
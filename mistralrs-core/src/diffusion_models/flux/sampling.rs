@@ -1,16 +1,34 @@
 #![allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
 
 use candle_core::{Device, Result, Tensor};
+use rand::SeedableRng;
+use rand_distr::{Distribution, StandardNormal};
+use rand_isaac::Isaac64Rng;
 
+/// Initial latents with one sample per entry of `seeds`. Seeded samples are drawn on the CPU,
+/// so a seed gives the same image on every device.
 pub fn get_noise(
-    num_samples: usize,
+    seeds: &[Option<u64>],
     height: usize,
     width: usize,
     device: &Device,
 ) -> Result<Tensor> {
     let height = height.div_ceil(16) * 2;
     let width = width.div_ceil(16) * 2;
-    Tensor::randn(0f32, 1., (num_samples, 16, height, width), device)
+    let samples = seeds
+        .iter()
+        .map(|seed| match seed {
+            Some(seed) => {
+                let mut rng = Isaac64Rng::seed_from_u64(*seed);
+                let data = (0..16 * height * width)
+                    .map(|_| StandardNormal.sample(&mut rng))
+                    .collect::<Vec<f32>>();
+                Tensor::from_vec(data, (1, 16, height, width), &Device::Cpu)?.to_device(device)
+            }
+            None => Tensor::randn(0f32, 1., (1, 16, height, width), device),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Tensor::cat(&samples, 0)
 }
 
 #[derive(Debug, Clone)]
@@ -22,14 +40,21 @@ pub struct State {
     pub vec: Tensor,
 }
 
+/// Pack latents into 2x2 patches: `(b, c, h, w)` to `(b, h/2 * w/2, c * 4)`. Inverse of
+/// [`unpack`].
+pub fn pack(xs: &Tensor) -> Result<Tensor> {
+    let (bs, c, h, w) = xs.dims4()?;
+    xs.reshape((bs, c, h / 2, 2, w / 2, 2))? // (b, c, h, ph, w, pw)
+        .permute((0, 2, 4, 1, 3, 5))? // (b, h, w, c, ph, pw)
+        .reshape((bs, h / 2 * w / 2, c * 4))
+}
+
 impl State {
     pub fn new(t5_emb: &Tensor, clip_emb: &Tensor, img: &Tensor) -> Result<Self> {
         let dtype = img.dtype();
-        let (bs, c, h, w) = img.dims4()?;
+        let (bs, _c, h, w) = img.dims4()?;
         let dev = img.device();
-        let img = img.reshape((bs, c, h / 2, 2, w / 2, 2))?; // (b, c, h, ph, w, pw)
-        let img = img.permute((0, 2, 4, 1, 3, 5))?; // (b, h, w, c, ph, pw)
-        let img = img.reshape((bs, h / 2 * w / 2, c * 4))?;
+        let img = pack(img)?;
         let img_ids = Tensor::stack(
             &[
                 Tensor::full(0u32, (h / 2, w / 2), dev)?,
@@ -84,6 +109,39 @@ pub fn get_schedule(num_steps: usize, shift: Option<(usize, f64, f64)>) -> Vec<f
     }
 }
 
+/// Keep the last `strength` fraction of `timesteps` (at least one step), so denoising starts
+/// from a partly noised source image rather than pure noise.
+pub fn truncate_schedule(timesteps: &[f64], strength: f64) -> Vec<f64> {
+    let num_steps = timesteps.len().saturating_sub(1);
+    let steps = ((num_steps as f64 * strength).round() as usize).clamp(1, num_steps.max(1));
+    timesteps[num_steps.saturating_sub(steps)..].to_vec()
+}
+
+/// Packed latents of the source image for inpainting. After every step, the region outside
+/// the mask is reset to the source image noised to the current timestep.
+pub struct Inpaint {
+    pub init: Tensor,
+    pub noise: Tensor,
+    /// 1.0 where the model repaints, 0.0 where the source image is kept.
+    pub mask: Tensor,
+}
+
+impl Inpaint {
+    fn blend(&self, img: &Tensor, t: f64) -> Result<Tensor> {
+        let known = (self.init.affine(1. - t, 0.)? + self.noise.affine(t, 0.)?)?;
+        let keep = self.mask.affine(-1., 1.)?;
+        img.mul(&self.mask)? + known.mul(&keep)?
+    }
+}
+
+/// Text conditioning of the negative prompt for classifier-free guidance.
+pub struct NegativePrompt {
+    pub txt: Tensor,
+    pub txt_ids: Tensor,
+    pub vec: Tensor,
+    pub scale: f64,
+}
+
 pub fn unpack(xs: &Tensor, height: usize, width: usize) -> Result<Tensor> {
     let (b, _h_w, c_ph_pw) = xs.dims3()?;
     let height = height.div_ceil(16);
@@ -103,6 +161,8 @@ fn denoise_inner(
     vec_: &Tensor,
     timesteps: &[f64],
     guidance: Option<f64>,
+    negative: Option<&NegativePrompt>,
+    inpaint: Option<&Inpaint>,
 ) -> Result<Tensor> {
     let b_sz = img.dim(0)?;
     let dev = img.device();
@@ -118,8 +178,24 @@ fn denoise_inner(
             _ => continue,
         };
         let t_vec = Tensor::full(*t_curr as f32, b_sz, dev)?;
-        let pred = model.forward(&img, img_ids, txt, txt_ids, &t_vec, vec_, guidance.as_ref())?;
-        img = (img + pred * (t_prev - t_curr))?
+        let mut pred =
+            model.forward(&img, img_ids, txt, txt_ids, &t_vec, vec_, guidance.as_ref())?;
+        if let Some(negative) = negative {
+            let negative_pred = model.forward(
+                &img,
+                img_ids,
+                &negative.txt,
+                &negative.txt_ids,
+                &t_vec,
+                &negative.vec,
+                guidance.as_ref(),
+            )?;
+            pred = (&negative_pred + ((pred - &negative_pred)? * negative.scale)?)?;
+        }
+        img = (img + pred * (t_prev - t_curr))?;
+        if let Some(inpaint) = inpaint {
+            img = inpaint.blend(&img, *t_prev)?;
+        }
     }
     Ok(img)
 }
@@ -134,6 +210,8 @@ pub fn denoise(
     vec_: &Tensor,
     timesteps: &[f64],
     guidance: f64,
+    negative: Option<&NegativePrompt>,
+    inpaint: Option<&Inpaint>,
 ) -> Result<Tensor> {
     denoise_inner(
         model,
@@ -144,6 +222,8 @@ pub fn denoise(
         vec_,
         timesteps,
         Some(guidance),
+        negative,
+        inpaint,
     )
 }
 
@@ -156,6 +236,40 @@ pub fn denoise_no_guidance(
     txt_ids: &Tensor,
     vec_: &Tensor,
     timesteps: &[f64],
+    negative: Option<&NegativePrompt>,
+    inpaint: Option<&Inpaint>,
 ) -> Result<Tensor> {
-    denoise_inner(model, img, img_ids, txt, txt_ids, vec_, timesteps, None)
+    denoise_inner(
+        model, img, img_ids, txt, txt_ids, vec_, timesteps, None, negative, inpaint,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use candle_core::Device;
+
+    use super::{get_noise, truncate_schedule};
+
+    #[test]
+    fn seeded_noise_is_reproducible() -> candle_core::Result<()> {
+        let a = get_noise(&[Some(7), Some(8)], 32, 32, &Device::Cpu)?;
+        let b = get_noise(&[Some(7)], 32, 32, &Device::Cpu)?;
+        assert_eq!(a.dims(), [2, 16, 4, 4]);
+        let (a0, a1, b0) = (
+            a.get(0)?.flatten_all()?.to_vec1::<f32>()?,
+            a.get(1)?.flatten_all()?.to_vec1::<f32>()?,
+            b.get(0)?.flatten_all()?.to_vec1::<f32>()?,
+        );
+        assert_eq!(a0, b0);
+        assert_ne!(a0, a1);
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_schedule_keeps_the_tail() {
+        let timesteps = [1.0, 0.75, 0.5, 0.25, 0.0];
+        assert_eq!(truncate_schedule(&timesteps, 1.0), timesteps);
+        assert_eq!(truncate_schedule(&timesteps, 0.5), [0.5, 0.25, 0.0]);
+        assert_eq!(truncate_schedule(&timesteps, 0.0), [0.25, 0.0]);
+    }
 }
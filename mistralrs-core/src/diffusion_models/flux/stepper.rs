@@ -3,6 +3,7 @@ use std::{cmp::Ordering, fs::File, sync::Arc};
 use candle_core::{DType, Device, Result, Tensor, D};
 use candle_nn::Module;
 use hf_hub::api::sync::{Api, ApiBuilder};
+use image::{imageops::FilterType, DynamicImage};
 use mistralrs_quant::ShardedVarBuilder;
 use tokenizers::Tokenizer;
 use tracing::info;
@@ -12,7 +13,7 @@ use crate::{
         clip::text::{ClipConfig, ClipTextTransformer},
         flux,
         t5::{self, T5EncoderModel},
-        DiffusionGenerationParams,
+        DiffusionGenerationParams, DiffusionImageInput,
     },
     pipeline::DiffusionModel,
     utils::varbuilder_utils::{from_mmaped_safetensors, DeviceForLoadTensor},
@@ -23,6 +24,9 @@ use super::{autoencoder::AutoEncoder, model::Flux};
 const T5_XXL_SAFETENSOR_FILES: &[&str] =
     &["t5_xxl-shard-0.safetensors", "t5_xxl-shard-1.safetensors"];
 
+/// Classifier-free guidance scale when a negative prompt is given without one.
+const DEFAULT_TRUE_CFG_SCALE: f64 = 4.0;
+
 #[derive(Clone, Copy, Debug)]
 pub struct FluxStepperShift {
     pub base_shift: f64,
@@ -218,12 +222,58 @@ impl FluxStepper {
     }
 }
 
-impl DiffusionModel for FluxStepper {
-    fn forward(
-        &mut self,
+impl FluxStepper {
+    /// Packed VAE latents of the source image, repeated for every prompt, and the packed
+    /// repaint mask if there is one. Both match the packed shape of `noise`.
+    fn encode_image_input(
+        &self,
+        input: &DiffusionImageInput,
+        noise: &Tensor,
+    ) -> Result<(Tensor, Option<Tensor>)> {
+        let (bs, c, latent_h, latent_w) = noise.dims4()?;
+        // The VAE downsamples by 8.
+        let (height, width) = (latent_h * 8, latent_w * 8);
+
+        let rgb = input
+            .image
+            .resize_exact(width as u32, height as u32, FilterType::Lanczos3)
+            .to_rgb8();
+        let pixels = Tensor::from_vec(rgb.into_raw(), (height, width, 3), &self.device)?
+            .permute((2, 0, 1))?
+            .unsqueeze(0)?
+            .to_dtype(DType::F32)?
+            .affine(1. / 127.5, -1.)?
+            .to_dtype(self.dtype)?;
+        let latents = self.flux_vae.encode(&pixels)?.repeat((bs, 1, 1, 1))?;
+        let init = flux::sampling::pack(&latents)?;
+
+        let mask = match input.repaint_mask() {
+            Some(mask) => {
+                let mask = DynamicImage::ImageLuma8(mask)
+                    .resize_exact(latent_w as u32, latent_h as u32, FilterType::Triangle)
+                    .to_luma8();
+                let mask =
+                    Tensor::from_vec(mask.into_raw(), (1, 1, latent_h, latent_w), &self.device)?
+                        .to_dtype(DType::F32)?
+                        .affine(1. / 255., 0.)?
+                        .broadcast_as((bs, c, latent_h, latent_w))?
+                        .contiguous()?
+                        .to_dtype(self.dtype)?;
+                Some(flux::sampling::pack(&mask)?)
+            }
+            None => None,
+        };
+        Ok((init, mask))
+    }
+}
+
+impl FluxStepper {
+    /// T5 and CLIP embeddings of `prompts`.
+    fn embed_prompts(
+        &self,
+        t5_encoder: &mut T5EncoderModel,
         prompts: Vec<String>,
-        params: DiffusionGenerationParams,
-    ) -> Result<Tensor> {
+    ) -> Result<(Tensor, Tensor)> {
         let mut t5_input_ids = get_tokenization(&self.t5_tok, prompts.clone(), &self.device)?;
         if !self.is_guidance {
             match t5_input_ids.dim(1)?.cmp(&256) {
@@ -236,8 +286,26 @@ impl DiffusionModel for FluxStepper {
                 }
             }
         }
+        let t5_embed = t5_encoder.forward(&t5_input_ids)?;
+
+        let clip_input_ids = get_tokenization(&self.clip_tok, prompts, &self.device)?;
+        let clip_embed = self
+            .clip_text
+            .forward(&clip_input_ids)?
+            .to_dtype(self.dtype)?;
+        Ok((t5_embed, clip_embed))
+    }
+}
 
-        let t5_embed = {
+impl DiffusionModel for FluxStepper {
+    fn forward(
+        &mut self,
+        prompts: Vec<String>,
+        params: DiffusionGenerationParams,
+        seeds: &[Option<u64>],
+    ) -> Result<Tensor> {
+        let bs = prompts.len();
+        let (t5_embed, clip_embed, negative_embeds) = {
             info!("Hotloading T5 XXL model.");
             let mut t5_encoder = get_t5_model(
                 &self.api,
@@ -246,51 +314,79 @@ impl DiffusionModel for FluxStepper {
                 self.silent,
                 self.offloaded,
             )?;
-            t5_encoder.forward(&t5_input_ids)?
+            let (t5_embed, clip_embed) = self.embed_prompts(&mut t5_encoder, prompts)?;
+            let negative_embeds = match &params.negative_prompt {
+                Some(negative) => {
+                    Some(self.embed_prompts(&mut t5_encoder, vec![negative.clone(); bs])?)
+                }
+                None => None,
+            };
+            (t5_embed, clip_embed, negative_embeds)
         };
 
-        let clip_input_ids = get_tokenization(&self.clip_tok, prompts, &self.device)?;
-        let clip_embed = self
-            .clip_text
-            .forward(&clip_input_ids)?
+        let noise = flux::sampling::get_noise(seeds, params.height, params.width, self.device())?
             .to_dtype(self.dtype)?;
 
-        let img = flux::sampling::get_noise(
-            t5_embed.dim(0)?,
-            params.height,
-            params.width,
-            self.device(),
-        )?
-        .to_dtype(self.dtype)?;
-
-        let state = flux::sampling::State::new(&t5_embed, &clip_embed, &img)?;
-        let timesteps = flux::sampling::get_schedule(
-            self.cfg.num_steps,
+        let state = flux::sampling::State::new(&t5_embed, &clip_embed, &noise)?;
+        let negative = match negative_embeds {
+            Some((t5_embed, clip_embed)) => {
+                let negative_state = flux::sampling::State::new(&t5_embed, &clip_embed, &noise)?;
+                Some(flux::sampling::NegativePrompt {
+                    txt: negative_state.txt,
+                    txt_ids: negative_state.txt_ids,
+                    vec: negative_state.vec,
+                    scale: params.true_cfg_scale.unwrap_or(DEFAULT_TRUE_CFG_SCALE),
+                })
+            }
+            None => None,
+        };
+        let mut timesteps = flux::sampling::get_schedule(
+            params.num_steps.unwrap_or(self.cfg.num_steps),
             self.cfg
                 .guidance_config
                 .map(|s| (state.img.dims()[1], s.base_shift, s.max_shift)),
         );
 
+        // Image-to-image: skip the early steps and start from the source image noised to the
+        // first remaining timestep.
+        let mut img = state.img.clone();
+        let mut inpaint = None;
+        if let Some(input) = &params.image_input {
+            let (init, mask) = self.encode_image_input(input, &noise)?;
+            timesteps = flux::sampling::truncate_schedule(&timesteps, input.strength);
+            let t0 = timesteps[0];
+            img = (init.affine(1. - t0, 0.)? + state.img.affine(t0, 0.)?)?;
+            inpaint = mask.map(|mask| flux::sampling::Inpaint {
+                init,
+                noise: state.img.clone(),
+                mask,
+            });
+        }
+
         let img = if let Some(guidance_cfg) = &self.cfg.guidance_config {
             flux::sampling::denoise(
                 &mut self.flux_model,
-                &state.img,
+                &img,
                 &state.img_ids,
                 &state.txt,
                 &state.txt_ids,
                 &state.vec,
                 &timesteps,
-                guidance_cfg.guidance_scale,
+                params.guidance_scale.unwrap_or(guidance_cfg.guidance_scale),
+                negative.as_ref(),
+                inpaint.as_ref(),
             )?
         } else {
             flux::sampling::denoise_no_guidance(
                 &mut self.flux_model,
-                &state.img,
+                &img,
                 &state.img_ids,
                 &state.txt,
                 &state.txt_ids,
                 &state.vec,
                 &timesteps,
+                negative.as_ref(),
+                inpaint.as_ref(),
             )?
         };
 
@@ -3,6 +3,8 @@ pub(crate) mod flux;
 pub(crate) mod processor;
 pub(crate) mod t5;
 
+use image::{DynamicImage, GrayImage, Luma};
+
 macro_rules! generate_repr {
     ($t:ident) => {
         #[cfg(feature = "pyo3_macros")]
@@ -16,11 +18,36 @@ macro_rules! generate_repr {
 }
 
 #[cfg_attr(feature = "pyo3_macros", pyo3::pyclass)]
-#[cfg_attr(feature = "pyo3_macros", pyo3(get_all))]
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct DiffusionGenerationParams {
+    #[cfg_attr(feature = "pyo3_macros", pyo3(get))]
     pub height: usize,
+    #[cfg_attr(feature = "pyo3_macros", pyo3(get))]
     pub width: usize,
+    /// Denoising steps. Defaults to the model's own: 4 for FLUX.1-schnell, 50 for FLUX.1-dev.
+    #[cfg_attr(feature = "pyo3_macros", pyo3(get))]
+    pub num_steps: Option<usize>,
+    /// Distilled guidance strength, for models trained with it (FLUX.1-dev, default 4.0).
+    /// Ignored by models without guidance distillation.
+    #[cfg_attr(feature = "pyo3_macros", pyo3(get))]
+    pub guidance_scale: Option<f64>,
+    /// Seed for the initial noise. With several images, image `i` uses `seed + i`.
+    #[cfg_attr(feature = "pyo3_macros", pyo3(get))]
+    pub seed: Option<u64>,
+    /// Steer away from this prompt with classifier-free guidance. Each step then runs the model
+    /// twice.
+    #[cfg_attr(feature = "pyo3_macros", pyo3(get))]
+    pub negative_prompt: Option<String>,
+    /// Classifier-free guidance scale used with `negative_prompt`. Defaults to 4.0.
+    #[cfg_attr(feature = "pyo3_macros", pyo3(get))]
+    pub true_cfg_scale: Option<f64>,
+    /// Images to generate. They are denoised together as one batch.
+    #[cfg_attr(feature = "pyo3_macros", pyo3(get))]
+    pub num_images: usize,
+    /// Start from an existing image instead of pure noise, for editing and variations.
+    #[serde(skip)]
+    pub image_input: Option<DiffusionImageInput>,
 }
 
 generate_repr!(DiffusionGenerationParams);
@@ -31,6 +58,115 @@ impl Default for DiffusionGenerationParams {
         Self {
             height: 720,
             width: 1280,
+            num_steps: None,
+            guidance_scale: None,
+            seed: None,
+            negative_prompt: None,
+            true_cfg_scale: None,
+            num_images: 1,
+            image_input: None,
         }
     }
 }
+
+impl DiffusionGenerationParams {
+    /// Whether images generated with `self` and `other` can share a denoising run: everything
+    /// but the seed must match.
+    pub(crate) fn batches_with(&self, other: &Self) -> bool {
+        self.height == other.height
+            && self.width == other.width
+            && self.num_steps == other.num_steps
+            && self.guidance_scale == other.guidance_scale
+            && self.negative_prompt == other.negative_prompt
+            && self.true_cfg_scale == other.true_cfg_scale
+            && self.image_input == other.image_input
+    }
+}
+
+/// Source image for image-to-image generation and inpainting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffusionImageInput {
+    pub image: DynamicImage,
+    /// Which part of `image` to repaint: transparent pixels of a mask with an alpha channel,
+    /// otherwise white pixels. Without a mask, transparent pixels of `image` are repainted if
+    /// it has any, and the whole image is reworked if not.
+    pub mask: Option<DynamicImage>,
+    /// How far the result may move from `image`: 0.0 keeps it, 1.0 keeps only the layout of
+    /// the unmasked region.
+    pub strength: f64,
+}
+
+impl DiffusionImageInput {
+    /// Per-pixel repaint weight in 0..=255 (255 repaints), or `None` to rework everything.
+    pub fn repaint_mask(&self) -> Option<GrayImage> {
+        match &self.mask {
+            Some(mask) if mask.color().has_alpha() => Some(inverted_alpha(mask)),
+            Some(mask) => Some(mask.to_luma8()),
+            None if self.image.color().has_alpha() => {
+                let mask = inverted_alpha(&self.image);
+                mask.pixels().any(|p| p.0[0] > 0).then_some(mask)
+            }
+            None => None,
+        }
+    }
+}
+
+fn inverted_alpha(image: &DynamicImage) -> GrayImage {
+    let rgba = image.to_rgba8();
+    GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        Luma([255 - rgba.get_pixel(x, y).0[3]])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+
+    use super::{DiffusionGenerationParams, DiffusionImageInput};
+
+    fn input(image: DynamicImage, mask: Option<DynamicImage>) -> DiffusionImageInput {
+        DiffusionImageInput {
+            image,
+            mask,
+            strength: 1.0,
+        }
+    }
+
+    #[test]
+    fn repaint_mask_sources() {
+        let opaque = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 1, Rgba([9, 9, 9, 255])));
+        assert!(input(opaque.clone(), None).repaint_mask().is_none());
+
+        let mut holed = RgbaImage::from_pixel(2, 1, Rgba([9, 9, 9, 255]));
+        holed.put_pixel(1, 0, Rgba([0, 0, 0, 0]));
+        let holed = DynamicImage::ImageRgba8(holed);
+        let mask = input(opaque.clone(), Some(holed.clone()))
+            .repaint_mask()
+            .unwrap();
+        assert_eq!(mask.as_raw(), &[0, 255]);
+        let mask = input(holed, None).repaint_mask().unwrap();
+        assert_eq!(mask.as_raw(), &[0, 255]);
+
+        let mut gray = GrayImage::new(2, 1);
+        gray.put_pixel(0, 0, Luma([255]));
+        let mask = input(opaque, Some(DynamicImage::ImageLuma8(gray)))
+            .repaint_mask()
+            .unwrap();
+        assert_eq!(mask.as_raw(), &[255, 0]);
+    }
+
+    #[test]
+    fn only_seeds_may_differ_within_a_batch() {
+        let params = DiffusionGenerationParams::default();
+        let reseeded = DiffusionGenerationParams {
+            seed: Some(3),
+            ..params.clone()
+        };
+        assert!(params.batches_with(&reseeded));
+        let taller = DiffusionGenerationParams {
+            height: 1024,
+            ..params.clone()
+        };
+        assert!(!params.batches_with(&taller));
+    }
+}
@@ -51,7 +51,8 @@ pub struct DiffusionInputsProcessor;
 #[derive(Clone)]
 pub struct ModelInputs {
     pub(crate) prompts: Vec<String>,
-    pub(crate) params: DiffusionGenerationParams,
+    /// Per sequence, with `seed` already offset by the sequence's index in its request.
+    pub(crate) params: Vec<DiffusionGenerationParams>,
 }
 
 impl InputsProcessor for DiffusionInputsProcessor {
@@ -74,14 +75,24 @@ impl InputsProcessor for DiffusionInputsProcessor {
         _paged_attn_metadata: Option<PagedAttentionMeta>,
         _mapper: Option<&dyn DeviceMapper>,
     ) -> Result<InputProcessorOutput> {
+        let params = input_seqs
+            .iter()
+            .map(|seq| {
+                let mut params = seq
+                    .get_diffusion_diffusion_params()
+                    .context("Diffusion model params must be present")?;
+                params.seed = params
+                    .seed
+                    .map(|seed| seed.wrapping_add(seq.get_response_index() as u64));
+                Ok(params)
+            })
+            .collect::<Result<Vec<_>>>()?;
         let inputs = ModelInputs {
             prompts: input_seqs
                 .iter_mut()
                 .map(|seq| seq.get_initial_prompt().to_string())
                 .collect::<Vec<_>>(),
-            params: input_seqs[0]
-                .get_diffusion_diffusion_params()
-                .context("Diffusion model params must be present")?,
+            params,
         };
         Ok(InputProcessorOutput {
             inputs: Box::new(inputs),
@@ -0,0 +1,116 @@
+//! Qwen3-Reranker as a cross-encoder.
+//!
+//! The reranker is a Qwen3 causal LM that answers "yes" or "no" to whether a document matches
+//! a query. Only the final hidden state is needed: the relevance score is
+//! `sigmoid(h · (w_yes - w_no))`, which equals the softmax probability of "yes" over the two
+//! answers. Sequence-classification checkpoints (`Qwen3ForSequenceClassification`) store that
+//! difference directly as `score.weight`.
+
+use candle_core::{DType, Device, IndexOp, Result, Tensor, D};
+use mistralrs_quant::{QuantMethod, ShardedVarBuilder};
+use std::sync::Arc;
+
+use crate::{
+    amoe::AnyMoeBaseModelMixin,
+    device_map::DeviceMapper,
+    embedding_models::qwen3_embedding::{Config, Model as Qwen3Model},
+    paged_attention::AttentionImplementation,
+    pipeline::{
+        text_models_inputs_processor::FlashParams, EmbeddingModel, IsqModel, NormalLoadingMetadata,
+    },
+    utils::unvarbuilder::UnVarBuilder,
+};
+
+/// Token id of "yes" in the Qwen3 vocabulary.
+const YES_TOKEN_ID: usize = 9693;
+/// Token id of "no" in the Qwen3 vocabulary.
+const NO_TOKEN_ID: usize = 2152;
+
+pub struct Model {
+    model: Qwen3Model,
+    /// `(1, hidden_size)` projection from the last hidden state to the relevance logit.
+    score: Tensor,
+}
+
+impl Model {
+    pub fn new(
+        cfg: &Config,
+        vb: ShardedVarBuilder,
+        is_gptx: bool,
+        normal_loading_metadata: NormalLoadingMetadata,
+        attention_mechanism: AttentionImplementation,
+    ) -> Result<Self> {
+        let vb_head = normal_loading_metadata
+            .mapper
+            .set_nm_device(vb.clone(), false);
+        let score = if vb_head.contains_tensor("score.weight") {
+            vb_head.get((1, cfg.hidden_size), "score.weight")?
+        } else {
+            let head = if cfg.tie_word_embeddings {
+                vb_head
+                    .pp("model")
+                    .pp("embed_tokens")
+                    .get((cfg.vocab_size, cfg.hidden_size), "weight")?
+            } else {
+                vb_head
+                    .pp("lm_head")
+                    .get((cfg.vocab_size, cfg.hidden_size), "weight")?
+            };
+            (head.i(YES_TOKEN_ID..YES_TOKEN_ID + 1)? - head.i(NO_TOKEN_ID..NO_TOKEN_ID + 1)?)?
+        };
+        let score = score
+            .to_device(&normal_loading_metadata.real_device)?
+            .to_dtype(DType::F32)?;
+
+        let model = Qwen3Model::new(
+            cfg,
+            vb.pp("model"),
+            is_gptx,
+            normal_loading_metadata,
+            attention_mechanism,
+        )?;
+        Ok(Self { model, score })
+    }
+}
+
+impl IsqModel for Model {
+    fn get_layers(
+        &mut self,
+    ) -> (
+        Vec<(&mut Arc<dyn QuantMethod>, Option<usize>)>,
+        &dyn DeviceMapper,
+    ) {
+        self.model.get_layers()
+    }
+
+    fn residual_tensors(&self) -> Vec<(String, Tensor)> {
+        let uvb = UnVarBuilder::new();
+        uvb.pp("model").extend(self.model.residual_tensors());
+        uvb.add_tensor("score.weight", self.score.clone());
+        uvb.to_safetensors()
+    }
+
+    fn imatrix_names(&self) -> candle_core::Result<Vec<Option<String>>> {
+        self.model.imatrix_names()
+    }
+}
+
+impl EmbeddingModel for Model {
+    /// Returns a `(batch, 1)` tensor of relevance scores in `[0, 1]`.
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        flash_params: &FlashParams,
+    ) -> candle_core::Result<Tensor> {
+        let xs = self.model.forward(input_ids, flash_params)?;
+        let last = xs
+            .i((.., xs.dim(D::Minus2)? - 1, ..))?
+            .to_dtype(DType::F32)?;
+        candle_nn::ops::sigmoid(&last.matmul(&self.score.t()?)?)
+    }
+    fn device(&self) -> &Device {
+        EmbeddingModel::device(&self.model)
+    }
+}
+
+impl AnyMoeBaseModelMixin for Model {}
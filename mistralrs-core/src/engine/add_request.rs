@@ -1,8 +1,11 @@
 use crate::{
-    pipeline::{KvCache, NormalCache},
+    audit::SequenceAudit,
+    pipeline::{FimTemplate, KvCache, NormalCache},
     prefix_cacher::MatchingCache,
     request::{DetokenizationRequest, NormalRequest, TokenizationRequest},
     sequence::SeqStepType,
+    speculative::prediction::PredictedOutput,
+    telemetry::SequenceTrace,
     tools::{ToolCallingMatcher, ToolChoice},
     ModelCategory, RequestMessage, Response,
 };
@@ -11,7 +14,7 @@ use either::Either;
 use std::{
     ops::Deref,
     sync::{atomic::Ordering, Arc},
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 use tracing::warn;
 
@@ -61,6 +64,12 @@ impl Engine {
                         ))
                         .await;
                 } else {
+                    // Agentic loops record the request themselves, once the session is known.
+                    if !in_agentic_loop {
+                        if let Some(audit) = self.audit_scope(&request) {
+                            audit.request(&request);
+                        }
+                    }
                     self.add_request(*request).await;
                 }
             }
@@ -78,7 +87,15 @@ impl Engine {
         }
     }
 
-    pub(super) async fn add_request(&self, request: NormalRequest) {
+    pub(super) async fn add_request(&self, mut request: NormalRequest) {
+        // Every image is its own sequence in one group, so they are scheduled, and denoised,
+        // together.
+        if let RequestMessage::ImageGeneration {
+            generation_params, ..
+        } = &request.messages
+        {
+            request.sampling_params.n_choices = generation_params.num_images;
+        }
         let is_chat = matches!(
             request.messages,
             RequestMessage::Chat { .. } | RequestMessage::MultimodalChat { .. }
@@ -102,6 +119,8 @@ impl Engine {
             | RequestMessage::EmbeddingTokens { .. } => None,
         };
         let truncate_sequence = request.truncate_sequence;
+        let deadline = request.timeout.map(|timeout| Instant::now() + timeout);
+        let audit = self.audit_scope(&request);
         if is_chat
             && !get_mut_arcmutex!(self.pipeline)
                 .get_chat_template()
@@ -194,6 +213,44 @@ impl Engine {
             RequestMessage::ImageGeneration { save_file, .. } => save_file.clone(),
             _ => None,
         };
+
+        let speech_reference = match &request.messages {
+            RequestMessage::SpeechGeneration { reference, .. } => reference.clone(),
+            _ => None,
+        };
+        // When the model has fill-in-the-middle tokens, a completion's suffix goes into the
+        // prompt and the output is just the infill.
+        let fim = match (&request.messages, &request.suffix) {
+            (RequestMessage::Completion { text, fim_mode, .. }, Some(suffix)) => {
+                let tokenizer = get_mut_arcmutex!(self.pipeline).tokenizer();
+                let template = tokenizer
+                    .as_ref()
+                    .and_then(|tokenizer| Some((tokenizer, FimTemplate::detect(tokenizer)?)));
+                match template {
+                    Some((tokenizer, template)) => {
+                        let Some(prompt) = template.prompt(text, suffix, *fim_mode) else {
+                            request
+                                .response
+                                .send(Response::ValidationError(
+                                    format!(
+                                        "fim_mode \"spm\" is not supported by {} models; use \"psm\".",
+                                        template.name
+                                    )
+                                    .into(),
+                                ))
+                                .await
+                                .unwrap_or_else(|_| warn!("Receiver disconnected"));
+                            return;
+                        };
+                        Some((prompt, template.stop_token_ids(tokenizer)))
+                    }
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+        let (fim_prompt, fim_stop_toks) = fim.unzip();
+        let is_fim = fim_prompt.is_some();
         let mut added_seq = false;
 
         let (mut prompt_tokens, prompt_text) = match request.messages {
@@ -236,7 +293,7 @@ impl Engine {
                     return;
                 };
                 let prompt = tokenizer
-                    .encode_fast(text.clone(), true)
+                    .encode_fast(fim_prompt.unwrap_or_else(|| text.clone()), true)
                     .map_err(anyhow::Error::msg);
                 (
                     handle_seq_error!(prompt, request.response)
@@ -246,7 +303,7 @@ impl Engine {
                 )
             }
             RequestMessage::ImageGeneration { prompt, .. }
-            | RequestMessage::SpeechGeneration { prompt } => (vec![u32::MAX], prompt),
+            | RequestMessage::SpeechGeneration { prompt, .. } => (vec![u32::MAX], prompt),
             RequestMessage::CompletionTokens(it)
             | RequestMessage::EmbeddingTokens { prompt: it } => {
                 let Some(tokenizer) = &get_mut_arcmutex!(self.pipeline).tokenizer() else {
@@ -340,7 +397,7 @@ impl Engine {
             .get_metadata()
             .num_hidden_layers;
 
-        let (stop_toks, stop_strings) = match request.sampling_params.stop_toks {
+        let (mut stop_toks, stop_strings) = match request.sampling_params.stop_toks {
             None => (vec![], vec![]),
             Some(StopTokens::Ids(ref i)) => {
                 let tok_env = {
@@ -411,15 +468,35 @@ impl Engine {
                 (stop_toks, stop_strings)
             }
         };
+        stop_toks.extend(fim_stop_toks.unwrap_or_default());
+
+        let tokenizer = get_mut_arcmutex!(self.pipeline).tokenizer();
+
+        // Tokenized once and tracked separately by each choice.
+        let prediction_toks = match (&request.prediction, &tokenizer) {
+            (Some(prediction), Some(tokenizer)) => {
+                let encoding = tokenizer
+                    .encode_fast(prediction.as_str(), false)
+                    .map_err(anyhow::Error::msg);
+                Some(
+                    handle_seq_error!(encoding, request.response)
+                        .get_ids()
+                        .to_vec(),
+                )
+            }
+            _ => None,
+        };
 
-        let group = Arc::new(tokio::sync::Mutex::new(SequenceGroup::new(
+        let mut group = SequenceGroup::new(
             request.sampling_params.n_choices,
             request.is_streaming,
             is_chat,
             best_of,
-        )));
-
-        let tokenizer = get_mut_arcmutex!(self.pipeline).tokenizer();
+        );
+        if prediction_toks.is_some() {
+            group.prediction_tokens = Some(Default::default());
+        }
+        let group = Arc::new(tokio::sync::Mutex::new(group));
 
         let sampler = Sampler::new(
             Some(request.sampling_params.temperature.unwrap_or(1.0)),
@@ -594,7 +671,7 @@ impl Engine {
                 response_index,
                 now.as_secs(),
                 recognizer,
-                request.suffix.clone(),
+                request.suffix.clone().filter(|_| !is_fim),
                 if echo_prompt {
                     Some(prompt_text.clone())
                 } else {
@@ -617,6 +694,17 @@ impl Engine {
                 request.return_raw_logits,
                 eos_toks,
             );
+            seq.set_system_fingerprint(self.system_fingerprint.clone());
+            seq.set_priority(request.priority);
+            seq.set_deadline(deadline);
+            seq.set_speech_reference(speech_reference.clone());
+            seq.set_prediction(prediction_toks.clone().map(PredictedOutput::new));
+            seq.set_trace(SequenceTrace::new(request.trace_context, request.id));
+            seq.set_audit(audit.clone().map(SequenceAudit::new));
+            if let Some(seed) = request.sampling_params.seed {
+                // Offset by the choice index so `n > 1` still gives distinct samples.
+                seq.seed_rng(seed.wrapping_add(response_index as u64));
+            }
 
             // Only "track" a new sequence if it is a traditional one
             if matches!(seq_step_type, SeqStepType::PromptAndDecode) {
@@ -0,0 +1,266 @@
+//! Per-model admission control, applied before a request is queued on an engine.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use super::{agentic_loop::AGENTIC_LOOP_REENTRY_SENTINEL, EngineMetrics};
+use crate::{request::NormalRequest, Request, RequestMessage};
+
+/// Bounds on how much work one model may have queued. `None` disables a limit.
+///
+/// Requests over a limit are turned away by `MistralRs::send_request` with
+/// `MistralRsError::Overloaded` instead of joining the scheduler's waiting list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AdmissionLimits {
+    /// Sequences waiting to be scheduled, counting requests the engine has not picked up yet.
+    pub max_waiting_sequences: Option<usize>,
+    /// Prompt tokens summed over the waiting sequences.
+    pub max_queued_prompt_tokens: Option<usize>,
+    /// Agentic tool loops admitted at once, counting ones the engine has not started yet. Only
+    /// requests that start a loop are checked.
+    pub max_agentic_loops: Option<usize>,
+}
+
+/// Snapshot of one model's queues.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct QueueDepth {
+    /// Requests sent to the engine but not yet picked up.
+    pub pending_requests: usize,
+    pub waiting_sequences: usize,
+    pub running_sequences: usize,
+    pub queued_prompt_tokens: usize,
+    pub agentic_loops: usize,
+}
+
+impl QueueDepth {
+    pub(crate) fn new(metrics: &EngineMetrics, pending_requests: usize) -> Self {
+        Self {
+            pending_requests,
+            waiting_sequences: metrics.num_waiting,
+            running_sequences: metrics.num_running,
+            queued_prompt_tokens: metrics.waiting_prompt_tokens,
+            agentic_loops: metrics.agentic_loops,
+        }
+    }
+}
+
+impl AdmissionLimits {
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The reason to turn `request` away at the given queue depth, if any. An admitted agentic
+    /// request takes a slot in `agentic_loops`, carried on the request until its loop ends.
+    pub(crate) fn rejection(
+        &self,
+        request: &mut Request,
+        depth: &QueueDepth,
+        agentic_loops: &AgenticLoopCount,
+    ) -> Option<String> {
+        let Request::Normal(request) = request else {
+            return None;
+        };
+        if let Some(max) = self.max_waiting_sequences {
+            let waiting = depth.waiting_sequences + depth.pending_requests;
+            if waiting >= max {
+                return Some(format!(
+                    "{waiting} sequences are waiting (limit {max}); retry later"
+                ));
+            }
+        }
+        if let Some(max) = self.max_queued_prompt_tokens {
+            if depth.queued_prompt_tokens >= max {
+                return Some(format!(
+                    "{} prompt tokens are queued (limit {max}); retry later",
+                    depth.queued_prompt_tokens
+                ));
+            }
+        }
+        if let Some(max) = self.max_agentic_loops {
+            if starts_agentic_loop(request) {
+                match agentic_loops.try_acquire(max) {
+                    Some(permit) => request.agentic_permit = Some(permit),
+                    None => {
+                        return Some(format!(
+                            "{max} agentic loops are in flight (limit {max}); retry later"
+                        ))
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Agentic requests admitted to one model whose loop has not finished. Counting from admission
+/// rather than from loop start keeps a burst of requests from overshooting the limit.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AgenticLoopCount(Arc<AtomicUsize>);
+
+impl AgenticLoopCount {
+    /// Take a slot unless `max` are taken.
+    fn try_acquire(&self, max: usize) -> Option<AgenticLoopPermit> {
+        self.0
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .ok()?;
+        Some(AgenticLoopPermit(Arc::new(PermitRelease(Arc::clone(
+            &self.0,
+        )))))
+    }
+
+    #[cfg(test)]
+    fn get(&self) -> usize {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+/// An admitted agentic request's slot in its model's loop count. Released once the request and
+/// every clone of the permit are dropped, which the agentic loop does when it ends.
+#[derive(Clone, Debug)]
+pub struct AgenticLoopPermit(Arc<PermitRelease>);
+
+#[derive(Debug)]
+struct PermitRelease(Arc<AtomicUsize>);
+
+impl Drop for PermitRelease {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Whether the engine will run `request` through the agentic loop. Requests whose only agentic
+/// surface is a server-side tool callback are not detected here.
+fn starts_agentic_loop(request: &NormalRequest) -> bool {
+    let is_chat = matches!(
+        request.messages,
+        RequestMessage::Chat { .. } | RequestMessage::MultimodalChat { .. }
+    );
+    let max_tool_rounds = request.max_tool_rounds.is_some()
+        && request.max_tool_rounds != AGENTIC_LOOP_REENTRY_SENTINEL;
+    is_chat
+        && (max_tool_rounds
+            || request.web_search_options.is_some()
+            || request.enable_code_execution
+            || request.tool_dispatch_url.is_some())
+}
+
+/// How long a rejected client should wait: about the time for one running sequence to finish,
+/// judged from the mean request latency so far. Between 1 and 60 seconds.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+pub(crate) fn retry_after(metrics: &EngineMetrics) -> Duration {
+    let latency = &metrics.request_latency_seconds;
+    let secs = if latency.count == 0 {
+        1.0
+    } else {
+        latency.sum / latency.count as f64 / metrics.num_running.max(1) as f64
+    };
+    Duration::from_secs(secs.ceil().clamp(1.0, 60.0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn depth(waiting: usize, pending: usize, tokens: usize) -> QueueDepth {
+        QueueDepth {
+            pending_requests: pending,
+            waiting_sequences: waiting,
+            running_sequences: 0,
+            queued_prompt_tokens: tokens,
+            agentic_loops: 0,
+        }
+    }
+
+    #[test]
+    fn limits_count_pending_requests() {
+        let limits = AdmissionLimits {
+            max_waiting_sequences: Some(4),
+            ..Default::default()
+        };
+        let mut request = Request::Normal(Box::new(NormalRequest::new_simple(
+            RequestMessage::Completion {
+                text: "hi".to_string(),
+                echo_prompt: false,
+                best_of: None,
+                fim_mode: crate::FimMode::Psm,
+            },
+            crate::SamplingParams::deterministic(),
+            tokio::sync::mpsc::channel(1).0,
+            0,
+            None,
+            None,
+        )));
+
+        let loops = AgenticLoopCount::default();
+
+        assert!(limits
+            .rejection(&mut request, &depth(3, 0, 0), &loops)
+            .is_none());
+        assert!(limits
+            .rejection(&mut request, &depth(3, 1, 0), &loops)
+            .is_some());
+        // Agentic limits do not apply to plain completions.
+        let limits = AdmissionLimits {
+            max_agentic_loops: Some(1),
+            ..Default::default()
+        };
+        assert!(limits
+            .rejection(&mut request, &depth(0, 0, 0), &loops)
+            .is_none());
+        assert!(limits
+            .rejection(&mut request, &depth(0, 0, 0), &loops)
+            .is_none());
+        assert_eq!(loops.get(), 0);
+    }
+
+    #[test]
+    fn agentic_limit_counts_admitted_requests() {
+        let limits = AdmissionLimits {
+            max_agentic_loops: Some(1),
+            ..Default::default()
+        };
+        let mut request = NormalRequest::new_simple(
+            RequestMessage::Chat {
+                messages: Vec::new(),
+                enable_thinking: None,
+                reasoning_effort: None,
+            },
+            crate::SamplingParams::deterministic(),
+            tokio::sync::mpsc::channel(1).0,
+            0,
+            None,
+            None,
+        );
+        request.enable_code_execution = true;
+        let request = Request::Normal(Box::new(request));
+        let loops = AgenticLoopCount::default();
+
+        // Other pending requests don't count against the agentic limit.
+        let mut first = request.clone();
+        assert!(limits
+            .rejection(&mut first, &depth(0, 8, 0), &loops)
+            .is_none());
+        // The first request holds the only slot even before the engine starts its loop.
+        let mut second = request.clone();
+        assert!(limits
+            .rejection(&mut second, &depth(0, 0, 0), &loops)
+            .is_some());
+        assert_eq!(loops.get(), 1);
+
+        drop(first);
+        assert_eq!(loops.get(), 0);
+        assert!(limits
+            .rejection(&mut second, &depth(0, 0, 0), &loops)
+            .is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 use either::Either;
 use image::DynamicImage;
@@ -7,21 +7,24 @@ use indexmap::IndexMap;
 use serde_json::Value;
 
 use crate::{
+    audit::AuditScope,
     files::{
-        compose_tool_response_with_files, merge_required_outputs_into_args,
+        compose_tool_response_with_files, file_to_tool_file, merge_required_outputs_into_args,
         required_files_tool_addendum, tool_file_to_file, File, RequestedFile,
     },
     get_mut_arcmutex,
     pipeline::SupportedModality,
     response::{AgenticToolCallData, AgenticToolCallPhase},
-    search, AgentPermission, AgentToolApproval, AgentToolApprovalCallback,
-    AgentToolApprovalDecision, AgentToolApprovalHandler, AgentToolKind, AgentToolMetadata,
-    AgentToolSource, MessageContent, NormalRequest, RequestMessage, Response, ToolCallResponse,
-    ToolChoice, WebSearchOptions,
+    search,
+    telemetry::{TraceContext, TraceSpan},
+    AgentPermission, AgentToolApproval, AgentToolApprovalCallback, AgentToolApprovalDecision,
+    AgentToolApprovalHandler, AgentToolKind, AgentToolMetadata, AgentToolSource, MessageContent,
+    NormalRequest, RequestMessage, Response, ToolCallResponse, ToolCancellation, ToolChoice,
+    WebSearchOptions,
 };
 
 use super::file_tools::{do_list_files, do_read_file};
-use super::Engine;
+use super::{Engine, IntervalLogger};
 
 /// Default cap on tool-use rounds when the request doesn't set one.
 pub const DEFAULT_MAX_TOOL_ROUNDS: usize = 256;
@@ -228,7 +231,8 @@ fn save_session(engine: &Arc<Engine>, session_id: &str, visible_req: &NormalRequ
         RequestMessage::MultimodalChat { images, videos, .. } => (images.clone(), videos.clone()),
         _ => (Vec::new(), Vec::new()),
     };
-    let entry = super::agentic_session::AgenticSessionEntry::new(messages, images, videos);
+    let entry = super::agentic_session::AgenticSessionEntry::new(messages, images, videos)
+        .with_owner(visible_req.user.clone());
     engine
         .session_store
         .lock()
@@ -392,6 +396,27 @@ async fn approve_agent_tool(
     ctx: &DispatchCtx<'_>,
     tc: &ToolCallResponse,
     round: usize,
+) -> AgentToolApprovalDecision {
+    let decision = agent_tool_decision(ctx, tc, round).await;
+    // Under `Auto` nothing was decided, so only the tool call itself is recorded.
+    if let Some(audit) = ctx.audit.as_ref() {
+        if ctx.agent_permission != AgentPermission::Auto {
+            audit.approval(
+                round,
+                &tc.function.name,
+                &tc.function.arguments,
+                decision.approve,
+                decision.message.as_deref(),
+            );
+        }
+    }
+    decision
+}
+
+async fn agent_tool_decision(
+    ctx: &DispatchCtx<'_>,
+    tc: &ToolCallResponse,
+    round: usize,
 ) -> AgentToolApprovalDecision {
     let tool = tool_metadata_for(ctx, tc);
     let message = match ctx.agent_permission {
@@ -493,6 +518,9 @@ struct DispatchCtx<'a> {
     required_files: &'a [RequestedFile],
     agent_permission: AgentPermission,
     agent_approval_handler: Option<AgentToolApprovalHandler>,
+    /// Context of the loop's span; inner requests and tool calls are recorded under it.
+    trace_context: Option<TraceContext>,
+    audit: Option<AuditScope>,
 }
 
 fn web_search_metadata(content: &str) -> (Option<usize>, Vec<String>) {
@@ -598,6 +626,7 @@ async fn do_custom_tool(
     mut request: NormalRequest,
     tc: &ToolCallResponse,
     round: usize,
+    trace_context: Option<TraceContext>,
 ) -> (NormalRequest, AgenticToolCallData, Vec<File>) {
     let messages = get_messages_mut(&mut request);
     append_assistant_tool_call(messages, tc);
@@ -612,11 +641,15 @@ async fn do_custom_tool(
             tc
         };
 
+    let traceparent = trace_context.map(|context| context.traceparent());
     let mut tool_call_ctx;
-    let dispatch_tool_ctx = if is_code_exec_tool(&tc.function.name) {
+    let dispatch_tool_ctx = if is_code_exec_tool(&tc.function.name) || traceparent.is_some() {
         tool_call_ctx = ctx.tool_call_ctx.clone();
-        tool_call_ctx.round = Some(round);
-        tool_call_ctx.tool_name = Some(tc.function.name.clone());
+        if is_code_exec_tool(&tc.function.name) {
+            tool_call_ctx.round = Some(round);
+            tool_call_ctx.tool_name = Some(tc.function.name.clone());
+        }
+        tool_call_ctx.traceparent = traceparent;
         &tool_call_ctx
     } else {
         ctx.tool_call_ctx
@@ -724,6 +757,46 @@ async fn dispatch_tool(
     visible_req: NormalRequest,
     tc: &ToolCallResponse,
     round: usize,
+) -> Option<(NormalRequest, AgenticToolCallData, Vec<File>)> {
+    let name = &tc.function.name;
+    let mut span = TraceSpan::start(format!("execute_tool {name}"), ctx.trace_context);
+    span.set_attribute("gen_ai.operation.name", "execute_tool");
+    span.set_attribute("gen_ai.tool.name", name.as_str());
+    span.set_attribute(
+        "mistralrs.tool.kind",
+        match tool_metadata_for(ctx, tc).kind {
+            AgentToolKind::CodeExecution => "code_execution",
+            AgentToolKind::WebSearch => "web_search",
+            AgentToolKind::File => "file",
+            AgentToolKind::Custom => "custom",
+            AgentToolKind::External => "external",
+        },
+    );
+    span.set_attribute("mistralrs.tool.round", round);
+    let started = Instant::now();
+    let outcome = run_tool(ctx, visible_req, tc, round, span.context()).await;
+    if let Some(audit) = ctx.audit.as_ref() {
+        audit.tool_call(
+            round,
+            name,
+            &tc.function.arguments,
+            if outcome.is_some() {
+                "completed"
+            } else {
+                "unhandled"
+            },
+            started.elapsed(),
+        );
+    }
+    outcome
+}
+
+async fn run_tool(
+    ctx: &DispatchCtx<'_>,
+    visible_req: NormalRequest,
+    tc: &ToolCallResponse,
+    round: usize,
+    trace_context: Option<TraceContext>,
 ) -> Option<(NormalRequest, AgenticToolCallData, Vec<File>)> {
     let name = &tc.function.name;
     if is_read_file_tool(name) {
@@ -746,7 +819,7 @@ async fn dispatch_tool(
         });
     }
     if ctx.engine.tool_callbacks.contains_key(name) {
-        return Some(do_custom_tool(ctx, visible_req, tc, round).await);
+        return Some(do_custom_tool(ctx, visible_req, tc, round, trace_context).await);
     }
     if let Some(url) = ctx.dispatch_url {
         return Some(do_http_tool(visible_req, tc, url));
@@ -754,20 +827,76 @@ async fn dispatch_tool(
     None
 }
 
-/// Store full file bodies and emit wire-elided clones on the user channel. Truncated bodies stay fetchable via the store.
+/// Store full file bodies, owned by `owner`, and emit wire-elided clones on the user channel. Truncated bodies stay fetchable via the store.
 async fn emit_files(
     engine: &Engine,
     session_id: &str,
+    owner: Option<&str>,
     files: Vec<File>,
     sender: &tokio::sync::mpsc::Sender<Response>,
 ) {
-    for f in files {
+    for mut f in files {
+        f.owner = owner.map(str::to_string);
         let wire = f.elide_for_wire();
         engine.file_store.insert(f, Some(session_id.to_string()));
         let _ = sender.send(Response::File(wire)).await;
     }
 }
 
+/// Records the number of completed tool rounds and releases the running-loop gauge when the loop
+/// task exits, whichever path it takes.
+struct ToolRoundsMetric<'a> {
+    logger: &'a IntervalLogger,
+    rounds: usize,
+}
+
+impl<'a> ToolRoundsMetric<'a> {
+    /// Counts the loop as running until dropped.
+    fn start(logger: &'a IntervalLogger) -> Self {
+        logger.begin_agentic_loop();
+        Self { logger, rounds: 0 }
+    }
+}
+
+impl Drop for ToolRoundsMetric<'_> {
+    fn drop(&mut self) {
+        self.logger.observe_agentic_tool_rounds(self.rounds);
+        self.logger.end_agentic_loop();
+    }
+}
+
+/// Cancels in-flight tool calls once the client disconnects or the request deadline passes. The
+/// watcher task is aborted when the loop exits.
+struct AbandonWatch(tokio::task::JoinHandle<()>);
+
+impl AbandonWatch {
+    fn start(
+        user_sender: tokio::sync::mpsc::Sender<Response>,
+        deadline: Option<Instant>,
+        cancellation: ToolCancellation,
+    ) -> Self {
+        Self(tokio::spawn(async move {
+            let expired = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                () = user_sender.closed() => {}
+                () = expired => {}
+            }
+            cancellation.cancel();
+        }))
+    }
+}
+
+impl Drop for AbandonWatch {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
 /// Drive tool-use rounds (search, code exec, custom tools) without recursion. Forwards every reply except the first probe.
 pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest) {
     let web_search_options = request.web_search_options.clone();
@@ -780,26 +909,54 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
     let required_files: Vec<RequestedFile> = request.files.clone().unwrap_or_default();
 
     let run_id: String = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+    let owner = request.user.clone();
+    // Held by the loop task, so the admission slot is released when the loop ends and the
+    // inner rounds don't carry it.
+    let agentic_permit = request.agentic_permit.take();
 
     let mut session_id = request
         .session_id
         .clone()
         .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
-    {
-        let mut store = this.session_store.lock().unwrap();
-        let existing = if request.session_id.is_some() {
-            store.get(&session_id).map(|e| (session_id.clone(), e))
-        } else {
-            let msgs = get_messages(&request);
-            store.find_by_messages(msgs)
-        };
-        if let Some((matched_id, entry)) = existing {
+    let existing = if request.session_id.is_some() {
+        // A miss reads the storage backend, so it runs on a blocking thread.
+        let store = Arc::clone(&this.session_store);
+        let id = session_id.clone();
+        tokio::task::spawn_blocking(move || {
+            super::agentic_session::AgenticSessionStore::get(&store, &id)
+        })
+        .await
+        .ok()
+        .flatten()
+        .map(|e| (session_id.clone(), e))
+    } else {
+        let msgs = get_messages(&request);
+        this.session_store
+            .lock()
+            .unwrap()
+            .find_by_messages(msgs, owner.as_deref())
+    };
+    match existing {
+        Some((matched_id, entry)) if entry.accessible_by(owner.as_deref()) => {
             session_id = matched_id;
             super::agentic_session::splice_session_into_request(&mut request, &entry);
         }
+        // Another key's session: start a fresh one instead of reading or overwriting it.
+        Some(_) => session_id = uuid::Uuid::new_v4().to_string(),
+        None => {}
+    }
+    // Inner rounds carry the resolved session so their audit records are attributed to it.
+    request.session_id = Some(session_id.clone());
+    let audit = this.audit_scope(&request);
+    if let Some(audit) = &audit {
+        audit.request(&request);
     }
 
+    let input_files: Vec<File> = request.input_files.take().unwrap_or_default();
+    for f in &input_files {
+        this.file_store.attach_to_session(f, &session_id);
+    }
     this.file_store.touch_session(&session_id);
 
     let turn = count_user_messages(&request);
@@ -813,6 +970,9 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
 
     let user_sender = request.response.clone();
     let is_streaming = request.is_streaming;
+    // The deadline covers every round, so each inner request gets whatever time is left.
+    let deadline = request.timeout.map(|timeout| Instant::now() + timeout);
+    let trace_context = request.trace_context;
 
     let mut probe = request.clone();
     if let Some(ref opts) = web_search_options {
@@ -871,6 +1031,11 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
 
     let this_clone = this.clone();
     let handle = tokio::spawn(async move {
+        let mut loop_span = TraceSpan::start("agentic_loop", trace_context);
+        loop_span.set_attribute("gen_ai.conversation.id", session_id.clone());
+        let cancellation = ToolCancellation::default();
+        let _abandon_watch =
+            AbandonWatch::start(user_sender.clone(), deadline, cancellation.clone());
         let tool_call_ctx = mistralrs_mcp::ToolCallContext {
             session_id: Some(session_id.clone()),
             round: None,
@@ -879,6 +1044,9 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
             agent_approval_notifier,
             code_execution_permission,
             code_execution_approval_notifier,
+            input_files: input_files.iter().map(file_to_tool_file).collect(),
+            cancellation: cancellation.clone(),
+            traceparent: None,
         };
         let dispatch_ctx = DispatchCtx {
             engine: &this_clone,
@@ -893,11 +1061,15 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
             required_files: &required_files,
             agent_permission,
             agent_approval_handler,
+            trace_context: loop_span.context(),
+            audit,
         };
 
         let mut current = probe;
         let max_rounds = current.max_tool_rounds.unwrap_or(DEFAULT_MAX_TOOL_ROUNDS);
         let mut round = 0;
+        let mut rounds_metric = ToolRoundsMetric::start(&this_clone.logger);
+        let _agentic_permit = agentic_permit;
 
         loop {
             let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
@@ -910,13 +1082,21 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
             current.max_tool_rounds = AGENTIC_LOOP_REENTRY_SENTINEL;
             current.tool_dispatch_url = None;
             current.files = None;
+            current.timeout =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            current.trace_context = dispatch_ctx.trace_context;
             let _ = this_clone
                 .tx
                 .send(crate::request::Request::Normal(Box::new(current)))
                 .await;
 
             if !is_streaming {
-                let Some(resp) = receiver.recv().await else {
+                // Returning drops `receiver`, so the engine cancels the inner sequence too.
+                let resp = tokio::select! {
+                    resp = receiver.recv() => resp,
+                    () = user_sender.closed() => return,
+                };
+                let Some(resp) = resp else {
                     tracing::warn!("Engine closed without sending a response.");
                     return;
                 };
@@ -944,7 +1124,7 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
                     _ => None,
                 };
 
-                if tc_opt.is_none() || round >= max_rounds {
+                if tc_opt.is_none() || round >= max_rounds || cancellation.is_canceled() {
                     save_session(&this_clone, &session_id, &visible_req);
                     let mut final_resp = done.clone();
                     final_resp.session_id = Some(session_id.clone());
@@ -975,6 +1155,9 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
                             .unwrap_or_else(|| "Agent action was denied.".to_string()),
                     ))
                 };
+                if user_sender.is_closed() {
+                    return;
+                }
                 let Some((next_visible, complete_data, files)) = outcome else {
                     save_session(&this_clone, &session_id, &visible_req);
                     let mut final_resp = done.clone();
@@ -983,7 +1166,14 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
                     return;
                 };
 
-                emit_files(&this_clone, &session_id, files, &user_sender).await;
+                emit_files(
+                    &this_clone,
+                    &session_id,
+                    owner.as_deref(),
+                    files,
+                    &user_sender,
+                )
+                .await;
 
                 let _ = user_sender
                     .send(Response::AgenticToolCallProgress {
@@ -994,6 +1184,7 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
                     .await;
 
                 round += 1;
+                rounds_metric.rounds = round;
 
                 visible_req = next_visible.clone();
                 visible_req.response = user_sender.clone();
@@ -1003,7 +1194,14 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
                 let mut last_choice = None;
                 let mut held_final_chunk: Option<crate::ChatCompletionChunkResponse> = None;
 
-                while let Some(resp) = receiver.recv().await {
+                loop {
+                    let resp = tokio::select! {
+                        resp = receiver.recv() => resp,
+                        () = user_sender.closed() => return,
+                    };
+                    let Some(resp) = resp else {
+                        break;
+                    };
                     let Some(resp) = forward_passthrough(resp, &user_sender).await else {
                         return;
                     };
@@ -1051,7 +1249,7 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
                     _ => None,
                 };
 
-                if tc_opt.is_none() || round >= max_rounds {
+                if tc_opt.is_none() || round >= max_rounds || cancellation.is_canceled() {
                     save_session(&this_clone, &session_id, &visible_req);
                     if let Some(mut final_chunk) = held_final_chunk {
                         final_chunk.session_id = Some(session_id.clone());
@@ -1083,12 +1281,22 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
                             .unwrap_or_else(|| "Agent action was denied.".to_string()),
                     ))
                 };
+                if user_sender.is_closed() {
+                    return;
+                }
                 let Some((next_visible, complete_data, files)) = outcome else {
                     save_session(&this_clone, &session_id, &visible_req);
                     break;
                 };
 
-                emit_files(&this_clone, &session_id, files, &user_sender).await;
+                emit_files(
+                    &this_clone,
+                    &session_id,
+                    owner.as_deref(),
+                    files,
+                    &user_sender,
+                )
+                .await;
 
                 let _ = user_sender
                     .send(Response::AgenticToolCallProgress {
@@ -1099,6 +1307,7 @@ pub(super) async fn agentic_loop(this: Arc<Engine>, mut request: NormalRequest)
                     .await;
 
                 round += 1;
+                rounds_metric.rounds = round;
 
                 visible_req = next_visible.clone();
                 visible_req.response = user_sender.clone();
@@ -1,13 +1,17 @@
 use std::collections::HashMap;
-use std::io::Cursor;
-use std::time::{Duration, Instant};
+use std::fs;
+use std::io::{Cursor, ErrorKind, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use either::Either;
 use image::{DynamicImage, ImageFormat};
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 use crate::{MessageContent, NormalRequest, RequestMessage, VideoInput};
 
@@ -21,13 +25,132 @@ pub struct AgenticSessionEntry {
     /// Positional with `messages`.
     pub images: Vec<DynamicImage>,
     pub videos: Vec<VideoInput>,
+    /// Who the session belongs to (see [`NormalRequest::user`]). `None` for shared sessions.
+    pub owner: Option<String>,
     last_accessed: Instant,
 }
 
 /// Agentic conversation state, keyed by session ID. Also supports content-based matching for clients that don't pass an ID.
+///
+/// With a [`SessionStorage`] backend, saved sessions are written through and reloaded on a miss,
+/// so they outlive the in-memory TTL and engine restarts. Writes happen on a blocking thread
+/// after the store's lock is released; [`Self::get`], [`Self::delete`] and [`Self::list`] take
+/// the shared store and touch storage with its lock released. Agent action approvals stay in
+/// memory.
 pub struct AgenticSessionStore {
     sessions: HashMap<String, AgenticSessionEntry>,
     approved_agent_sessions: HashMap<String, Instant>,
+    storage: Option<Arc<dyn SessionStorage>>,
+    writer: Arc<SessionWriter>,
+}
+
+/// Sessions waiting to be written to storage. Only the newest snapshot of each session is kept,
+/// and a single writer drains them, so repeated saves coalesce and land in order.
+#[derive(Default)]
+struct SessionWriter {
+    pending: Mutex<PendingWrites>,
+    /// Held while touching storage, so a delete can't race an in-flight write.
+    io: Mutex<()>,
+}
+
+#[derive(Default)]
+struct PendingWrites {
+    entries: HashMap<String, AgenticSessionEntry>,
+    writing: bool,
+}
+
+impl SessionWriter {
+    /// Queue `entry` and return whether the caller should start the writer.
+    fn queue(&self, session_id: &str, entry: AgenticSessionEntry) -> bool {
+        let mut pending = self.pending.lock().unwrap();
+        pending.entries.insert(session_id.to_string(), entry);
+        !std::mem::replace(&mut pending.writing, true)
+    }
+
+    fn pending(&self, session_id: &str) -> Option<AgenticSessionEntry> {
+        self.pending
+            .lock()
+            .unwrap()
+            .entries
+            .get(session_id)
+            .cloned()
+    }
+
+    /// Write queued sessions until none are left.
+    fn drain(&self, storage: &dyn SessionStorage) {
+        loop {
+            let _io = self.io.lock().unwrap();
+            let (session_id, entry) = {
+                let mut pending = self.pending.lock().unwrap();
+                let Some(session_id) = pending.entries.keys().next().cloned() else {
+                    pending.writing = false;
+                    return;
+                };
+                let entry = pending.entries.remove(&session_id).unwrap();
+                (session_id, entry)
+            };
+            let result =
+                SerializedSession::from_entry(&entry).and_then(|s| storage.save(&session_id, &s));
+            if let Err(e) = result {
+                warn!("Failed to persist session `{session_id}`: {e}");
+            }
+        }
+    }
+
+    fn delete(&self, storage: &dyn SessionStorage, session_id: &str) -> Result<bool> {
+        let _io = self.io.lock().unwrap();
+        let pending = self
+            .pending
+            .lock()
+            .unwrap()
+            .entries
+            .remove(session_id)
+            .is_some();
+        Ok(storage.delete(session_id)? || pending)
+    }
+}
+
+/// The store's storage handles, taken out so I/O can run without holding the store's lock.
+#[derive(Clone)]
+struct SessionBackend {
+    storage: Arc<dyn SessionStorage>,
+    writer: Arc<SessionWriter>,
+}
+
+impl SessionBackend {
+    fn load(&self, session_id: &str) -> Option<AgenticSessionEntry> {
+        if let Some(entry) = self.writer.pending(session_id) {
+            return Some(entry);
+        }
+        let loaded = self
+            .storage
+            .load(session_id)
+            .and_then(|s| s.map(SerializedSession::into_entry).transpose());
+        match loaded {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Failed to load stored session `{session_id}`: {e}");
+                None
+            }
+        }
+    }
+
+    fn delete(&self, session_id: &str) -> bool {
+        match self.writer.delete(&*self.storage, session_id) {
+            Ok(stored) => stored,
+            Err(e) => {
+                warn!("Failed to delete stored session `{session_id}`: {e}");
+                false
+            }
+        }
+    }
+
+    fn list(&self) -> Vec<SessionInfo> {
+        self.storage.list().unwrap_or_else(|e| {
+            warn!("Failed to list stored sessions: {e}");
+            Vec::new()
+        })
+    }
 }
 
 impl Default for AgenticSessionStore {
@@ -38,9 +161,15 @@ impl Default for AgenticSessionStore {
 
 impl AgenticSessionStore {
     pub fn new() -> Self {
+        Self::with_storage(None)
+    }
+
+    pub fn with_storage(storage: Option<Arc<dyn SessionStorage>>) -> Self {
         Self {
             sessions: HashMap::new(),
             approved_agent_sessions: HashMap::new(),
+            storage,
+            writer: Arc::new(SessionWriter::default()),
         }
     }
 
@@ -59,17 +188,35 @@ impl AgenticSessionStore {
         true
     }
 
-    /// Updates `last_accessed`.
-    pub fn get(&mut self, session_id: &str) -> Option<AgenticSessionEntry> {
-        let entry = self.sessions.get_mut(session_id)?;
-        entry.last_accessed = Instant::now();
-        Some(entry.clone())
+    /// Updates `last_accessed`. Falls back to the storage backend on a miss, reading it with the
+    /// lock released. Storage is read on the calling thread, so async callers should run this on
+    /// a blocking one.
+    pub fn get(store: &Mutex<Self>, session_id: &str) -> Option<AgenticSessionEntry> {
+        let backend = {
+            let mut guard = store.lock().unwrap();
+            if let Some(entry) = guard.sessions.get_mut(session_id) {
+                entry.last_accessed = Instant::now();
+                return Some(entry.clone());
+            }
+            guard.backend()?
+        };
+        let entry = backend.load(session_id)?;
+        let mut guard = store.lock().unwrap();
+        // A save that landed while the lock was released is newer than what was loaded.
+        if let Some(current) = guard.sessions.get_mut(session_id) {
+            current.last_accessed = Instant::now();
+            return Some(current.clone());
+        }
+        guard.evict();
+        guard.sessions.insert(session_id.to_string(), entry.clone());
+        Some(entry)
     }
 
-    /// Find a stored session whose user-visible messages (no tool turns) are a prefix of `incoming`.
+    /// Find a session `owner` may use whose user-visible messages (no tool turns) are a prefix of `incoming`.
     pub fn find_by_messages(
         &mut self,
         incoming: &[IndexMap<String, MessageContent>],
+        owner: Option<&str>,
     ) -> Option<(String, AgenticSessionEntry)> {
         // Need at least 2 messages (system/user + assistant) to match meaningfully.
         if incoming.len() < 2 {
@@ -77,6 +224,9 @@ impl AgenticSessionStore {
         }
 
         for (id, entry) in &mut self.sessions {
+            if !entry.accessible_by(owner) {
+                continue;
+            }
             let stored_visible = user_visible_messages(&entry.messages);
             if stored_visible.len() > incoming.len() {
                 continue;
@@ -98,22 +248,54 @@ impl AgenticSessionStore {
 
     /// Save or update. Evicts stale entries if needed.
     pub fn save(&mut self, session_id: String, entry: AgenticSessionEntry) {
+        self.persist(&session_id, &entry);
         self.evict();
         self.sessions.insert(session_id, entry);
     }
 
-    /// Returns whether the session existed.
-    pub fn delete(&mut self, session_id: &str) -> bool {
-        self.approved_agent_sessions.remove(session_id);
-        self.sessions.remove(session_id).is_some()
+    /// Returns whether the session existed. The stored copy is removed with the lock released.
+    pub fn delete(store: &Mutex<Self>, session_id: &str) -> bool {
+        let (in_memory, backend) = {
+            let mut guard = store.lock().unwrap();
+            guard.approved_agent_sessions.remove(session_id);
+            (guard.sessions.remove(session_id).is_some(), guard.backend())
+        };
+        let stored = backend.is_some_and(|backend| backend.delete(session_id));
+        in_memory || stored
     }
 
-    pub fn list_ids(&self) -> Vec<String> {
-        self.sessions.keys().cloned().collect()
+    pub fn list_ids(store: &Mutex<Self>) -> Vec<String> {
+        Self::list(store).into_iter().map(|info| info.id).collect()
     }
 
-    pub fn export(&mut self, session_id: &str) -> Result<Option<SerializedSession>> {
-        let Some(entry) = self.get(session_id) else {
+    /// Sessions held in memory or by the storage backend, most recently updated first. Storage
+    /// is listed with the lock released.
+    pub fn list(store: &Mutex<Self>) -> Vec<SessionInfo> {
+        let backend = store.lock().unwrap().backend();
+        let stored = backend.map(|backend| backend.list()).unwrap_or_default();
+        let now = SystemTime::now();
+        let mut sessions: HashMap<String, SessionInfo> =
+            stored.into_iter().map(|s| (s.id.clone(), s)).collect();
+        let guard = store.lock().unwrap();
+        for (id, entry) in &guard.sessions {
+            let accessed = now
+                .checked_sub(entry.last_accessed.elapsed())
+                .map_or(0, unix_secs);
+            let info = sessions.entry(id.clone()).or_insert_with(|| SessionInfo {
+                id: id.clone(),
+                updated_at: 0,
+                owner: None,
+            });
+            info.updated_at = info.updated_at.max(accessed);
+            info.owner.clone_from(&entry.owner);
+        }
+        let mut sessions: Vec<SessionInfo> = sessions.into_values().collect();
+        sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then(a.id.cmp(&b.id)));
+        sessions
+    }
+
+    pub fn export(store: &Mutex<Self>, session_id: &str) -> Result<Option<SerializedSession>> {
+        let Some(entry) = Self::get(store, session_id) else {
             return Ok(None);
         };
         Ok(Some(SerializedSession::from_entry(&entry)?))
@@ -128,9 +310,8 @@ impl AgenticSessionStore {
 
     /// Clone the first `num_turns` complete turns of `src` into `dest`. A turn ends at the first
     /// `role: assistant` message that has no `tool_calls` field. Images and videos are copied as-is.
-    pub fn fork(&mut self, src: &str, dest: String, num_turns: usize) -> Result<()> {
-        let entry = self
-            .get(src)
+    pub fn fork(store: &Mutex<Self>, src: &str, dest: String, num_turns: usize) -> Result<()> {
+        let entry = Self::get(store, src)
             .ok_or_else(|| anyhow::anyhow!("source session {src} not found"))?;
 
         let mut turns_seen = 0;
@@ -155,12 +336,40 @@ impl AgenticSessionStore {
             Some(i) => entry.messages[..=i].to_vec(),
             None => entry.messages.clone(),
         };
-        let forked = AgenticSessionEntry::new(messages, entry.images.clone(), entry.videos.clone());
-        self.save(dest, forked);
+        let forked = AgenticSessionEntry::new(messages, entry.images.clone(), entry.videos.clone())
+            .with_owner(entry.owner.clone());
+        store.lock().unwrap().save(dest, forked);
         Ok(())
     }
 
-    /// Drop expired and over-limit entries.
+    fn backend(&self) -> Option<SessionBackend> {
+        Some(SessionBackend {
+            storage: Arc::clone(self.storage.as_ref()?),
+            writer: Arc::clone(&self.writer),
+        })
+    }
+
+    /// Write-through to the storage backend. Encoding and the write run on a blocking thread when
+    /// called inside a Tokio runtime. Failures are logged; the in-memory copy still serves.
+    fn persist(&self, session_id: &str, entry: &AgenticSessionEntry) {
+        let Some(storage) = &self.storage else {
+            return;
+        };
+        if !self.writer.queue(session_id, entry.clone()) {
+            return;
+        }
+        let writer = Arc::clone(&self.writer);
+        let storage = Arc::clone(storage);
+        let drain = move || writer.drain(&*storage);
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(drain);
+            }
+            Err(_) => drain(),
+        }
+    }
+
+    /// Drop expired and over-limit entries. Persisted copies are kept.
     fn evict(&mut self) {
         let now = Instant::now();
 
@@ -194,9 +403,20 @@ impl AgenticSessionEntry {
             messages,
             images,
             videos,
+            owner: None,
             last_accessed: Instant::now(),
         }
     }
+
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// Whether the sender named `user` may continue this session. Shared sessions are open to all.
+    pub fn accessible_by(&self, user: Option<&str>) -> bool {
+        self.owner.is_none() || self.owner.as_deref() == user
+    }
 }
 
 /// User-visible messages only, skipping tool call/response messages.
@@ -313,6 +533,8 @@ pub struct SerializedSession {
     #[serde(default)]
     #[cfg_attr(feature = "utoipa", schema(value_type = Vec<serde_json::Value>))]
     pub files: Vec<crate::files::File>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -343,6 +565,7 @@ impl SerializedSession {
             images,
             videos,
             files: Vec::new(),
+            owner: entry.owner.clone(),
         })
     }
 
@@ -363,6 +586,7 @@ impl SerializedSession {
             messages: self.messages,
             images,
             videos,
+            owner: self.owner,
             last_accessed: Instant::now(),
         })
     }
@@ -398,6 +622,173 @@ impl SerializedVideo {
     }
 }
 
+/// A session known to the store, as returned by `GET /v1/sessions`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct SessionInfo {
+    pub id: String,
+    /// Unix timestamp (seconds) of the last save or access.
+    pub updated_at: u64,
+    /// Name of the API key the session belongs to. Absent for shared sessions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+}
+
+/// Durable backend for [`AgenticSessionStore`]. Shared by every model's store.
+pub trait SessionStorage: Send + Sync {
+    fn load(&self, session_id: &str) -> Result<Option<SerializedSession>>;
+    fn save(&self, session_id: &str, session: &SerializedSession) -> Result<()>;
+    /// Returns whether the session existed.
+    fn delete(&self, session_id: &str) -> Result<bool>;
+    fn list(&self) -> Result<Vec<SessionInfo>>;
+}
+
+/// Stores each session as `<id>.json` in a directory.
+///
+/// Writes go to a temporary file that is synced and renamed into place, so a crash never leaves
+/// a truncated session behind. With a retention period, sessions not saved within it are removed.
+pub struct FileSessionStorage {
+    dir: PathBuf,
+    retention: Option<Duration>,
+}
+
+impl FileSessionStorage {
+    pub fn new(dir: impl Into<PathBuf>, retention: Option<Duration>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("creating session directory {}", dir.display()))?;
+        let storage = Self { dir, retention };
+        // Prune anything that expired while the server was down.
+        storage.entries()?;
+        Ok(storage)
+    }
+
+    fn path(&self, session_id: &str) -> Result<PathBuf> {
+        let valid = !session_id.is_empty()
+            && !session_id.starts_with('.')
+            && session_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            bail!("Invalid session ID `{session_id}`.");
+        }
+        Ok(self.dir.join(format!("{session_id}.json")))
+    }
+
+    fn expired(&self, modified: SystemTime) -> bool {
+        self.retention
+            .is_some_and(|retention| modified.elapsed().is_ok_and(|elapsed| elapsed > retention))
+    }
+
+    /// Stored sessions with their modification times, removing expired ones.
+    fn entries(&self) -> Result<Vec<(String, SystemTime)>> {
+        let mut entries = Vec::new();
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            let Some(id) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".json"))
+                .filter(|id| !id.starts_with('.'))
+            else {
+                continue;
+            };
+            let modified = fs::metadata(&path)?.modified()?;
+            if self.expired(modified) {
+                remove_if_exists(&path)?;
+                continue;
+            }
+            entries.push((id.to_string(), modified));
+        }
+        Ok(entries)
+    }
+}
+
+impl SessionStorage for FileSessionStorage {
+    fn load(&self, session_id: &str) -> Result<Option<SerializedSession>> {
+        let path = self.path(session_id)?;
+        let modified = match fs::metadata(&path) {
+            Ok(metadata) => metadata.modified()?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if self.expired(modified) {
+            remove_if_exists(&path)?;
+            return Ok(None);
+        }
+        let bytes = fs::read(&path)?;
+        let session = serde_json::from_slice(&bytes)
+            .with_context(|| format!("parsing stored session {}", path.display()))?;
+        Ok(Some(session))
+    }
+
+    fn save(&self, session_id: &str, session: &SerializedSession) -> Result<()> {
+        let path = self.path(session_id)?;
+        let tmp = self
+            .dir
+            .join(format!(".{session_id}.{}.tmp", uuid::Uuid::new_v4()));
+        let write = || -> Result<()> {
+            let mut file = fs::File::create(&tmp)?;
+            file.write_all(&serde_json::to_vec(session)?)?;
+            file.sync_all()?;
+            fs::rename(&tmp, &path)?;
+            Ok(())
+        };
+        let result = write();
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    fn delete(&self, session_id: &str) -> Result<bool> {
+        remove_if_exists(&self.path(session_id)?)
+    }
+
+    fn list(&self) -> Result<Vec<SessionInfo>> {
+        /// Only the owner is needed from each stored session.
+        #[derive(Deserialize)]
+        struct Owner {
+            #[serde(default)]
+            owner: Option<String>,
+        }
+
+        let mut sessions = Vec::new();
+        for (id, modified) in self.entries()? {
+            let path = self.dir.join(format!("{id}.json"));
+            let owner = match fs::read(&path) {
+                Ok(bytes) => {
+                    serde_json::from_slice::<Owner>(&bytes)
+                        .with_context(|| format!("parsing stored session {}", path.display()))?
+                        .owner
+                }
+                // Deleted since the directory was read.
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            sessions.push(SessionInfo {
+                id,
+                updated_at: unix_secs(modified),
+                owner,
+            });
+        }
+        Ok(sessions)
+    }
+}
+
+fn remove_if_exists(path: &std::path::Path) -> Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
 fn encode_png_base64(img: &DynamicImage) -> Result<String> {
     let mut buf = Vec::new();
     img.write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)
@@ -409,3 +800,73 @@ fn decode_png_base64(s: &str) -> Result<DynamicImage> {
     let bytes = BASE64.decode(s).context("base64 decoding image")?;
     image::load_from_memory(&bytes).context("loading image bytes")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_message(text: &str) -> IndexMap<String, MessageContent> {
+        IndexMap::from([
+            ("role".to_string(), Either::Left("user".to_string())),
+            ("content".to_string(), Either::Left(text.to_string())),
+        ])
+    }
+
+    #[test]
+    fn stored_sessions_survive_a_new_store() {
+        let dir = std::env::temp_dir().join(format!("mistralrs-sessions-{}", uuid::Uuid::new_v4()));
+        let storage: Arc<dyn SessionStorage> =
+            Arc::new(FileSessionStorage::new(&dir, None).unwrap());
+
+        let mut store = AgenticSessionStore::with_storage(Some(storage.clone()));
+        store.save(
+            "sess-1".to_string(),
+            AgenticSessionEntry::new(vec![user_message("hi")], Vec::new(), Vec::new()),
+        );
+        store.approve_agent_actions("sess-1");
+
+        let reloaded = Mutex::new(AgenticSessionStore::with_storage(Some(storage.clone())));
+        assert_eq!(
+            AgenticSessionStore::list_ids(&reloaded),
+            vec!["sess-1".to_string()]
+        );
+        let entry = AgenticSessionStore::get(&reloaded, "sess-1").unwrap();
+        assert_eq!(entry.messages, vec![user_message("hi")]);
+        assert!(!reloaded.lock().unwrap().agent_actions_approved("sess-1"));
+
+        assert!(AgenticSessionStore::delete(&reloaded, "sess-1"));
+        assert!(storage.load("sess-1").unwrap().is_none());
+        assert!(storage
+            .save("../escape", &SerializedSession::from_entry(&entry).unwrap())
+            .is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sessions_only_match_their_owner() {
+        let messages = vec![user_message("hi"), user_message("again")];
+        let mut store = AgenticSessionStore::new();
+        store.save(
+            "sess-a".to_string(),
+            AgenticSessionEntry::new(messages.clone(), Vec::new(), Vec::new())
+                .with_owner(Some("alice".to_string())),
+        );
+
+        assert!(store.find_by_messages(&messages, Some("bob")).is_none());
+        assert!(store.find_by_messages(&messages, None).is_none());
+        let (id, entry) = store.find_by_messages(&messages, Some("alice")).unwrap();
+        assert_eq!(id, "sess-a");
+        assert!(!entry.accessible_by(Some("bob")));
+
+        let store = Mutex::new(store);
+        assert_eq!(
+            AgenticSessionStore::list(&store)[0].owner.as_deref(),
+            Some("alice")
+        );
+        AgenticSessionStore::fork(&store, "sess-a", "sess-b".to_string(), 1).unwrap();
+        let forked = AgenticSessionStore::get(&store, "sess-b").unwrap();
+        assert!(forked.accessible_by(Some("alice")));
+        assert!(!forked.accessible_by(Some("bob")));
+    }
+}
@@ -1,12 +1,119 @@
 #![allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
 
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use tracing::info;
 
+use crate::sequence::Sequence;
+
+/// Bucket bounds (seconds) for time-to-first-token and end-to-end request latency.
+const REQUEST_LATENCY_BUCKETS: &[f64] = &[
+    0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 40.0, 80.0, 160.0,
+];
+/// Bucket bounds (seconds) for inter-token latency.
+const INTER_TOKEN_LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.02, 0.04, 0.06, 0.08, 0.1, 0.15, 0.25, 0.5, 1.0, 2.5,
+];
+/// Bucket bounds for the number of tool rounds in one agentic request.
+const TOOL_ROUND_BUCKETS: &[f64] = &[0.0, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0, 256.0];
+
+/// A fixed-bucket histogram that can be observed from any thread.
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// Per-bucket (non-cumulative) counts; the last slot is the `+Inf` bucket.
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    /// `f64` bits of the running sum.
+    sum: AtomicU64,
+}
+
+/// Point-in-time copy of a [`Histogram`] in Prometheus layout.
+#[derive(Debug, Clone)]
+pub struct HistogramSnapshot {
+    /// Upper bounds of the finite buckets.
+    pub bounds: Vec<f64>,
+    /// Cumulative counts for each bound in `bounds`. The `+Inf` bucket equals `count`.
+    pub cumulative_counts: Vec<u64>,
+    pub count: u64,
+    pub sum: f64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0f64.to_bits()),
+        }
+    }
+
+    pub fn observe(&self, value: f64) {
+        let idx = self
+            .bounds
+            .iter()
+            .position(|b| value <= *b)
+            .unwrap_or(self.bounds.len());
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let _ = self
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + value).to_bits())
+            });
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut running = 0;
+        let cumulative_counts = self.buckets[..self.bounds.len()]
+            .iter()
+            .map(|b| {
+                running += b.load(Ordering::Relaxed);
+                running
+            })
+            .collect();
+        HistogramSnapshot {
+            bounds: self.bounds.to_vec(),
+            cumulative_counts,
+            count: self.count.load(Ordering::Relaxed),
+            sum: f64::from_bits(self.sum.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn reset(&self) {
+        for b in &self.buckets {
+            b.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.sum.store(0f64.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Cumulative per-engine metrics, as exported by the server's `/metrics` route.
+#[derive(Debug, Clone)]
+pub struct EngineMetrics {
+    pub tokens_processed_total: usize,
+    pub sequences_total: usize,
+    pub prefix_cache_hits_total: usize,
+    pub num_running: usize,
+    pub num_waiting: usize,
+    /// Prompt tokens summed over the waiting sequences.
+    pub waiting_prompt_tokens: usize,
+    /// Agentic tool loops currently running.
+    pub agentic_loops: usize,
+    /// `(hits, misses)`, or `None` if the model has no encoder cache.
+    pub encoder_cache: Option<(usize, usize)>,
+    /// Paged KV cache occupancy in `[0, 1]`, or `None` without paged attention.
+    pub kv_cache_usage: Option<f64>,
+    pub time_to_first_token_seconds: HistogramSnapshot,
+    pub inter_token_latency_seconds: HistogramSnapshot,
+    pub request_latency_seconds: HistogramSnapshot,
+    pub agentic_tool_rounds: HistogramSnapshot,
+}
+
 pub struct IntervalLogger {
     enable_logging: Arc<AtomicBool>,
     prefix_cache_hits: Arc<AtomicUsize>,
@@ -14,8 +121,18 @@ pub struct IntervalLogger {
     total_new_seqs: Arc<AtomicUsize>,
     num_running: Arc<AtomicUsize>,
     num_waiting: Arc<AtomicUsize>,
+    waiting_prompt_tokens: AtomicUsize,
+    agentic_loops: AtomicUsize,
     encoder_cache_hits: Option<Arc<AtomicUsize>>,
     encoder_cache_misses: Option<Arc<AtomicUsize>>,
+    /// Never swapped to zero, unlike `tokens_processed`.
+    tokens_processed_total: AtomicUsize,
+    /// `f64` bits of the paged KV usage fraction; `u64::MAX` until the first paged schedule.
+    kv_cache_usage: AtomicU64,
+    time_to_first_token: Histogram,
+    inter_token_latency: Histogram,
+    request_latency: Histogram,
+    agentic_tool_rounds: Histogram,
 }
 
 impl IntervalLogger {
@@ -95,8 +212,16 @@ impl IntervalLogger {
             enable_logging,
             num_running,
             num_waiting,
+            waiting_prompt_tokens: AtomicUsize::new(0),
+            agentic_loops: AtomicUsize::new(0),
             encoder_cache_hits,
             encoder_cache_misses,
+            tokens_processed_total: AtomicUsize::new(0),
+            kv_cache_usage: AtomicU64::new(u64::MAX),
+            time_to_first_token: Histogram::new(REQUEST_LATENCY_BUCKETS),
+            inter_token_latency: Histogram::new(INTER_TOKEN_LATENCY_BUCKETS),
+            request_latency: Histogram::new(REQUEST_LATENCY_BUCKETS),
+            agentic_tool_rounds: Histogram::new(TOOL_ROUND_BUCKETS),
         }
     }
 
@@ -111,17 +236,85 @@ impl IntervalLogger {
         self.total_new_seqs.store(0, Ordering::Relaxed);
         self.num_running.store(0, Ordering::Relaxed);
         self.num_waiting.store(0, Ordering::Relaxed);
+        self.waiting_prompt_tokens.store(0, Ordering::Relaxed);
         if let Some(ref hits) = self.encoder_cache_hits {
             hits.store(0, Ordering::Relaxed);
         }
         if let Some(ref misses) = self.encoder_cache_misses {
             misses.store(0, Ordering::Relaxed);
         }
+        self.tokens_processed_total.store(0, Ordering::Relaxed);
+        self.time_to_first_token.reset();
+        self.inter_token_latency.reset();
+        self.request_latency.reset();
+        self.agentic_tool_rounds.reset();
     }
 
     pub fn add_tokens_processed(&self, num_tokens: usize) {
         self.tokens_processed
             .fetch_add(num_tokens, Ordering::Relaxed);
+        self.tokens_processed_total
+            .fetch_add(num_tokens, Ordering::Relaxed);
+    }
+
+    pub fn set_kv_cache_usage(&self, usage: f64) {
+        self.kv_cache_usage
+            .store(usage.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Record latencies for sequences that just finished a forward step.
+    ///
+    /// The first step after which a sequence holds a generated token records the time since
+    /// it was created as its TTFT, once; chunked prefill steps before that record nothing.
+    /// After a decode step each sequence gained one token, so the step time is its
+    /// inter-token latency. Finished sequences also record their end-to-end latency.
+    pub(crate) fn observe_step<'a>(
+        &self,
+        seqs: impl IntoIterator<Item = &'a Sequence>,
+        is_prompt: bool,
+        step_time: Duration,
+    ) {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time travel has occurred!")
+            .as_millis();
+        for seq in seqs {
+            let since_created = now_ms.saturating_sub(seq.timestamp()) as f64 / 1000.;
+            if seq.take_first_token() {
+                self.time_to_first_token.observe(since_created);
+            }
+            if !is_prompt {
+                self.inter_token_latency.observe(step_time.as_secs_f64());
+            }
+            if seq.is_finished_paged_attn() {
+                self.request_latency.observe(since_created);
+            }
+        }
+    }
+
+    /// Record how many tool rounds an agentic request ran before finishing.
+    pub fn observe_agentic_tool_rounds(&self, rounds: usize) {
+        self.agentic_tool_rounds.observe(rounds as f64);
+    }
+
+    /// Cumulative metrics for export.
+    pub fn metrics(&self) -> EngineMetrics {
+        let kv_bits = self.kv_cache_usage.load(Ordering::Relaxed);
+        EngineMetrics {
+            tokens_processed_total: self.tokens_processed_total.load(Ordering::Relaxed),
+            sequences_total: self.total_new_seqs.load(Ordering::Relaxed),
+            prefix_cache_hits_total: self.prefix_cache_hits.load(Ordering::Relaxed),
+            num_running: self.num_running.load(Ordering::Relaxed),
+            num_waiting: self.num_waiting.load(Ordering::Relaxed),
+            waiting_prompt_tokens: self.waiting_prompt_tokens.load(Ordering::Relaxed),
+            agentic_loops: self.agentic_loops.load(Ordering::Relaxed),
+            encoder_cache: self.encoder_cache_stats(),
+            kv_cache_usage: (kv_bits != u64::MAX).then(|| f64::from_bits(kv_bits)),
+            time_to_first_token_seconds: self.time_to_first_token.snapshot(),
+            inter_token_latency_seconds: self.inter_token_latency.snapshot(),
+            request_latency_seconds: self.request_latency.snapshot(),
+            agentic_tool_rounds: self.agentic_tool_rounds.snapshot(),
+        }
     }
 
     pub fn add_new_sequence(&self) {
@@ -140,6 +333,18 @@ impl IntervalLogger {
         self.num_waiting.store(waiting, Ordering::Relaxed);
     }
 
+    pub fn set_waiting_prompt_tokens(&self, tokens: usize) {
+        self.waiting_prompt_tokens.store(tokens, Ordering::Relaxed);
+    }
+
+    pub fn begin_agentic_loop(&self) {
+        self.agentic_loops.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn end_agentic_loop(&self) {
+        self.agentic_loops.fetch_sub(1, Ordering::Relaxed);
+    }
+
     /// Return cumulative prefix cache (hits, total_sequences).
     pub fn prefix_cache_stats(&self) -> (usize, usize) {
         (
@@ -156,3 +361,23 @@ impl IntervalLogger {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_snapshot_is_cumulative() {
+        let h = Histogram::new(&[1.0, 2.0, 4.0]);
+        for v in [0.5, 1.5, 1.7, 3.0, 10.0] {
+            h.observe(v);
+        }
+        let snap = h.snapshot();
+        assert_eq!(snap.cumulative_counts, vec![1, 3, 4]);
+        assert_eq!(snap.count, 5);
+        assert!((snap.sum - 16.7).abs() < 1e-9);
+
+        h.reset();
+        assert_eq!(h.snapshot().count, 0);
+    }
+}
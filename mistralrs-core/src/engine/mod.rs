@@ -1,4 +1,5 @@
 use crate::{
+    audit::{self, AuditLog, AuditScope},
     distributed,
     paged_attention::block_hash::compute_block_hashes,
     pipeline::{
@@ -11,11 +12,11 @@ use crate::{
     scheduler::{Scheduler, SchedulerOutput},
     search::{self, rag::SearchPipeline},
     sequence::{SeqStepType, StopReason},
-    tools, CompletionResponse, SchedulerConfig, DEBUG,
+    telemetry, tools, CompletionResponse, SchedulerConfig, DEBUG,
 };
 use interprocess::local_socket::{traits::Listener, ListenerOptions};
 use llguidance::ParserFactory;
-pub use logger::IntervalLogger;
+pub use logger::{EngineMetrics, HistogramSnapshot, IntervalLogger};
 use mistralrs_quant::RingConfig;
 use rand::SeedableRng;
 use rand_isaac::Isaac64Rng;
@@ -45,13 +46,16 @@ use tokio::{
 use crate::{
     get_mut_arcmutex, handle_pipeline_forward_error,
     pipeline::{ModelCategory, Pipeline},
-    request::Request,
+    request::{NormalRequest, Request},
     response::{ChatCompletionResponse, Choice, ResponseMessage},
     sequence::{SequenceRecognizer, SequenceState},
     Constraint,
 };
 
 mod add_request;
+mod admission;
+pub(crate) use admission::{retry_after, AgenticLoopCount};
+pub use admission::{AdmissionLimits, AgenticLoopPermit, QueueDepth};
 pub(crate) mod agentic_loop;
 pub use agentic_loop::DEFAULT_MAX_TOOL_ROUNDS;
 pub(crate) mod agentic_session;
@@ -174,8 +178,10 @@ pub struct Engine {
     logger: Arc<IntervalLogger>,
     handles: Arc<Mutex<Vec<JoinHandle<()>>>>,
     pending_notify: Arc<Notify>,
+    system_fingerprint: Arc<str>,
     pub(crate) session_store: Arc<std::sync::Mutex<agentic_session::AgenticSessionStore>>,
     pub(crate) file_store: crate::files::FileStore,
+    audit_log: Option<Arc<AuditLog>>,
 }
 
 impl Drop for Engine {
@@ -204,6 +210,7 @@ impl Engine {
         logger: Arc<IntervalLogger>,
         session_store: Arc<std::sync::Mutex<agentic_session::AgenticSessionStore>>,
         file_store: crate::files::FileStore,
+        audit_log: Option<Arc<AuditLog>>,
     ) -> anyhow::Result<Self> {
         no_kv_cache |= get_mut_arcmutex!(pipeline).get_metadata().no_kv_cache;
 
@@ -228,6 +235,16 @@ impl Engine {
 
         let has_paged_attention = get_mut_arcmutex!(scheduler).kv_cache_manager().is_some();
 
+        let system_fingerprint = {
+            let pipeline = get_mut_arcmutex!(pipeline);
+            crate::response::system_fingerprint(
+                &pipeline.name(),
+                &pipeline.device(),
+                pipeline.get_metadata().activation_dtype,
+            )
+            .into()
+        };
+
         Ok(Self {
             tx,
             rx: Arc::new(Mutex::new(rx)),
@@ -249,11 +266,20 @@ impl Engine {
             logger,
             handles: Arc::new(Mutex::new(Vec::new())),
             pending_notify: Arc::new(Notify::new()),
+            system_fingerprint,
             session_store,
             file_store,
+            audit_log,
         })
     }
 
+    /// Audit records for `request`, if an audit log is configured.
+    pub(crate) fn audit_scope(&self, request: &NormalRequest) -> Option<AuditScope> {
+        let log = self.audit_log.as_ref()?;
+        let model = get_mut_arcmutex!(self.pipeline).name();
+        Some(AuditScope::new(log.clone(), request, model))
+    }
+
     /// Returns the maximum supported sequence length for the underlying model, if applicable.
     #[allow(dead_code)]
     pub fn max_sequence_length(&self) -> Option<usize> {
@@ -416,7 +442,7 @@ impl Engine {
                                 .await
                         };
 
-                        handle_pipeline_forward_error!(
+                        let step_time = handle_pipeline_forward_error!(
                             "completion step",
                             res,
                             &mut scheduled.completion,
@@ -426,6 +452,17 @@ impl Engine {
                         );
 
                         self.logger.add_tokens_processed(scheduled.completion.len());
+                        self.logger.observe_step(
+                            scheduled.completion.iter().map(|seq| &**seq),
+                            false,
+                            step_time,
+                        );
+                        telemetry::record_step(
+                            scheduled.completion.iter().map(|seq| &**seq),
+                            false,
+                            step_time,
+                        );
+                        audit::record_step(scheduled.completion.iter().map(|seq| &**seq));
 
                         last_completion_ids = current_completion_ids;
                     }
@@ -503,6 +540,17 @@ impl Engine {
                             .map(|seq| seq.get_toks().len())
                             .sum();
                         self.logger.add_tokens_processed(total_processed_tokens);
+                        self.logger.observe_step(
+                            scheduled.prompt.iter().map(|seq| &**seq),
+                            true,
+                            prompt_exec_time,
+                        );
+                        telemetry::record_step(
+                            scheduled.prompt.iter().map(|seq| &**seq),
+                            true,
+                            prompt_exec_time,
+                        );
+                        audit::record_step(scheduled.prompt.iter().map(|seq| &**seq));
 
                         for seq in scheduled.prompt.iter_mut() {
                             match seq.sequence_stepping_type() {
@@ -711,7 +759,7 @@ impl Engine {
                             }
                         };
 
-                        handle_pipeline_forward_error!(
+                        let step_time = handle_pipeline_forward_error!(
                             "step",
                             res,
                             &mut guards_mut,
@@ -719,6 +767,17 @@ impl Engine {
                             'lp,
                             self.prefix_cacher
                         );
+                        self.logger.observe_step(
+                            guards_mut.iter().map(|seq| &**seq),
+                            is_prompt,
+                            step_time,
+                        );
+                        telemetry::record_step(
+                            guards_mut.iter().map(|seq| &**seq),
+                            is_prompt,
+                            step_time,
+                        );
+                        audit::record_step(guards_mut.iter().map(|seq| &**seq));
 
                         let total_processed_tokens: usize = guards_mut
                             .iter()
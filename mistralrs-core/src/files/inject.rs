@@ -46,6 +46,8 @@ pub fn tool_file_to_file(
             mime_type: Some(mime),
             bytes: 0,
             created_at,
+            purpose: None,
+            owner: None,
             source,
             content: FileContent::Error {
                 code: "not_produced".to_string(),
@@ -74,11 +76,31 @@ pub fn tool_file_to_file(
         mime_type: Some(mime),
         bytes: tf.size_bytes,
         created_at,
+        purpose: None,
+        owner: None,
         source,
         content,
     }
 }
 
+/// Convert a stored `File` back to a `ToolFile` so it can be handed to a tool as an input.
+pub fn file_to_tool_file(f: &File) -> ToolFile {
+    let (text, data_base64, error) = match &f.content {
+        FileContent::Text { text, .. } => (text.clone(), None, None),
+        FileContent::Binary { data_base64 } => (None, data_base64.clone(), None),
+        FileContent::Error { message, .. } => (None, None, Some(message.clone())),
+    };
+    ToolFile {
+        name: f.name.clone(),
+        format: f.format.clone().unwrap_or_default(),
+        mime_type: f.mime_type.clone(),
+        text,
+        data_base64,
+        size_bytes: f.bytes,
+        error,
+    }
+}
+
 /// Append a metadata-only `Files:` summary. Bodies are never inlined; the model fetches via `read_file(id)`.
 pub fn compose_tool_response_with_files(raw: &str, files: &[File]) -> String {
     if files.is_empty() {
@@ -191,6 +213,8 @@ mod tests {
             mime_type: Some("text/plain".into()),
             bytes: body.len() as u64,
             created_at: 0,
+            purpose: None,
+            owner: None,
             source: FileSource {
                 tool: "execute_python".into(),
                 round: 0,
@@ -10,11 +10,14 @@ use serde::{Deserialize, Serialize};
 mod inject;
 mod store;
 pub use inject::{
-    compose_tool_response_with_files, merge_required_outputs_into_args,
+    compose_tool_response_with_files, file_to_tool_file, merge_required_outputs_into_args,
     required_files_tool_addendum, tool_file_to_file,
 };
 pub use store::{FileStore, DEFAULT_FILE_TTL};
 
+/// `FileSource::tool` for files uploaded through `POST /v1/files`.
+pub const UPLOAD_SOURCE: &str = "upload";
+
 /// Max text bytes shown to the model inline. Above this it gets a preview + id and calls `read_file`.
 pub const MODEL_INLINE_BYTES: usize = 1024;
 
@@ -71,11 +74,11 @@ impl FileContent {
     }
 }
 
-/// A file produced by an agentic run.
+/// A file produced by an agentic run or uploaded through `POST /v1/files`.
 #[cfg_attr(feature = "pyo3_macros", pyclass)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct File {
-    /// `file_<run>_r<round>_<idx>`.
+    /// `file_<run>_r<round>_<idx>`, or `file_upload_<id>` for uploads.
     pub id: String,
     pub name: String,
     /// `csv`, `json`, `png`, `parquet`, etc. Inferred from the filename extension if not set.
@@ -87,6 +90,13 @@ pub struct File {
     /// Unix epoch seconds.
     #[serde(default)]
     pub created_at: u64,
+    /// OpenAI `purpose` given at upload time. `None` for files produced by the runtime.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    /// Name of the API key that uploaded the file, or sent the request or batch that produced it.
+    /// Other keys can't see owned files. `None` for files every caller may read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
     pub source: FileSource,
     #[serde(flatten)]
     pub content: FileContent,
@@ -97,8 +107,89 @@ impl File {
         format!("file_{run_id}_r{round}_{idx}")
     }
 
+    /// Build an uploaded file. The mime type comes from the declared `content_type` when it is
+    /// specific, else from the filename extension; bodies with a text mime type (or undeclared
+    /// bodies that are valid utf-8) are stored as text.
+    pub fn from_upload(
+        name: &str,
+        content_type: Option<&str>,
+        data: &[u8],
+        purpose: impl Into<String>,
+    ) -> File {
+        let id = format!(
+            "file_upload_{}",
+            &uuid::Uuid::new_v4().simple().to_string()[..12]
+        );
+        let format = format_from_name(name);
+        let declared = content_type
+            .map(|m| m.split(';').next().unwrap_or(m).trim().to_ascii_lowercase())
+            .filter(|m| !m.is_empty() && m != "application/octet-stream");
+        let mime = declared
+            .or_else(|| format.as_deref().map(mime_for_format))
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+
+        let text = if is_text_mime(&mime) || mime == "application/octet-stream" {
+            std::str::from_utf8(data).ok()
+        } else {
+            None
+        };
+        let (mime, content) = match text {
+            Some(t) => (
+                if mime == "application/octet-stream" {
+                    "text/plain".to_string()
+                } else {
+                    mime
+                },
+                FileContent::Text {
+                    text: Some(t.to_string()),
+                    preview: Some(Self::truncate_utf8(t, MODEL_INLINE_BYTES).to_string()),
+                },
+            ),
+            None => (
+                mime,
+                FileContent::Binary {
+                    data_base64: Some(base64::engine::general_purpose::STANDARD.encode(data)),
+                },
+            ),
+        };
+
+        File {
+            id,
+            name: name.to_string(),
+            format,
+            mime_type: Some(mime),
+            bytes: data.len() as u64,
+            created_at: Self::now_unix_secs(),
+            purpose: Some(purpose.into()),
+            owner: None,
+            source: FileSource {
+                tool: UPLOAD_SOURCE.to_string(),
+                round: 0,
+                turn: 0,
+            },
+            content,
+        }
+    }
+
+    /// Raw body bytes, decoding base64 for binary files. `None` if elided or an error placeholder.
+    pub fn body_bytes(&self) -> Option<Vec<u8>> {
+        match &self.content {
+            FileContent::Text { text: Some(t), .. } => Some(t.as_bytes().to_vec()),
+            FileContent::Binary {
+                data_base64: Some(b),
+            } => base64::engine::general_purpose::STANDARD.decode(b).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn is_audio(&self) -> bool {
+        self.mime_type
+            .as_deref()
+            .is_some_and(|m| m.to_ascii_lowercase().starts_with("audio/"))
+    }
+
     /// Slice `s` to at most `n` bytes on a UTF-8 char boundary.
-    pub(crate) fn truncate_utf8(s: &str, n: usize) -> &str {
+    pub fn truncate_utf8(s: &str, n: usize) -> &str {
         if s.len() <= n {
             return s;
         }
@@ -165,6 +256,8 @@ impl File {
             mime_type: self.mime_type.clone(),
             bytes: self.bytes,
             created_at: self.created_at,
+            purpose: self.purpose.clone(),
+            owner: self.owner.clone(),
             source: self.source.clone(),
             content,
         }
@@ -258,6 +351,11 @@ impl File {
         self.bytes
     }
 
+    #[getter]
+    fn purpose(&self) -> Option<&str> {
+        self.purpose.as_deref()
+    }
+
     #[getter]
     fn source(&self) -> FileSource {
         self.source.clone()
@@ -407,6 +505,8 @@ mod tests {
             mime_type: Some("text/plain".into()),
             bytes: body.len() as u64,
             created_at: 0,
+            purpose: None,
+            owner: None,
             source: FileSource {
                 tool: "execute_python".into(),
                 round: 0,
@@ -453,6 +553,8 @@ mod tests {
             mime_type: Some("application/octet-stream".into()),
             bytes: 64 * 1024 * 1024,
             created_at: 0,
+            purpose: None,
+            owner: None,
             source: FileSource {
                 tool: "execute_python".into(),
                 round: 0,
@@ -488,4 +590,27 @@ mod tests {
         assert!(!is_text_mime("image/png"));
         assert!(!is_text_mime("application/octet-stream"));
     }
+
+    #[test]
+    fn upload_sniffing() {
+        let csv = File::from_upload("data.csv", None, b"a,b\n1,2\n", "assistants");
+        assert!(csv.id.starts_with("file_upload_"));
+        assert_eq!(csv.mime_type.as_deref(), Some("text/csv"));
+        assert_eq!(csv.as_text(), Some("a,b\n1,2\n"));
+        assert_eq!(csv.purpose.as_deref(), Some("assistants"));
+        assert_eq!(csv.source.tool, UPLOAD_SOURCE);
+
+        // A specific declared content type wins over the extension.
+        let png = File::from_upload("blob", Some("image/png; q=1"), &[0x89, b'P'], "vision");
+        assert_eq!(png.mime_type.as_deref(), Some("image/png"));
+        assert!(png.is_image() && png.is_binary());
+        assert_eq!(png.body_bytes(), Some(vec![0x89, b'P']));
+
+        // Undeclared utf-8 becomes text; undeclared non-utf-8 stays opaque.
+        let notes = File::from_upload("notes", None, b"hello", "user_data");
+        assert_eq!(notes.mime_type.as_deref(), Some("text/plain"));
+        let blob = File::from_upload("blob.bin", None, &[0xff, 0xfe], "user_data");
+        assert_eq!(blob.mime_type.as_deref(), Some("application/octet-stream"));
+        assert!(blob.is_binary());
+    }
 }
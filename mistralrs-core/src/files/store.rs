@@ -53,6 +53,11 @@ impl FileStore {
 
     /// Evicts oldest entries to stay under `MAX_FILES`.
     pub fn insert(&self, file: File, session_id: Option<String>) {
+        self.insert_with_ttl(file, session_id, self.ttl);
+    }
+
+    /// Like [`Self::insert`], but with a per-entry TTL instead of the store default.
+    pub fn insert_with_ttl(&self, file: File, session_id: Option<String>, ttl: Duration) {
         let id = file.id.clone();
         let mut guard = self.inner.write().unwrap();
         let seq = guard.next_seq;
@@ -61,7 +66,7 @@ impl FileStore {
             id,
             StoredFile {
                 file: Arc::new(file),
-                expires_at: Instant::now() + self.ttl,
+                expires_at: Instant::now() + ttl,
                 session_id,
                 seq,
             },
@@ -83,6 +88,19 @@ impl FileStore {
         }
     }
 
+    /// Tag a client-supplied file with `session_id` so session listings include it. An existing
+    /// entry keeps its own expiry; a file from another store is inserted with the default TTL.
+    pub fn attach_to_session(&self, file: &File, session_id: &str) {
+        {
+            let mut guard = self.inner.write().unwrap();
+            if let Some(entry) = guard.by_id.get_mut(&file.id) {
+                entry.session_id = Some(session_id.to_string());
+                return;
+            }
+        }
+        self.insert(file.clone(), Some(session_id.to_string()));
+    }
+
     /// `None` if missing or expired.
     pub fn get(&self, id: &str) -> Option<Arc<File>> {
         let now = Instant::now();
@@ -101,12 +119,13 @@ impl FileStore {
     }
 
     /// Refresh the TTL on every file tagged with `session_id`. Call when the session is touched.
+    /// Never shortens an entry inserted with a longer TTL.
     pub fn touch_session(&self, session_id: &str) {
         let new_expiry = std::time::Instant::now() + self.ttl;
         let mut guard = self.inner.write().unwrap();
         for entry in guard.by_id.values_mut() {
             if entry.session_id.as_deref() == Some(session_id) {
-                entry.expires_at = new_expiry;
+                entry.expires_at = entry.expires_at.max(new_expiry);
             }
         }
     }
@@ -194,6 +213,8 @@ mod tests {
             mime_type: Some("text/plain".into()),
             bytes: 2,
             created_at: 0,
+            purpose: None,
+            owner: None,
             source: FileSource {
                 tool: "execute_python".into(),
                 round: 0,
@@ -244,4 +265,19 @@ mod tests {
         assert_eq!(swept, 1);
         assert!(s.is_empty());
     }
+
+    #[test]
+    fn attach_keeps_longer_ttl() {
+        let s = FileStore::with_ttl(Duration::from_millis(1));
+        s.insert_with_ttl(make("file_upload"), None, Duration::from_secs(60));
+        s.attach_to_session(&make("file_upload"), "sess1");
+        s.touch_session("sess1");
+        std::thread::sleep(Duration::from_millis(5));
+        let listed: Vec<_> = s
+            .list_for_session("sess1")
+            .iter()
+            .map(|f| f.id.clone())
+            .collect();
+        assert_eq!(listed, vec!["file_upload".to_string()]);
+    }
 }
@@ -2,10 +2,14 @@
 use candle_core::Device;
 use engine::Engine;
 pub use engine::{
-    agentic_session::{AgenticSessionStore, SerializedSession, SerializedVideo},
+    agentic_session::{
+        AgenticSessionStore, FileSessionStorage, SerializedSession, SerializedVideo, SessionInfo,
+        SessionStorage,
+    },
     get_engine_terminate_flag, reset_engine_terminate_flag, should_terminate_engine_sequences,
-    EngineInstruction, IntervalLogger, SearchEmbeddingModel, DEFAULT_MAX_TOOL_ROUNDS,
-    ENGINE_INSTRUCTIONS, TERMINATE_ALL_NEXT_STEP,
+    AdmissionLimits, AgenticLoopPermit, EngineInstruction, EngineMetrics, HistogramSnapshot,
+    IntervalLogger, QueueDepth, SearchEmbeddingModel, DEFAULT_MAX_TOOL_ROUNDS, ENGINE_INSTRUCTIONS,
+    TERMINATE_ALL_NEXT_STEP,
 };
 use hf_hub::Cache;
 pub use lora::Ordering;
@@ -58,6 +62,7 @@ pub use toml_selector::{get_toml_selected_model_device_map_params, get_toml_sele
 
 mod amoe;
 mod attention;
+mod audit;
 mod diagnostics;
 mod diffusion_models;
 pub mod distributed;
@@ -80,6 +85,7 @@ mod scheduler;
 mod sequence;
 pub mod speculative;
 mod speech_models;
+mod telemetry;
 mod toml_selector;
 mod tools;
 mod topology;
@@ -101,12 +107,13 @@ pub use device_map::{
     DeviceLayerMapMetadata, DeviceMapMetadata, DeviceMapSetting, LayerDeviceMapper,
 };
 pub use gguf::{GGUFArchitecture, GGUF_MULTI_FILE_DELIMITER};
-pub use mistralrs_audio::AudioInput;
+pub use mistralrs_audio::{encode_audio, ffmpeg_available, AudioEncoder, AudioFormat, AudioInput};
 pub use mistralrs_mcp::{
     AgentPermission, AgentToolApprovalNotifier, AgentToolApprovalRequest, AgentToolKind,
     AgentToolMetadata, AgentToolSource, CalledFunction, CodeExecutionApprovalNotifier,
     CodeExecutionApprovalRequest, CodeExecutionPermission, Function, MultimodalToolCallback, Tool,
-    ToolCallContext, ToolCallback, ToolCallbackKind, ToolCallbackWithTool, ToolOutput, ToolType,
+    ToolCallContext, ToolCallback, ToolCallbackKind, ToolCallbackWithTool, ToolCancellation,
+    ToolOutput, ToolType,
 };
 pub use mistralrs_mcp::{
     McpClient, McpClientConfig, McpServerConfig, McpServerSource, McpToolInfo,
@@ -264,9 +271,10 @@ impl Default for CodeExecutionConfig {
         }
     }
 }
+pub use audit::{AuditLog, AuditLogConfig, AuditRedaction};
 pub use files::{
     format_from_name, is_text_mime, mime_for_format, File, FileContent, FileSource, FileStore,
-    RequestedFile, MODEL_INLINE_BYTES, WIRE_EMBED_LIMIT_BYTES,
+    RequestedFile, MODEL_INLINE_BYTES, UPLOAD_SOURCE, WIRE_EMBED_LIMIT_BYTES,
 };
 pub use paged_attention::{MemoryGpuConfig, PagedAttentionConfig, PagedCacheType};
 pub use pipeline::hf::{
@@ -276,22 +284,23 @@ pub use pipeline::hf::{
 pub use pipeline::{
     chat_template::ChatTemplate, expand_isq_value, parse_isq_value, parse_uqff_shard,
     resolve_uqff_shorthand, AdapterPaths, AnyMoeLoader, AnyMoePipeline, AutoDeviceMapParams,
-    AutoLoader, AutoLoaderBuilder, DiffusionGenerationParams, DiffusionLoader,
-    DiffusionLoaderBuilder, DiffusionLoaderType, EmbeddingLoader, EmbeddingLoaderBuilder,
-    EmbeddingLoaderType, EmbeddingModelPaths, EmbeddingSpecificConfig, GGMLLoader,
-    GGMLLoaderBuilder, GGMLSpecificConfig, GGUFLoader, GGUFLoaderBuilder, GGUFSpecificConfig,
-    GemmaLoader, Idefics2Loader, IsqOrganization, LLaVALoader, LLaVANextLoader, LlamaLoader,
-    Loader, LocalModelPaths, LoraAdapterPaths, MistralLoader, MixtralLoader, Modalities, ModelKind,
-    ModelPaths, MultimodalLoader, MultimodalLoaderBuilder, MultimodalLoaderType,
-    MultimodalPromptPrefixer, MultimodalSpecificConfig, NormalLoader, NormalLoaderBuilder,
-    NormalLoaderType, NormalSpecificConfig, Phi2Loader, Phi3Loader, Phi3VLoader, Qwen2Loader,
-    SpeechLoader, SpeechPipeline, Starcoder2Loader, SupportedModality, TokenSource,
-    UQFF_MULTI_FILE_DELIMITER,
+    AutoLoader, AutoLoaderBuilder, CrossEncoderFormat, DiffusionGenerationParams,
+    DiffusionImageInput, DiffusionLoader, DiffusionLoaderBuilder, DiffusionLoaderType,
+    EmbeddingLoader, EmbeddingLoaderBuilder, EmbeddingLoaderType, EmbeddingModelPaths,
+    EmbeddingSpecificConfig, FimTemplate, GGMLLoader, GGMLLoaderBuilder, GGMLSpecificConfig,
+    GGUFLoader, GGUFLoaderBuilder, GGUFSpecificConfig, GemmaLoader, Idefics2Loader,
+    IsqOrganization, LLaVALoader, LLaVANextLoader, LlamaLoader, Loader, LocalModelPaths,
+    LoraAdapterPaths, MistralLoader, MixtralLoader, Modalities, ModelKind, ModelPaths,
+    MultimodalLoader, MultimodalLoaderBuilder, MultimodalLoaderType, MultimodalPromptPrefixer,
+    MultimodalSpecificConfig, NormalLoader, NormalLoaderBuilder, NormalLoaderType,
+    NormalSpecificConfig, Phi2Loader, Phi3Loader, Phi3VLoader, Qwen2Loader, SpeechLoader,
+    SpeechPipeline, Starcoder2Loader, SupportedModality, TokenSource, UQFF_MULTI_FILE_DELIMITER,
 };
 pub use request::{
-    ApproximateUserLocation, Constraint, DetokenizationRequest, ImageGenerationResponseFormat,
-    LlguidanceGrammar, MessageContent, NormalRequest, ReasoningEffort, Request, RequestMessage,
-    SearchContextSize, TokenizationRequest, WebSearchOptions, WebSearchUserLocation,
+    ApproximateUserLocation, Constraint, DetokenizationRequest, FimMode,
+    ImageGenerationResponseFormat, LlguidanceGrammar, MessageContent, NormalRequest,
+    ReasoningEffort, Request, RequestMessage, RequestPriority, SearchContextSize, SpeechReference,
+    TokenizationRequest, WebSearchOptions, WebSearchUserLocation,
 };
 pub use response::*;
 pub use sampler::{
@@ -299,10 +308,14 @@ pub use sampler::{
     TopLogprob,
 };
 pub use scheduler::{DefaultSchedulerMethod, SchedulerConfig};
-pub use search::{SearchCallback, SearchFunctionParameters, SearchResult};
+pub use search::{rag::cosine_similarity, SearchCallback, SearchFunctionParameters, SearchResult};
 use serde::Serialize;
 pub use speculative::{MtpConfig, SpeculativeConfig};
 pub use speech_models::{utils as speech_utils, SpeechGenerationConfig, SpeechLoaderType};
+pub use telemetry::{
+    current_trace_context, init_otlp_exporter, tracing_enabled, with_trace_context, AttributeValue,
+    OtlpExporterConfig, TraceContext, TraceSpan,
+};
 use tokio::runtime::Runtime;
 use toml_selector::{TomlLoaderArgs, TomlSelector};
 pub use tools::{ToolCallResponse, ToolCallType, ToolCallbacks, ToolChoice};
@@ -330,6 +343,12 @@ pub struct EngineConfig {
     pub search_embedding_model: Option<SearchEmbeddingModel>,
     pub search_callback: Option<Arc<SearchCallback>>,
     pub tool_callbacks: tools::ToolCallbacksWithTools,
+    /// Durable backend for agentic sessions. In-memory only when `None`.
+    pub session_storage: Option<Arc<dyn SessionStorage>>,
+    /// Queue bounds enforced when requests are sent to this model.
+    pub admission_limits: AdmissionLimits,
+    /// Structured record of requests, tool calls and approvals. Disabled when `None`.
+    pub audit_log: Option<Arc<AuditLog>>,
 }
 
 impl Default for EngineConfig {
@@ -343,6 +362,9 @@ impl Default for EngineConfig {
             search_embedding_model: None,
             search_callback: None,
             tool_callbacks: HashMap::new(),
+            session_storage: None,
+            admission_limits: AdmissionLimits::default(),
+            audit_log: None,
         }
     }
 }
@@ -394,6 +416,8 @@ pub struct MistralRsConfig {
     pub modalities: Modalities,
     pub max_seq_len: Option<usize>,
     pub generation_defaults: Option<ModelGenerationDefaults>,
+    /// Set for embedding models that are cross-encoders.
+    pub cross_encoder: Option<CrossEncoderFormat>,
 }
 
 /// Configuration for recreating a model loader when reloading an unloaded model.
@@ -456,6 +480,9 @@ struct EngineInstance {
     session_store: Arc<std::sync::Mutex<engine::agentic_session::AgenticSessionStore>>,
     /// Shared with the engine for fetch-by-id from the SDK/HTTP layer.
     pub(crate) file_store: files::FileStore,
+    admission_limits: AdmissionLimits,
+    /// Agentic requests admitted and not finished, checked against `max_agentic_loops`.
+    agentic_loops: engine::AgenticLoopCount,
 }
 
 /// The MistralRs struct handles sending requests to multiple engines.
@@ -501,6 +528,9 @@ struct RebootState {
     search_embedding_model: Option<SearchEmbeddingModel>,
     search_callback: Option<Arc<search::SearchCallback>>,
     tool_callbacks: tools::ToolCallbacksWithTools,
+    session_storage: Option<Arc<dyn SessionStorage>>,
+    admission_limits: AdmissionLimits,
+    audit_log: Option<Arc<AuditLog>>,
     mcp_client_config: Option<McpClientConfig>,
     /// Optional loader config for reloading after unload
     loader_config: Option<ModelLoaderConfig>,
@@ -540,6 +570,13 @@ pub enum MistralRsError {
     ModelAlreadyLoaded(String),
     /// Model is already unloaded
     ModelAlreadyUnloaded(String),
+    /// The model's queues are over an admission limit.
+    Overloaded {
+        model_id: String,
+        reason: String,
+        /// How long the client should wait before retrying.
+        retry_after: Duration,
+    },
     /// Other error with a message.
     Other(String),
 }
@@ -578,6 +615,9 @@ pub struct MistralRsBuilder {
     mcp_client_config: Option<McpClientConfig>,
     loader_config: Option<ModelLoaderConfig>,
     code_exec_config: Option<CodeExecutionConfig>,
+    session_storage: Option<Arc<dyn SessionStorage>>,
+    admission_limits: AdmissionLimits,
+    audit_log: Option<Arc<AuditLog>>,
 }
 
 impl MistralRsBuilder {
@@ -606,6 +646,9 @@ impl MistralRsBuilder {
             mcp_client_config: None,
             loader_config: None,
             code_exec_config: None,
+            session_storage: None,
+            admission_limits: AdmissionLimits::default(),
+            audit_log: None,
         }
     }
 
@@ -719,6 +762,24 @@ impl MistralRsBuilder {
         self
     }
 
+    /// Persist agentic sessions to `storage` so they survive restarts and model reloads.
+    pub fn with_session_storage(mut self, storage: Arc<dyn SessionStorage>) -> Self {
+        self.session_storage = Some(storage);
+        self
+    }
+
+    /// Bound this model's queues; requests over a limit fail with [`MistralRsError::Overloaded`].
+    pub fn with_admission_limits(mut self, limits: AdmissionLimits) -> Self {
+        self.admission_limits = limits;
+        self
+    }
+
+    /// Record requests, tool calls and approval decisions to `audit_log`.
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
     pub async fn build(self) -> Arc<MistralRs> {
         MistralRs::new(self).await
     }
@@ -757,6 +818,7 @@ impl MistralRs {
             _ => Some(metadata.max_seq_len),
         };
         let generation_defaults = pipeline_guard.generation_defaults();
+        let cross_encoder = pipeline_guard.cross_encoder_format();
         let encoder_cache_counters = pipeline_guard.encoder_cache_counters();
         drop(pipeline_guard);
 
@@ -776,14 +838,18 @@ impl MistralRs {
             modalities,
             max_seq_len,
             generation_defaults,
+            cross_encoder,
         };
 
         // Shared between engine and EngineInstance so the SDK/HTTP API
         // can access sessions without going through the request channel.
         let session_store = Arc::new(std::sync::Mutex::new(
-            engine::agentic_session::AgenticSessionStore::new(),
+            engine::agentic_session::AgenticSessionStore::with_storage(
+                config.session_storage.clone(),
+            ),
         ));
         let session_store_for_engine = Arc::clone(&session_store);
+        let admission_limits = config.admission_limits;
         let file_store = files::FileStore::new();
         let file_store_for_engine = file_store.clone();
 
@@ -810,6 +876,7 @@ impl MistralRs {
                         logger_for_engine,
                         session_store_for_engine,
                         file_store_for_engine,
+                        config.audit_log.clone(),
                     )
                     .expect("Engine creation failed.");
                     Arc::new(engine).run().await;
@@ -837,6 +904,7 @@ impl MistralRs {
                         logger_for_engine,
                         session_store_for_engine,
                         file_store_for_engine,
+                        config.audit_log.clone(),
                     )
                     .expect("Engine creation failed.");
                     Arc::new(engine).run().await;
@@ -853,6 +921,8 @@ impl MistralRs {
             logger,
             session_store,
             file_store,
+            admission_limits,
+            agentic_loops: engine::AgenticLoopCount::default(),
         })
     }
 
@@ -878,7 +948,10 @@ impl MistralRs {
                     let tools_count = mcp_callbacks_with_tools.len();
 
                     for (name, callback_with_tool) in mcp_callbacks_with_tools {
-                        tool_callbacks.insert(name.clone(), callback_with_tool.clone());
+                        tool_callbacks.insert(
+                            name.clone(),
+                            telemetry::trace_tool_callback("mcp_call", callback_with_tool.clone()),
+                        );
                     }
 
                     if tools_count == 0 {
@@ -968,7 +1041,8 @@ impl MistralRs {
                     let callbacks = manager.get_tool_callbacks(&input_modalities);
                     let count = callbacks.len();
                     for (name, cb) in callbacks {
-                        tool_callbacks.insert(name, cb);
+                        tool_callbacks
+                            .insert(name, telemetry::trace_tool_callback("code_execution", cb));
                     }
                     warn!("============================================================");
                     warn!("  CODE EXECUTION IS ENABLED");
@@ -1034,6 +1108,9 @@ impl MistralRs {
             loader_config,
             #[cfg_attr(not(feature = "code-execution"), allow(unused_variables))]
             code_exec_config,
+            session_storage,
+            admission_limits,
+            audit_log,
         } = config;
 
         mistralrs_quant::cublaslt::maybe_init_cublas_lt_wrapper(
@@ -1064,6 +1141,9 @@ impl MistralRs {
             search_embedding_model,
             search_callback: search_callback.clone(),
             tool_callbacks: tool_callbacks.clone(),
+            session_storage: session_storage.clone(),
+            admission_limits,
+            audit_log: audit_log.clone(),
             mcp_client_config: mcp_client_config.clone(),
             loader_config,
         };
@@ -1077,6 +1157,9 @@ impl MistralRs {
             search_embedding_model,
             search_callback,
             tool_callbacks,
+            session_storage,
+            admission_limits,
+            audit_log,
         };
 
         let engine_instance =
@@ -1131,6 +1214,7 @@ impl MistralRs {
                         text: "hello".to_string(),
                         echo_prompt: false,
                         best_of: None,
+                        fim_mode: FimMode::Psm,
                     },
                     sampling_params: SamplingParams {
                         max_len: Some(1),
@@ -1158,6 +1242,13 @@ impl MistralRs {
                     truncate_sequence: false,
                     session_id: None,
                     files: None,
+                    input_files: None,
+                    priority: RequestPriority::Normal,
+                    timeout: None,
+                    trace_context: None,
+                    user: None,
+                    prediction: None,
+                    agentic_permit: None,
                 }));
                 debug!("Beginning dummy run.");
                 let start = Instant::now();
@@ -1227,6 +1318,9 @@ impl MistralRs {
                 search_embedding_model: reboot_state.search_embedding_model,
                 search_callback: reboot_state.search_callback.clone(),
                 tool_callbacks: reboot_state.tool_callbacks.clone(),
+                session_storage: reboot_state.session_storage.clone(),
+                admission_limits: reboot_state.admission_limits,
+                audit_log: reboot_state.audit_log.clone(),
             };
             let new_engine_instance = Self::create_engine_instance(
                 reboot_state.pipeline.clone(),
@@ -1342,6 +1436,22 @@ impl MistralRs {
         out
     }
 
+    /// Store a server-produced file (for example a batch result) in the file store of
+    /// `model_id`, or the default model. `ttl` overrides the store's default expiry.
+    pub fn add_file(
+        &self,
+        model_id: Option<&str>,
+        file: files::File,
+        ttl: Option<Duration>,
+    ) -> Result<(), MistralRsError> {
+        let store = self.get_file_store(model_id)?;
+        match ttl {
+            Some(ttl) => store.insert_with_ttl(file, None, ttl),
+            None => store.insert(file, None),
+        }
+        Ok(())
+    }
+
     /// Returns whether the file existed.
     pub fn remove_file(&self, id: &str) -> bool {
         let Ok(engines) = self.engines.read() else {
@@ -1391,12 +1501,8 @@ impl MistralRs {
         session_id: &str,
     ) -> Result<Option<engine::agentic_session::SerializedSession>, MistralRsError> {
         let store = self.get_session_store(model_id)?;
-        let exported = {
-            let mut guard = store.lock().map_err(|_| MistralRsError::SenderPoisoned)?;
-            guard
-                .export(session_id)
-                .map_err(|e| MistralRsError::Other(e.to_string()))?
-        };
+        let exported = engine::agentic_session::AgenticSessionStore::export(&store, session_id)
+            .map_err(|e| MistralRsError::Other(e.to_string()))?;
         let Some(mut session) = exported else {
             return Ok(None);
         };
@@ -1442,10 +1548,13 @@ impl MistralRs {
         num_turns: usize,
     ) -> Result<(), MistralRsError> {
         let store = self.get_session_store(model_id)?;
-        let mut guard = store.lock().map_err(|_| MistralRsError::SenderPoisoned)?;
-        guard
-            .fork(src_session_id, dest_session_id, num_turns)
-            .map_err(|e| MistralRsError::Other(e.to_string()))
+        engine::agentic_session::AgenticSessionStore::fork(
+            &store,
+            src_session_id,
+            dest_session_id,
+            num_turns,
+        )
+        .map_err(|e| MistralRsError::Other(e.to_string()))
     }
 
     /// Delete an agentic session. Returns whether the session existed.
@@ -1455,15 +1564,26 @@ impl MistralRs {
         session_id: &str,
     ) -> Result<bool, MistralRsError> {
         let store = self.get_session_store(model_id)?;
-        let mut guard = store.lock().map_err(|_| MistralRsError::SenderPoisoned)?;
-        Ok(guard.delete(session_id))
+        Ok(engine::agentic_session::AgenticSessionStore::delete(
+            &store, session_id,
+        ))
     }
 
-    /// All stored session IDs. SDK-only, not exposed via HTTP.
+    /// All stored session IDs.
     pub fn list_session_ids(&self, model_id: Option<&str>) -> Result<Vec<String>, MistralRsError> {
         let store = self.get_session_store(model_id)?;
-        let guard = store.lock().map_err(|_| MistralRsError::SenderPoisoned)?;
-        Ok(guard.list_ids())
+        Ok(engine::agentic_session::AgenticSessionStore::list_ids(
+            &store,
+        ))
+    }
+
+    /// All stored sessions with their last update time, most recent first.
+    pub fn list_sessions(
+        &self,
+        model_id: Option<&str>,
+    ) -> Result<Vec<SessionInfo>, MistralRsError> {
+        let store = self.get_session_store(model_id)?;
+        Ok(engine::agentic_session::AgenticSessionStore::list(&store))
     }
 
     pub fn get_id(&self) -> String {
@@ -1600,6 +1720,78 @@ impl MistralRs {
         Ok(false)
     }
 
+    /// Current queue depth of a model. If model_id is None, uses default engine.
+    pub fn queue_depth(&self, model_id: Option<&str>) -> Result<QueueDepth, MistralRsError> {
+        let resolved_model_id = self.resolve_alias_or_default(model_id)?;
+
+        let engines = self
+            .engines
+            .read()
+            .map_err(|_| MistralRsError::SenderPoisoned)?;
+        let engine_instance = engines
+            .get(&resolved_model_id)
+            .ok_or(MistralRsError::ModelNotFound(resolved_model_id.clone()))?;
+        Ok(Self::engine_queue_depth(engine_instance))
+    }
+
+    /// Maximum number of sequences a model's scheduler runs at once. If model_id is None, uses
+    /// default engine.
+    pub fn max_seqs(&self, model_id: Option<&str>) -> Result<usize, MistralRsError> {
+        let resolved_model_id = self.resolve_alias_or_default(model_id)?;
+
+        let engines = self
+            .engines
+            .read()
+            .map_err(|_| MistralRsError::SenderPoisoned)?;
+        let engine_instance = engines
+            .get(&resolved_model_id)
+            .ok_or(MistralRsError::ModelNotFound(resolved_model_id.clone()))?;
+        Ok(engine_instance.reboot_state.method.max_seqs())
+    }
+
+    fn engine_queue_depth(engine_instance: &EngineInstance) -> QueueDepth {
+        let sender = &engine_instance.sender;
+        let pending = sender.max_capacity() - sender.capacity();
+        QueueDepth::new(&engine_instance.logger.metrics(), pending)
+    }
+
+    /// Check `request` against the admission limits of the model it targets. Fails with
+    /// [`MistralRsError::Overloaded`] if it should be retried later. Requests to unloaded models
+    /// are admitted here and reloaded by [`Self::get_sender`]. An admitted agentic request gets
+    /// an [`AgenticLoopPermit`] that counts against the loop limit until its loop ends.
+    pub fn check_admission(
+        &self,
+        request: &mut Request,
+        model_id: Option<&str>,
+    ) -> Result<(), MistralRsError> {
+        let resolved_model_id = self.resolve_alias_or_default(model_id)?;
+
+        let engines = self
+            .engines
+            .read()
+            .map_err(|_| MistralRsError::SenderPoisoned)?;
+        let Some(engine_instance) = engines.get(&resolved_model_id) else {
+            return Ok(());
+        };
+        if engine_instance.admission_limits.is_unlimited() {
+            return Ok(());
+        }
+        let depth = Self::engine_queue_depth(engine_instance);
+        let rejection = engine_instance.admission_limits.rejection(
+            request,
+            &depth,
+            &engine_instance.agentic_loops,
+        );
+        match rejection {
+            Some(reason) => Err(MistralRsError::Overloaded {
+                model_id: resolved_model_id,
+                reason,
+                retry_after: engine::retry_after(&engine_instance.logger.metrics()),
+            }),
+            None => Ok(()),
+        }
+    }
+
     /// Get the interval logger for a specific model. If model_id is None, uses default engine.
     pub fn get_logger(
         &self,
@@ -1636,6 +1828,24 @@ impl MistralRs {
         }
     }
 
+    /// Get the pair format of a cross-encoder model, or `None` for any other model.
+    pub fn cross_encoder_format(
+        &self,
+        model_id: Option<&str>,
+    ) -> Result<Option<CrossEncoderFormat>, MistralRsError> {
+        let resolved_model_id = self.resolve_alias_or_default(model_id)?;
+
+        let engines = self
+            .engines
+            .read()
+            .map_err(|_| MistralRsError::SenderPoisoned)?;
+        if let Some(engine_instance) = engines.get(&resolved_model_id) {
+            Ok(engine_instance.config.cross_encoder)
+        } else {
+            Err(MistralRsError::EnginePoisoned)
+        }
+    }
+
     /// Get the maximum supported sequence length for a model, if applicable.
     pub fn max_sequence_length(
         &self,
@@ -1730,6 +1940,9 @@ impl MistralRs {
             search_embedding_model: engine_config.search_embedding_model,
             search_callback: engine_config.search_callback.clone(),
             tool_callbacks: engine_config.tool_callbacks.clone(),
+            session_storage: engine_config.session_storage.clone(),
+            admission_limits: engine_config.admission_limits,
+            audit_log: engine_config.audit_log.clone(),
             mcp_client_config: config.mcp_client_config.clone(),
             loader_config: config.loader_config.clone(),
         };
@@ -1847,12 +2060,13 @@ impl MistralRs {
 
     /// Dispatch a request to the appropriate engine based on the model_id in the request
     pub fn send_request(&self, mut request: Request) -> Result<(), MistralRsError> {
-        let model_id = match &mut request {
-            Request::Normal(normal_req) => normal_req.model_id.as_deref(),
+        let model_id = match &request {
+            Request::Normal(normal_req) => normal_req.model_id.clone(),
             _ => None, // Other request types don't specify model_id
         };
 
-        let sender = self.get_sender(model_id)?;
+        self.check_admission(&mut request, model_id.as_deref())?;
+        let sender = self.get_sender(model_id.as_deref())?;
         sender
             .blocking_send(request)
             .map_err(|_| MistralRsError::SenderPoisoned)
@@ -2046,6 +2260,9 @@ impl MistralRs {
                 search_embedding_model: engine_instance.reboot_state.search_embedding_model,
                 search_callback: engine_instance.reboot_state.search_callback.clone(),
                 tool_callbacks: engine_instance.reboot_state.tool_callbacks.clone(),
+                session_storage: engine_instance.reboot_state.session_storage.clone(),
+                admission_limits: engine_instance.reboot_state.admission_limits,
+                audit_log: engine_instance.reboot_state.audit_log.clone(),
             },
             mcp_client_config: engine_instance.reboot_state.mcp_client_config.clone(),
             category: engine_instance.category.clone(),
@@ -2193,6 +2410,9 @@ impl MistralRs {
             search_embedding_model: unloaded_state.engine_config.search_embedding_model,
             search_callback: unloaded_state.engine_config.search_callback.clone(),
             tool_callbacks: unloaded_state.engine_config.tool_callbacks.clone(),
+            session_storage: unloaded_state.engine_config.session_storage.clone(),
+            admission_limits: unloaded_state.engine_config.admission_limits,
+            audit_log: unloaded_state.engine_config.audit_log.clone(),
             mcp_client_config: unloaded_state.mcp_client_config.clone(),
             loader_config: Some(unloaded_state.loader_config.clone()),
         };
@@ -2,6 +2,7 @@
 //! The primary method `schedule` returns the batched sequences as inputs.
 
 use std::{
+    cmp::Reverse,
     collections::{HashMap, VecDeque},
     sync::{atomic::Ordering, Arc, Mutex},
 };
@@ -109,7 +110,8 @@ impl PagedAttentionScheduler {
     }
 
     /// Bucket sequences by (length, has_images && is_prompt, token_offset).
-    /// Returns the bucket with the shortest sequence length; sequences from other buckets
+    /// Returns the bucket with the shortest sequence length among those holding the highest
+    /// priority class; sequences from other buckets
     /// are preempted (blocks freed, state set to Waiting, added to waiting queue).
     ///
     /// This ensures all sequences in a batch have the same length, which is required for
@@ -125,6 +127,7 @@ impl PagedAttentionScheduler {
         }
 
         let mut buckets: HashMap<BucketKey, VecDeque<Arc<Mutex<Sequence>>>> = HashMap::new();
+        let mut bucket_priorities: HashMap<BucketKey, usize> = HashMap::new();
 
         for seq in sequences {
             let seq_guard = get_mut_arcmutex!(seq);
@@ -141,8 +144,11 @@ impl PagedAttentionScheduler {
                 seq_guard.images().is_some() && seq_guard.is_prompt(),
                 seq_guard.token_offset(),
             );
+            let priority = seq_guard.effective_priority();
             drop(seq_guard);
 
+            let bucket_priority = bucket_priorities.entry(key).or_default();
+            *bucket_priority = (*bucket_priority).max(priority);
             buckets.entry(key).or_default().push_back(seq);
         }
 
@@ -150,10 +156,10 @@ impl PagedAttentionScheduler {
             return buckets.into_values().next().unwrap();
         }
 
-        // Find the bucket with the shortest sequence length
+        // Find the bucket with the shortest sequence length, highest priority class first
         let min_key = *buckets
             .keys()
-            .min_by_key(|(len, _, _)| *len)
+            .min_by_key(|key| (Reverse(bucket_priorities[*key]), key.0))
             .expect("No sequence buckets");
 
         let selected = buckets.remove(&min_key).unwrap();
@@ -177,6 +183,9 @@ impl PagedAttentionScheduler {
     }
 
     pub fn schedule(&mut self, logger: &IntervalLogger) -> PagedAttentionSchedulerOutput {
+        self.drop_disconnected_waiting();
+        self.age_and_sort_waiting();
+
         let mut scheduled: VecDeque<Arc<Mutex<Sequence>>> = VecDeque::new();
         let mut for_waiting_again: VecDeque<Arc<Mutex<Sequence>>> = VecDeque::new();
         while !self.waiting.is_empty() {
@@ -192,6 +201,7 @@ impl PagedAttentionScheduler {
             let tokens = seq_guard.get_toks().to_vec();
             let num_tokens = tokens.len();
             let mm_features = seq_guard.mm_features().to_vec();
+            let priority = seq_guard.effective_priority();
             drop(seq_guard);
 
             // Compute block hashes for prefix cache lookup
@@ -214,9 +224,22 @@ impl PagedAttentionScheduler {
             };
             let num_computed = computed.num_computed_tokens;
             // Try to allocate blocks
-            let alloc_result = kv_mgr.allocate_slots(seq_id, num_tokens, &computed.block_ids);
+            let mut alloc_result = kv_mgr.allocate_slots(seq_id, num_tokens, &computed.block_ids);
             drop(kv_mgr);
 
+            // Free blocks held by lower priority classes before waiting on the starvation timeout.
+            while alloc_result.is_none() {
+                let Some(victim) = self.take_lower_priority_running(priority, &scheduled) else {
+                    break;
+                };
+                self.preempt_deferred(victim, &mut for_waiting_again);
+                alloc_result = get_mut_arcmutex!(self.kv_cache_manager).allocate_slots(
+                    seq_id,
+                    num_tokens,
+                    &computed.block_ids,
+                );
+            }
+
             match alloc_result {
                 Some(_) => {
                     // Allocation succeeded
@@ -234,7 +257,7 @@ impl PagedAttentionScheduler {
                     if *count > WAITING_TIMEOUT {
                         // Try to preempt a running sequence
                         if let Some(seq_to_preempt) = self.running.pop_back() {
-                            self._preempt(seq_to_preempt);
+                            self.preempt_deferred(seq_to_preempt, &mut for_waiting_again);
 
                             // Retry allocation
                             let mut kv_mgr = get_mut_arcmutex!(self.kv_cache_manager);
@@ -319,8 +342,13 @@ impl PagedAttentionScheduler {
                 .map(|seq| get_mut_arcmutex!(seq).prefix_cache_len())
                 .collect();
 
-            logger.set_num_running(self.running.len());
-            logger.set_num_waiting(self.waiting.len());
+            scheduled.iter().for_each(|seq| {
+                let mut seq = get_mut_arcmutex!(seq);
+                seq.mark_admitted();
+                seq.cancel_if_abandoned();
+            });
+            self.report_queues(logger);
+            logger.set_kv_cache_usage(get_mut_arcmutex!(self.kv_cache_manager).usage());
 
             return PagedAttentionSchedulerOutput {
                 scheduled: scheduled.into_iter().collect(),
@@ -393,6 +421,9 @@ impl PagedAttentionScheduler {
             });
             TERMINATE_ALL_NEXT_STEP.store(false, Ordering::SeqCst);
         }
+        self.running
+            .iter()
+            .for_each(|seq| get_mut_arcmutex!(seq).cancel_if_abandoned());
 
         // Eagerly cache any newly-full blocks so other requests can hit the prefix cache
         // sooner, rather than waiting until finish/preempt. cache_blocks is idempotent.
@@ -419,8 +450,8 @@ impl PagedAttentionScheduler {
             }
         }
 
-        logger.set_num_running(self.running.len());
-        logger.set_num_waiting(self.waiting.len());
+        self.report_queues(logger);
+        logger.set_kv_cache_usage(get_mut_arcmutex!(self.kv_cache_manager).usage());
 
         PagedAttentionSchedulerOutput {
             scheduled: self.running.clone().into_iter().collect(),
@@ -466,6 +497,17 @@ impl PagedAttentionScheduler {
 }
 
 impl PagedAttentionScheduler {
+    fn report_queues(&self, logger: &IntervalLogger) {
+        logger.set_num_running(self.running.len());
+        logger.set_num_waiting(self.waiting.len());
+        logger.set_waiting_prompt_tokens(
+            self.waiting
+                .iter()
+                .map(|seq| get_mut_arcmutex!(seq).prompt_tokens())
+                .sum(),
+        );
+    }
+
     fn _preempt(&mut self, seq: Arc<Mutex<Sequence>>) {
         let mut seq_guard = get_mut_arcmutex!(seq);
         // Don't resurrect sequences that are already in a terminal state
@@ -499,10 +541,76 @@ impl PagedAttentionScheduler {
         self.waiting.push_front(seq);
     }
 
-    fn sort_running_by_priority_fcfs(&mut self) {
-        self.running
+    /// Preempt `seq` while the waiting queue is being walked. `_preempt` puts it at the front of
+    /// the queue, where it would be mistaken for the sequence being admitted, so it is moved to
+    /// `deferred` and rejoins the queue after this pass.
+    fn preempt_deferred(
+        &mut self,
+        seq: Arc<Mutex<Sequence>>,
+        deferred: &mut VecDeque<Arc<Mutex<Sequence>>>,
+    ) {
+        let waiting_before = self.waiting.len();
+        self._preempt(seq);
+        if self.waiting.len() > waiting_before {
+            deferred.push_back(self.waiting.pop_front().unwrap());
+        }
+    }
+
+    /// Remove the most recently admitted running sequence with the lowest effective priority, if
+    /// that priority is below `priority`. Sequences scheduled in the current pass are skipped.
+    fn take_lower_priority_running(
+        &mut self,
+        priority: usize,
+        scheduled: &VecDeque<Arc<Mutex<Sequence>>>,
+    ) -> Option<Arc<Mutex<Sequence>>> {
+        let idx = self
+            .running
+            .iter()
+            .enumerate()
+            .filter(|(_, seq)| !scheduled.iter().any(|s| Arc::ptr_eq(s, seq)))
+            .map(|(i, seq)| (i, get_mut_arcmutex!(seq).effective_priority()))
+            .filter(|&(_, p)| p < priority)
+            .min_by_key(|&(i, p)| (p, Reverse(i)))?
+            .0;
+        self.running.remove(idx)
+    }
+
+    /// Drop waiting sequences whose client disconnected. Waiting sequences hold no blocks, so only
+    /// the per-sequence bookkeeping needs clearing. The rest are canceled once scheduled.
+    fn drop_disconnected_waiting(&mut self) {
+        let mut dropped = Vec::new();
+        self.waiting.retain(|seq| {
+            let seq_guard = get_mut_arcmutex!(seq);
+            let disconnected = seq_guard.can_drop_while_waiting();
+            if disconnected {
+                dropped.push(*seq_guard.id());
+            }
+            !disconnected
+        });
+        for id in dropped {
+            self.seq_block_hashes.remove(&id);
+            self.waiting_counts.remove(&id);
+        }
+    }
+
+    /// Start the aging clock of every waiting sequence and order the queue by effective
+    /// priority. The sort is stable, so preempted sequences stay ahead of new arrivals within a
+    /// class. Keys are cached because effective priority changes with time.
+    fn age_and_sort_waiting(&mut self) {
+        for seq in &self.waiting {
+            get_mut_arcmutex!(seq).mark_waiting();
+        }
+        self.waiting
             .make_contiguous()
-            .sort_by_key(|seq| get_mut_arcmutex!(seq).timestamp());
+            .sort_by_cached_key(|seq| Reverse(get_mut_arcmutex!(seq).effective_priority()));
+    }
+
+    /// Highest effective priority first; within a class, newest first.
+    fn sort_running_by_priority_fcfs(&mut self) {
+        self.running.make_contiguous().sort_by_key(|seq| {
+            let seq = get_mut_arcmutex!(seq);
+            (seq.effective_priority(), seq.timestamp())
+        });
         self.running.make_contiguous().reverse();
     }
 }
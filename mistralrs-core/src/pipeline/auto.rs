@@ -393,6 +393,12 @@ impl AutoLoader {
         if let Ok(tp) = MultimodalLoaderType::from_causal_lm_name(name) {
             return Ok(Detected::Multimodal(tp));
         }
+        if name.ends_with("ForSequenceClassification") {
+            if let Ok(tp) = EmbeddingLoaderType::from_causal_lm_name(name) {
+                info!("Detected sequence-classification model; using cross-encoder loader `{tp}`.");
+                return Ok(Detected::Embedding(Some(tp)));
+            }
+        }
         let tp = NormalLoaderType::from_causal_lm_name(name)?;
         Ok(Detected::Normal(tp))
     }
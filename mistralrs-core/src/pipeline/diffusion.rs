@@ -325,21 +325,49 @@ impl Pipeline for DiffusionPipeline {
         assert!(!return_raw_logits);
 
         let ModelInputs { prompts, params } = *inputs.downcast().expect("Downcast failed.");
-        let img = self.model.forward(prompts, params)?.to_dtype(DType::U8)?;
-        let (_b, c, h, w) = img.dims4()?;
-        let mut images = Vec::new();
-        for b_img in img.chunk(img.dim(0)?, 0)? {
-            let flattened = b_img.squeeze(0)?.permute((1, 2, 0))?.flatten_all()?;
-            if c != 3 {
-                candle_core::bail!("Expected 3 channels in image output");
+
+        // Sequences with the same prompt and settings share one denoising run; only their
+        // seeds differ.
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        for i in 0..prompts.len() {
+            match batches.iter_mut().find(|batch| {
+                prompts[batch[0]] == prompts[i] && params[batch[0]].batches_with(&params[i])
+            }) {
+                Some(batch) => batch.push(i),
+                None => batches.push(vec![i]),
+            }
+        }
+
+        let mut images = vec![None; prompts.len()];
+        for batch in batches {
+            let seeds = batch.iter().map(|&i| params[i].seed).collect::<Vec<_>>();
+            let img = self
+                .model
+                .forward(
+                    vec![prompts[batch[0]].clone(); batch.len()],
+                    params[batch[0]].clone(),
+                    &seeds,
+                )?
+                .to_dtype(DType::U8)?;
+            let (_b, c, h, w) = img.dims4()?;
+            for (b_img, &i) in img.chunk(img.dim(0)?, 0)?.into_iter().zip(&batch) {
+                let flattened = b_img.squeeze(0)?.permute((1, 2, 0))?.flatten_all()?;
+                if c != 3 {
+                    candle_core::bail!("Expected 3 channels in image output");
+                }
+                #[allow(clippy::cast_possible_truncation)]
+                let image = DynamicImage::ImageRgb8(
+                    RgbImage::from_raw(w as u32, h as u32, flattened.to_vec1::<u8>()?).ok_or(
+                        candle_core::Error::Msg("RgbImage has invalid capacity.".to_string()),
+                    )?,
+                );
+                images[i] = Some(image);
             }
-            #[allow(clippy::cast_possible_truncation)]
-            images.push(DynamicImage::ImageRgb8(
-                RgbImage::from_raw(w as u32, h as u32, flattened.to_vec1::<u8>()?).ok_or(
-                    candle_core::Error::Msg("RgbImage has invalid capacity.".to_string()),
-                )?,
-            ));
         }
+        let images = images
+            .into_iter()
+            .map(|image| image.expect("every sequence is in a batch"))
+            .collect();
         Ok(ForwardInputsResult::Image { images })
     }
     async fn sample_causal_gen(
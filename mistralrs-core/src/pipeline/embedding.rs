@@ -21,7 +21,9 @@ use crate::pipeline::EmbeddingModel;
 use crate::pipeline::EmbeddingModelLoader;
 use crate::pipeline::{AutoEmbeddingLoader, EmbeddingModulePaths};
 use crate::pipeline::{ChatTemplate, EmbeddingModelPaths, IsqOrganization, Processor};
-use crate::pipeline::{EmbeddingGemmaLoader, Qwen3EmbeddingLoader};
+use crate::pipeline::{
+    CrossEncoderFormat, EmbeddingGemmaLoader, Qwen3EmbeddingLoader, Qwen3RerankerLoader,
+};
 use crate::prefix_cacher::PrefixCacheManagerV2;
 use crate::sequence::Sequence;
 use crate::utils::tokenizer::get_tokenizer;
@@ -71,6 +73,7 @@ pub struct EmbeddingPipeline {
     mapper: Box<dyn DeviceMapper + Send + Sync>,
     modules: Vec<Box<dyn Module + Send + Sync>>,
     processor: Arc<dyn Processor + Send + Sync>,
+    cross_encoder: Option<CrossEncoderFormat>,
 }
 
 /// A loader for an embedding (non-quantized) model.
@@ -163,6 +166,7 @@ impl EmbeddingLoaderBuilder {
         let loader: Box<dyn EmbeddingModelLoader> = match loader {
             Some(EmbeddingLoaderType::EmbeddingGemma) => Box::new(EmbeddingGemmaLoader),
             Some(EmbeddingLoaderType::Qwen3Embedding) => Box::new(Qwen3EmbeddingLoader),
+            Some(EmbeddingLoaderType::Qwen3Reranker) => Box::new(Qwen3RerankerLoader),
             None => Box::new(AutoEmbeddingLoader),
         };
         Box::new(EmbeddingLoader {
@@ -507,14 +511,21 @@ impl Loader for EmbeddingLoader {
 
         let multi_progress = Arc::new(new_multi_progress());
 
-        let modules_config: Vec<_> = paths
-            .get_modules()
-            .context("Embedding models require the `modules.json` file.")?
-            .to_vec();
-        assert!(matches!(
-            modules_config.first(),
-            Some(EmbeddingModulePaths::Transformer { .. })
-        ));
+        // Cross-encoders produce a score directly, so sentence-transformers modules don't apply.
+        let cross_encoder = self.inner.cross_encoder_format(&config)?;
+        let modules_config: Vec<_> = if cross_encoder.is_some() {
+            Vec::new()
+        } else {
+            let modules_config = paths
+                .get_modules()
+                .context("Embedding models require the `modules.json` file.")?
+                .to_vec();
+            assert!(matches!(
+                modules_config.first(),
+                Some(EmbeddingModulePaths::Transformer { .. })
+            ));
+            modules_config
+        };
 
         let mut modules: Vec<Box<dyn Module + Send + Sync>> = Vec::new();
         for module in &modules_config {
@@ -684,6 +695,7 @@ impl Loader for EmbeddingLoader {
             processor: Arc::new(EmbeddingProcessor {
                 has_causal_attention,
             }),
+            cross_encoder,
         })))
     }
 
@@ -754,6 +766,9 @@ impl CacheManagerMixin for EmbeddingPipeline {
 }
 
 impl MetadataMixin for EmbeddingPipeline {
+    fn cross_encoder_format(&self) -> Option<CrossEncoderFormat> {
+        self.cross_encoder
+    }
     fn device(&self) -> Device {
         self.model.device().clone()
     }
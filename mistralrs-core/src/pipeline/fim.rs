@@ -0,0 +1,136 @@
+//! Fill-in-the-middle (FIM) prompts for code models.
+//!
+//! Code models trained for infilling mark the prefix, the suffix and the point to generate the
+//! middle from with special tokens. The token strings differ per model family, so the template
+//! is picked by looking the tokens up in the model's tokenizer.
+
+use tokenizers::Tokenizer;
+
+use crate::FimMode;
+
+/// The special tokens of one model family's FIM format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FimTemplate {
+    pub name: &'static str,
+    pub prefix: &'static str,
+    pub suffix: &'static str,
+    pub middle: &'static str,
+    /// Whether the family was trained on suffix-prefix-middle prompts. Only the StarCoder
+    /// layout, `<prefix><suffix>{suffix}<middle>{prefix}`, is supported.
+    pub spm: bool,
+    /// Tokens that end the infill, in addition to the model's EOS tokens. Only those present
+    /// in the vocabulary are used.
+    pub stop: &'static [&'static str],
+}
+
+/// Known FIM formats, checked in order.
+const FIM_TEMPLATES: &[FimTemplate] = &[
+    // Qwen2.5-Coder, Qwen3-Coder
+    FimTemplate {
+        name: "qwen",
+        prefix: "<|fim_prefix|>",
+        suffix: "<|fim_suffix|>",
+        middle: "<|fim_middle|>",
+        spm: false,
+        stop: &[
+            "<|endoftext|>",
+            "<|fim_pad|>",
+            "<|repo_name|>",
+            "<|file_sep|>",
+        ],
+    },
+    // StarCoder, StarCoder2, SantaCoder
+    FimTemplate {
+        name: "starcoder",
+        prefix: "<fim_prefix>",
+        suffix: "<fim_suffix>",
+        middle: "<fim_middle>",
+        spm: true,
+        stop: &["<|endoftext|>", "<fim_pad>", "<file_sep>"],
+    },
+    // DeepSeek-Coder
+    FimTemplate {
+        name: "deepseek",
+        prefix: "<｜fim▁begin｜>",
+        suffix: "<｜fim▁hole｜>",
+        middle: "<｜fim▁end｜>",
+        spm: false,
+        stop: &["<｜end▁of▁sentence｜>", "<|EOT|>"],
+    },
+];
+
+impl FimTemplate {
+    /// The first known template whose prefix, suffix and middle tokens are all in `tokenizer`.
+    pub fn detect(tokenizer: &Tokenizer) -> Option<Self> {
+        Self::detect_with(|token| tokenizer.token_to_id(token).is_some())
+    }
+
+    fn detect_with(has_token: impl Fn(&str) -> bool) -> Option<Self> {
+        FIM_TEMPLATES
+            .iter()
+            .find(|t| [t.prefix, t.suffix, t.middle].into_iter().all(&has_token))
+            .copied()
+    }
+
+    /// The prompt asking for the text between `prefix` and `suffix`. PSM order is
+    /// prefix-suffix-middle; SPM puts the suffix first so the prefix runs straight into the
+    /// generated text. `None` for SPM if the family wasn't trained on it.
+    pub fn prompt(&self, prefix: &str, suffix: &str, mode: FimMode) -> Option<String> {
+        match mode {
+            FimMode::Psm => Some(format!(
+                "{}{prefix}{}{suffix}{}",
+                self.prefix, self.suffix, self.middle
+            )),
+            FimMode::Spm if self.spm => Some(format!(
+                "{}{}{suffix}{}{prefix}",
+                self.prefix, self.suffix, self.middle
+            )),
+            FimMode::Spm => None,
+        }
+    }
+
+    /// Ids of the stop tokens present in `tokenizer`.
+    pub fn stop_token_ids(&self, tokenizer: &Tokenizer) -> Vec<u32> {
+        self.stop
+            .iter()
+            .filter_map(|token| tokenizer.token_to_id(token))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FimTemplate;
+    use crate::FimMode;
+
+    #[test]
+    fn detects_by_special_tokens() {
+        let vocab = [
+            "<fim_prefix>",
+            "<fim_suffix>",
+            "<fim_middle>",
+            "<|endoftext|>",
+        ];
+        let template = FimTemplate::detect_with(|t| vocab.contains(&t)).unwrap();
+        assert_eq!(template.name, "starcoder");
+
+        let partial = ["<|fim_prefix|>", "<|fim_suffix|>"];
+        assert!(FimTemplate::detect_with(|t| partial.contains(&t)).is_none());
+    }
+
+    #[test]
+    fn builds_psm_and_spm_prompts() {
+        let template = FimTemplate::detect_with(|t| t.starts_with("<|fim_")).unwrap();
+        assert_eq!(
+            template.prompt("def f(", "):\n", FimMode::Psm).unwrap(),
+            "<|fim_prefix|>def f(<|fim_suffix|>):\n<|fim_middle|>"
+        );
+        assert!(template.prompt("def f(", "):\n", FimMode::Spm).is_none());
+
+        let template = FimTemplate::detect_with(|t| t.starts_with("<fim_")).unwrap();
+        assert_eq!(
+            template.prompt("def f(", "):\n", FimMode::Spm).unwrap(),
+            "<fim_prefix><fim_suffix>):\n<fim_middle>def f("
+        );
+    }
+}
@@ -30,11 +30,13 @@ use crate::{
 };
 
 pub trait DiffusionModel {
-    /// This returns a tensor of shape (bs, c, h, w), with values in [0, 255].
+    /// This returns a tensor of shape (bs, c, h, w), with values in [0, 255]. `seeds` has one
+    /// entry per prompt and seeds that image's initial noise; `params.seed` is not used.
     fn forward(
         &mut self,
         prompts: Vec<String>,
         params: DiffusionGenerationParams,
+        seeds: &[Option<u64>],
     ) -> candle_core::Result<Tensor>;
     fn device(&self) -> &Device;
     fn max_seq_len(&self) -> usize;
@@ -10,6 +10,7 @@ use crate::{
     embedding_models::{
         embedding_gemma::{EmbeddingGemma, EmbeddingGemmaConfig},
         qwen3_embedding::{Config as Qwen3EmbeddingConfig, Model as Qwen3EmbeddingModel},
+        qwen3_reranker::Model as Qwen3RerankerModel,
     },
     matformer::MatformerSliceConfig,
     pipeline::{loaders::auto_device_map::NonMappedSubModel, NormalLoadingMetadata},
@@ -56,6 +57,11 @@ pub trait EmbeddingModelLoader: IsqModelLoader + Send + Sync + DeviceMappedModel
     fn is_gptx(&self, config: &str) -> Result<bool>;
     fn has_causal_attention(&self, config: &str) -> Result<bool>;
     fn get_config_repr(&self, config: &str) -> Result<Box<dyn Debug>>;
+    /// Set for cross-encoders, which score a query/document pair instead of embedding text.
+    /// The model then outputs a single relevance score per sequence.
+    fn cross_encoder_format(&self, _config: &str) -> Result<Option<CrossEncoderFormat>> {
+        Ok(None)
+    }
     fn get_device_for_tensor(
         &self,
         config: &str,
@@ -93,6 +99,8 @@ pub enum EmbeddingLoaderType {
     EmbeddingGemma,
     #[serde(rename = "qwen3embedding")]
     Qwen3Embedding,
+    #[serde(rename = "qwen3reranker")]
+    Qwen3Reranker,
 }
 
 // https://github.com/huggingface/transformers/blob/cff06aac6fad28019930be03f5d467055bf62177/src/transformers/models/auto/modeling_auto.py#L448
@@ -101,6 +109,7 @@ impl EmbeddingLoaderType {
         match name {
             "Gemma3TextModel" => Ok(Self::EmbeddingGemma),
             "Qwen3ForCausalLM" => Ok(Self::Qwen3Embedding),
+            "Qwen3ForSequenceClassification" => Ok(Self::Qwen3Reranker),
             other => anyhow::bail!(
                 "Unsupported Hugging Face Transformers model class `{other}`. Please raise an issue."
             ),
@@ -114,8 +123,9 @@ impl FromStr for EmbeddingLoaderType {
         match s {
             "embeddinggemma" => Ok(Self::EmbeddingGemma),
             "qwen3embedding" => Ok(Self::Qwen3Embedding),
+            "qwen3reranker" => Ok(Self::Qwen3Reranker),
             a => Err(format!(
-                "Unknown architecture `{a}`. Possible architectures: `embeddinggemma`, `qwen3embedding`."
+                "Unknown architecture `{a}`. Possible architectures: `embeddinggemma`, `qwen3embedding`, `qwen3reranker`."
             )),
         }
     }
@@ -126,6 +136,32 @@ impl Display for EmbeddingLoaderType {
         match self {
             Self::EmbeddingGemma => write!(f, "embeddinggemma"),
             Self::Qwen3Embedding => write!(f, "qwen3embedding"),
+            Self::Qwen3Reranker => write!(f, "qwen3reranker"),
+        }
+    }
+}
+
+/// How a cross-encoder expects a query and a document to be combined into one prompt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrossEncoderFormat {
+    /// Qwen3-Reranker's yes/no judgement prompt.
+    Qwen3Reranker,
+}
+
+impl CrossEncoderFormat {
+    /// Instruction used when the request does not provide one.
+    pub const DEFAULT_INSTRUCTION: &'static str =
+        "Given a web search query, retrieve relevant passages that answer the query";
+
+    /// Render a query/document pair as the prompt the model was trained on.
+    pub fn format_pair(&self, query: &str, document: &str, instruction: Option<&str>) -> String {
+        let instruction = instruction.unwrap_or(Self::DEFAULT_INSTRUCTION);
+        match self {
+            Self::Qwen3Reranker => format!(
+                "<|im_start|>system\nJudge whether the Document meets the requirements based on the Query and the Instruct provided. Note that the answer can only be \"yes\" or \"no\".<|im_end|>\n\
+                 <|im_start|>user\n<Instruct>: {instruction}\n<Query>: {query}\n<Document>: {document}<|im_end|>\n\
+                 <|im_start|>assistant\n<think>\n\n</think>\n\n"
+            ),
         }
     }
 }
@@ -280,6 +316,7 @@ impl AutoEmbeddingLoader {
         match tp {
             EmbeddingLoaderType::EmbeddingGemma => Ok(Box::new(EmbeddingGemmaLoader)),
             EmbeddingLoaderType::Qwen3Embedding => Ok(Box::new(Qwen3EmbeddingLoader)),
+            EmbeddingLoaderType::Qwen3Reranker => Ok(Box::new(Qwen3RerankerLoader)),
         }
     }
 }
@@ -303,6 +340,9 @@ impl EmbeddingModelLoader for AutoEmbeddingLoader {
     fn is_gptx(&self, config: &str) -> Result<bool> {
         Self::get_loader(config)?.is_gptx(config)
     }
+    fn cross_encoder_format(&self, config: &str) -> Result<Option<CrossEncoderFormat>> {
+        Self::get_loader(config)?.cross_encoder_format(config)
+    }
 }
 
 impl IsqModelLoader for AutoEmbeddingLoader {
@@ -739,3 +779,99 @@ impl DeviceMappedModelLoader for Qwen3EmbeddingLoader {
         Ok(Box::new(cfg))
     }
 }
+
+/// Qwen3-Reranker, either as the original causal LM or as a `Qwen3ForSequenceClassification`
+/// conversion. The backbone is identical to Qwen3-Embedding, so sizing and ISQ are shared.
+pub struct Qwen3RerankerLoader;
+
+impl EmbeddingModelLoader for Qwen3RerankerLoader {
+    fn load(
+        &self,
+        config: &str,
+        vb: ShardedVarBuilder,
+        normal_loading_metadata: NormalLoadingMetadata,
+        attention_mechanism: AttentionImplementation,
+    ) -> Result<Box<dyn EmbeddingModel + Send + Sync>> {
+        let cfg: Qwen3EmbeddingConfig = serde_json::from_str(config)?;
+
+        Ok(Box::new(Qwen3RerankerModel::new(
+            &cfg,
+            vb,
+            self.is_gptx(config)?,
+            normal_loading_metadata,
+            attention_mechanism,
+        )?))
+    }
+    fn has_causal_attention(&self, _: &str) -> Result<bool> {
+        Ok(true)
+    }
+    fn is_gptx(&self, _: &str) -> Result<bool> {
+        Ok(true)
+    }
+    fn get_config_repr(&self, config: &str) -> Result<Box<dyn Debug>> {
+        Qwen3EmbeddingLoader.get_config_repr(config)
+    }
+    fn cross_encoder_format(&self, _config: &str) -> Result<Option<CrossEncoderFormat>> {
+        Ok(Some(CrossEncoderFormat::Qwen3Reranker))
+    }
+}
+
+impl IsqModelLoader for Qwen3RerankerLoader {
+    fn isq_layer_regexes(&self, config: &str) -> Result<Vec<Regex>> {
+        Qwen3EmbeddingLoader.isq_layer_regexes(config)
+    }
+    fn immediate_isq_predicates(&self, config: &str) -> Result<Vec<Regex>> {
+        self.isq_layer_regexes(config)
+    }
+}
+
+impl DeviceMappedModelLoader for Qwen3RerankerLoader {
+    fn mapped_max_act_size_elems(
+        &self,
+        config: &str,
+        params: &AutoDeviceMapParams,
+    ) -> Result<usize> {
+        Qwen3EmbeddingLoader.mapped_max_act_size_elems(config, params)
+    }
+    fn non_mapped_max_act_size_elems(
+        &self,
+        config: &str,
+        params: &AutoDeviceMapParams,
+    ) -> Result<usize> {
+        Qwen3EmbeddingLoader.non_mapped_max_act_size_elems(config, params)
+    }
+    fn non_mapped_size_in_bytes(
+        &self,
+        config: &str,
+        dtype: DType,
+        weight_pack_factor: usize,
+        matformer_config: Option<&MatformerSliceConfig>,
+    ) -> Result<usize> {
+        Qwen3EmbeddingLoader.non_mapped_size_in_bytes(
+            config,
+            dtype,
+            weight_pack_factor,
+            matformer_config,
+        )
+    }
+    fn layer_sizes_in_bytes(
+        &self,
+        config: &str,
+        dtype: DType,
+        weight_pack_factor: usize,
+        matformer_config: Option<&MatformerSliceConfig>,
+    ) -> Result<Vec<usize>> {
+        Qwen3EmbeddingLoader.layer_sizes_in_bytes(
+            config,
+            dtype,
+            weight_pack_factor,
+            matformer_config,
+        )
+    }
+    fn num_layers(&self, config: &str) -> Result<usize> {
+        Qwen3EmbeddingLoader.num_layers(config)
+    }
+    fn model_config(&self, config: &str) -> Result<Box<dyn ModelConfigLike>> {
+        Qwen3EmbeddingLoader.model_config(config)
+    }
+}
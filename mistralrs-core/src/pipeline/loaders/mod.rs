@@ -37,9 +37,9 @@ pub use multimodal_loaders::{
 };
 
 pub use embedding_loaders::{
-    AutoEmbeddingLoader, EmbeddingGemmaLoader, EmbeddingLoaderType, EmbeddingModel,
-    EmbeddingModelLoader, EmbeddingModule, EmbeddingModulePaths, EmbeddingModuleType,
-    Qwen3EmbeddingLoader,
+    AutoEmbeddingLoader, CrossEncoderFormat, EmbeddingGemmaLoader, EmbeddingLoaderType,
+    EmbeddingModel, EmbeddingModelLoader, EmbeddingModule, EmbeddingModulePaths,
+    EmbeddingModuleType, Qwen3EmbeddingLoader, Qwen3RerankerLoader,
 };
 
 pub use diffusion_loaders::{
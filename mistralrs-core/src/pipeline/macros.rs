@@ -259,13 +259,21 @@ macro_rules! get_embedding_paths {
 
         let mut parsed_modules = Vec::new();
         let is_local = std::path::Path::new(&$this.model_id).exists();
+        // Cross-encoders ship without `modules.json`.
         let modules_path = if is_local {
-            model_id.join("modules.json")
+            Some(model_id.join("modules.json"))
+        } else if emb_dir_list.contains(&"modules.json".to_string()) {
+            Some($crate::api_get_file!(
+                api,
+                "modules.json",
+                model_id,
+                &revision
+            ))
         } else {
-            $crate::api_get_file!(api, "modules.json", model_id, &revision)
+            None
         };
 
-        if modules_path.exists() {
+        if let Some(modules_path) = modules_path.filter(|path| path.exists()) {
             let modules: Vec<$crate::pipeline::EmbeddingModule> =
                 serde_json::from_str(&std::fs::read_to_string(&modules_path)?)?;
             for module in modules {
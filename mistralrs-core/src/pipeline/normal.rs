@@ -1295,7 +1295,11 @@ impl Pipeline for NormalPipeline {
         rng: Arc<std::sync::Mutex<Isaac64Rng>>,
         metadata: Option<crate::pipeline::text_models_inputs_processor::PagedAttentionMeta>,
     ) -> candle_core::Result<bool> {
-        if !self.model.has_speculative_proposer() {
+        // Predicted outputs need no proposer, only a KV cache that can be rolled back.
+        let verify_predictions = !self.model.is_xlora()
+            && !self.cache().is_hybrid()
+            && seqs.iter().any(|seq| seq.has_prediction());
+        if !self.model.has_speculative_proposer() && !verify_predictions {
             crate::speculative::driver::clear_staged_speculative_tokens(seqs);
             return Ok(false);
         }
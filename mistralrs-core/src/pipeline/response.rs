@@ -1,4 +1,4 @@
-use std::{io::Cursor, sync::Arc};
+use std::{io::Cursor, path::Path, sync::Arc};
 
 use base64::{engine::general_purpose::STANDARD, Engine};
 use candle_core::Tensor;
@@ -10,6 +10,20 @@ use crate::{
     ImageChoice, ImageGenerationResponse, ImageGenerationResponseFormat,
 };
 
+/// Where the `index`-th image of a request is saved. Images after the first get `-{index}`
+/// before the extension so they do not overwrite it.
+fn numbered_path(path: &Path, index: usize) -> String {
+    if index == 0 {
+        return path.to_string_lossy().into_owned();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}-{index}.{}", ext.to_string_lossy()),
+        None => format!("{stem}-{index}"),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
 pub async fn send_image_responses(
     input_seqs: &mut [&mut Sequence],
     images: Vec<DynamicImage>,
@@ -29,7 +43,7 @@ pub async fn send_image_responses(
         {
             ImageGenerationResponseFormat::Url => {
                 let saved_file = match seq.image_gen_save_file() {
-                    Some(path) => path.to_string_lossy().into_owned(),
+                    Some(path) => numbered_path(path, seq.get_response_index()),
                     None => format!("image-generation-{}.png", Uuid::new_v4()),
                 };
                 image
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 use candle_core::{DType, Result, Tensor};
 use rand_isaac::Isaac64Rng;
@@ -180,6 +180,7 @@ pub(crate) async fn finish_or_add_toks_to_seq(
                         tool_calls
                     };
 
+                    let stop_sequence = is_done.and_then(|x| seq.matched_stop_string(x));
                     seq.add_streaming_chunk_choice_to_group(crate::ChunkChoice {
                         delta: crate::Delta {
                             content: fixup_sentencepiece!(Option content_delta),
@@ -199,6 +200,7 @@ pub(crate) async fn finish_or_add_toks_to_seq(
                         } else {
                             None
                         },
+                        stop_sequence,
                     });
                 } else {
                     seq.add_streaming_completion_chunk_choice_to_group(
@@ -221,19 +223,16 @@ pub(crate) async fn finish_or_add_toks_to_seq(
                 }
             }
 
-            // Send usage on final chunk.
-            let usage_opt = if is_done.is_some() {
-                let usage = seq.get_mut_group().get_usage();
+            // Every chunk carries the usage so far; the final chunk has the totals.
+            let usage = seq.get_mut_group().get_usage();
+            if is_done.is_some() {
                 seq.get_mut_group().total_prompt_toks = 0;
                 seq.get_mut_group().total_toks = 0;
-                Some(usage)
-            } else {
-                None
-            };
+            }
 
             if seq
                 .get_mut_group()
-                .maybe_send_streaming_response(seq, this.name().clone(), usage_opt)
+                .maybe_send_streaming_response(seq, this.name().clone(), usage)
                 .await
                 .is_err()
             {
@@ -377,6 +376,7 @@ pub(crate) async fn finish_or_add_toks_to_seq(
                         reasoning_content,
                     },
                     logprobs: logprobs.map(|l| crate::Logprobs { content: Some(l) }),
+                    stop_sequence: seq.matched_stop_string(reason),
                 };
                 seq.add_choice_to_group(choice);
             } else {
@@ -412,7 +412,7 @@ pub(crate) async fn finish_or_add_toks_to_seq(
                             choices: group.get_choices().to_vec(),
                             created: seq.creation_time(),
                             model: pipeline_name,
-                            system_fingerprint: crate::SYSTEM_FINGERPRINT.to_string(),
+                            system_fingerprint: seq.system_fingerprint(),
                             object: "chat.completion".to_string(),
                             usage: group.get_usage(),
                             agentic_tool_calls: None,
@@ -431,7 +431,7 @@ pub(crate) async fn finish_or_add_toks_to_seq(
                             choices: group.get_completion_choices().to_vec(),
                             created: seq.creation_time(),
                             model: pipeline_name,
-                            system_fingerprint: crate::SYSTEM_FINGERPRINT.to_string(),
+                            system_fingerprint: seq.system_fingerprint(),
                             object: "text_completion".to_string(),
                             usage: group.get_usage(),
                         },
@@ -505,6 +505,7 @@ pub async fn sample_sequence(
 ) -> Result<Logprobs> {
     let logits = logits.squeeze(0)?.squeeze(0)?.to_dtype(DType::F32)?;
 
+    let rng = seq.sampling_rng(&rng);
     let sampler = seq.sampler();
     let ctx_clone = seq.get_toks().to_vec();
     let rng_clone = rng.clone();
@@ -532,6 +533,7 @@ pub async fn sample_sequence(
         )?
     };
 
+    let mut mask_time = None;
     let bias_if_not_allowed = match &mut seq.recognizer {
         SequenceRecognizer::Llguidance(ref mut llg) => {
             if !llg.is_stopped()
@@ -542,7 +544,9 @@ pub async fn sample_sequence(
             {
                 None
             } else {
+                let started = Instant::now();
                 let mask = llg.compute_mask_or_eos().map_err(candle_core::Error::msg)?;
+                mask_time = Some(started.elapsed());
                 if mask.is_allowed(first_lobprobs_response.token) {
                     // shouldn't really happen, except for EOS
                     None
@@ -592,6 +596,9 @@ pub async fn sample_sequence(
         }
         None => first_lobprobs_response,
     };
+    if let (Some(elapsed), Some(trace)) = (mask_time, seq.trace()) {
+        trace.record_grammar_mask(elapsed);
+    }
 
     match seq.recognizer {
         SequenceRecognizer::Llguidance(ref mut llg) => {
@@ -16,7 +16,7 @@ use crate::utils::varbuilder_utils::DeviceForLoadTensor;
 use crate::utils::{tokens::get_token, varbuilder_utils::from_mmaped_safetensors};
 use crate::{
     api_get_file, distributed, DeviceMapSetting, MessageContent, PagedAttentionConfig, Pipeline,
-    SpeechGenerationConfig, TryIntoDType,
+    Response, SpeechGenerationConfig, SpeechReference, TryIntoDType,
 };
 use anyhow::Result;
 use candle_core::{Device, Tensor};
@@ -31,7 +31,7 @@ use std::env;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokenizers::Tokenizer;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc::Sender, Mutex};
 
 #[derive(Clone, Debug)]
 pub struct SpeechModelPaths {
@@ -106,6 +106,10 @@ pub struct SpeechInputsProcessor;
 #[derive(Clone)]
 pub struct ModelInputs {
     pub(crate) prompts: Vec<String>,
+    /// Where to send audio chunks, for sequences that requested streaming.
+    pub(crate) streams: Vec<Option<Sender<Response>>>,
+    /// Voice to clone for each sequence.
+    pub(crate) references: Vec<Option<SpeechReference>>,
 }
 
 impl InputsProcessor for SpeechInputsProcessor {
@@ -133,6 +137,14 @@ impl InputsProcessor for SpeechInputsProcessor {
                 .iter()
                 .map(|seq| seq.get_initial_prompt().to_string())
                 .collect(),
+            streams: input_seqs
+                .iter()
+                .map(|seq| seq.get_mut_group().is_streaming.then(|| seq.responder()))
+                .collect(),
+            references: input_seqs
+                .iter()
+                .map(|seq| seq.speech_reference().cloned())
+                .collect(),
         };
         Ok(InputProcessorOutput {
             inputs: Box::new(inputs),
@@ -402,16 +414,36 @@ impl Pipeline for SpeechPipeline {
     ) -> candle_core::Result<ForwardInputsResult> {
         assert!(!return_raw_logits);
 
-        let ModelInputs { prompts } = *inputs.downcast().expect("Downcast failed.");
+        let ModelInputs {
+            prompts,
+            streams,
+            references,
+        } = *inputs.downcast().expect("Downcast failed.");
         let mut pcms = Vec::new();
         let mut rates = Vec::new();
         let mut channels_all = Vec::new();
-        for prompt in prompts {
+        for ((prompt, stream), reference) in prompts.into_iter().zip(streams).zip(references) {
+            let mut send_chunk = stream.map(|tx| {
+                move |chunk: SpeechGenerationOutput| {
+                    let chunk = Response::SpeechChunk {
+                        pcm: chunk.pcm,
+                        rate: chunk.rate,
+                        channels: chunk.channels,
+                    };
+                    // A client that went away is handled when the final response is sent.
+                    let _ = tokio::task::block_in_place(|| tx.blocking_send(chunk));
+                }
+            });
+            let on_chunk = send_chunk
+                .as_mut()
+                .map(|f| f as &mut dyn FnMut(SpeechGenerationOutput));
             let SpeechGenerationOutput {
                 pcm,
                 rate,
                 channels,
-            } = self.model.generate(&prompt, &self.cfg)?;
+            } = self
+                .model
+                .generate(&prompt, reference.as_ref(), &self.cfg, on_chunk)?;
             pcms.push(pcm);
             rates.push(rate);
             channels_all.push(channels);
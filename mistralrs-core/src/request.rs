@@ -10,11 +10,11 @@ use serde_json::Value;
 use crate::VideoInput;
 
 use crate::{
-    response::Response, sampler::SamplingParams, tools::ToolChoice, AgentPermission,
-    AgentToolApprovalHandler, CodeExecutionPermission, CustomLogitsProcessor,
-    DiffusionGenerationParams, Tool,
+    engine::AgenticLoopPermit, response::Response, sampler::SamplingParams,
+    telemetry::TraceContext, tools::ToolChoice, AgentPermission, AgentToolApprovalHandler,
+    CodeExecutionPermission, CustomLogitsProcessor, DiffusionGenerationParams, Tool,
 };
-use std::{fmt::Debug, path::PathBuf, sync::Arc};
+use std::{fmt::Debug, path::PathBuf, sync::Arc, time::Duration};
 use tokio::sync::mpsc::Sender;
 
 pub type LlguidanceGrammar = llguidance::api::TopLevelGrammar;
@@ -67,6 +67,44 @@ impl ReasoningEffort {
     }
 }
 
+/// Scheduling class of a request. Both schedulers admit higher classes first and preempt lower
+/// classes first. Waiting requests age into higher classes so low priority work is never starved.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "pyo3_macros", pyo3::pyclass(eq, eq_int))]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum RequestPriority {
+    /// Background work such as batch jobs
+    Low,
+    #[default]
+    Normal,
+    /// Latency-sensitive, interactive traffic
+    High,
+}
+
+impl RequestPriority {
+    /// Numeric rank: 0 for `Low`, 1 for `Normal`, 2 for `High`.
+    pub fn rank(&self) -> usize {
+        match self {
+            Self::Low => 0,
+            Self::Normal => 1,
+            Self::High => 2,
+        }
+    }
+}
+
+/// Order of the prefix and suffix in a fill-in-the-middle prompt.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum FimMode {
+    /// Prefix, suffix, then the middle is generated
+    #[default]
+    Psm,
+    /// Suffix, prefix, then the middle is generated. Only for model families trained on it
+    Spm,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 /// Message or messages for a [`Request`].
 pub enum RequestMessage {
@@ -80,6 +118,9 @@ pub enum RequestMessage {
         text: String,
         echo_prompt: bool,
         best_of: Option<usize>,
+        /// Prompt order when the request's `suffix` is filled in with the model's FIM tokens.
+        #[serde(default)]
+        fim_mode: FimMode,
     },
     CompletionTokens(Vec<u32>),
     MultimodalChat {
@@ -102,6 +143,9 @@ pub enum RequestMessage {
     },
     SpeechGeneration {
         prompt: String,
+        /// Voice to clone, if any.
+        #[serde(skip)]
+        reference: Option<SpeechReference>,
     },
     Embedding {
         prompt: String,
@@ -111,6 +155,15 @@ pub enum RequestMessage {
     },
 }
 
+/// A recording of the voice to use for speech generation, with a transcript of what is said
+/// in it. The model continues the recording, so the transcript should use the same speaker
+/// tags as the prompt.
+#[derive(Clone, Debug)]
+pub struct SpeechReference {
+    pub audio: AudioInput,
+    pub transcript: String,
+}
+
 fn default_responder<T>() -> Sender<T> {
     let (sender, _) = tokio::sync::mpsc::channel(1);
     sender
@@ -221,7 +274,8 @@ impl WebSearchOptions {
 /// - `is_streaming`: Control whether the request is streaming, if so chunk responses will be sent
 /// - `id`: Request ID
 /// - `constraint`: Constraint to use during generation
-/// - `suffix`: Suffix to add
+/// - `suffix`: Text after the completion. Models with fill-in-the-middle tokens infill between
+///   the prompt and the suffix; for other models it is appended to the output.
 /// - `tools`: Tools available in this request
 /// - `tool_choice`: Choice of tools
 /// - `logits_processors`: Custom logits processors. Order of application:
@@ -273,6 +327,32 @@ pub struct NormalRequest {
     /// Required output files. The runtime asks the model to produce them and surfaces a `File` (or error placeholder) for each.
     #[serde(default)]
     pub files: Option<Vec<crate::files::RequestedFile>>,
+    /// Uploaded files attached to the messages. Registered with the session for `read_file` /
+    /// `list_files` and staged into the code-execution working directory.
+    #[serde(default)]
+    pub input_files: Option<Vec<crate::files::File>>,
+    /// Scheduling class used for admission and preemption.
+    #[serde(default)]
+    pub priority: RequestPriority,
+    /// Time allowed for the whole request, tool rounds included, counted from when the engine
+    /// receives it. Past the deadline the sequence finishes with `StopReason::Canceled`.
+    #[serde(default)]
+    pub timeout: Option<Duration>,
+    /// Trace to record this request's spans in, usually from the caller's `traceparent`.
+    #[serde(skip)]
+    pub trace_context: Option<TraceContext>,
+    /// Who sent the request, e.g. the name of the API key used. Recorded in the audit log and
+    /// stamped as the owner of the files and agentic sessions the request produces.
+    #[serde(default)]
+    pub user: Option<String>,
+    /// Text the output is expected to largely repeat, such as a file being edited. Its tokens
+    /// are verified speculatively, so matching spans are accepted several per forward pass.
+    /// Takes effect with PagedAttention on text models.
+    #[serde(default)]
+    pub prediction: Option<String>,
+    /// Slot held against the model's agentic loop limit. Set at admission; leave `None`.
+    #[serde(skip)]
+    pub agentic_permit: Option<AgenticLoopPermit>,
 }
 
 impl NormalRequest {
@@ -310,6 +390,13 @@ impl NormalRequest {
             truncate_sequence: false,
             session_id: None,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }
     }
 }
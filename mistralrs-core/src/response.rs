@@ -1,18 +1,32 @@
 use std::{
     error::Error,
     fmt::{Debug, Display},
+    hash::{DefaultHasher, Hash, Hasher},
     sync::Arc,
 };
 
-use candle_core::Tensor;
+use candle_core::{DType, Device, Tensor};
 #[cfg(feature = "pyo3_macros")]
 use pyo3::{pyclass, pymethods};
 use serde::Serialize;
 
 use crate::{sampler::TopLogprob, tools::ToolCallResponse};
 
+/// Fingerprint used when a response is not produced by a loaded model.
 pub const SYSTEM_FINGERPRINT: &str = "local";
 
+/// Fingerprint of the backend configuration serving `model`: the mistral.rs version, the model,
+/// its device and its activation dtype. Seeded requests give the same output only while this
+/// fingerprint stays the same.
+pub fn system_fingerprint(model: &str, device: &Device, dtype: DType) -> String {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    model.hash(&mut hasher);
+    format!("{:?}", device.location()).hash(&mut hasher);
+    dtype.as_str().hash(&mut hasher);
+    format!("fp_{:016x}", hasher.finish())
+}
+
 macro_rules! generate_repr {
     ($t:ident) => {
         #[cfg(feature = "pyo3_macros")]
@@ -89,6 +103,9 @@ pub struct Choice {
     pub index: usize,
     pub message: ResponseMessage,
     pub logprobs: Option<Logprobs>,
+    /// The stop string that ended generation, if one did. Not part of the OpenAI schema.
+    #[serde(skip)]
+    pub stop_sequence: Option<String>,
 }
 
 generate_repr!(Choice);
@@ -102,6 +119,9 @@ pub struct ChunkChoice {
     pub index: usize,
     pub delta: Delta,
     pub logprobs: Option<ResponseLogprob>,
+    /// The stop string that ended generation, if one did. Not part of the OpenAI schema.
+    #[serde(skip)]
+    pub stop_sequence: Option<String>,
 }
 
 generate_repr!(ChunkChoice);
@@ -133,10 +153,26 @@ pub struct Usage {
     pub total_time_sec: f32,
     pub total_prompt_time_sec: f32,
     pub total_completion_time_sec: f32,
+    /// Only set when the request had a `prediction`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
 }
 
 generate_repr!(Usage);
 
+#[cfg_attr(feature = "pyo3_macros", pyclass)]
+#[cfg_attr(feature = "pyo3_macros", pyo3(get_all))]
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+/// How the predicted output of a request fared during speculative verification.
+pub struct CompletionTokensDetails {
+    /// Predicted tokens that were verified and kept in the output.
+    pub accepted_prediction_tokens: usize,
+    /// Predicted tokens that were verified and rejected. They cost compute like output tokens.
+    pub rejected_prediction_tokens: usize,
+}
+
+generate_repr!(CompletionTokensDetails);
+
 #[cfg_attr(feature = "pyo3_macros", pyclass)]
 #[cfg_attr(feature = "pyo3_macros", pyo3(get_all))]
 #[derive(Debug, Clone, Serialize)]
@@ -191,6 +227,7 @@ pub struct ChatCompletionChunkResponse {
     pub model: String,
     pub system_fingerprint: String,
     pub object: String,
+    /// Cumulative usage and timing up to this chunk. The final chunk carries the totals.
     pub usage: Option<Usage>,
     /// Set on the final chunk so streaming clients can read it.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -239,6 +276,8 @@ pub struct CompletionChunkResponse {
     pub model: String,
     pub system_fingerprint: String,
     pub object: String,
+    /// Cumulative usage and timing up to this chunk. The final chunk carries the totals.
+    pub usage: Option<Usage>,
 }
 
 generate_repr!(CompletionChunkResponse);
@@ -320,6 +359,12 @@ pub enum Response {
         rate: usize,
         channels: usize,
     },
+    /// Audio decoded so far by a streaming speech request; a final `Speech` follows.
+    SpeechChunk {
+        pcm: Arc<Vec<f32>>,
+        rate: usize,
+        channels: usize,
+    },
     // Raw
     Raw {
         logits_chunks: Vec<Tensor>,
@@ -363,6 +408,11 @@ pub enum ResponseOk {
         rate: usize,
         channels: usize,
     },
+    SpeechChunk {
+        pcm: Arc<Vec<f32>>,
+        rate: usize,
+        channels: usize,
+    },
     // Raw
     Raw {
         logits_chunks: Vec<Tensor>,
@@ -459,6 +509,15 @@ impl Response {
                 rate,
                 channels,
             }),
+            Self::SpeechChunk {
+                pcm,
+                rate,
+                channels,
+            } => Ok(ResponseOk::SpeechChunk {
+                pcm,
+                rate,
+                channels,
+            }),
             Self::Raw {
                 logits_chunks,
                 tokens,
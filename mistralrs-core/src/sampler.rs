@@ -70,6 +70,10 @@ pub struct SamplingParams {
     pub logits_bias: Option<HashMap<u32, f32>>,
     pub n_choices: usize,
     pub dry_params: Option<DrySamplingParams>,
+    /// Seed for this request's sampling RNG. Seeded requests sample from their own stream, so
+    /// the output does not depend on what else is in the batch.
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 impl SamplingParams {
@@ -94,6 +98,7 @@ impl SamplingParams {
             logits_bias: None,
             n_choices: 1,
             dry_params: None,
+            seed: None,
         }
     }
 
@@ -116,6 +121,7 @@ impl SamplingParams {
             logits_bias: None,
             n_choices: 1,
             dry_params: None,
+            seed: None,
         }
     }
 
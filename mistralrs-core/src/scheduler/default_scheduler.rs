@@ -1,4 +1,5 @@
 use std::{
+    cmp::Reverse,
     collections::{HashMap, VecDeque},
     num::NonZeroUsize,
     sync::{atomic::Ordering, Arc},
@@ -17,7 +18,10 @@ pub trait FcfsBacker: Default {
     fn add(&mut self, item: Sequence);
     fn into_iter(self) -> impl Iterator<Item = Sequence>;
     fn len(&self) -> usize;
-    fn sort_ascending_ids(&mut self);
+    fn iter(&self) -> impl Iterator<Item = &Sequence>;
+    fn retain(&mut self, f: impl FnMut(&Sequence) -> bool);
+    /// Order by effective priority (highest first), then by ascending ID.
+    fn sort_by_priority(&mut self);
 }
 
 impl FcfsBacker for VecDeque<Sequence> {
@@ -30,13 +34,20 @@ impl FcfsBacker for VecDeque<Sequence> {
     fn into_iter(self) -> impl Iterator<Item = Sequence> {
         <Self as IntoIterator>::into_iter(self)
     }
-    fn sort_ascending_ids(&mut self) {
+    fn sort_by_priority(&mut self) {
         let slice = self.make_contiguous();
-        slice.sort_by_key(|seq| *seq.id());
+        // Cached keys: effective priority changes with time, so it must not change mid-sort.
+        slice.sort_by_cached_key(|seq| (Reverse(seq.effective_priority()), *seq.id()));
     }
     fn len(&self) -> usize {
         VecDeque::len(self)
     }
+    fn iter(&self) -> impl Iterator<Item = &Sequence> {
+        VecDeque::iter(self)
+    }
+    fn retain(&mut self, f: impl FnMut(&Sequence) -> bool) {
+        VecDeque::retain(self, f)
+    }
 }
 
 pub struct DefaultSchedulerOutput<'a> {
@@ -128,6 +139,14 @@ impl<Backer: FcfsBacker> BucketingManager<Backer> for FixedBucketingManager {
                 }
             }
         }
+        // The highest effective priority present in each bucket.
+        let bucket_priorities: HashMap<BucketKey, usize> = seq_buckets
+            .iter()
+            .map(|(key, seqs)| {
+                let priority = seqs.iter().map(Sequence::effective_priority).max();
+                (*key, priority.unwrap_or_default())
+            })
+            .collect();
         let running = if seq_buckets.len() <= 1 {
             // Full steam ahead or have everything
             seq_buckets
@@ -137,10 +156,10 @@ impl<Backer: FcfsBacker> BucketingManager<Backer> for FixedBucketingManager {
                 .collect::<Vec<_>>()
         } else {
             // Set the min seqs to be the running ones, and the rest to be waiting (but their states are not changed!)
-            // Allow the min seqs to catch up.
+            // Allow the min seqs to catch up. Only buckets holding the highest priority class are considered.
             let min = *seq_buckets
                 .keys()
-                .min_by_key(|(x, _, _)| *x)
+                .min_by_key(|key| (Reverse(bucket_priorities[*key]), key.0))
                 .expect("No sequence buckets.");
             let len = if !discrete {
                 seq_priorities
@@ -207,6 +226,8 @@ impl<Backer: FcfsBacker> DefaultScheduler<Backer> {
         // Filter out all done sequences
         let running = std::mem::take(&mut self.running);
         let mut waiting = std::mem::take(&mut self.waiting);
+        // Nobody is listening for these, so drop them without a final step.
+        waiting.retain(|seq| !seq.can_drop_while_waiting());
         let mut running = running
             .into_iter()
             .filter(|seq| seq.is_running())
@@ -215,8 +236,7 @@ impl<Backer: FcfsBacker> DefaultScheduler<Backer> {
         match (waiting.len(), running.len()) {
             (0, 0) => {
                 self.running = running;
-                logger.set_num_running(self.running.len());
-                logger.set_num_waiting(self.waiting.len());
+                self.report_queues(logger);
                 return DefaultSchedulerOutput {
                     prompt: vec![].into(),
                     completion: vec![].into(),
@@ -230,8 +250,9 @@ impl<Backer: FcfsBacker> DefaultScheduler<Backer> {
                 self.waiting = Backer::new();
                 let running = std::mem::take(&mut self.running);
                 self.running = self.bucket_and_waitlist_seqs(running);
-                logger.set_num_running(self.running.len());
-                logger.set_num_waiting(self.waiting.len());
+                self.running.iter_mut().for_each(Sequence::mark_admitted);
+                self.cancel_abandoned();
+                self.report_queues(logger);
                 return DefaultSchedulerOutput {
                     prompt: self.running.iter_mut().collect::<Vec<_>>().into(),
                     completion: vec![].into(),
@@ -245,8 +266,8 @@ impl<Backer: FcfsBacker> DefaultScheduler<Backer> {
                         .for_each(|seq| seq.set_state(SequenceState::Done(StopReason::Canceled)));
                     TERMINATE_ALL_NEXT_STEP.store(false, Ordering::SeqCst);
                 }
-                logger.set_num_running(self.running.len());
-                logger.set_num_waiting(self.waiting.len());
+                self.cancel_abandoned();
+                self.report_queues(logger);
                 return DefaultSchedulerOutput {
                     prompt: vec![].into(),
                     completion: self.running.iter_mut().collect::<Vec<_>>().into(),
@@ -256,17 +277,25 @@ impl<Backer: FcfsBacker> DefaultScheduler<Backer> {
         }
 
         // Sort the waiting seqs
-        waiting.sort_ascending_ids();
+        waiting.sort_by_priority();
 
-        // If the waiting sequence will fit, add it. Otherwise remove it
+        // If the waiting sequence will fit, add it, preempting a running sequence of a lower
+        // class if needed. Otherwise remove it
         let mut new_waiting = Backer::new();
-        for seq in waiting.into_iter() {
+        for mut seq in waiting.into_iter() {
+            if !self.sequence_fits(&running, &seq) {
+                if let Some(idx) = lowest_priority_below(&running, seq.effective_priority()) {
+                    // The preempted sequence keeps its state and cache, like a bucketed-out one.
+                    new_waiting.add(running.remove(idx));
+                }
+            }
             if self.sequence_fits(&running, &seq) {
                 if seq.is_waiting() {
                     seq.set_state(SequenceState::RunningPrompt);
                 }
                 running.push(seq);
             } else {
+                seq.mark_waiting();
                 new_waiting.add(seq);
             }
         }
@@ -281,8 +310,9 @@ impl<Backer: FcfsBacker> DefaultScheduler<Backer> {
         self.running = running;
         self.waiting = new_waiting;
 
-        logger.set_num_running(self.running.len());
-        logger.set_num_waiting(self.waiting.len());
+        self.running.iter_mut().for_each(Sequence::mark_admitted);
+        self.cancel_abandoned();
+        self.report_queues(logger);
 
         let mut completion = Vec::new();
         let mut prompt = Vec::new();
@@ -300,6 +330,17 @@ impl<Backer: FcfsBacker> DefaultScheduler<Backer> {
         }
     }
 
+    /// Cancel scheduled sequences whose client disconnected or whose deadline passed.
+    fn cancel_abandoned(&self) {
+        self.running.iter().for_each(Sequence::cancel_if_abandoned);
+    }
+
+    fn report_queues(&self, logger: &IntervalLogger) {
+        logger.set_num_running(self.running.len());
+        logger.set_num_waiting(self.waiting.len());
+        logger.set_waiting_prompt_tokens(self.waiting.iter().map(Sequence::prompt_tokens).sum());
+    }
+
     fn sequence_fits(&self, running: &[Sequence], _seq: &Sequence) -> bool {
         match &self.method {
             DefaultSchedulerMethod::Fixed(n) => (running.len() + 1) <= (*n).into(),
@@ -307,6 +348,18 @@ impl<Backer: FcfsBacker> DefaultScheduler<Backer> {
     }
 }
 
+/// Index of the most recently admitted running sequence with the lowest effective priority, if
+/// that priority is below `priority`.
+fn lowest_priority_below(running: &[Sequence], priority: usize) -> Option<usize> {
+    running
+        .iter()
+        .enumerate()
+        .map(|(i, seq)| (i, seq.effective_priority()))
+        .filter(|&(_, p)| p < priority)
+        .min_by_key(|&(i, p)| (p, Reverse(i)))
+        .map(|(i, _)| i)
+}
+
 impl Scheduler for DefaultScheduler<VecDeque<Sequence>> {
     fn schedule(&mut self, logger: &IntervalLogger) -> SchedulerOutput<'_> {
         SchedulerOutput::DefaultScheduler {
@@ -348,3 +401,87 @@ impl Scheduler for DefaultScheduler<VecDeque<Sequence>> {
         // DefaultScheduler doesn't use PagedAttention prefix caching
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::{channel, Receiver};
+
+    use super::*;
+    use crate::{
+        request::RequestPriority,
+        response::Response,
+        sampler::Sampler,
+        sequence::{SeqStepType, SequenceGroup, SequenceRecognizer},
+    };
+
+    /// A waiting sequence and the receiver that keeps its client connected.
+    fn sequence(id: usize, priority: RequestPriority) -> (Sequence, Receiver<Response>) {
+        let (tx, rx) = channel(1);
+        let sampler =
+            Sampler::new(None, 0, None, None, None, None, None, 32, 1.0, 0.0, vec![]).unwrap();
+        let group = Arc::new(tokio::sync::Mutex::new(SequenceGroup::new(
+            1, false, true, None,
+        )));
+        let mut seq = Sequence::new_waiting(
+            vec![1, 2, 3, 4],
+            "prompt".to_string(),
+            id,
+            id as u128,
+            0,
+            tx,
+            sampler,
+            vec![],
+            vec![],
+            None,
+            false,
+            false,
+            group,
+            0,
+            0,
+            SequenceRecognizer::None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            SeqStepType::PromptAndDecode,
+            None,
+            None,
+            None,
+            false,
+            vec![],
+        );
+        seq.set_priority(priority);
+        (seq, rx)
+    }
+
+    #[test]
+    fn aged_low_sequence_survives_normal_arrivals() {
+        let logger = IntervalLogger::new(Duration::from_secs(60), None);
+        let mut scheduler: DefaultScheduler<VecDeque<Sequence>> =
+            DefaultScheduler::new(DefaultSchedulerMethod::Fixed(1.try_into().unwrap()));
+        let mut receivers = Vec::new();
+
+        // The batch job has waited one aging interval, so it is admitted as `Normal`.
+        let (mut low, rx) = sequence(0, RequestPriority::Low);
+        low.set_waited(Duration::from_secs(30));
+        receivers.push(rx);
+        scheduler.waiting.add(low);
+        scheduler.schedule(&logger);
+
+        for id in 1..=10 {
+            let (normal, rx) = sequence(id, RequestPriority::Normal);
+            receivers.push(rx);
+            scheduler.waiting.add(normal);
+            scheduler.schedule(&logger);
+            let running: Vec<usize> = scheduler.running.iter().map(|seq| *seq.id()).collect();
+            assert_eq!(running, vec![0], "preempted by arrival {id}");
+        }
+        assert_eq!(scheduler.waiting.len(), 10);
+    }
+}
@@ -40,6 +40,16 @@ impl SchedulerConfig {
             ))),
         }
     }
+
+    /// Maximum number of sequences the scheduler runs at once.
+    pub fn max_seqs(&self) -> usize {
+        match self {
+            Self::DefaultScheduler {
+                method: DefaultSchedulerMethod::Fixed(n),
+            } => n.get(),
+            Self::PagedAttentionMeta { max_num_seqs, .. } => *max_num_seqs,
+        }
+    }
 }
 
 pub enum SchedulerOutput<'a> {
@@ -258,7 +258,8 @@ fn format_document_prompt(title: &str, text: &str) -> String {
     format!("title: {title} | text: {}", text.trim())
 }
 
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+/// Cosine similarity of two embeddings, or `0.0` if either is all zeros.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
     let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
     let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
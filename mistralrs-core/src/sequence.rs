@@ -1,11 +1,17 @@
 use crate::{
+    audit::SequenceAudit,
     get_mut_arcmutex, get_mut_group,
     paged_attention::block_hash::MultiModalFeature,
     pipeline::{text_models_inputs_processor::PagedAttentionMeta, LayerCaches},
     reasoning_parsers::{ReasoningMode, ReasoningParser},
-    response::{ChatCompletionChunkResponse, Choice, ChunkChoice, Response, SYSTEM_FINGERPRINT},
+    response::{
+        ChatCompletionChunkResponse, Choice, ChunkChoice, CompletionTokensDetails, Response,
+        SYSTEM_FINGERPRINT,
+    },
     sampler::{Logprobs, Sampler},
-    AudioInput, ChatCompletionResponse, Usage, VideoInput,
+    speculative::prediction::PredictedOutput,
+    telemetry::SequenceTrace,
+    AudioInput, ChatCompletionResponse, RequestPriority, SpeechReference, Usage, VideoInput,
 };
 use crate::{
     pipeline::{DiffusionGenerationParams, KvCache},
@@ -15,12 +21,17 @@ use crate::{
     ImageGenerationResponse, ImageGenerationResponseFormat,
 };
 use candle_core::Tensor;
+use rand::SeedableRng;
+use rand_isaac::Isaac64Rng;
 use std::{
     fmt::Display,
     hash::{DefaultHasher, Hash, Hasher},
     path::PathBuf,
-    sync::{Arc, RwLock},
-    time::{Instant, SystemTime, UNIX_EPOCH},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use tokio::sync::{
     mpsc::{error::SendError, Sender},
@@ -29,6 +40,12 @@ use tokio::sync::{
 
 pub type SeqPreallocatedCache = Vec<Option<(Tensor, Tensor)>>;
 
+/// Time spent waiting for admission after which a sequence is treated as one priority class
+/// higher.
+const PRIORITY_AGING_INTERVAL: Duration = Duration::from_secs(30);
+/// Bucketing weight of one priority class, in passes of scheduling urgency.
+const PRIORITY_CLASS_WEIGHT: f64 = 16.0;
+
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum StopReason {
     Eos,
@@ -234,6 +251,7 @@ pub struct MultimodalData {
     pub image_gen_response_format: Option<ImageGenerationResponseFormat>,
     pub diffusion_params: Option<DiffusionGenerationParams>,
     pub image_gen_save_file: Option<PathBuf>,
+    pub speech_reference: Option<SpeechReference>,
     /// Per-item multimodal feature positions for prefix caching block hashing.
     /// Each entry records which token range a multimodal item (image/audio) occupies,
     /// so that only blocks overlapping with that item include its content hash.
@@ -263,6 +281,7 @@ impl MultimodalData {
             image_gen_response_format,
             diffusion_params,
             image_gen_save_file,
+            speech_reference: None,
             mm_features: Vec::new(),
         }
     }
@@ -489,6 +508,9 @@ pub struct Sequence {
     max_len: Option<usize>,
     timestamp: u128,
     sampler: Arc<Sampler>,
+    /// Per-sequence RNG for seeded requests. Unseeded sequences share the engine's RNG.
+    rng: Option<Arc<std::sync::Mutex<Isaac64Rng>>>,
+    system_fingerprint: Arc<str>,
     stop_tokens: Vec<u32>,
     stop_strings: Vec<String>,
     return_logprobs: bool,
@@ -511,6 +533,7 @@ pub struct Sequence {
     // Speculative
     staged_speculative_tokens: Vec<u32>,
     staged_speculative_logits: Option<Tensor>,
+    prediction: Option<PredictedOutput>,
 
     // Prefix caching
     prefill_prompt_toks: Option<Vec<u32>>,
@@ -542,12 +565,19 @@ pub struct Sequence {
     stream_idx: usize,
     pub recognizer: SequenceRecognizer,
     scheduling_urgency: usize, // The number of passes since scheduling
+    priority: RequestPriority,
+    waiting_since: Option<Instant>, // When the sequence started waiting for admission
+    admitted_rank: usize,           // Effective priority at the last admission, aging included
+    deadline: Option<Instant>,
+    trace: Option<SequenceTrace>,
+    audit: Option<SequenceAudit>,
 
     // GPU things
     pub prompt_tok_per_sec: f32,
     pub prompt_timestamp: Option<u128>,
     pub total_prompt_time: Option<u128>,
     pub step_start_instant: Option<Instant>,
+    first_token_observed: AtomicBool,
     group: Arc<Mutex<SequenceGroup>>,
     state: RwLock<SequenceState>,
 
@@ -624,6 +654,8 @@ impl Sequence {
             seq_preallocated_cache,
             responder,
             sampler: sampler.into(),
+            rng: None,
+            system_fingerprint: SYSTEM_FINGERPRINT.into(),
             stop_tokens,
             stop_strings,
             max_len,
@@ -647,7 +679,14 @@ impl Sequence {
             last_is_done: None,
             staged_speculative_tokens: Vec::new(),
             staged_speculative_logits: None,
+            prediction: None,
             scheduling_urgency: 0,
+            priority: RequestPriority::Normal,
+            waiting_since: None,
+            admitted_rank: 0,
+            deadline: None,
+            trace: None,
+            audit: None,
             // Multimodal data
             multimodal: MultimodalData::new(
                 input_images,
@@ -665,6 +704,7 @@ impl Sequence {
             eos_tokens,
             total_prompt_time: None,
             step_start_instant: None,
+            first_token_observed: AtomicBool::new(false),
             reasoning_parser: None,
             reasoning_mode: None,
         }
@@ -680,12 +720,96 @@ impl Sequence {
         self
     }
 
-    /// Simple metric: (scheduling urgency) + log2(length)
+    /// Simple metric: (scheduling urgency) + log2(length) + (class weight)
     /// Takes into account: urgency (scales linear) and length (scales logarithmic)
     /// Scaling urgency is the number of scheduling passes where we have not been scheduled.
+    /// Each effective priority class is worth `PRIORITY_CLASS_WEIGHT` passes of urgency.
     pub fn compute_priority(&self) -> f64 {
         #![allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
-        (self.scheduling_urgency as f64) + (self.len() as f64).log2()
+        (self.scheduling_urgency as f64)
+            + (self.len() as f64).log2()
+            + (self.effective_priority() as f64) * PRIORITY_CLASS_WEIGHT
+    }
+
+    pub fn priority(&self) -> RequestPriority {
+        self.priority
+    }
+
+    pub fn set_priority(&mut self, priority: RequestPriority) {
+        self.priority = priority;
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    pub(crate) fn set_trace(&mut self, trace: Option<SequenceTrace>) {
+        self.trace = trace;
+    }
+
+    pub(crate) fn trace(&self) -> Option<&SequenceTrace> {
+        self.trace.as_ref()
+    }
+
+    pub(crate) fn set_audit(&mut self, audit: Option<SequenceAudit>) {
+        self.audit = audit;
+    }
+
+    pub(crate) fn audit(&self) -> Option<&SequenceAudit> {
+        self.audit.as_ref()
+    }
+
+    /// Whether the receiving end of this sequence's responses was dropped, e.g. because the HTTP
+    /// client disconnected.
+    pub fn client_disconnected(&self) -> bool {
+        self.responder.is_closed()
+    }
+
+    /// Whether a waiting sequence can be discarded outright: its client disconnected and it holds
+    /// no recurrent state slot, which only the engine can free after a step.
+    pub fn can_drop_while_waiting(&self) -> bool {
+        self.client_disconnected() && self.recurrent_state_idx().is_none()
+    }
+
+    /// Mark a running sequence `Done(Canceled)` once its client has disconnected or its deadline
+    /// has passed. It is stepped once more, which sends the final response and lets the scheduler
+    /// free its cache like any finished sequence.
+    pub fn cancel_if_abandoned(&self) {
+        let expired = self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline);
+        if (expired || self.client_disconnected()) && self.is_running() {
+            self.set_state(SequenceState::Done(StopReason::Canceled));
+        }
+    }
+
+    /// Start aging the sequence's priority, unless it is already waiting. Called on every
+    /// scheduling pass that leaves it waiting for admission.
+    pub fn mark_waiting(&mut self) {
+        self.waiting_since.get_or_insert_with(Instant::now);
+    }
+
+    /// Stop the waiting clock once admitted. The rank earned while waiting is kept, so an aged
+    /// sequence isn't preempted again by the class it waited behind.
+    pub fn mark_admitted(&mut self) {
+        self.admitted_rank = self.effective_priority();
+        self.waiting_since = None;
+    }
+
+    /// Rank used for admission and preemption: the request's class (or the rank it was last
+    /// admitted at, if higher), raised by one class for every `PRIORITY_AGING_INTERVAL` spent
+    /// waiting and capped at `High`.
+    pub fn effective_priority(&self) -> usize {
+        let aged = self.waiting_since.map_or(0, |since| {
+            (since.elapsed().as_secs() / PRIORITY_AGING_INTERVAL.as_secs()) as usize
+        });
+        (self.priority.rank().max(self.admitted_rank) + aged).min(RequestPriority::High.rank())
+    }
+
+    /// Pretend the sequence started waiting `waited` ago.
+    #[cfg(test)]
+    pub(crate) fn set_waited(&mut self, waited: Duration) {
+        self.waiting_since = Instant::now().checked_sub(waited);
     }
 
     pub fn prefill_v2_normal(
@@ -791,6 +915,54 @@ impl Sequence {
     pub(crate) fn clear_staged_speculative_tokens(&mut self) {
         self.staged_speculative_tokens.clear();
         self.staged_speculative_logits = None;
+        if let Some(prediction) = &mut self.prediction {
+            prediction.set_staged(0);
+        }
+    }
+
+    pub(crate) fn set_prediction(&mut self, prediction: Option<PredictedOutput>) {
+        self.prediction = prediction;
+    }
+
+    pub(crate) fn has_prediction(&self) -> bool {
+        self.prediction.is_some()
+    }
+
+    /// Up to `max_len` tokens of the predicted output expected to come next.
+    pub(crate) fn prediction_proposal(&mut self, max_len: usize) -> Vec<u32> {
+        let generated = self.tokens.get(self.prompt_len..).unwrap_or_default();
+        self.prediction
+            .as_mut()
+            .map(|prediction| prediction.proposal(generated, max_len).to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Stage tokens for verification in the next forward pass, the first `predicted` of which
+    /// come from the predicted output and the rest are padding.
+    pub(crate) fn stage_prediction(&mut self, tokens: Vec<u32>, predicted: usize) {
+        self.set_staged_speculative(tokens, None);
+        if let Some(prediction) = &mut self.prediction {
+            prediction.set_staged(predicted);
+        }
+    }
+
+    /// How many of the staged tokens came from the predicted output. Resets the count, so call
+    /// this once when taking the staged tokens for verification.
+    pub(crate) fn take_prediction_staged(&mut self) -> usize {
+        self.prediction.as_mut().map_or(0, |prediction| {
+            let staged = prediction.staged();
+            prediction.set_staged(0);
+            staged
+        })
+    }
+
+    /// Count the verification of `proposed` predicted tokens, `accepted` of which were kept,
+    /// towards the request's usage.
+    pub(crate) fn record_prediction_outcome(&self, accepted: usize, proposed: usize) {
+        let mut group = get_mut_group!(self);
+        let details = group.prediction_tokens.get_or_insert_with(Default::default);
+        details.accepted_prediction_tokens += accepted;
+        details.rejected_prediction_tokens += proposed.saturating_sub(accepted);
     }
 
     pub fn get_initial_prompt(&self) -> &str {
@@ -901,6 +1073,29 @@ impl Sequence {
         self.sampler.clone()
     }
 
+    /// Give this sequence its own RNG stream, seeded with `seed`.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Some(Arc::new(std::sync::Mutex::new(Isaac64Rng::seed_from_u64(
+            seed,
+        ))));
+    }
+
+    /// The RNG to sample this sequence with: its own stream if seeded, otherwise `shared`.
+    pub fn sampling_rng(
+        &self,
+        shared: &Arc<std::sync::Mutex<Isaac64Rng>>,
+    ) -> Arc<std::sync::Mutex<Isaac64Rng>> {
+        self.rng.as_ref().unwrap_or(shared).clone()
+    }
+
+    pub fn system_fingerprint(&self) -> String {
+        self.system_fingerprint.to_string()
+    }
+
+    pub fn set_system_fingerprint(&mut self, fingerprint: Arc<str>) {
+        self.system_fingerprint = fingerprint;
+    }
+
     /// Add a some prefill tokens. Only meant for internal speculative decoding usage.
     pub fn set_prefill_toks(&mut self, toks: Vec<u32>) {
         self.prefill_prompt_toks = Some(toks)
@@ -1025,6 +1220,16 @@ impl Sequence {
         &self.stop_strings
     }
 
+    /// The stop string that `reason` refers to, if it is a stop-string stop.
+    pub fn matched_stop_string(&self, reason: StopReason) -> Option<String> {
+        match reason {
+            StopReason::StopString {
+                stop_string_idx, ..
+            } => self.stop_strings.get(stop_string_idx).cloned(),
+            _ => None,
+        }
+    }
+
     /// Returns the delta between the last two decoded sequences
     pub fn get_delta(
         &mut self,
@@ -1062,6 +1267,13 @@ impl Sequence {
         self.prompt_timestamp
     }
 
+    /// True exactly once: on the first call after the sequence generated a token. Chunked
+    /// prefill steps before that return false.
+    pub(crate) fn take_first_token(&self) -> bool {
+        self.tokens.len() > self.prompt_len
+            && !self.first_token_observed.swap(true, Ordering::Relaxed)
+    }
+
     /// Set the step start instant for accurate prompt timing measurement.
     /// Call this right before step() is called.
     pub fn set_step_start_instant(&mut self) {
@@ -1284,6 +1496,14 @@ impl Sequence {
         self.multimodal.diffusion_params()
     }
 
+    pub fn set_speech_reference(&mut self, reference: Option<SpeechReference>) {
+        self.multimodal.speech_reference = reference;
+    }
+
+    pub fn speech_reference(&self) -> Option<&SpeechReference> {
+        self.multimodal.speech_reference.as_ref()
+    }
+
     pub fn eos_tokens(&self) -> &[u32] {
         &self.eos_tokens
     }
@@ -1393,6 +1613,8 @@ pub struct SequenceGroup {
     pub total_prompt_time: u128,
     pub total_time: u128,
     pub total_completion_time: u128,
+    /// Set for requests with a predicted output.
+    pub prediction_tokens: Option<CompletionTokensDetails>,
     choices: Vec<Choice>,
     image_choices: Vec<ImageChoice>,
     speech_pcms: Vec<(Arc<Vec<f32>>, usize, usize)>, // (pcm, rate, channels)
@@ -1425,6 +1647,7 @@ impl SequenceGroup {
             total_prompt_time: 0,
             total_time: 0,
             total_completion_time: 0,
+            prediction_tokens: None,
             chat_streaming_chunks: Vec::new(),
             completion_streaming_chunks: Vec::new(),
             is_streaming,
@@ -1487,6 +1710,7 @@ impl SequenceGroup {
             total_time_sec: self.total_time as f32 / 1000.,
             total_completion_time_sec: self.total_completion_time as f32 / 1000.,
             total_prompt_time_sec: self.total_prompt_time as f32 / 1000.,
+            completion_tokens_details: self.prediction_tokens,
         }
     }
 
@@ -1575,7 +1799,7 @@ impl SequenceGroup {
         &mut self,
         seq: &Sequence,
         model: String,
-        usage_opt: Option<Usage>,
+        usage: Usage,
     ) -> Result<(), Box<SendError<Response>>> {
         if self.chat_streaming_chunks.len() == self.n_choices && self.is_streaming {
             let mut swap_streaming_chunks = vec![];
@@ -1588,9 +1812,9 @@ impl SequenceGroup {
                     choices: swap_streaming_chunks,
                     created: seq.creation_time() as u128,
                     model: model.clone(),
-                    system_fingerprint: SYSTEM_FINGERPRINT.to_string(),
+                    system_fingerprint: seq.system_fingerprint(),
                     object: "chat.completion.chunk".to_string(),
-                    usage: usage_opt,
+                    usage: Some(usage),
                     session_id: None,
                 }))
                 .await?;
@@ -1608,8 +1832,9 @@ impl SequenceGroup {
                     choices: swap_streaming_chunks,
                     created: seq.creation_time() as u128,
                     model: model.clone(),
-                    system_fingerprint: SYSTEM_FINGERPRINT.to_string(),
+                    system_fingerprint: seq.system_fingerprint(),
                     object: "text_completion".to_string(),
+                    usage: Some(usage),
                 }))
                 .await?;
         }
@@ -1673,6 +1898,34 @@ mod tests {
         )
     }
 
+    #[test]
+    fn disconnected_sequence_is_canceled_once_running() {
+        // `make_test_sequence` drops the receiver, as a disconnected client would.
+        let seq = make_test_sequence();
+        assert!(seq.can_drop_while_waiting());
+
+        seq.cancel_if_abandoned();
+        assert!(matches!(seq.getstate(), SequenceState::Waiting));
+
+        seq.set_state(SequenceState::RunningCompletion);
+        seq.cancel_if_abandoned();
+        assert!(matches!(
+            seq.getstate(),
+            SequenceState::Done(StopReason::Canceled)
+        ));
+    }
+
+    #[test]
+    fn first_token_is_taken_once() {
+        let mut seq = make_test_sequence();
+        // A chunked prefill step ends without a generated token.
+        assert!(!seq.take_first_token());
+        seq.tokens.push(9);
+        assert!(seq.take_first_token());
+        seq.tokens.push(10);
+        assert!(!seq.take_first_token());
+    }
+
     #[test]
     fn prefill_v2_normal_sets_prefix_cache_len_for_multimodal_trimming() {
         let mut seq = make_test_sequence();
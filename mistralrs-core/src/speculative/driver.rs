@@ -11,6 +11,7 @@ use crate::prefix_cacher::PrefixCacheManagerV2;
 use crate::sequence::{Sequence, SequenceState};
 
 use super::cache::{SpeculativeCacheAccess, SpeculativeCacheGuard, SpeculativeCacheOutcome};
+use super::prediction::{pad_proposals, MAX_PREDICTION_PROPOSAL};
 use super::proposer::{SpeculativeProposalBatch, SpeculativeProposeBatchCtx};
 use super::staging::{staged_batch_state, StagedBatchState};
 use super::verifier::{finish_verified_step, VerificationOutcome};
@@ -56,7 +57,11 @@ where
     P: SpeculativePipelineExt,
     C: SpeculativeCacheAccess,
 {
-    if !target.has_speculative_proposer() || seqs.is_empty() || logits.len() != seqs.len() {
+    let has_predictions = seqs.iter().any(|seq| seq.has_prediction());
+    if !(target.has_speculative_proposer() || has_predictions)
+        || seqs.is_empty()
+        || logits.len() != seqs.len()
+    {
         clear_staged_speculative_tokens(seqs);
         return Ok(false);
     }
@@ -215,6 +220,7 @@ where
         };
         let proposal = seq.take_staged_speculative_tokens();
         let proposal_logits = seq.take_staged_speculative_logits();
+        let predicted = seq.take_prediction_staged();
         if proposal.len() != staged_len {
             seq.clear_staged_speculative_tokens();
             cache_guards.push(None);
@@ -237,6 +243,10 @@ where
             None,
         )
         .await?;
+        if predicted > 0 {
+            // Accepted padding matched by chance isn't counted as predicted.
+            seq.record_prediction_outcome(outcome.accepted_drafts.min(predicted), predicted);
+        }
         let accepted_all = outcome.accepted_drafts == outcome.proposed_drafts;
         cache_outcomes.push(Some(SpeculativeCacheOutcome {
             keep_len: outcome.keep_len,
@@ -308,6 +318,9 @@ where
     if active_indices.is_empty() {
         return Ok(());
     }
+    if stage_predictions(seqs, active_indices, base_lens, cache) {
+        return Ok(());
+    }
     let Some(proposal_len) = target.speculative_proposal_len() else {
         clear_active_staged(seqs, active_indices);
         return Ok(());
@@ -346,6 +359,9 @@ where
             .iter()
             .map(|idx| &*seqs[*idx] as &Sequence)
             .collect::<Vec<_>>();
+        // Drafts are sampled from each sequence's own stream so a seeded request stays
+        // reproducible however it is batched.
+        let rngs = sequences.iter().map(|seq| seq.sampling_rng(&rng)).collect();
         target.speculative_propose(SpeculativeProposeBatchCtx {
             sampled_tokens,
             sampled_tokens_emitted: true,
@@ -354,7 +370,7 @@ where
             sequences: &sequences,
             cache: cache.proposer_cache(&sequences)?,
             target_hiddens,
-            rng: rng.clone(),
+            rngs,
         })?
     };
 
@@ -381,6 +397,52 @@ where
     Ok(())
 }
 
+/// Stage the next tokens of each sequence's predicted output, if any active sequence has some
+/// left. The verify forward must stay rectangular, so sequences with a shorter prediction or
+/// none are padded to the longest one (see [`pad_proposals`]) and verified alongside; for that
+/// step they get no drafts from the model's own proposer. Returns whether anything was staged;
+/// if not, the proposer (if any) is used.
+fn stage_predictions<C>(
+    seqs: &mut [&mut Sequence],
+    active_indices: &[usize],
+    base_lens: &[usize],
+    cache: &C,
+) -> bool
+where
+    C: SpeculativeCacheAccess,
+{
+    let mut proposals = active_indices
+        .iter()
+        .map(|idx| seqs[*idx].prediction_proposal(MAX_PREDICTION_PROPOSAL))
+        .collect::<Vec<_>>();
+    let predicted = proposals.iter().map(Vec::len).collect::<Vec<_>>();
+    // Padding repeats the token just sampled, the last one in each sequence.
+    let pads = active_indices
+        .iter()
+        .map(|idx| seqs[*idx].get_toks().last().copied().unwrap_or_default())
+        .collect::<Vec<_>>();
+    let proposal_len = pad_proposals(&mut proposals, &pads);
+    if proposal_len == 0 {
+        return false;
+    }
+
+    let can_stage = {
+        let sequences = active_indices
+            .iter()
+            .map(|idx| &*seqs[*idx] as &Sequence)
+            .collect::<Vec<_>>();
+        cache.can_stage_proposal(&sequences, base_lens, proposal_len)
+    };
+    if !can_stage {
+        return false;
+    }
+
+    for ((idx, proposal), predicted) in active_indices.iter().zip(proposals).zip(predicted) {
+        seqs[*idx].stage_prediction(proposal, predicted);
+    }
+    true
+}
+
 fn clear_active_staged(seqs: &mut [&mut Sequence], active_indices: &[usize]) {
     for idx in active_indices {
         seqs[*idx].clear_staged_speculative_tokens();
@@ -2,6 +2,7 @@ pub mod cache;
 pub mod config;
 pub mod driver;
 pub mod logging;
+pub(crate) mod prediction;
 pub mod proposer;
 pub(crate) mod staging;
 pub mod target;
@@ -0,0 +1,146 @@
+//! Predicted outputs: text the client expects the response to largely repeat (e.g. a file
+//! being edited), used as a proposal source for speculative verification.
+
+/// Most predicted tokens staged for one verification forward.
+pub(crate) const MAX_PREDICTION_PROPOSAL: usize = 16;
+
+/// N-gram lengths, longest first, used to find the output's place in the prediction again
+/// after the two diverge.
+const RESYNC_NGRAMS: [usize; 3] = [4, 3, 2];
+
+#[derive(Clone, Debug)]
+pub(crate) struct PredictedOutput {
+    tokens: Vec<u32>,
+    /// Index in `tokens` predicting the next generated token, or `None` while the output
+    /// does not line up with the prediction.
+    cursor: Option<usize>,
+    /// Generated tokens already matched against the prediction.
+    seen: usize,
+    /// Where to look for the output when resyncing: just past the last aligned position.
+    search_from: usize,
+    /// How many of the sequence's staged speculative tokens came from this prediction. The
+    /// rest are padding.
+    staged: usize,
+}
+
+impl PredictedOutput {
+    pub(crate) fn new(tokens: Vec<u32>) -> Self {
+        Self {
+            tokens,
+            cursor: Some(0),
+            seen: 0,
+            search_from: 0,
+            staged: 0,
+        }
+    }
+
+    /// Up to `max_len` predicted tokens expected to follow `generated`, the tokens produced so
+    /// far. Empty when the output has left the prediction and not found its way back.
+    pub(crate) fn proposal(&mut self, generated: &[u32], max_len: usize) -> &[u32] {
+        self.advance(generated);
+        match self.cursor {
+            Some(cursor) => {
+                let end = (cursor + max_len).min(self.tokens.len());
+                &self.tokens[cursor..end]
+            }
+            None => &[],
+        }
+    }
+
+    pub(crate) fn set_staged(&mut self, staged: usize) {
+        self.staged = staged;
+    }
+
+    pub(crate) fn staged(&self) -> usize {
+        self.staged
+    }
+
+    fn advance(&mut self, generated: &[u32]) {
+        for &token in generated.get(self.seen..).unwrap_or_default() {
+            self.cursor = match self.cursor {
+                Some(cursor) if self.tokens.get(cursor) == Some(&token) => Some(cursor + 1),
+                _ => None,
+            };
+            if let Some(cursor) = self.cursor {
+                self.search_from = cursor;
+            }
+        }
+        self.seen = generated.len();
+        if self.cursor.is_none() {
+            self.cursor = self.resync(generated);
+            if let Some(cursor) = self.cursor {
+                self.search_from = cursor;
+            }
+        }
+    }
+
+    /// Position after the first occurrence of the last few generated tokens in the rest of the
+    /// prediction.
+    fn resync(&self, generated: &[u32]) -> Option<usize> {
+        let rest = &self.tokens[self.search_from..];
+        RESYNC_NGRAMS
+            .into_iter()
+            .filter(|n| generated.len() >= *n)
+            .find_map(|n| {
+                let tail = &generated[generated.len() - n..];
+                rest.windows(n)
+                    .position(|window| window == tail)
+                    .map(|pos| self.search_from + pos + n)
+            })
+    }
+}
+
+/// Pad the proposals of a batch to the longest one, so sequences with little or no prediction
+/// left are verified in the same forward as the rest. Each is padded by repeating its entry in
+/// `pads`; exact-match verification only keeps padding the model would have generated anyway.
+/// Returns the common width, 0 if no sequence has a proposal.
+pub(crate) fn pad_proposals(proposals: &mut [Vec<u32>], pads: &[u32]) -> usize {
+    let width = proposals.iter().map(Vec::len).max().unwrap_or(0);
+    if width > 0 {
+        for (proposal, pad) in proposals.iter_mut().zip(pads) {
+            proposal.resize(width, *pad);
+        }
+    }
+    width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pad_proposals, PredictedOutput};
+
+    #[test]
+    fn proposes_the_prediction_while_output_matches() {
+        let all: Vec<u32> = (1..=10).collect();
+        let mut prediction = PredictedOutput::new(all.clone());
+        assert_eq!(prediction.proposal(&[], 4), &[1, 2, 3, 4]);
+        assert_eq!(prediction.proposal(&[1, 2, 3], 4), &[4, 5, 6, 7]);
+        assert_eq!(prediction.proposal(&[1, 2, 3, 4, 5, 6, 7, 8], 4), &[9, 10]);
+        assert!(prediction.proposal(&all, 4).is_empty());
+    }
+
+    #[test]
+    fn resyncs_after_an_edit() {
+        let mut prediction = PredictedOutput::new((1..=10).collect());
+        assert_eq!(prediction.proposal(&[1, 2], 3), &[3, 4, 5]);
+        // The output replaces 3 with 42 and carries on from 4.
+        assert!(prediction.proposal(&[1, 2, 42], 3).is_empty());
+        assert!(prediction.proposal(&[1, 2, 42, 4], 3).is_empty());
+        assert_eq!(prediction.proposal(&[1, 2, 42, 4, 5], 3), &[6, 7, 8]);
+        // Dropping 6..=7 resyncs on the tokens after the gap.
+        assert_eq!(prediction.proposal(&[1, 2, 42, 4, 5, 8, 9], 3), &[10]);
+    }
+
+    #[test]
+    fn mixed_batches_are_padded_to_the_longest_prediction() {
+        let mut proposals = vec![vec![5, 6, 7, 8], vec![], vec![9, 10]];
+        assert_eq!(pad_proposals(&mut proposals, &[1, 2, 3]), 4);
+        assert_eq!(
+            proposals,
+            vec![vec![5, 6, 7, 8], vec![2, 2, 2, 2], vec![9, 10, 3, 3]]
+        );
+
+        let mut proposals = vec![vec![], vec![]];
+        assert_eq!(pad_proposals(&mut proposals, &[1, 2]), 0);
+        assert!(proposals.iter().all(Vec::is_empty));
+    }
+}
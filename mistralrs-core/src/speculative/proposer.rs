@@ -23,7 +23,8 @@ pub struct SpeculativeProposeBatchCtx<'a> {
     pub sequences: &'a [&'a Sequence],
     pub cache: SpeculativeKvCache<'a>,
     pub target_hiddens: Option<Tensor>,
-    pub rng: Arc<Mutex<Isaac64Rng>>,
+    /// Sampling RNG of each sequence, in the order of `sequences`.
+    pub rngs: Vec<Arc<Mutex<Isaac64Rng>>>,
 }
 
 #[derive(Clone, Debug)]
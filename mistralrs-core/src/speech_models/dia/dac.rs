@@ -130,6 +130,101 @@ impl candle_core::Module for ResidualUnit {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct EncoderBlock {
+    res1: ResidualUnit,
+    res2: ResidualUnit,
+    res3: ResidualUnit,
+    snake1: Snake1d,
+    conv1: Conv1d,
+}
+
+impl EncoderBlock {
+    pub fn new(out_dim: usize, stride: usize, vb: VarBuilder) -> Result<Self> {
+        let vb = vb.pp("block");
+        let res1 = ResidualUnit::new(out_dim / 2, 1, vb.pp(0))?;
+        let res2 = ResidualUnit::new(out_dim / 2, 3, vb.pp(1))?;
+        let res3 = ResidualUnit::new(out_dim / 2, 9, vb.pp(2))?;
+        let snake1 = Snake1d::new(out_dim / 2, vb.pp(3))?;
+        let cfg = Conv1dConfig {
+            stride,
+            padding: stride.div_ceil(2),
+            ..Default::default()
+        };
+        let conv1 = conv1d_weight_norm(out_dim / 2, out_dim, 2 * stride, cfg, vb.pp(4))?;
+        Ok(Self {
+            res1,
+            res2,
+            res3,
+            snake1,
+            conv1,
+        })
+    }
+}
+
+impl candle_core::Module for EncoderBlock {
+    fn forward(&self, xs: &Tensor) -> Result<Tensor> {
+        let xs = xs
+            .apply(&self.res1)?
+            .apply(&self.res2)?
+            .apply(&self.res3)?
+            .apply(&self.snake1)?;
+        Convolution.forward_1d(&self.conv1, &xs)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    conv1: Conv1d,
+    blocks: Vec<EncoderBlock>,
+    snake1: Snake1d,
+    conv2: Conv1d,
+}
+
+impl Encoder {
+    pub fn new(
+        mut d_model: usize,
+        strides: &[usize],
+        d_latent: usize,
+        vb: VarBuilder,
+    ) -> Result<Self> {
+        let vb = vb.pp("block");
+        let cfg1 = Conv1dConfig {
+            padding: 3,
+            ..Default::default()
+        };
+        let conv1 = conv1d_weight_norm(1, d_model, 7, cfg1, vb.pp(0))?;
+        let mut blocks = Vec::with_capacity(strides.len());
+        for (idx, stride) in strides.iter().enumerate() {
+            d_model *= 2;
+            blocks.push(EncoderBlock::new(d_model, *stride, vb.pp(idx + 1))?);
+        }
+        let snake1 = Snake1d::new(d_model, vb.pp(strides.len() + 1))?;
+        let cfg2 = Conv1dConfig {
+            padding: 1,
+            ..Default::default()
+        };
+        let conv2 = conv1d_weight_norm(d_model, d_latent, 3, cfg2, vb.pp(strides.len() + 2))?;
+        Ok(Self {
+            conv1,
+            blocks,
+            snake1,
+            conv2,
+        })
+    }
+}
+
+impl candle_core::Module for Encoder {
+    fn forward(&self, xs: &Tensor) -> Result<Tensor> {
+        let mut xs = Convolution.forward_1d(&self.conv1, xs)?;
+        for block in self.blocks.iter() {
+            xs = xs.apply(block)?
+        }
+        xs = self.snake1.forward(&xs)?;
+        Convolution.forward_1d(&self.conv2, &xs)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DecoderBlock {
     snake1: Snake1d,
@@ -222,7 +317,6 @@ impl candle_core::Module for Decoder {
     }
 }
 
-#[allow(unused)]
 #[derive(Clone, Debug)]
 pub struct VectorQuantizer {
     in_proj: Conv1d,
@@ -250,6 +344,26 @@ impl VectorQuantizer {
     pub fn decode_code(&self, embed_id: &Tensor) -> Result<Tensor> {
         self.embed_code(embed_id)?.transpose(1, 2)
     }
+
+    /// Quantize `(b, dim, t)` latents to the codebook entries with the highest cosine
+    /// similarity. Returns the `(b, t)` codes and their projected embeddings.
+    pub fn encode(&self, xs: &Tensor) -> Result<(Tensor, Tensor)> {
+        let latents = xs.apply(&self.in_proj)?;
+        let (b, d, t) = latents.dims3()?;
+        let latents = l2_normalize(&latents.transpose(1, 2)?.reshape((b * t, d))?)?;
+        let codebook = l2_normalize(self.codebook.embeddings())?;
+        let codes = latents
+            .matmul(&codebook.t()?)?
+            .argmax(D::Minus1)?
+            .reshape((b, t))?;
+        let quantized = self.decode_code(&codes)?.apply(&self.out_proj)?;
+        Ok((codes, quantized))
+    }
+}
+
+fn l2_normalize(xs: &Tensor) -> Result<Tensor> {
+    let norm = xs.sqr()?.sum_keepdim(D::Minus1)?.sqrt()?.maximum(1e-12)?;
+    xs.broadcast_div(&norm)
 }
 
 #[derive(Clone, Debug)]
@@ -289,18 +403,43 @@ impl ResidualVectorQuantizer {
             None => candle_core::bail!("empty codebooks"),
         }
     }
+
+    /// Quantize `(b, dim, t)` latents residually, returning `(b, n_codebooks, t)` codes.
+    pub fn encode(&self, xs: &Tensor) -> Result<Tensor> {
+        let mut residual = xs.clone();
+        let mut codes = Vec::with_capacity(self.quantizers.len());
+        for quantizer in &self.quantizers {
+            let (codes_i, quantized) = quantizer.encode(&residual)?;
+            residual = (residual - quantized)?;
+            codes.push(codes_i);
+        }
+        Tensor::stack(&codes, 1)
+    }
 }
 
+/// Samples per code frame: the product of the encoder strides.
+pub const HOP_LENGTH: usize = 512;
+
 #[derive(Debug, Clone)]
 pub struct Model {
-    // pub encoder: Encoder,
+    /// Only needed for voice cloning, so weights without an encoder still load.
+    pub encoder: Option<Encoder>,
     pub quantizer: ResidualVectorQuantizer,
     pub decoder: Decoder,
 }
 
 impl Model {
     pub fn new(cfg: &Config, vb: VarBuilder) -> Result<Self> {
-        // let encoder = Encoder::new(64, &[2, 4, 8, 8], cfg.latent_dim, vb.pp("encoder").pp("model"))?;
+        let encoder = if vb.contains_tensor("encoder.block.0.weight_v") {
+            Some(Encoder::new(
+                64,
+                &[2, 4, 8, 8],
+                cfg.latent_dim,
+                vb.pp("encoder"),
+            )?)
+        } else {
+            None
+        };
         let quantizer = ResidualVectorQuantizer::new(
             cfg.latent_dim,
             cfg.num_codebooks,
@@ -316,12 +455,21 @@ impl Model {
             vb.pp("decoder").pp("model"),
         )?;
         Ok(Self {
-            // encoder,
+            encoder,
             decoder,
             quantizer,
         })
     }
 
+    /// Encode `(b, 1, samples)` audio, with `samples` a multiple of [`HOP_LENGTH`], to
+    /// `(b, n_codebooks, frames)` codes.
+    pub fn encode(&self, audio: &Tensor) -> Result<Tensor> {
+        let Some(encoder) = &self.encoder else {
+            candle_core::bail!("These DAC weights do not include the encoder.");
+        };
+        self.quantizer.encode(&audio.apply(encoder)?)
+    }
+
     pub fn decode_codes(&self, audio_codes: &Tensor) -> Result<Tensor> {
         let audio_values = self.quantizer.from_codes(audio_codes)?;
         audio_values.apply(&self.decoder)
@@ -0,0 +1,200 @@
+//! Long-form synthesis: splitting scripts into segments Dia can voice in one pass, and
+//! crossfading the segment audio back together.
+
+use mistralrs_audio::AudioInput;
+
+use super::RATE;
+
+const SPEAKER_TAGS: [&str; 2] = ["[S1]", "[S2]"];
+
+/// Split `text` into segments of at most `max_chars` bytes where possible. Cuts fall between
+/// speaker turns first and between sentences when a single turn is too long; every segment
+/// that continues a turn repeats its speaker tag. A sentence longer than `max_chars` is kept
+/// whole.
+pub(super) fn split_script(text: &str, max_chars: usize) -> Vec<String> {
+    let text = text.trim();
+    if text.len() <= max_chars {
+        return vec![text.to_string()];
+    }
+
+    let mut units = Vec::new();
+    for (tag, turn) in speaker_turns(text) {
+        let with_tag = |body: &str| match tag {
+            Some(tag) => format!("{tag} {body}"),
+            None => body.to_string(),
+        };
+        let whole = with_tag(turn);
+        if whole.len() <= max_chars {
+            units.push(whole);
+        } else {
+            units.extend(sentences(turn).into_iter().map(with_tag));
+        }
+    }
+
+    let mut segments: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for unit in units {
+        if !current.is_empty() && current.len() + 1 + unit.len() > max_chars {
+            segments.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&unit);
+    }
+    if !current.is_empty() {
+        segments.push(current);
+    }
+    segments
+}
+
+/// Split on speaker tags, returning each turn's tag (if any) and trimmed body.
+fn speaker_turns(text: &str) -> Vec<(Option<&'static str>, &str)> {
+    let mut turns = Vec::new();
+    let mut tag = None;
+    let mut rest = text;
+    loop {
+        let next = SPEAKER_TAGS
+            .iter()
+            .filter_map(|t| rest.find(t).map(|pos| (pos, *t)))
+            .min_by_key(|(pos, _)| *pos);
+        let (body, after) = match next {
+            Some((pos, next_tag)) => (&rest[..pos], Some((pos, next_tag))),
+            None => (rest, None),
+        };
+        let body = body.trim();
+        if !body.is_empty() {
+            turns.push((tag, body));
+        }
+        match after {
+            Some((pos, next_tag)) => {
+                tag = Some(next_tag);
+                rest = &rest[pos + next_tag.len()..];
+            }
+            None => return turns,
+        }
+    }
+}
+
+/// Split after `.`, `!` or `?` followed by whitespace.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if matches!(c, '.' | '!' | '?')
+            && chars.peek().is_some_and(|(_, next)| next.is_whitespace())
+        {
+            let sentence = text[start..i + 1].trim();
+            if !sentence.is_empty() {
+                out.push(sentence);
+            }
+            start = i + 1;
+        }
+    }
+    let tail = text[start..].trim();
+    if !tail.is_empty() {
+        out.push(tail);
+    }
+    out
+}
+
+/// Joins mono segment audio, pushed a piece at a time, crossfading over `overlap` samples
+/// at each segment boundary.
+pub(super) struct SegmentJoiner {
+    overlap: usize,
+    /// The newest samples, held back in case a segment boundary follows.
+    tail: Vec<f32>,
+    /// Whether the next push starts a new segment.
+    new_segment: bool,
+}
+
+impl SegmentJoiner {
+    pub(super) fn new(overlap: usize) -> Self {
+        Self {
+            overlap,
+            tail: Vec::new(),
+            new_segment: false,
+        }
+    }
+
+    /// Mark the next push as the start of a new segment.
+    pub(super) fn start_segment(&mut self) {
+        self.new_segment = !self.tail.is_empty();
+    }
+
+    /// Add samples, returning those that can no longer be affected by a crossfade.
+    pub(super) fn push(&mut self, samples: &[f32]) -> Vec<f32> {
+        let mut out = Vec::new();
+        let mut samples = samples.to_vec();
+        if self.new_segment && !samples.is_empty() {
+            self.new_segment = false;
+            let n = self.tail.len().min(samples.len());
+            // Equal-gain crossfade: the fade-out and fade-in gains sum to 1, so the level holds
+            // across the boundary.
+            let tail = faded(self.tail.split_off(self.tail.len() - n), 0, n);
+            let head = faded(samples.drain(..n).collect(), n, 0);
+            out.append(&mut self.tail);
+            out.extend(tail.iter().zip(&head).map(|(t, h)| t + h));
+        }
+        self.tail.extend(samples);
+        let ready = self.tail.len().saturating_sub(self.overlap);
+        out.extend(self.tail.drain(..ready));
+        out
+    }
+
+    /// Return everything still held back.
+    pub(super) fn finish(&mut self) -> Vec<f32> {
+        self.new_segment = false;
+        std::mem::take(&mut self.tail)
+    }
+}
+
+fn faded(samples: Vec<f32>, fade_in: usize, fade_out: usize) -> Vec<f32> {
+    let mut audio = AudioInput {
+        samples,
+        sample_rate: RATE as u32,
+        channels: 1,
+    };
+    audio.apply_fade(fade_in, fade_out);
+    audio.samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_script, SegmentJoiner};
+
+    #[test]
+    fn short_scripts_are_not_split() {
+        let text = "[S1] Hello there. [S2] Hi!";
+        assert_eq!(split_script(text, 100), vec![text]);
+    }
+
+    #[test]
+    fn splits_between_turns_then_sentences() {
+        let text = "[S1] One two. Three four. [S2] Five six.";
+        assert_eq!(
+            split_script(text, 20),
+            vec!["[S1] One two.", "[S1] Three four.", "[S2] Five six."]
+        );
+        assert_eq!(
+            split_script(text, 30),
+            vec!["[S1] One two. Three four.", "[S2] Five six."]
+        );
+    }
+
+    #[test]
+    fn joiner_crossfades_segments() {
+        let mut joiner = SegmentJoiner::new(4);
+        let mut out = joiner.push(&[1.0; 10]);
+        assert_eq!(out.len(), 6);
+        joiner.start_segment();
+        out.extend(joiner.push(&[0.5; 10]));
+        out.extend(joiner.finish());
+        // The 4-sample overlap is shared, and the crossfade gains sum to exactly 1.
+        assert_eq!(out.len(), 16);
+        assert_eq!(out[..6], [1.0; 6]);
+        assert_eq!(out[6..10], [1.0, 0.875, 0.75, 0.625]);
+        assert_eq!(out[10..], [0.5; 6]);
+    }
+}
@@ -15,11 +15,16 @@ use rand::{
 use rand_isaac::Isaac64Rng;
 
 pub use config::DiaConfig;
+use longform::{split_script, SegmentJoiner};
+use mistralrs_audio::AudioInput;
 use tracing::info;
 
-use crate::ops::apply_triangular;
+use crate::{ops::apply_triangular, SpeechReference};
 
-use super::{utils::normalize_loudness, SpeechGenerationConfig, SpeechGenerationOutput};
+use super::{
+    utils::{apply_gain, loudness_gain, normalize_loudness},
+    SpeechGenerationConfig, SpeechGenerationOutput,
+};
 
 /// Aggregated outputs for generation preparation.
 pub struct PrepareGenerationOutput {
@@ -35,11 +40,39 @@ mod audio;
 mod cache;
 mod config;
 mod dac;
+mod longform;
 mod model;
 
 const RATE: usize = 44100;
 const CHANNELS: usize = 1;
 const TOKENS_PER_SECOND: usize = 86;
+/// Decoder steps between streamed chunks.
+const STREAM_INTERVAL_FRAMES: usize = TOKENS_PER_SECOND / 2;
+/// Frames re-decoded before each streamed chunk.
+const STREAM_CONTEXT_FRAMES: usize = 16;
+/// Frames held back until the next chunk so the codec has lookahead.
+const STREAM_LOOKAHEAD_FRAMES: usize = 8;
+/// Scripts longer than this many bytes are voiced a few sentences at a time.
+const SEGMENT_MAX_CHARS: usize = 200;
+/// Crossfade between segments, 50 ms.
+const SEGMENT_CROSSFADE_SAMPLES: usize = RATE / 20;
+
+/// Progress of a streaming generation.
+struct SpeechStream<'a> {
+    on_chunk: &'a mut dyn FnMut(SpeechGenerationOutput),
+    /// Frames of the current segment already passed to `on_chunk`.
+    emitted: usize,
+    /// Loudness gain, fixed once measured.
+    gain: Option<f64>,
+    joiner: SegmentJoiner,
+}
+
+/// Audio the decoder continues from, with the text spoken in it.
+struct AudioPrompt {
+    /// `(frames, codebooks)` DAC codes, without the delay pattern.
+    codes: Tensor,
+    transcript: String,
+}
 
 fn create_attn_mask(q_padding_mask_1d: &Tensor, k_padding_mask_1d: &Tensor) -> Result<Tensor> {
     let (b1, _tq) = q_padding_mask_1d.dims2()?;
@@ -94,15 +127,19 @@ impl DiaPipeline {
         })
     }
 
-    fn prepare_audio_prompt(&self) -> Result<Tensor> {
+    /// The BOS frame, then any prompt codes, then room for the delay pattern, all delayed.
+    fn prepare_audio_prompt(&self, prompt_codes: Option<&Tensor>) -> Result<Tensor> {
         let num_channels = self.cfg.data.channels;
         let audio_pad_value = self.cfg.data.audio_pad_value;
         let audio_bos_value = self.cfg.data.audio_bos_value;
         let delay_pattern = &self.cfg.data.delay_pattern;
         let max_delay_pattern = *delay_pattern.iter().max().unwrap() as usize;
 
-        let prefill =
+        let mut prefill =
             (Tensor::ones((1, num_channels), DType::F32, &self.device)? * audio_bos_value as f64)?;
+        if let Some(prompt_codes) = prompt_codes {
+            prefill = Tensor::cat(&[&prefill, prompt_codes], 0)?;
+        }
 
         let delay_pad_tensor = (Tensor::ones(
             (max_delay_pattern - 1, num_channels),
@@ -159,12 +196,16 @@ impl DiaPipeline {
     /// - encoder positions
     /// - cross cache
     /// - self cache
-    fn prepare_generation(&self, text: &str) -> Result<PrepareGenerationOutput> {
+    fn prepare_generation(
+        &self,
+        text: &str,
+        prompt_codes: Option<&Tensor>,
+    ) -> Result<PrepareGenerationOutput> {
         let enc_input_cond = self.prepare_text_prompt(text)?;
         let enc_input_uncond = enc_input_cond.zeros_like()?;
         let enc_input = Tensor::cat(&[&enc_input_uncond, &enc_input_cond], 0)?;
 
-        let prefill = self.prepare_audio_prompt()?;
+        let prefill = self.prepare_audio_prompt(prompt_codes)?;
 
         let encoder_positions =
             Tensor::arange(0f32, self.cfg.data.text_length as f32, &self.device)?
@@ -335,7 +376,9 @@ impl DiaPipeline {
         Ok(next)
     }
 
-    fn generate_output(&self, generated_codes: &Tensor) -> Result<Vec<f32>> {
+    /// Undo the delay pattern, keeping only frames every codebook has reached, with invalid
+    /// codes zeroed. Returns `(1, frames, codebooks)`.
+    fn revert_codes(&self, generated_codes: &Tensor) -> Result<Tensor> {
         let num_channels = self.cfg.data.channels;
         let seq_length = generated_codes.dim(0)?;
         let audio_pad_value = self.cfg.data.audio_pad_value;
@@ -366,22 +409,114 @@ impl DiaPipeline {
         let invalid_mask = codebook
             .lt(min_valid_index)?
             .bitwise_or(&codebook.gt(max_valid_index)?)?;
-        codebook = invalid_mask.where_cond(&codebook.zeros_like()?, &codebook)?;
+        invalid_mask.where_cond(&codebook.zeros_like()?, &codebook)
+    }
 
+    /// Decode `(1, frames, codebooks)` codes to mono PCM.
+    fn decode_frames(&self, codebook: &Tensor) -> Result<Tensor> {
         let codes = codebook.transpose(1, 2)?;
         let pcm = self.dac.decode_codes(&codes.to_dtype(DType::U32)?)?;
-        let pcm = pcm.i((0, 0))?;
-        let pcm = normalize_loudness(&pcm, RATE as u32, true)?;
-        let pcm = pcm.to_vec1::<f32>()?;
+        pcm.i((0, 0))
+    }
 
-        Ok(pcm)
+    /// Decode the frames that are ready and hand them to the stream. Until `last`, the newest
+    /// frames are held back so the codec sees some lookahead; earlier frames are re-decoded as
+    /// left context so chunk boundaries line up.
+    fn stream_frames(
+        &self,
+        stream: &mut SpeechStream<'_>,
+        generated_codes: &Tensor,
+        last: bool,
+    ) -> Result<()> {
+        let max_delay_pattern = *self.cfg.data.delay_pattern.iter().max().unwrap() as usize;
+        if generated_codes.dim(0)? <= max_delay_pattern {
+            return Ok(());
+        }
+        let codebook = self.revert_codes(generated_codes)?;
+        let available = codebook.dim(1)?;
+        let ready = if last {
+            available
+        } else {
+            available.saturating_sub(STREAM_LOOKAHEAD_FRAMES)
+        };
+        if ready <= stream.emitted {
+            return Ok(());
+        }
+
+        let start = stream.emitted.saturating_sub(STREAM_CONTEXT_FRAMES);
+        let pcm = self.decode_frames(&codebook.i((.., start..available, ..))?)?;
+        let samples_per_frame = pcm.dim(0)? / (available - start);
+        let pcm = pcm
+            .i((stream.emitted - start) * samples_per_frame..(ready - start) * samples_per_frame)?;
+
+        // The whole clip is not available to measure, so the gain is fixed by the first chunk
+        // loud enough to measure.
+        if stream.gain.is_none() {
+            stream.gain = loudness_gain(&pcm, RATE as u32)?;
+        }
+        let pcm = match stream.gain {
+            Some(gain) => apply_gain(&pcm, gain, true)?,
+            None => pcm,
+        };
+        let pcm = stream.joiner.push(&pcm.to_vec1::<f32>()?);
+        if !pcm.is_empty() {
+            (stream.on_chunk)(SpeechGenerationOutput {
+                pcm: Arc::new(pcm),
+                rate: RATE,
+                channels: CHANNELS,
+            });
+        }
+        stream.emitted = ready;
+        Ok(())
     }
 
-    pub fn generate(
+    /// Encode the reference recording to the codes the decoder is prompted with.
+    fn encode_reference(&self, reference: &SpeechReference) -> Result<AudioPrompt> {
+        let audio = AudioInput {
+            samples: reference.audio.to_mono(),
+            sample_rate: reference.audio.sample_rate,
+            channels: 1,
+        }
+        .resample(RATE as u32);
+
+        // Prompts are limited to half the decoder context to leave room for the new speech.
+        let frames = audio.samples.len().div_ceil(dac::HOP_LENGTH);
+        let max_frames = self.cfg.data.audio_length / 2;
+        if frames == 0 {
+            candle_core::bail!("The reference audio is empty.");
+        }
+        if frames > max_frames {
+            candle_core::bail!(
+                "The reference audio is too long, at most {}s is supported.",
+                max_frames / TOKENS_PER_SECOND
+            );
+        }
+
+        let mut samples = audio.samples;
+        samples.resize(frames * dac::HOP_LENGTH, 0.);
+        let audio = Tensor::from_vec(samples, (1, 1, frames * dac::HOP_LENGTH), &self.device)?;
+        let codes = self
+            .dac
+            .encode(&audio)?
+            .squeeze(0)?
+            .t()?
+            .to_dtype(DType::F32)?
+            .contiguous()?;
+        Ok(AudioPrompt {
+            codes,
+            transcript: reference.transcript.trim().to_string(),
+        })
+    }
+
+    /// Voice one segment, continuing from `prompt` if given. Returns the segment's PCM before
+    /// loudness normalization and its `(frames, codebooks)` codes.
+    fn generate_segment(
         &self,
         text: &str,
+        prompt: Option<&AudioPrompt>,
         cfg: &SpeechGenerationConfig,
-    ) -> Result<SpeechGenerationOutput> {
+        mut stream: Option<&mut SpeechStream<'_>>,
+    ) -> Result<(Tensor, Tensor)> {
         let SpeechGenerationConfig::Dia {
             max_tokens,
             cfg_scale,
@@ -393,9 +528,21 @@ impl DiaPipeline {
         let audio_pad_value = self.cfg.data.audio_pad_value as u32;
         let audio_eos_value = self.cfg.data.audio_eos_value as u32;
         let delay_pattern = &self.cfg.data.delay_pattern;
-        let max_tokens = max_tokens.unwrap_or(self.cfg.data.audio_length);
         let max_delay_pattern = *delay_pattern.iter().max().unwrap() as usize;
 
+        // The model reads the prompt's transcript followed by the new text, and carries on
+        // from the prompt audio.
+        let (text, prompt_codes) = match prompt {
+            Some(prompt) => (format!("{} {text}", prompt.transcript), Some(&prompt.codes)),
+            None => (text.to_string(), None),
+        };
+        let prompt_frames = match prompt_codes {
+            Some(codes) => codes.dim(0)?,
+            None => 0,
+        };
+        let max_tokens = max_tokens.unwrap_or(self.cfg.data.audio_length);
+        let end_step = (prompt_frames + max_tokens).min(self.cfg.data.audio_length);
+
         let PrepareGenerationOutput {
             mut generated_tokens,
             decoder_attn_mask,
@@ -403,7 +550,7 @@ impl DiaPipeline {
             encoder_positions,
             cross_cache: mut decoder_cross_attn_cache,
             self_cache: mut decoder_self_attn_cache,
-        } = self.prepare_generation(text)?;
+        } = self.prepare_generation(&text, prompt_codes)?;
 
         let self_attn_mask = apply_triangular(
             &Tensor::ones(
@@ -415,7 +562,30 @@ impl DiaPipeline {
             false,
         )?;
 
-        let mut dec_step = 0;
+        // Feed the prompt frames through the decoder to fill the self-attention cache; their
+        // predictions are already known.
+        for step in 0..prompt_frames {
+            let dec_positions = Tensor::full(step as f32, (2, 1), &self.device)?;
+            let tokens = generated_tokens
+                .i((step..step + 1, ..))?
+                .unsqueeze(0)?
+                .repeat((2, 1, 1))?;
+            self.model.decoder.decode_step(
+                &tokens.to_dtype(DType::U32)?,
+                &encoder_out,
+                Some(&self_attn_mask),
+                Some(&decoder_attn_mask),
+                &encoder_positions,
+                &dec_positions,
+                &mut decoder_self_attn_cache,
+                &mut decoder_cross_attn_cache,
+                step,
+            )?;
+        }
+
+        // The BOS frame and the prompt are not part of the output.
+        let first_new = prompt_frames + 1;
+        let mut dec_step = prompt_frames;
 
         let mut bos_countdown = max_delay_pattern;
         let mut eos_detected = false;
@@ -424,7 +594,7 @@ impl DiaPipeline {
         let mut rng = Isaac64Rng::seed_from_u64(0);
 
         let mut start = Instant::now();
-        while dec_step < max_tokens {
+        while dec_step < end_step {
             let dec_positions = Tensor::full(dec_step as f32, (2, 1), &self.device)?;
             let current_tokens = generated_tokens
                 .i((dec_step..dec_step + 1, ..))?
@@ -448,8 +618,8 @@ impl DiaPipeline {
                 dec_step,
             )?;
 
-            if (!eos_detected && pred_c[0] == audio_eos_value)
-                || dec_step == max_tokens - max_delay_pattern - 1
+            if !eos_detected
+                && (pred_c[0] == audio_eos_value || dec_step + max_delay_pattern + 1 >= end_step)
             {
                 eos_detected = true;
                 eos_countdown = Some(max_delay_pattern);
@@ -469,6 +639,7 @@ impl DiaPipeline {
 
             bos_countdown = bos_countdown.saturating_sub(1);
 
+            // While the delay pattern is filling, keep the BOS and prompt codes it placed.
             let apply_mask = bos_countdown > 0;
             if apply_mask {
                 let len = pred_c.len();
@@ -500,20 +671,99 @@ impl DiaPipeline {
             }
 
             dec_step += 1;
+            let generated = dec_step - prompt_frames;
+
+            if let Some(stream) = &mut stream {
+                if generated % STREAM_INTERVAL_FRAMES == 0 {
+                    let generated_codes = generated_tokens.i((first_new..dec_step + 1, ..))?;
+                    self.stream_frames(stream, &generated_codes, false)?;
+                }
+            }
 
             let end = Instant::now();
-            if dec_step % TOKENS_PER_SECOND == 0 {
+            if generated % TOKENS_PER_SECOND == 0 {
                 info!(
-                    "Generated {}s of audio, {dec_step} tokens at {:.2} tokens/second.",
-                    dec_step / TOKENS_PER_SECOND,
+                    "Generated {}s of audio, {generated} tokens at {:.2} tokens/second.",
+                    generated / TOKENS_PER_SECOND,
                     TOKENS_PER_SECOND as f32 / (end - start).as_secs_f32()
                 );
                 start = end;
             }
         }
 
-        let generated_codes = generated_tokens.i((0..dec_step + 1, ..))?;
-        let pcm = self.generate_output(&generated_codes)?;
+        let generated_codes = generated_tokens.i((first_new..dec_step + 1, ..))?;
+        if let Some(stream) = stream {
+            self.stream_frames(stream, &generated_codes, true)?;
+        }
+        let codebook = self.revert_codes(&generated_codes)?;
+        let pcm = self.decode_frames(&codebook)?;
+        Ok((pcm, codebook.squeeze(0)?))
+    }
+
+    /// Generate speech for `text`, in the voice of `reference` if given. Scripts too long for
+    /// one pass are voiced in segments split at speaker turns and sentences; each segment
+    /// continues from the reference, or otherwise from the segment before it, and the audio is
+    /// crossfaded together. With `on_chunk`, audio is also passed out roughly every half second
+    /// while decoding; the returned output still holds the complete clip.
+    pub fn generate(
+        &self,
+        text: &str,
+        reference: Option<&SpeechReference>,
+        cfg: &SpeechGenerationConfig,
+        on_chunk: Option<&mut dyn FnMut(SpeechGenerationOutput)>,
+    ) -> Result<SpeechGenerationOutput> {
+        let reference = reference
+            .map(|reference| self.encode_reference(reference))
+            .transpose()?;
+        let max_prompt_frames = self.cfg.data.audio_length / 2;
+
+        let mut stream = on_chunk.map(|on_chunk| SpeechStream {
+            on_chunk,
+            emitted: 0,
+            gain: None,
+            joiner: SegmentJoiner::new(SEGMENT_CROSSFADE_SAMPLES),
+        });
+        let mut joiner = SegmentJoiner::new(SEGMENT_CROSSFADE_SAMPLES);
+        let mut pcm = Vec::new();
+        let mut previous: Option<AudioPrompt> = None;
+
+        for segment in split_script(text, SEGMENT_MAX_CHARS) {
+            joiner.start_segment();
+            if let Some(stream) = &mut stream {
+                stream.emitted = 0;
+                stream.joiner.start_segment();
+            }
+
+            let prompt = reference.as_ref().or(previous.as_ref());
+            let (segment_pcm, codes) =
+                self.generate_segment(&segment, prompt, cfg, stream.as_mut())?;
+            pcm.extend(joiner.push(&segment_pcm.to_vec1::<f32>()?));
+
+            previous = if reference.is_none() && codes.dim(0)? <= max_prompt_frames {
+                Some(AudioPrompt {
+                    codes,
+                    transcript: segment,
+                })
+            } else {
+                None
+            };
+        }
+        pcm.extend(joiner.finish());
+
+        if let Some(stream) = &mut stream {
+            let tail = stream.joiner.finish();
+            if !tail.is_empty() {
+                (stream.on_chunk)(SpeechGenerationOutput {
+                    pcm: Arc::new(tail),
+                    rate: RATE,
+                    channels: CHANNELS,
+                });
+            }
+        }
+
+        let len = pcm.len();
+        let pcm = Tensor::from_vec(pcm, len, &self.device)?;
+        let pcm = normalize_loudness(&pcm, RATE as u32, true)?.to_vec1::<f32>()?;
         Ok(SpeechGenerationOutput {
             pcm: Arc::new(pcm),
             rate: RATE,
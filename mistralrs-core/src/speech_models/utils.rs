@@ -11,20 +11,30 @@ pub(crate) fn normalize_loudness(
     sample_rate: u32,
     loudness_compressor: bool,
 ) -> Result<Tensor> {
+    match loudness_gain(wav, sample_rate)? {
+        Some(gain) => apply_gain(wav, gain, loudness_compressor),
+        None => Ok(wav.clone()),
+    }
+}
+
+/// Gain bringing `wav` to -14 LUFS, or `None` if it is too quiet to measure.
+pub(crate) fn loudness_gain(wav: &Tensor, sample_rate: u32) -> Result<Option<f64>> {
     let energy = wav.sqr()?.mean_all()?.sqrt()?.to_vec0::<f32>()?;
     if energy < 2e-3 {
-        return Ok(wav.clone());
+        return Ok(None);
     }
     let wav_array = wav.to_vec1::<f32>()?;
     let mut meter = bs1770::ChannelLoudnessMeter::new(sample_rate);
     meter.push(wav_array.into_iter());
     let power = meter.as_100ms_windows();
-    let loudness = match bs1770::gated_mean(power) {
-        None => return Ok(wav.clone()),
-        Some(gp) => gp.loudness_lkfs() as f64,
+    let Some(gp) = bs1770::gated_mean(power) else {
+        return Ok(None);
     };
-    let delta_loudness = -14. - loudness;
-    let gain = 10f64.powf(delta_loudness / 20.);
+    let delta_loudness = -14. - gp.loudness_lkfs() as f64;
+    Ok(Some(10f64.powf(delta_loudness / 20.)))
+}
+
+pub(crate) fn apply_gain(wav: &Tensor, gain: f64, loudness_compressor: bool) -> Result<Tensor> {
     let wav = (wav * gain)?;
     if loudness_compressor {
         wav.tanh()
@@ -0,0 +1,540 @@
+//! Distributed tracing for the request lifecycle, exported to an OpenTelemetry collector.
+//!
+//! Nothing is recorded until [`init_otlp_exporter`] is called. After that every request gets a
+//! trace: the server span (when it came in over HTTP), one span per sequence with its queueing and
+//! prefill steps underneath, and, for agentic requests, the loop with one span per tool call.
+//! Decode steps and grammar mask computation happen once per token, so they are summed into
+//! attributes of the sequence span instead. A W3C `traceparent` passed in with the request makes
+//! these spans part of the caller's trace.
+
+mod otlp;
+
+use std::{
+    borrow::Cow,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, SystemTime},
+};
+
+use mistralrs_mcp::{CalledFunction, ToolCallContext};
+pub use otlp::OtlpExporterConfig;
+
+use crate::{
+    sequence::{Sequence, SequenceState},
+    ToolCallbackKind, ToolCallbackWithTool,
+};
+
+static EXPORTER: OnceLock<otlp::OtlpExporter> = OnceLock::new();
+
+/// Start exporting spans to the collector described by `config`. Spans are batched and sent from
+/// a background thread. Only the first call has any effect.
+pub fn init_otlp_exporter(config: OtlpExporterConfig) -> anyhow::Result<()> {
+    if EXPORTER.get().is_some() {
+        return Ok(());
+    }
+    let exporter = otlp::OtlpExporter::start(config)?;
+    let _ = EXPORTER.set(exporter);
+    Ok(())
+}
+
+/// Whether spans are being exported.
+pub fn tracing_enabled() -> bool {
+    EXPORTER.get().is_some()
+}
+
+fn export(span: SpanData) {
+    if let Some(exporter) = EXPORTER.get() {
+        exporter.export(span);
+    }
+}
+
+/// The W3C trace context of a span: which trace it belongs to and its own id, which children use
+/// as their parent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+    /// Whether the caller wants this trace recorded.
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// Parse a `traceparent` header value such as
+    /// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+    pub fn from_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let span_id = parts.next()?;
+        let flags = parts.next()?;
+        // Later versions may append fields; version 00 has exactly four.
+        if !is_lower_hex(version, 2)
+            || version == "ff"
+            || (version == "00" && parts.next().is_some())
+        {
+            return None;
+        }
+        if !is_lower_hex(trace_id, 32) || !is_lower_hex(span_id, 16) || !is_lower_hex(flags, 2) {
+            return None;
+        }
+        let trace_id = u128::from_str_radix(trace_id, 16).ok()?;
+        let span_id = u64::from_str_radix(span_id, 16).ok()?;
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        if trace_id == 0 || span_id == 0 {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            span_id,
+            sampled: flags & 1 == 1,
+        })
+    }
+
+    /// This context as a `traceparent` header value.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id,
+            self.span_id,
+            u8::from(self.sampled)
+        )
+    }
+}
+
+fn is_lower_hex(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn new_span_id() -> u64 {
+    rand::random::<u64>().max(1)
+}
+
+fn new_trace_id() -> u128 {
+    rand::random::<u128>().max(1)
+}
+
+tokio::task_local! {
+    static CURRENT_CONTEXT: TraceContext;
+}
+
+/// Run `f` with `context` as the parent for requests it sends, see [`current_trace_context`].
+pub async fn with_trace_context<F: Future>(context: TraceContext, f: F) -> F::Output {
+    CURRENT_CONTEXT.scope(context, f).await
+}
+
+/// The context set by an enclosing [`with_trace_context`] on this task, if any.
+pub fn current_trace_context() -> Option<TraceContext> {
+    CURRENT_CONTEXT.try_with(|context| *context).ok()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SpanKind {
+    Internal,
+    Server,
+    Client,
+}
+
+/// A span attribute value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeValue {
+    String(String),
+    Int(i64),
+    Double(f64),
+    Bool(bool),
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<i64> for AttributeValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<usize> for AttributeValue {
+    fn from(value: usize) -> Self {
+        Self::Int(i64::try_from(value).unwrap_or(i64::MAX))
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        Self::Double(value)
+    }
+}
+
+impl From<bool> for AttributeValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+/// A finished (or, inside [`TraceSpan`], still open) span as handed to the exporter.
+#[derive(Clone, Debug)]
+pub(crate) struct SpanData {
+    pub name: Cow<'static, str>,
+    pub trace_id: u128,
+    pub span_id: u64,
+    pub parent_span_id: Option<u64>,
+    pub kind: SpanKind,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub attributes: Vec<(&'static str, AttributeValue)>,
+    pub error: Option<String>,
+}
+
+impl SpanData {
+    fn new(
+        name: Cow<'static, str>,
+        kind: SpanKind,
+        context: TraceContext,
+        parent: Option<TraceContext>,
+        start: SystemTime,
+    ) -> Self {
+        Self {
+            name,
+            trace_id: context.trace_id,
+            span_id: context.span_id,
+            parent_span_id: parent.map(|parent| parent.span_id),
+            kind,
+            start,
+            end: start,
+            attributes: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+/// An open span, exported when dropped.
+///
+/// Inert when tracing is disabled or the parent trace is not sampled; an unsampled span still
+/// hands its parent's context on so the decision holds for the whole trace.
+#[derive(Debug)]
+pub struct TraceSpan {
+    context: Option<TraceContext>,
+    data: Option<Box<SpanData>>,
+}
+
+impl TraceSpan {
+    /// Start an internal span under `parent`, or a new trace without one.
+    pub fn start(name: impl Into<Cow<'static, str>>, parent: Option<TraceContext>) -> Self {
+        Self::new(name.into(), SpanKind::Internal, parent)
+    }
+
+    /// Start a span for a request received by a server.
+    pub fn server(name: impl Into<Cow<'static, str>>, parent: Option<TraceContext>) -> Self {
+        Self::new(name.into(), SpanKind::Server, parent)
+    }
+
+    /// Start a span for a call out to another service.
+    pub fn client(name: impl Into<Cow<'static, str>>, parent: Option<TraceContext>) -> Self {
+        Self::new(name.into(), SpanKind::Client, parent)
+    }
+
+    fn disabled() -> Self {
+        Self {
+            context: None,
+            data: None,
+        }
+    }
+
+    fn new(name: Cow<'static, str>, kind: SpanKind, parent: Option<TraceContext>) -> Self {
+        if !tracing_enabled() {
+            return Self::disabled();
+        }
+        if let Some(parent) = parent.filter(|parent| !parent.sampled) {
+            return Self {
+                context: Some(parent),
+                data: None,
+            };
+        }
+        let context = TraceContext {
+            trace_id: parent.map_or_else(new_trace_id, |parent| parent.trace_id),
+            span_id: new_span_id(),
+            sampled: true,
+        };
+        Self {
+            context: Some(context),
+            data: Some(Box::new(SpanData::new(
+                name,
+                kind,
+                context,
+                parent,
+                SystemTime::now(),
+            ))),
+        }
+    }
+
+    /// The context to parent child spans and outgoing requests on.
+    pub fn context(&self) -> Option<TraceContext> {
+        self.context
+    }
+
+    pub fn set_attribute(&mut self, key: &'static str, value: impl Into<AttributeValue>) {
+        if let Some(data) = &mut self.data {
+            data.attributes.push((key, value.into()));
+        }
+    }
+
+    /// Mark the span as failed.
+    pub fn set_error(&mut self, message: impl Into<String>) {
+        if let Some(data) = &mut self.data {
+            data.error = Some(message.into());
+        }
+    }
+}
+
+impl Drop for TraceSpan {
+    fn drop(&mut self) {
+        if let Some(mut data) = self.data.take() {
+            data.end = SystemTime::now();
+            export(*data);
+        }
+    }
+}
+
+/// Count and total time of an operation repeated for every token.
+#[derive(Default)]
+struct Totals {
+    count: AtomicUsize,
+    micros: AtomicU64,
+}
+
+impl Totals {
+    fn add(&self, elapsed: Duration) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn report(&self, span: &mut TraceSpan, count_key: &'static str, time_key: &'static str) {
+        let count = self.count.load(Ordering::Relaxed);
+        if count == 0 {
+            return;
+        }
+        span.set_attribute(count_key, count);
+        span.set_attribute(
+            time_key,
+            self.micros.load(Ordering::Relaxed) as f64 / 1000.0,
+        );
+    }
+}
+
+/// The span covering one sequence from creation until it finishes. Engine steps are recorded
+/// under it after the fact by [`record_step`], since a step runs a whole batch at once.
+pub(crate) struct SequenceTrace {
+    span: Mutex<TraceSpan>,
+    context: TraceContext,
+    created: SystemTime,
+    prefill_started: AtomicBool,
+    decode: Totals,
+    grammar_mask: Totals,
+}
+
+impl SequenceTrace {
+    /// `None` unless this sequence will be recorded.
+    pub(crate) fn new(parent: Option<TraceContext>, request_id: usize) -> Option<Self> {
+        let mut span = TraceSpan::start("sequence", parent);
+        let context = span.context().filter(|context| context.sampled)?;
+        span.set_attribute("mistralrs.request_id", request_id);
+        Some(Self {
+            created: span.data.as_ref().map_or_else(SystemTime::now, |d| d.start),
+            span: Mutex::new(span),
+            context,
+            prefill_started: AtomicBool::new(false),
+            decode: Totals::default(),
+            grammar_mask: Totals::default(),
+        })
+    }
+
+    /// Count one grammar mask computation of `elapsed`.
+    pub(crate) fn record_grammar_mask(&self, elapsed: Duration) {
+        self.grammar_mask.add(elapsed);
+    }
+
+    fn child(&self, name: &'static str, start: SystemTime, end: SystemTime) -> SpanData {
+        let context = TraceContext {
+            span_id: new_span_id(),
+            ..self.context
+        };
+        let mut span = SpanData::new(
+            Cow::Borrowed(name),
+            SpanKind::Internal,
+            context,
+            Some(self.context),
+            start,
+        );
+        span.end = end;
+        span
+    }
+
+    /// End the sequence span now rather than when the sequence is dropped.
+    fn finish(&self, seq: &Sequence, reason: impl ToString) {
+        let Ok(mut span) = self.span.lock() else {
+            return;
+        };
+        span.set_attribute("mistralrs.finish_reason", reason.to_string());
+        span.set_attribute("gen_ai.usage.input_tokens", seq.prompt_tokens());
+        span.set_attribute(
+            "gen_ai.usage.output_tokens",
+            seq.len().saturating_sub(seq.prompt_tokens()),
+        );
+        self.decode.report(
+            &mut span,
+            "mistralrs.decode_steps",
+            "mistralrs.decode_time_ms",
+        );
+        self.grammar_mask.report(
+            &mut span,
+            "mistralrs.grammar_masks",
+            "mistralrs.grammar_mask_time_ms",
+        );
+        *span = TraceSpan::disabled();
+    }
+}
+
+/// Record sequences that just finished a forward step of `step_time`. Prompt steps get a span;
+/// decode steps are added to the sequence's totals.
+///
+/// The first prompt step of a sequence also records how long it waited in the queue. Sequences
+/// that finished in this step end their sequence span.
+pub(crate) fn record_step<'a>(
+    seqs: impl IntoIterator<Item = &'a Sequence>,
+    is_prompt: bool,
+    step_time: Duration,
+) {
+    if !tracing_enabled() {
+        return;
+    }
+    let end = SystemTime::now();
+    let start = end.checked_sub(step_time).unwrap_or(end);
+    let seqs: Vec<&Sequence> = seqs.into_iter().collect();
+    let batch_size = seqs.len();
+    for seq in seqs {
+        let Some(trace) = seq.trace() else {
+            continue;
+        };
+        if is_prompt {
+            if !trace.prefill_started.swap(true, Ordering::Relaxed) {
+                export(trace.child("queue", trace.created, start.max(trace.created)));
+            }
+            let mut step = trace.child("prefill", start, end);
+            step.attributes
+                .push(("mistralrs.batch_size", batch_size.into()));
+            step.attributes
+                .push(("mistralrs.sequence_length", seq.len().into()));
+            export(step);
+        } else {
+            trace.decode.add(step_time);
+        }
+
+        match seq.getstate() {
+            SequenceState::Done(reason) => trace.finish(seq, reason),
+            SequenceState::FinishedAborted => trace.finish(seq, "aborted"),
+            _ => {}
+        }
+    }
+}
+
+/// Wrap a tool callback so each call is recorded as a client span named `span_name`, parented on
+/// the `traceparent` the agentic loop puts in the tool call context.
+pub(crate) fn trace_tool_callback(
+    span_name: &'static str,
+    tool: ToolCallbackWithTool,
+) -> ToolCallbackWithTool {
+    fn span_for(span_name: &'static str, tool_name: &str, ctx: &ToolCallContext) -> TraceSpan {
+        // Calls outside a traced request are not worth a trace of their own.
+        let Some(parent) = ctx
+            .traceparent
+            .as_deref()
+            .and_then(TraceContext::from_traceparent)
+        else {
+            return TraceSpan::disabled();
+        };
+        let mut span = TraceSpan::client(span_name, Some(parent));
+        span.set_attribute("gen_ai.tool.name", tool_name);
+        span
+    }
+
+    let tool_name = tool.tool.function.name.clone();
+    let callback = match tool.callback {
+        ToolCallbackKind::Text(callback) => ToolCallbackKind::Text(Arc::new(
+            move |f: &CalledFunction, ctx: &ToolCallContext| {
+                let mut span = span_for(span_name, &tool_name, ctx);
+                let result = callback(f, ctx);
+                if let Err(e) = &result {
+                    span.set_error(e.to_string());
+                }
+                result
+            },
+        )),
+        ToolCallbackKind::Multimodal(callback) => ToolCallbackKind::Multimodal(Arc::new(
+            move |f: &CalledFunction, ctx: &ToolCallContext| {
+                let mut span = span_for(span_name, &tool_name, ctx);
+                let result = callback(f, ctx);
+                if let Err(e) = &result {
+                    span.set_error(e.to_string());
+                }
+                result
+            },
+        )),
+    };
+    ToolCallbackWithTool {
+        callback,
+        tool: tool.tool,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TraceContext;
+
+    #[test]
+    fn traceparent_round_trip() {
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let context = TraceContext::from_traceparent(header).unwrap();
+        assert_eq!(context.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(context.span_id, 0x00f067aa0ba902b7);
+        assert!(context.sampled);
+        assert_eq!(context.traceparent(), header);
+
+        let unsampled = TraceContext::from_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00",
+        )
+        .unwrap();
+        assert!(!unsampled.sampled);
+
+        for invalid in [
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+        ] {
+            assert!(
+                TraceContext::from_traceparent(invalid).is_none(),
+                "{invalid}"
+            );
+        }
+        // Future versions may carry extra fields.
+        assert!(TraceContext::from_traceparent(
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra"
+        )
+        .is_some());
+    }
+}
@@ -0,0 +1,263 @@
+//! OTLP/HTTP span exporter using the JSON encoding.
+
+use std::{
+    sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{json, Value};
+
+use super::{AttributeValue, SpanData, SpanKind};
+
+/// Spans waiting for the exporter thread. Past this, new spans are dropped.
+const MAX_QUEUED_SPANS: usize = 8192;
+const MAX_BATCH_SIZE: usize = 512;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where and how to send spans.
+#[derive(Clone, Debug)]
+pub struct OtlpExporterConfig {
+    /// Collector base URL, e.g. `http://localhost:4318`. Spans are POSTed to
+    /// `{endpoint}/v1/traces`.
+    pub endpoint: String,
+    /// Reported as the `service.name` resource attribute.
+    pub service_name: String,
+    /// Extra headers sent with every export, e.g. collector credentials.
+    pub headers: Vec<(String, String)>,
+}
+
+impl OtlpExporterConfig {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            service_name: "mistralrs".to_string(),
+            headers: Vec::new(),
+        }
+    }
+
+    fn traces_url(&self) -> String {
+        format!("{}/v1/traces", self.endpoint.trim_end_matches('/'))
+    }
+}
+
+pub(super) struct OtlpExporter {
+    sender: SyncSender<SpanData>,
+}
+
+impl OtlpExporter {
+    pub(super) fn start(config: OtlpExporterConfig) -> anyhow::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel(MAX_QUEUED_SPANS);
+        // reqwest::blocking runs its own runtime, so it gets a plain thread.
+        std::thread::Builder::new()
+            .name("otlp-exporter".to_string())
+            .spawn(move || run(&config, &receiver))?;
+        Ok(Self { sender })
+    }
+
+    /// Queue a span without blocking the caller.
+    pub(super) fn export(&self, span: SpanData) {
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(span) {
+            tracing::debug!("OTLP export queue is full, dropping span");
+        }
+    }
+}
+
+fn run(config: &OtlpExporterConfig, receiver: &mpsc::Receiver<SpanData>) {
+    let client = match reqwest::blocking::Client::builder()
+        .timeout(EXPORT_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Failed to create the OTLP exporter client: {e}");
+            return;
+        }
+    };
+    let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+    let mut last_flush = Instant::now();
+    loop {
+        let disconnected =
+            match receiver.recv_timeout(FLUSH_INTERVAL.saturating_sub(last_flush.elapsed())) {
+                Ok(span) => {
+                    batch.push(span);
+                    false
+                }
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => true,
+            };
+        if batch.len() >= MAX_BATCH_SIZE || last_flush.elapsed() >= FLUSH_INTERVAL || disconnected {
+            if !batch.is_empty() {
+                if let Err(e) = post_spans(&client, config, &batch) {
+                    tracing::warn!("Failed to export {} spans over OTLP: {e}", batch.len());
+                }
+                batch.clear();
+            }
+            last_flush = Instant::now();
+        }
+        if disconnected {
+            return;
+        }
+    }
+}
+
+pub(super) fn post_spans(
+    client: &reqwest::blocking::Client,
+    config: &OtlpExporterConfig,
+    spans: &[SpanData],
+) -> anyhow::Result<()> {
+    let body = serde_json::to_vec(&encode_spans(&config.service_name, spans))?;
+    let mut request = client
+        .post(config.traces_url())
+        .header("Content-Type", "application/json");
+    for (name, value) in &config.headers {
+        request = request.header(name, value);
+    }
+    let response = request.body(body).send()?;
+    if !response.status().is_success() {
+        anyhow::bail!("collector returned HTTP {}", response.status());
+    }
+    Ok(())
+}
+
+/// An `ExportTraceServiceRequest` in the OTLP/JSON encoding: ids as hex, 64-bit integers as
+/// strings, enums as numbers.
+fn encode_spans(service_name: &str, spans: &[SpanData]) -> Value {
+    let service_name = encode_attribute("service.name", &AttributeValue::from(service_name));
+    json!({
+        "resourceSpans": [{
+            "resource": { "attributes": [service_name] },
+            "scopeSpans": [{
+                "scope": {
+                    "name": "mistralrs",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "spans": spans.iter().map(encode_span).collect::<Vec<_>>(),
+            }],
+        }],
+    })
+}
+
+fn encode_span(span: &SpanData) -> Value {
+    let mut encoded = json!({
+        "traceId": format!("{:032x}", span.trace_id),
+        "spanId": format!("{:016x}", span.span_id),
+        "name": span.name,
+        "kind": match span.kind {
+            SpanKind::Internal => 1,
+            SpanKind::Server => 2,
+            SpanKind::Client => 3,
+        },
+        "startTimeUnixNano": unix_nanos(span.start).to_string(),
+        "endTimeUnixNano": unix_nanos(span.end).to_string(),
+        "attributes": span
+            .attributes
+            .iter()
+            .map(|(key, value)| encode_attribute(key, value))
+            .collect::<Vec<_>>(),
+    });
+    if let Some(parent) = span.parent_span_id {
+        encoded["parentSpanId"] = Value::String(format!("{parent:016x}"));
+    }
+    if let Some(message) = &span.error {
+        encoded["status"] = json!({ "code": 2, "message": message });
+    }
+    encoded
+}
+
+fn encode_attribute(key: &str, value: &AttributeValue) -> Value {
+    let value = match value {
+        AttributeValue::String(s) => json!({ "stringValue": s }),
+        AttributeValue::Int(i) => json!({ "intValue": i.to_string() }),
+        AttributeValue::Double(d) => json!({ "doubleValue": d }),
+        AttributeValue::Bool(b) => json!({ "boolValue": b }),
+    };
+    json!({ "key": key, "value": value })
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        borrow::Cow,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        time::{Duration, SystemTime},
+    };
+
+    use super::*;
+
+    /// Accepts one request and returns its path and body, standing in for an OTLP collector.
+    fn collector() -> (String, std::thread::JoinHandle<(String, Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}")
+                .unwrap();
+            let path = request_line.split_whitespace().nth(1).unwrap().to_string();
+            (path, serde_json::from_slice(&body).unwrap())
+        });
+        (endpoint, handle)
+    }
+
+    #[test]
+    fn exports_spans_to_collector() {
+        let (endpoint, collector) = collector();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+        let span = SpanData {
+            name: Cow::Borrowed("prefill"),
+            trace_id: 0x4bf92f3577b34da6a3ce929d0e0e4736,
+            span_id: 0xb7,
+            parent_span_id: Some(0x00f067aa0ba902b7),
+            kind: SpanKind::Internal,
+            start,
+            end: start + Duration::from_millis(5),
+            attributes: vec![("mistralrs.batch_size", AttributeValue::Int(3))],
+            error: None,
+        };
+        let config = OtlpExporterConfig::new(format!("{endpoint}/"));
+        post_spans(&reqwest::blocking::Client::new(), &config, &[span]).unwrap();
+
+        let (path, body) = collector.join().unwrap();
+        assert_eq!(path, "/v1/traces");
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0]["value"]["stringValue"],
+            "mistralrs"
+        );
+        let span = &resource["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["name"], "prefill");
+        assert_eq!(span["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(span["spanId"], "00000000000000b7");
+        assert_eq!(span["parentSpanId"], "00f067aa0ba902b7");
+        assert_eq!(span["startTimeUnixNano"], "1000000000");
+        assert_eq!(span["endTimeUnixNano"], "1005000000");
+        assert_eq!(span["attributes"][0]["value"]["intValue"], "3");
+    }
+}
@@ -122,7 +122,6 @@ macro_rules! handle_pipeline_forward_error {
                 };
                 use $crate::response::Response;
                 use $crate::sequence::SequenceState;
-                use $crate::response::SYSTEM_FINGERPRINT;
                 use tracing::error;
                 error!("{} - Model failed with error: {:?}", $stage, &e);
                 for seq in $seq_slice.iter_mut() {
@@ -147,6 +146,7 @@ macro_rules! handle_pipeline_forward_error {
                                 reasoning_content: None,
                             },
                             logprobs: None,
+                            stop_sequence: None,
                         };
                         seq.add_choice_to_group(choice);
                     } else {
@@ -169,7 +169,7 @@ macro_rules! handle_pipeline_forward_error {
                             choices: group.get_choices().to_vec(),
                             created: seq.creation_time(),
                             model: pipeline_name.clone(),
-                            system_fingerprint: SYSTEM_FINGERPRINT.to_string(),
+                            system_fingerprint: seq.system_fingerprint(),
                             object: "chat.completion".to_string(),
                             usage: group.get_usage(),
                             agentic_tool_calls: None,
@@ -190,7 +190,7 @@ macro_rules! handle_pipeline_forward_error {
                             choices: group.get_completion_choices().to_vec(),
                             created: seq.creation_time(),
                             model: pipeline_name.clone(),
-                            system_fingerprint: SYSTEM_FINGERPRINT.to_string(),
+                            system_fingerprint: seq.system_fingerprint(),
                             object: "text_completion".to_string(),
                             usage: group.get_usage(),
                         };
@@ -164,20 +164,25 @@ impl Gemma4MtpRuntime {
         seq_ids: &[usize],
         base_lens: &[usize],
         sequences: &[&Sequence],
-        rng: Arc<Mutex<Isaac64Rng>>,
+        rngs: &[Arc<Mutex<Isaac64Rng>>],
         cache: SpeculativeKvCache<'_>,
     ) -> Result<Vec<SpeculativeProposal>> {
         let batch = sampled_tokens.len();
         if batch == 0 {
             return Ok(Vec::new());
         }
-        if seq_ids.len() != batch || base_lens.len() != batch || sequences.len() != batch {
+        if seq_ids.len() != batch
+            || base_lens.len() != batch
+            || sequences.len() != batch
+            || rngs.len() != batch
+        {
             candle_core::bail!(
-                "MTP batch shape mismatch: sampled={}, seq_ids={}, base_lens={}, sequences={}",
+                "MTP batch shape mismatch: sampled={}, seq_ids={}, base_lens={}, sequences={}, rngs={}",
                 batch,
                 seq_ids.len(),
                 base_lens.len(),
-                sequences.len()
+                sequences.len(),
+                rngs.len()
             );
         }
         if target_hiddens.dim(0)? != batch {
@@ -203,7 +208,7 @@ impl Gemma4MtpRuntime {
                     target_hiddens,
                     base_lens,
                     sequences,
-                    rng,
+                    rngs,
                     &cache,
                 )
             }
@@ -219,7 +224,7 @@ impl Gemma4MtpRuntime {
         target_hiddens: Tensor,
         base_lens: &[usize],
         sequences: &[&Sequence],
-        rng: Arc<Mutex<Isaac64Rng>>,
+        rngs: &[Arc<Mutex<Isaac64Rng>>],
         cache: &Gemma4MtpStepCache<'_>,
     ) -> Result<Vec<SpeculativeProposal>> {
         let batch = sampled_tokens.len();
@@ -243,7 +248,7 @@ impl Gemma4MtpRuntime {
             let input_embed = target_embedder(&last_token)?;
             let (_argmax_token, draft_logits, next_hidden) =
                 self.model.step(input_embed, hidden, base_lens, cache)?;
-            let draft_token = sample_draft_tokens(&draft_logits, sequences, &mut contexts, &rng)?;
+            let draft_token = sample_draft_tokens(&draft_logits, sequences, &mut contexts, rngs)?;
             tokens.push(draft_token.clone());
             logits.push(draft_logits);
             last_token = draft_token;
@@ -269,7 +274,7 @@ fn sample_draft_tokens(
     logits: &Tensor,
     sequences: &[&Sequence],
     contexts: &mut [Vec<u32>],
-    rng: &Arc<Mutex<Isaac64Rng>>,
+    rngs: &[Arc<Mutex<Isaac64Rng>>],
 ) -> Result<Tensor> {
     let batch = sequences.len();
     if contexts.len() != batch {
@@ -280,7 +285,7 @@ fn sample_draft_tokens(
     }
 
     let mut tokens = Vec::with_capacity(batch);
-    for (row, seq) in sequences.iter().enumerate() {
+    for (row, (seq, rng)) in sequences.iter().zip(rngs).enumerate() {
         let row_logits = logits.get(row)?.squeeze(0)?.to_dtype(DType::F32)?;
         let sampled = seq.sampler().sample(
             row_logits,
@@ -325,7 +330,7 @@ impl SpeculativeProposer for Gemma4MtpRuntime {
             ctx.seq_ids,
             ctx.base_lens,
             ctx.sequences,
-            ctx.rng,
+            &ctx.rngs,
             ctx.cache,
         )?;
         Ok(SpeculativeProposalBatch::new(proposals))
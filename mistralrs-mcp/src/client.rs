@@ -441,8 +441,9 @@ impl McpClient {
                 let timeout_duration =
                     Duration::from_secs(self.config.tool_timeout_secs.unwrap_or(30));
 
-                let callback: Arc<ToolCallback> = Arc::new(move |called_function, _ctx| {
+                let callback: Arc<ToolCallback> = Arc::new(move |called_function, ctx| {
                     let connection = Arc::clone(&connection_clone);
+                    let cancellation = ctx.cancellation.clone();
                     let tool_name = original_tool_name.clone();
                     let semaphore = Arc::clone(&semaphore_clone);
                     let arguments: serde_json::Value =
@@ -457,18 +458,20 @@ impl McpClient {
                                 anyhow::anyhow!("Failed to acquire concurrency permit")
                             })?;
 
-                            // Execute tool call with timeout
-                            match tokio::time::timeout(
+                            // Execute tool call with timeout, giving up early if the request is abandoned
+                            let call = tokio::time::timeout(
                                 timeout_duration,
                                 connection.call_tool(&tool_name, arguments),
-                            )
-                            .await
-                            {
-                                Ok(result) => result,
-                                Err(_) => Err(anyhow::anyhow!(
-                                    "Tool call timed out after {} seconds",
-                                    timeout_duration.as_secs()
-                                )),
+                            );
+                            tokio::select! {
+                                result = call => match result {
+                                    Ok(result) => result,
+                                    Err(_) => Err(anyhow::anyhow!(
+                                        "Tool call timed out after {} seconds",
+                                        timeout_duration.as_secs()
+                                    )),
+                                },
+                                () = cancellation.canceled() => Err(anyhow::anyhow!("Tool call was canceled")),
                             }
                         })
                     })
@@ -594,8 +597,9 @@ impl McpClient {
             let semaphore_clone = Arc::clone(&self.concurrency_semaphore);
             let timeout_duration = Duration::from_secs(self.config.tool_timeout_secs.unwrap_or(30));
 
-            let callback: Arc<ToolCallback> = Arc::new(move |called_function, _ctx| {
+            let callback: Arc<ToolCallback> = Arc::new(move |called_function, ctx| {
                 let connection = Arc::clone(&connection_clone);
+                let cancellation = ctx.cancellation.clone();
                 let tool_name = original_tool_name.clone();
                 let semaphore = Arc::clone(&semaphore_clone);
                 let arguments: serde_json::Value =
@@ -611,18 +615,20 @@ impl McpClient {
                             .await
                             .map_err(|_| anyhow::anyhow!("Failed to acquire concurrency permit"))?;
 
-                        // Execute tool call with timeout
-                        match tokio::time::timeout(
+                        // Execute tool call with timeout, giving up early if the request is abandoned
+                        let call = tokio::time::timeout(
                             timeout_duration,
                             connection.call_tool(&tool_name, arguments),
-                        )
-                        .await
-                        {
-                            Ok(result) => result,
-                            Err(_) => Err(anyhow::anyhow!(
-                                "Tool call timed out after {} seconds",
-                                timeout_duration.as_secs()
-                            )),
+                        );
+                        tokio::select! {
+                            result = call => match result {
+                                Ok(result) => result,
+                                Err(_) => Err(anyhow::anyhow!(
+                                    "Tool call timed out after {} seconds",
+                                    timeout_duration.as_secs()
+                                )),
+                            },
+                            () = cancellation.canceled() => Err(anyhow::anyhow!("Tool call was canceled")),
                         }
                     })
                 })
@@ -158,7 +158,7 @@ pub use tools::{
     AgentToolMetadata, AgentToolSource, CalledFunction, CodeExecutionApprovalNotifier,
     CodeExecutionApprovalRequest, CodeExecutionPermission, Function, MultimodalToolCallback, Tool,
     ToolCallContext, ToolCallback, ToolCallbackKind, ToolCallbackWithTool, ToolCallbacksWithTools,
-    ToolFile, ToolOutput, ToolType,
+    ToolCancellation, ToolFile, ToolOutput, ToolType,
 };
 pub use types::McpToolResult;
 
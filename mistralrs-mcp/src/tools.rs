@@ -4,7 +4,9 @@ use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::fmt;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use tokio::sync::Notify;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -131,6 +133,42 @@ impl From<AgentPermission> for CodeExecutionPermission {
     }
 }
 
+/// Fires when the request behind a tool call is abandoned, because its client disconnected or
+/// its deadline passed. Long-running tools should stop early once it is canceled.
+#[derive(Clone, Debug, Default)]
+pub struct ToolCancellation {
+    inner: Arc<CancellationState>,
+}
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    canceled: AtomicBool,
+    notify: Notify,
+}
+
+impl ToolCancellation {
+    pub fn cancel(&self) {
+        self.inner.canceled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.inner.canceled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once [`ToolCancellation::cancel`] has been called.
+    pub async fn canceled(&self) {
+        loop {
+            // Register before checking the flag so a concurrent `cancel` is not missed.
+            let notified = self.inner.notify.notified();
+            if self.is_canceled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
 /// Context provided to tool callbacks by the agentic loop.
 #[derive(Clone, Default)]
 pub struct ToolCallContext {
@@ -142,6 +180,11 @@ pub struct ToolCallContext {
     pub agent_approval_notifier: Option<Arc<AgentToolApprovalNotifier>>,
     pub code_execution_permission: Option<CodeExecutionPermission>,
     pub code_execution_approval_notifier: Option<Arc<CodeExecutionApprovalNotifier>>,
+    /// Files attached to the request. Tools with a working directory stage them there before running.
+    pub input_files: Vec<ToolFile>,
+    pub cancellation: ToolCancellation,
+    /// W3C `traceparent` of the span this call runs under, when the request is traced.
+    pub traceparent: Option<String>,
 }
 
 impl fmt::Debug for ToolCallContext {
@@ -160,6 +203,12 @@ impl fmt::Debug for ToolCallContext {
                 "code_execution_approval_notifier",
                 &self.code_execution_approval_notifier.is_some(),
             )
+            .field(
+                "input_files",
+                &self.input_files.iter().map(|f| &f.name).collect::<Vec<_>>(),
+            )
+            .field("canceled", &self.cancellation.is_canceled())
+            .field("traceparent", &self.traceparent)
             .finish()
     }
 }
@@ -165,6 +165,8 @@ class ChatCompletionRequest:
     code_execution_permission: CodeExecutionPermission | None = None
     session_id: str | None = None
     files: list[RequestedFile] | None = None
+    seed: int | None = None
+    prediction: str | None = None
 
 @dataclass
 class CompletionRequest:
@@ -187,6 +189,7 @@ class CompletionRequest:
     temperature: float | None = None
     top_p: float | None = None
     suffix: str | None = None
+    fim_mode: str | None = None
     top_k: int | None = None
     grammar: str | None = None
     grammar_type: str | None = None
@@ -198,6 +201,8 @@ class CompletionRequest:
     dry_allowed_length: int | None = None
     dry_sequence_breakers: list[str] | None = None
     truncate_sequence: bool = False
+    seed: int | None = None
+    prediction: str | None = None
 
 @dataclass
 class EmbeddingRequest:
@@ -236,6 +241,7 @@ class Architecture(Enum):
 class EmbeddingArchitecture(Enum):
     EmbeddingGemma = "embeddinggemma"
     Qwen3Embedding = "qwen3embedding"
+    Qwen3Reranker = "qwen3reranker"
 
 @dataclass
 class MultimodalArchitecture(Enum):
@@ -674,9 +680,15 @@ class Runner:
         width: int = 1280,
         model_id: str | None = None,
         save_file: str | None = None,
+        num_steps: int | None = None,
+        guidance_scale: float | None = None,
+        seed: int | None = None,
+        negative_prompt: str | None = None,
+        true_cfg_scale: float | None = None,
+        num_images: int = 1,
     ) -> ImageGenerationResponse:
         """
-        Generate an image.
+        Generate one or more images.
 
         Args:
             prompt: The image generation prompt.
@@ -685,10 +697,20 @@ class Runner:
             width: Image width in pixels.
             model_id: Optional model ID to send the request to. If None, uses the default model.
             save_file: Optional path where the PNG is written when response_format is Url. Defaults to an auto-generated filename.
+            num_steps: Denoising steps. Defaults to the model's own (4 for FLUX.1-schnell, 50 for FLUX.1-dev).
+            guidance_scale: Distilled guidance strength for FLUX.1-dev. Defaults to 4.0; ignored by FLUX.1-schnell.
+            seed: Seed for the initial noise. Image i uses seed + i.
+            negative_prompt: Steer away from this prompt with classifier-free guidance. Doubles the cost of each step.
+            true_cfg_scale: Classifier-free guidance scale used with negative_prompt. Defaults to 4.0.
+            num_images: Number of images, denoised together as one batch.
         """
 
     def generate_audio(
-        self, prompt: str, model_id: str | None = None
+        self,
+        prompt: str,
+        model_id: str | None = None,
+        reference_audio: str | None = None,
+        reference_text: str | None = None,
     ) -> SpeechGenerationResponse:
         """
         Generate audio given a (model specific) prompt. PCM and sampling rate as well as the number of channels is returned.
@@ -696,6 +718,8 @@ class Runner:
         Args:
             prompt: The audio generation prompt.
             model_id: Optional model ID to send the request to. If None, uses the default model.
+            reference_audio: Recording of the voice to clone, as a file path, URL or data URL. Requires `reference_text`.
+            reference_text: Transcript of `reference_audio`, using the same speaker tags as the prompt.
         """
 
     def send_re_isq(self, dtype: str, model_id: str | None = None) -> None:
@@ -947,6 +971,12 @@ class Usage:
     total_time_sec: float
     total_prompt_time_sec: float
     total_completion_time_sec: float
+    completion_tokens_details: CompletionTokensDetails | None
+
+@dataclass
+class CompletionTokensDetails:
+    accepted_prediction_tokens: int
+    rejected_prediction_tokens: int
 
 @dataclass
 class ToolCallType(Enum):
@@ -1108,6 +1138,7 @@ class File:
     format: str | None
     mime_type: str | None
     bytes: int
+    purpose: str | None
     source: FileSource
     text: str | None
     data_base64: str | None
@@ -36,14 +36,14 @@ use mistralrs_core::{
     AnyMoeLoader, AutoDeviceMapParams, ChatCompletionResponse, CompletionResponse, Constraint,
     DefaultSchedulerMethod, DetokenizationRequest, DeviceLayerMapMetadata, DeviceMapMetadata,
     DeviceMapSetting, DiffusionGenerationParams, DiffusionLoaderBuilder, DrySamplingParams,
-    EmbeddingLoaderBuilder, EmbeddingSpecificConfig, GGMLLoaderBuilder, GGMLSpecificConfig,
-    GGUFLoaderBuilder, GGUFSpecificConfig, ImageGenerationResponse, ImageGenerationResponseFormat,
-    LlguidanceGrammar, Loader, MemoryGpuConfig, MistralRs, MistralRsBuilder,
-    MultimodalLoaderBuilder, MultimodalSpecificConfig, NormalLoaderBuilder, NormalRequest,
-    NormalSpecificConfig, PagedAttentionConfig, PagedCacheType, ReasoningEffort,
-    Request as _Request, RequestMessage, Response, ResponseOk, SamplingParams, SchedulerConfig,
-    SearchEmbeddingModel, SpeculativeConfig, SpeechLoader, StopTokens, TokenSource,
-    TokenizationRequest, Tool, Topology,
+    EmbeddingLoaderBuilder, EmbeddingSpecificConfig, FimMode, GGMLLoaderBuilder,
+    GGMLSpecificConfig, GGUFLoaderBuilder, GGUFSpecificConfig, ImageGenerationResponse,
+    ImageGenerationResponseFormat, LlguidanceGrammar, Loader, MemoryGpuConfig, MistralRs,
+    MistralRsBuilder, MultimodalLoaderBuilder, MultimodalSpecificConfig, NormalLoaderBuilder,
+    NormalRequest, NormalSpecificConfig, PagedAttentionConfig, PagedCacheType, ReasoningEffort,
+    Request as _Request, RequestMessage, RequestPriority, Response, ResponseOk, SamplingParams,
+    SchedulerConfig, SearchEmbeddingModel, SpeculativeConfig, SpeechLoader, SpeechReference,
+    StopTokens, TokenSource, TokenizationRequest, Tool, Topology,
 };
 use mistralrs_core::{
     CalledFunction, SearchCallback, SearchFunctionParameters, SearchResult, ToolCallback,
@@ -595,6 +595,17 @@ fn build_constraint(grammar: Option<&str>, grammar_type: Option<&str>) -> PyApiR
 
     Ok(constraint)
 }
+
+fn parse_fim_mode(fim_mode: Option<&str>) -> PyApiResult<FimMode> {
+    match fim_mode {
+        None | Some("psm") => Ok(FimMode::Psm),
+        Some("spm") => Ok(FimMode::Spm),
+        Some(other) => Err(PyApiErr::from(format!(
+            "FIM mode `{other}` is not `psm` nor `spm`"
+        ))),
+    }
+}
+
 #[pymethods]
 impl Runner {
     #[new]
@@ -1299,6 +1310,7 @@ impl Runner {
                     n_choices: request.n_choices,
                     min_p: request.min_p,
                     dry_params,
+                    seed: request.seed,
                 },
                 response: tx,
                 return_logprobs: request.logprobs,
@@ -1326,6 +1338,13 @@ impl Runner {
                     .files
                     .clone()
                     .map(|fs| fs.into_iter().map(Into::into).collect()),
+                input_files: None,
+                priority: RequestPriority::Normal,
+                timeout: None,
+                trace_context: None,
+                user: None,
+                prediction: request.prediction.clone(),
+                agentic_permit: None,
             }));
 
             let is_streaming = request.stream;
@@ -1415,6 +1434,13 @@ impl Runner {
                         truncate_sequence,
                         session_id: None,
                         files: None,
+                        input_files: None,
+                        priority: RequestPriority::Normal,
+                        timeout: None,
+                        trace_context: None,
+                        user: None,
+                        prediction: None,
+                        agentic_permit: None,
                     }));
 
                     sender
@@ -1502,6 +1528,7 @@ impl Runner {
                     text: request.prompt.clone(),
                     echo_prompt: request.echo_prompt,
                     best_of: request.best_of,
+                    fim_mode: parse_fim_mode(request.fim_mode.as_deref())?,
                 },
                 sampling_params: SamplingParams {
                     temperature: request.temperature,
@@ -1517,6 +1544,7 @@ impl Runner {
                     n_choices: request.n_choices,
                     min_p: request.min_p,
                     dry_params,
+                    seed: request.seed,
                 },
                 response: tx,
                 return_logprobs: false,
@@ -1540,6 +1568,13 @@ impl Runner {
                 truncate_sequence: request.truncate_sequence,
                 session_id: None,
                 files: None,
+                input_files: None,
+                priority: RequestPriority::Normal,
+                timeout: None,
+                trace_context: None,
+                user: None,
+                prediction: request.prediction.clone(),
+                agentic_permit: None,
             }));
 
             let debug_repr = format!("{request:?}");
@@ -1564,6 +1599,12 @@ impl Runner {
         width = 1280,
         model_id = None,
         save_file = None,
+        num_steps = None,
+        guidance_scale = None,
+        seed = None,
+        negative_prompt = None,
+        true_cfg_scale = None,
+        num_images = 1,
     ))]
     fn generate_image(
         &self,
@@ -1574,6 +1615,12 @@ impl Runner {
         width: usize,
         model_id: Option<String>,
         save_file: Option<PathBuf>,
+        num_steps: Option<usize>,
+        guidance_scale: Option<f64>,
+        seed: Option<u64>,
+        negative_prompt: Option<String>,
+        true_cfg_scale: Option<f64>,
+        num_images: usize,
     ) -> PyApiResult<ImageGenerationResponse> {
         let (tx, mut rx) = channel(1);
 
@@ -1582,7 +1629,17 @@ impl Runner {
             messages: RequestMessage::ImageGeneration {
                 prompt: prompt.to_string(),
                 format: response_format,
-                generation_params: DiffusionGenerationParams { height, width },
+                generation_params: DiffusionGenerationParams {
+                    height,
+                    width,
+                    num_steps,
+                    guidance_scale,
+                    seed,
+                    negative_prompt,
+                    true_cfg_scale,
+                    num_images,
+                    image_input: None,
+                },
                 save_file,
             },
             sampling_params: SamplingParams::deterministic(),
@@ -1608,6 +1665,13 @@ impl Runner {
             truncate_sequence: false,
             session_id: None,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
 
         let runner = self.runner.clone();
@@ -1629,22 +1693,39 @@ impl Runner {
         Ok(response)
     }
 
-    /// Generate audio.
+    /// Generate audio, optionally in the voice of a reference recording.
     #[pyo3(signature = (
         prompt,
         model_id = None,
+        reference_audio = None,
+        reference_text = None,
     ))]
     fn generate_audio(
         &self,
         py: Python<'_>,
         prompt: String,
         model_id: Option<String>,
+        reference_audio: Option<String>,
+        reference_text: Option<String>,
     ) -> PyApiResult<SpeechGenerationResponse> {
+        let reference = match (reference_audio, reference_text) {
+            (Some(audio), Some(transcript)) => Some(SpeechReference {
+                audio: util::parse_audio_url(&audio)?,
+                transcript,
+            }),
+            (None, None) => None,
+            _ => {
+                return Err(PyApiErr::from(
+                    "`reference_audio` and `reference_text` must be given together.",
+                ))
+            }
+        };
+
         let (tx, mut rx) = channel(1);
 
         let request = _Request::Normal(Box::new(NormalRequest {
             id: 0,
-            messages: RequestMessage::SpeechGeneration { prompt },
+            messages: RequestMessage::SpeechGeneration { prompt, reference },
             sampling_params: SamplingParams::deterministic(),
             response: tx,
             return_logprobs: false,
@@ -1668,6 +1749,13 @@ impl Runner {
             truncate_sequence: false,
             session_id: None,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
 
         let runner = self.runner.clone();
@@ -2189,6 +2277,7 @@ impl Runner {
                     n_choices: request.n_choices,
                     min_p: request.min_p,
                     dry_params,
+                    seed: request.seed,
                 },
                 response: tx,
                 return_logprobs: request.logprobs,
@@ -2216,6 +2305,13 @@ impl Runner {
                     .files
                     .clone()
                     .map(|fs| fs.into_iter().map(Into::into).collect()),
+                input_files: None,
+                priority: RequestPriority::Normal,
+                timeout: None,
+                trace_context: None,
+                user: None,
+                prediction: request.prediction.clone(),
+                agentic_permit: None,
             }));
 
             let is_streaming = request.stream;
@@ -2291,6 +2387,7 @@ impl Runner {
                     text: request.prompt.clone(),
                     echo_prompt: request.echo_prompt,
                     best_of: request.best_of,
+                    fim_mode: parse_fim_mode(request.fim_mode.as_deref())?,
                 },
                 sampling_params: SamplingParams {
                     temperature: request.temperature,
@@ -2306,6 +2403,7 @@ impl Runner {
                     n_choices: request.n_choices,
                     min_p: request.min_p,
                     dry_params,
+                    seed: request.seed,
                 },
                 response: tx,
                 return_logprobs: false,
@@ -2329,6 +2427,13 @@ impl Runner {
                 truncate_sequence: request.truncate_sequence,
                 session_id: None,
                 files: None,
+                input_files: None,
+                priority: RequestPriority::Normal,
+                timeout: None,
+                trace_context: None,
+                user: None,
+                prediction: request.prediction.clone(),
+                agentic_permit: None,
             }));
 
             let debug_repr = format!("{request:?}");
@@ -2599,6 +2704,7 @@ fn mistralrs(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<mistralrs_core::Choice>()?;
     m.add_class::<mistralrs_core::ChunkChoice>()?;
     m.add_class::<mistralrs_core::Usage>()?;
+    m.add_class::<mistralrs_core::CompletionTokensDetails>()?;
     m.add_class::<mistralrs_core::AgenticToolCallRecord>()?;
     m.add_class::<mistralrs_core::ChatCompletionResponse>()?;
     m.add_class::<mistralrs_core::ChatCompletionChunkResponse>()?;
@@ -36,6 +36,7 @@ pub struct CompletionRequest {
     pub(crate) temperature: Option<f64>,
     pub(crate) top_p: Option<f64>,
     pub(crate) suffix: Option<String>,
+    pub(crate) fim_mode: Option<String>,
     pub(crate) top_k: Option<usize>,
     pub(crate) grammar: Option<String>,
     pub(crate) grammar_type: Option<String>,
@@ -47,6 +48,8 @@ pub struct CompletionRequest {
     pub(crate) dry_allowed_length: Option<usize>,
     pub(crate) dry_sequence_breakers: Option<Vec<String>>,
     pub(crate) truncate_sequence: bool,
+    pub(crate) seed: Option<u64>,
+    pub(crate) prediction: Option<String>,
 }
 
 #[pymethods]
@@ -67,6 +70,7 @@ impl CompletionRequest {
         temperature=None,
         top_p=None,
         suffix=None,
+        fim_mode=None,
         top_k=None,
         grammar = None,
         grammar_type = None,
@@ -78,6 +82,8 @@ impl CompletionRequest {
         dry_allowed_length=None,
         dry_sequence_breakers=None,
         truncate_sequence=false,
+        seed=None,
+        prediction=None,
     ))]
     fn new(
         prompt: String,
@@ -94,6 +100,7 @@ impl CompletionRequest {
         temperature: Option<f64>,
         top_p: Option<f64>,
         suffix: Option<String>,
+        fim_mode: Option<String>,
         top_k: Option<usize>,
         grammar: Option<String>,
         grammar_type: Option<String>,
@@ -105,12 +112,15 @@ impl CompletionRequest {
         dry_allowed_length: Option<usize>,
         dry_sequence_breakers: Option<Vec<String>>,
         truncate_sequence: Option<bool>,
+        seed: Option<u64>,
+        prediction: Option<String>,
     ) -> PyResult<Self> {
         Ok(Self {
             prompt,
             best_of,
             echo_prompt,
             suffix,
+            fim_mode,
             _model: model,
             logit_bias,
             max_tokens,
@@ -132,6 +142,8 @@ impl CompletionRequest {
             dry_base,
             dry_sequence_breakers,
             truncate_sequence: truncate_sequence.unwrap_or(false),
+            seed,
+            prediction,
         })
     }
 }
@@ -277,6 +289,9 @@ pub struct ChatCompletionRequest {
     pub(crate) session_id: Option<String>,
     /// Required output files; surfaced as `ChatCompletionResponse.files`.
     pub(crate) files: Option<Vec<crate::files::RequestedFile>>,
+    pub(crate) seed: Option<u64>,
+    /// Expected output, verified in one forward pass instead of generated token by token.
+    pub(crate) prediction: Option<String>,
 }
 
 #[pymethods]
@@ -319,6 +334,8 @@ impl ChatCompletionRequest {
         code_execution_permission=None,
         session_id=None,
         files=None,
+        seed=None,
+        prediction=None,
     ))]
     fn new(
         messages: Py<PyAny>,
@@ -357,6 +374,8 @@ impl ChatCompletionRequest {
         code_execution_permission: Option<Py<PyAny>>,
         session_id: Option<String>,
         files: Option<Vec<crate::files::RequestedFile>>,
+        seed: Option<u64>,
+        prediction: Option<String>,
     ) -> PyResult<Self> {
         let messages = Python::with_gil(|py| {
             if let Ok(messages) = messages.bind(py).downcast_exact::<PyList>() {
@@ -447,6 +466,8 @@ impl ChatCompletionRequest {
             code_execution_permission,
             session_id,
             files,
+            seed,
+            prediction,
         })
     }
 }
@@ -157,6 +157,7 @@ pub(crate) fn parse_chat_response(response: Response) -> PyApiResult<ChatComplet
         Response::CompletionChunk(_) => unreachable!(),
         Response::ImageGeneration(_) => unreachable!(),
         Response::Speech { .. } => unreachable!(),
+        Response::SpeechChunk { .. } => unreachable!(),
         Response::Raw { .. } => unreachable!(),
         Response::Embeddings { .. } => unreachable!(),
         Response::AgenticToolCallProgress { .. } => unreachable!(),
@@ -178,6 +179,7 @@ pub(crate) fn parse_completion_response(response: Response) -> PyApiResult<Compl
         Response::CompletionChunk(_) => unreachable!(),
         Response::ImageGeneration(_) => unreachable!(),
         Response::Speech { .. } => unreachable!(),
+        Response::SpeechChunk { .. } => unreachable!(),
         Response::Raw { .. } => unreachable!(),
         Response::Embeddings { .. } => unreachable!(),
         Response::AgenticToolCallProgress { .. } => unreachable!(),
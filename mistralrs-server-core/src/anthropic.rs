@@ -0,0 +1,1214 @@
+//! ## Anthropic Messages API compatibility.
+//!
+//! `POST /v1/messages` accepts the Anthropic request schema, translates it into a
+//! [`ChatCompletionRequest`] and runs it through the regular chat pipeline. Responses are
+//! translated back into content blocks: reasoning becomes `thinking`, tool calls become
+//! `tool_use`, and streaming uses the Anthropic SSE event types.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    pin::Pin,
+    task::Poll,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, KeepAliveStream},
+        IntoResponse, Sse,
+    },
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use either::Either;
+use mistralrs_core::{
+    ChatCompletionChunkResponse, ChatCompletionResponse, Function, MistralRs, Response, Tool,
+    ToolCallResponse, ToolChoice, ToolType, Usage,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc::Receiver;
+use uuid::Uuid;
+
+use crate::{
+    chat_completion::parse_request as parse_chat_request,
+    completion_core::BaseCompletionResponder,
+    handler_core::{
+        boxed_send_error, create_response_channel, overloaded, send_request_with_model,
+        with_retry_after, ModelErrorMessage,
+    },
+    openai::{
+        ChatCompletionRequest, FunctionCalled, Message, MessageContent, MessageInnerContent,
+        StopTokens, ToolCall,
+    },
+    streaming::{get_keep_alive_interval, DoneState},
+    types::{ExtractedMistralRsState, SharedMistralRsState},
+    util::sanitize_error_message,
+};
+
+/// Anthropic Messages API request.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicMessagesRequest {
+    #[serde(default = "default_model")]
+    pub model: String,
+    pub messages: Vec<AnthropicInputMessage>,
+    pub max_tokens: usize,
+    #[serde(default)]
+    pub system: Option<AnthropicSystem>,
+    #[serde(default)]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(default)]
+    pub stream: Option<bool>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub top_p: Option<f64>,
+    #[serde(default)]
+    pub top_k: Option<usize>,
+    #[serde(default)]
+    pub tools: Option<Vec<AnthropicTool>>,
+    #[serde(default)]
+    pub tool_choice: Option<AnthropicToolChoice>,
+    #[serde(default)]
+    pub thinking: Option<AnthropicThinking>,
+    /// Accepted for compatibility; not used.
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+fn default_model() -> String {
+    "default".to_string()
+}
+
+/// The `system` prompt: a string or a list of text blocks.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum AnthropicSystem {
+    Text(String),
+    Blocks(Vec<AnthropicSystemBlock>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicSystemBlock {
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicInputMessage {
+    pub role: String,
+    pub content: AnthropicContent,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum AnthropicContent {
+    Text(String),
+    Blocks(Vec<AnthropicContentBlock>),
+}
+
+/// Request-side content block.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicContentBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        source: AnthropicSource,
+    },
+    Document {
+        source: AnthropicSource,
+        #[serde(default)]
+        title: Option<String>,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default)]
+        content: Option<AnthropicContent>,
+        #[serde(default)]
+        is_error: Option<bool>,
+    },
+    /// Earlier reasoning echoed back by the client. Dropped before templating.
+    Thinking {
+        thinking: String,
+    },
+    RedactedThinking {
+        data: String,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicSource {
+    Base64 {
+        media_type: String,
+        data: String,
+    },
+    Url {
+        url: String,
+    },
+    Text {
+        data: String,
+    },
+    /// A file uploaded through `POST /v1/files`.
+    File {
+        file_id: String,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicTool {
+    /// Client tools have no type or `custom`; server tools (web search, bash, ...) are rejected.
+    #[serde(default, rename = "type")]
+    pub tp: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub input_schema: Option<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicToolChoice {
+    Auto,
+    /// The model must call some tool. Only supported with a single tool, which is then forced.
+    Any,
+    Tool {
+        name: String,
+    },
+    None,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicThinking {
+    Enabled {
+        /// Accepted for compatibility; reasoning length is bounded by `max_tokens` only.
+        #[serde(default)]
+        budget_tokens: Option<usize>,
+    },
+    Disabled,
+}
+
+/// Response-side content block.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicOutputBlock {
+    Thinking {
+        thinking: String,
+        signature: String,
+    },
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct AnthropicUsage {
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+}
+
+impl From<&Usage> for AnthropicUsage {
+    fn from(usage: &Usage) -> Self {
+        Self {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+        }
+    }
+}
+
+/// Anthropic `message` object.
+#[derive(Debug, Clone, Serialize)]
+pub struct AnthropicMessage {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub object: &'static str,
+    pub role: &'static str,
+    pub model: String,
+    pub content: Vec<AnthropicOutputBlock>,
+    pub stop_reason: Option<&'static str>,
+    pub stop_sequence: Option<String>,
+    pub usage: AnthropicUsage,
+}
+
+impl AnthropicMessage {
+    fn new(id: String, model: String) -> Self {
+        Self {
+            id,
+            object: "message",
+            role: "assistant",
+            model,
+            content: Vec::new(),
+            stop_reason: None,
+            stop_sequence: None,
+            usage: AnthropicUsage::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnthropicErrorBody {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicDelta {
+    TextDelta { text: String },
+    ThinkingDelta { thinking: String },
+    InputJsonDelta { partial_json: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnthropicMessageDelta {
+    pub stop_reason: &'static str,
+    pub stop_sequence: Option<String>,
+}
+
+/// Streaming events. The SSE `event:` name matches the `type` field.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicStreamEvent {
+    MessageStart {
+        message: AnthropicMessage,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: AnthropicOutputBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: AnthropicDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        delta: AnthropicMessageDelta,
+        usage: AnthropicUsage,
+    },
+    MessageStop,
+    Error {
+        error: AnthropicErrorBody,
+    },
+}
+
+impl AnthropicStreamEvent {
+    fn event_type(&self) -> &'static str {
+        match self {
+            Self::MessageStart { .. } => "message_start",
+            Self::ContentBlockStart { .. } => "content_block_start",
+            Self::ContentBlockDelta { .. } => "content_block_delta",
+            Self::ContentBlockStop { .. } => "content_block_stop",
+            Self::MessageDelta { .. } => "message_delta",
+            Self::MessageStop => "message_stop",
+            Self::Error { .. } => "error",
+        }
+    }
+
+    fn into_sse(self) -> Result<Event, axum::Error> {
+        Event::default().event(self.event_type()).json_data(self)
+    }
+}
+
+/// Map an OpenAI-style finish reason onto an Anthropic `stop_reason`.
+fn stop_reason(finish_reason: &str, stop_sequence: Option<&str>) -> &'static str {
+    match finish_reason {
+        "length" => "max_tokens",
+        "tool_calls" => "tool_use",
+        _ if stop_sequence.is_some() => "stop_sequence",
+        _ => "end_turn",
+    }
+}
+
+/// Tool arguments are JSON text; Anthropic wants the parsed object.
+fn tool_input(arguments: &str) -> Value {
+    serde_json::from_str(arguments).unwrap_or_else(|_| Value::String(arguments.to_string()))
+}
+
+fn tool_use_block(call: &ToolCallResponse) -> AnthropicOutputBlock {
+    AnthropicOutputBlock::ToolUse {
+        id: call.id.clone(),
+        name: call.function.name.clone(),
+        input: tool_input(&call.function.arguments),
+    }
+}
+
+fn text_message(role: &str, content: MessageContent) -> Message {
+    Message {
+        content: Some(content),
+        role: role.to_string(),
+        name: None,
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
+/// Escape text placed inside the `<document>` wrapper so it cannot close the tag or the
+/// `title` attribute.
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn data_url(media_type: &str, data: &str) -> String {
+    format!("data:{media_type};base64,{data}")
+}
+
+/// Convert a user-side content block into a chat content part.
+fn user_part(block: AnthropicContentBlock) -> Result<HashMap<String, MessageInnerContent>> {
+    Ok(match block {
+        AnthropicContentBlock::Text { text } => MessageContent::text_part(text),
+        AnthropicContentBlock::Image { source } => match source {
+            AnthropicSource::Base64 { media_type, data } => {
+                MessageContent::image_url_part(data_url(&media_type, &data))
+            }
+            AnthropicSource::Url { url } => MessageContent::image_url_part(url),
+            AnthropicSource::File { file_id } => MessageContent::file_part(file_id),
+            AnthropicSource::Text { .. } => anyhow::bail!("Image blocks cannot use a text source."),
+        },
+        AnthropicContentBlock::Document { source, title } => {
+            let text = match source {
+                AnthropicSource::File { file_id } => return Ok(MessageContent::file_part(file_id)),
+                AnthropicSource::Text { data } => data,
+                AnthropicSource::Base64 { media_type, data } if media_type.starts_with("text/") => {
+                    String::from_utf8(STANDARD.decode(data.as_bytes())?)
+                        .context("Document data is not valid UTF-8.")?
+                }
+                AnthropicSource::Base64 { media_type, .. } => anyhow::bail!(
+                    "Documents of type `{media_type}` are not supported. Only text documents can be used."
+                ),
+                AnthropicSource::Url { .. } => {
+                    anyhow::bail!("Documents with a `url` source are not supported.")
+                }
+            };
+            match title {
+                Some(title) => MessageContent::text_part(format!(
+                    "<document title=\"{}\">\n{}\n</document>",
+                    escape_markup(&title),
+                    escape_markup(&text)
+                )),
+                None => MessageContent::text_part(text),
+            }
+        }
+        AnthropicContentBlock::ToolUse { .. } => {
+            anyhow::bail!("`tool_use` blocks are only valid in assistant messages.")
+        }
+        AnthropicContentBlock::Thinking { .. } | AnthropicContentBlock::RedactedThinking { .. } => {
+            anyhow::bail!("`thinking` blocks are only valid in assistant messages.")
+        }
+        AnthropicContentBlock::ToolResult { .. } => {
+            anyhow::bail!("`tool_result` blocks must be at the top level of a user message.")
+        }
+    })
+}
+
+/// Flatten `tool_result` content to text. Chat templates only carry text for tool messages.
+fn tool_result_text(content: Option<AnthropicContent>) -> Result<String> {
+    match content {
+        None => Ok(String::new()),
+        Some(AnthropicContent::Text(text)) => Ok(text),
+        Some(AnthropicContent::Blocks(blocks)) => blocks
+            .into_iter()
+            .map(|block| match block {
+                AnthropicContentBlock::Text { text } => Ok(text),
+                _ => anyhow::bail!("Only text content is supported in `tool_result` blocks."),
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(|parts| parts.join("\n")),
+    }
+}
+
+fn convert_user_message(content: AnthropicContent, out: &mut Vec<Message>) -> Result<()> {
+    let blocks = match content {
+        AnthropicContent::Text(text) => {
+            out.push(text_message("user", MessageContent::from_text(text)));
+            return Ok(());
+        }
+        AnthropicContent::Blocks(blocks) => blocks,
+    };
+
+    let mut parts = Vec::new();
+    for block in blocks {
+        if let AnthropicContentBlock::ToolResult {
+            tool_use_id,
+            content,
+            is_error,
+        } = block
+        {
+            let mut text = tool_result_text(content)?;
+            if is_error == Some(true) {
+                text = format!("Error: {text}");
+            }
+            out.push(Message {
+                content: Some(MessageContent::from_text(text)),
+                role: "tool".to_string(),
+                name: None,
+                tool_calls: None,
+                tool_call_id: Some(tool_use_id),
+            });
+        } else {
+            parts.push(user_part(block)?);
+        }
+    }
+
+    let only_text = parts
+        .iter()
+        .all(|part| !part.contains_key("image_url") && !part.contains_key("file"));
+    if only_text && !parts.is_empty() {
+        let text = MessageContent::from_parts(parts)
+            .to_text()
+            .unwrap_or_default();
+        out.push(text_message("user", MessageContent::from_text(text)));
+    } else if !parts.is_empty() {
+        out.push(text_message("user", MessageContent::from_parts(parts)));
+    }
+    Ok(())
+}
+
+fn convert_assistant_message(content: AnthropicContent) -> Result<Message> {
+    let blocks = match content {
+        AnthropicContent::Text(text) => {
+            return Ok(text_message("assistant", MessageContent::from_text(text)))
+        }
+        AnthropicContent::Blocks(blocks) => blocks,
+    };
+
+    let mut text = Vec::new();
+    let mut tool_calls = Vec::new();
+    for block in blocks {
+        match block {
+            AnthropicContentBlock::Text { text: t } => text.push(t),
+            AnthropicContentBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                id: Some(id),
+                tp: ToolType::Function,
+                function: FunctionCalled {
+                    name,
+                    arguments: input.to_string(),
+                },
+            }),
+            AnthropicContentBlock::Thinking { .. }
+            | AnthropicContentBlock::RedactedThinking { .. } => {}
+            _ => anyhow::bail!(
+                "Assistant messages may only contain text, tool_use and thinking blocks."
+            ),
+        }
+    }
+
+    let content = if text.is_empty() && !tool_calls.is_empty() {
+        None
+    } else {
+        Some(MessageContent::from_text(text.join("")))
+    };
+    Ok(Message {
+        content,
+        role: "assistant".to_string(),
+        name: None,
+        tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+        tool_call_id: None,
+    })
+}
+
+fn convert_tool(tool: AnthropicTool) -> Result<Tool> {
+    if let Some(tp) = tool.tp.as_deref().filter(|tp| *tp != "custom") {
+        anyhow::bail!("Server tool `{tp}` is not supported. Only client tools can be used.");
+    }
+    let parameters = match tool.input_schema {
+        Some(schema) => Some(serde_json::from_value(schema).with_context(|| {
+            format!("`input_schema` of tool `{}` must be an object.", tool.name)
+        })?),
+        None => None,
+    };
+    Ok(Tool {
+        tp: ToolType::Function,
+        function: Function {
+            description: tool.description,
+            name: tool.name,
+            parameters,
+            strict: None,
+        },
+    })
+}
+
+/// Translate an Anthropic request into the OpenAI chat request the chat pipeline consumes.
+pub fn to_chat_request(request: AnthropicMessagesRequest) -> Result<ChatCompletionRequest> {
+    let mut messages = Vec::new();
+    match request.system {
+        Some(AnthropicSystem::Text(text)) => {
+            messages.push(text_message("system", MessageContent::from_text(text)))
+        }
+        Some(AnthropicSystem::Blocks(blocks)) if !blocks.is_empty() => {
+            let text = blocks
+                .into_iter()
+                .map(|b| b.text)
+                .collect::<Vec<_>>()
+                .join("\n");
+            messages.push(text_message("system", MessageContent::from_text(text)));
+        }
+        _ => {}
+    }
+
+    for message in request.messages {
+        match message.role.as_str() {
+            "user" => convert_user_message(message.content, &mut messages)?,
+            "assistant" => messages.push(convert_assistant_message(message.content)?),
+            other => {
+                anyhow::bail!("Invalid message role `{other}`. Expected `user` or `assistant`.")
+            }
+        }
+    }
+
+    let tools = request
+        .tools
+        .map(|tools| {
+            tools
+                .into_iter()
+                .map(convert_tool)
+                .collect::<Result<Vec<_>>>()
+        })
+        .transpose()?;
+    let tool_choice = match request.tool_choice {
+        None => None,
+        Some(AnthropicToolChoice::Auto) => Some(ToolChoice::Auto),
+        // There is no "some tool" constraint, so `any` is only honoured when it means one tool.
+        Some(AnthropicToolChoice::Any) => match tools.as_deref() {
+            Some([tool]) => Some(ToolChoice::Tool(tool.clone())),
+            _ => anyhow::bail!(
+                "`tool_choice` of type `any` is only supported with exactly one tool. Use `auto` or name the tool with type `tool`."
+            ),
+        },
+        Some(AnthropicToolChoice::None) => Some(ToolChoice::None),
+        Some(AnthropicToolChoice::Tool { name }) => {
+            let tool = tools
+                .iter()
+                .flatten()
+                .find(|t| t.function.name == name)
+                .with_context(|| format!("`tool_choice` names unknown tool `{name}`."))?;
+            Some(ToolChoice::Tool(tool.clone()))
+        }
+    };
+    let enable_thinking = request.thinking.map(|thinking| match thinking {
+        AnthropicThinking::Enabled { .. } => true,
+        AnthropicThinking::Disabled => false,
+    });
+
+    Ok(ChatCompletionRequest {
+        messages: Either::Left(messages),
+        model: request.model,
+        logit_bias: None,
+        logprobs: false,
+        top_logprobs: None,
+        max_tokens: Some(request.max_tokens),
+        n_choices: 1,
+        presence_penalty: None,
+        frequency_penalty: None,
+        seed: None,
+        repetition_penalty: None,
+        stop_seqs: request.stop_sequences.map(StopTokens::Multi),
+        temperature: request.temperature,
+        top_p: request.top_p,
+        stream: request.stream,
+        stream_options: None,
+        tools,
+        tool_choice,
+        response_format: None,
+        web_search_options: None,
+        prediction: None,
+        enable_code_execution: false,
+        agent_permission: None,
+        code_execution_permission: None,
+        session_id: None,
+        max_tool_rounds: None,
+        top_k: request.top_k,
+        grammar: None,
+        min_p: None,
+        dry_multiplier: None,
+        dry_base: None,
+        dry_allowed_length: None,
+        dry_sequence_breakers: None,
+        enable_thinking,
+        truncate_sequence: None,
+        reasoning_effort: None,
+        files: None,
+        priority: None,
+        timeout: None,
+    })
+}
+
+/// Translate a finished chat completion into an Anthropic `message`.
+pub fn chat_response_to_message(response: &ChatCompletionResponse, id: String) -> AnthropicMessage {
+    let mut message = AnthropicMessage::new(id, response.model.clone());
+    message.usage = AnthropicUsage::from(&response.usage);
+    let Some(choice) = response.choices.first() else {
+        message.stop_reason = Some("end_turn");
+        return message;
+    };
+
+    if let Some(reasoning) = choice
+        .message
+        .reasoning_content
+        .as_ref()
+        .filter(|r| !r.is_empty())
+    {
+        message.content.push(AnthropicOutputBlock::Thinking {
+            thinking: reasoning.clone(),
+            signature: String::new(),
+        });
+    }
+    if let Some(text) = choice.message.content.as_ref().filter(|t| !t.is_empty()) {
+        message
+            .content
+            .push(AnthropicOutputBlock::Text { text: text.clone() });
+    }
+    let tool_calls = choice.message.tool_calls.as_deref().unwrap_or_default();
+    message
+        .content
+        .extend(tool_calls.iter().map(tool_use_block));
+    message.stop_reason = Some(if tool_calls.is_empty() {
+        stop_reason(&choice.finish_reason, choice.stop_sequence.as_deref())
+    } else {
+        "tool_use"
+    });
+    if message.stop_reason == Some("stop_sequence") {
+        message.stop_sequence = choice.stop_sequence.clone();
+    }
+    message
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OpenBlock {
+    Thinking,
+    Text,
+}
+
+/// Translates chat chunks into Anthropic stream events.
+pub struct AnthropicStreamer {
+    rx: Receiver<Response>,
+    state: SharedMistralRsState,
+    done_state: DoneState,
+    message: Option<AnthropicMessage>,
+    pending: VecDeque<AnthropicStreamEvent>,
+    open_block: Option<OpenBlock>,
+    next_index: usize,
+    saw_tool_use: bool,
+}
+
+impl AnthropicStreamer {
+    pub fn new(
+        rx: Receiver<Response>,
+        state: SharedMistralRsState,
+        id: String,
+        model: String,
+    ) -> Self {
+        Self {
+            rx,
+            state,
+            done_state: DoneState::Running,
+            message: Some(AnthropicMessage::new(id, model)),
+            pending: VecDeque::new(),
+            open_block: None,
+            next_index: 0,
+            saw_tool_use: false,
+        }
+    }
+
+    fn close_block(&mut self) {
+        if self.open_block.take().is_some() {
+            self.pending
+                .push_back(AnthropicStreamEvent::ContentBlockStop {
+                    index: self.next_index,
+                });
+            self.next_index += 1;
+        }
+    }
+
+    /// Emit a delta into a block of the given kind, opening it (and closing any other) first.
+    fn delta(&mut self, kind: OpenBlock, delta: AnthropicDelta) {
+        if self.open_block != Some(kind) {
+            self.close_block();
+            let content_block = match kind {
+                OpenBlock::Thinking => AnthropicOutputBlock::Thinking {
+                    thinking: String::new(),
+                    signature: String::new(),
+                },
+                OpenBlock::Text => AnthropicOutputBlock::Text {
+                    text: String::new(),
+                },
+            };
+            self.pending
+                .push_back(AnthropicStreamEvent::ContentBlockStart {
+                    index: self.next_index,
+                    content_block,
+                });
+            self.open_block = Some(kind);
+        }
+        self.pending
+            .push_back(AnthropicStreamEvent::ContentBlockDelta {
+                index: self.next_index,
+                delta,
+            });
+    }
+
+    /// Tool calls arrive whole, so each one is a complete start/delta/stop triple.
+    fn tool_use(&mut self, call: &ToolCallResponse) {
+        self.close_block();
+        self.saw_tool_use = true;
+        let index = self.next_index;
+        self.pending
+            .push_back(AnthropicStreamEvent::ContentBlockStart {
+                index,
+                content_block: AnthropicOutputBlock::ToolUse {
+                    id: call.id.clone(),
+                    name: call.function.name.clone(),
+                    input: Value::Object(Default::default()),
+                },
+            });
+        self.pending
+            .push_back(AnthropicStreamEvent::ContentBlockDelta {
+                index,
+                delta: AnthropicDelta::InputJsonDelta {
+                    partial_json: call.function.arguments.clone(),
+                },
+            });
+        self.pending
+            .push_back(AnthropicStreamEvent::ContentBlockStop { index });
+        self.next_index += 1;
+    }
+
+    fn handle_chunk(&mut self, chunk: &ChatCompletionChunkResponse) {
+        let Some(choice) = chunk.choices.first() else {
+            return;
+        };
+        if let Some(thinking) = choice
+            .delta
+            .reasoning_content
+            .clone()
+            .filter(|t| !t.is_empty())
+        {
+            self.delta(
+                OpenBlock::Thinking,
+                AnthropicDelta::ThinkingDelta { thinking },
+            );
+        }
+        if let Some(text) = choice.delta.content.clone().filter(|t| !t.is_empty()) {
+            self.delta(OpenBlock::Text, AnthropicDelta::TextDelta { text });
+        }
+        for call in choice.delta.tool_calls.iter().flatten() {
+            self.tool_use(call);
+        }
+        if let Some(finish_reason) = &choice.finish_reason {
+            self.close_block();
+            let stop_reason = if self.saw_tool_use {
+                "tool_use"
+            } else {
+                stop_reason(finish_reason, choice.stop_sequence.as_deref())
+            };
+            let stop_sequence = choice
+                .stop_sequence
+                .clone()
+                .filter(|_| stop_reason == "stop_sequence");
+            self.pending.push_back(AnthropicStreamEvent::MessageDelta {
+                delta: AnthropicMessageDelta {
+                    stop_reason,
+                    stop_sequence,
+                },
+                usage: chunk
+                    .usage
+                    .as_ref()
+                    .map(AnthropicUsage::from)
+                    .unwrap_or_default(),
+            });
+            self.pending.push_back(AnthropicStreamEvent::MessageStop);
+            self.done_state = DoneState::Done;
+        }
+    }
+
+    fn fail(&mut self, kind: &'static str, message: String) {
+        self.pending.push_back(AnthropicStreamEvent::Error {
+            error: AnthropicErrorBody { kind, message },
+        });
+        self.done_state = DoneState::Done;
+    }
+}
+
+impl futures::Stream for AnthropicStreamer {
+    type Item = Result<Event, axum::Error>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(message) = self.message.take() {
+            return Poll::Ready(Some(
+                AnthropicStreamEvent::MessageStart { message }.into_sse(),
+            ));
+        }
+        if let Some(event) = self.pending.pop_front() {
+            return Poll::Ready(Some(event.into_sse()));
+        }
+        if matches!(self.done_state, DoneState::Done) {
+            return Poll::Ready(None);
+        }
+
+        match self.rx.poll_recv(cx) {
+            Poll::Ready(Some(response)) => {
+                match response {
+                    Response::Chunk(chunk) => {
+                        self.handle_chunk(&chunk);
+                        MistralRs::maybe_log_response(self.state.clone(), &chunk);
+                    }
+                    Response::ModelError(msg, _) => {
+                        MistralRs::maybe_log_error(
+                            self.state.clone(),
+                            &ModelErrorMessage(msg.to_string()),
+                        );
+                        self.fail("api_error", msg);
+                    }
+                    Response::ValidationError(e) => {
+                        self.fail("invalid_request_error", sanitize_error_message(e.as_ref()))
+                    }
+                    Response::InternalError(e) => {
+                        MistralRs::maybe_log_error(self.state.clone(), &*e);
+                        self.fail("api_error", sanitize_error_message(e.as_ref()));
+                    }
+                    _ => {}
+                }
+                // Either events were queued or the response was skipped; poll again.
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Responder for `/v1/messages`. Errors use the Anthropic error shape.
+pub type AnthropicResponder =
+    BaseCompletionResponder<AnthropicMessage, KeepAliveStream<AnthropicStreamer>>;
+
+fn error_response(
+    status: StatusCode,
+    kind: &'static str,
+    message: String,
+) -> axum::response::Response {
+    let body = serde_json::json!({
+        "type": "error",
+        "error": AnthropicErrorBody { kind, message },
+    });
+    (status, axum::Json(body)).into_response()
+}
+
+impl IntoResponse for AnthropicResponder {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            AnthropicResponder::Sse(s) => s.into_response(),
+            AnthropicResponder::Json(s) => axum::Json(s).into_response(),
+            AnthropicResponder::InternalError(e) => match overloaded(e.downcast_ref()) {
+                Some((reason, retry_after)) => with_retry_after(
+                    error_response(
+                        StatusCode::TOO_MANY_REQUESTS,
+                        "rate_limit_error",
+                        reason.to_string(),
+                    ),
+                    retry_after,
+                ),
+                None => error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "api_error",
+                    sanitize_error_message(e.as_ref()),
+                ),
+            },
+            AnthropicResponder::ValidationError(e) => error_response(
+                StatusCode::BAD_REQUEST,
+                "invalid_request_error",
+                sanitize_error_message(e.as_ref()),
+            ),
+            AnthropicResponder::ModelError(msg, _) => {
+                error_response(StatusCode::INTERNAL_SERVER_ERROR, "api_error", msg)
+            }
+        }
+    }
+}
+
+fn validation_error(state: SharedMistralRsState, e: anyhow::Error) -> AnthropicResponder {
+    MistralRs::maybe_log_error(state, &*e);
+    let e: Box<dyn Error + Send + Sync> = e.into();
+    AnthropicResponder::ValidationError(e)
+}
+
+/// Anthropic Messages endpoint handler.
+pub async fn messages(
+    State(state): ExtractedMistralRsState,
+    Json(request): Json<AnthropicMessagesRequest>,
+) -> AnthropicResponder {
+    let (tx, mut rx) = create_response_channel(None);
+    let message_id = format!("msg_{}", Uuid::new_v4().simple());
+    let model = request.model.clone();
+    let model_id = (model != "default").then(|| model.clone());
+
+    let chat_request = match to_chat_request(request) {
+        Ok(chat_request) => chat_request,
+        Err(e) => return validation_error(state, e),
+    };
+    let (request, is_streaming) =
+        match parse_chat_request(chat_request, state.clone(), tx, None, None, None).await {
+            Ok(x) => x,
+            Err(e) => return validation_error(state, e),
+        };
+    if let Err(e) = send_request_with_model(&state, request, model_id.as_deref()).await {
+        MistralRs::maybe_log_error(state, &*e);
+        return AnthropicResponder::InternalError(boxed_send_error(e));
+    }
+
+    if is_streaming {
+        let streamer = AnthropicStreamer::new(rx, state, message_id, model);
+        return AnthropicResponder::Sse(Sse::new(streamer).keep_alive(
+            KeepAlive::new().interval(Duration::from_millis(get_keep_alive_interval())),
+        ));
+    }
+
+    let response = loop {
+        match rx.recv().await {
+            Some(Response::AgenticToolCallProgress { .. }) | Some(Response::File(_)) => continue,
+            other => break other,
+        }
+    };
+    match response {
+        Some(Response::Done(response)) => {
+            MistralRs::maybe_log_response(state, &response);
+            AnthropicResponder::Json(chat_response_to_message(&response, message_id))
+        }
+        Some(Response::ModelError(msg, response)) => {
+            MistralRs::maybe_log_error(state, &ModelErrorMessage(msg.to_string()));
+            AnthropicResponder::ModelError(msg, chat_response_to_message(&response, message_id))
+        }
+        Some(Response::ValidationError(e)) => AnthropicResponder::ValidationError(e),
+        Some(Response::InternalError(e)) => {
+            MistralRs::maybe_log_error(state, &*e);
+            AnthropicResponder::InternalError(e)
+        }
+        _ => AnthropicResponder::InternalError(anyhow::anyhow!("Unexpected response type").into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mistralrs_core::{CalledFunction, Choice, ResponseMessage, ToolCallType};
+
+    fn request(value: Value) -> AnthropicMessagesRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn converts_tool_round_trip() {
+        let chat = to_chat_request(request(serde_json::json!({
+            "model": "default",
+            "max_tokens": 256,
+            "system": [{"type": "text", "text": "Be brief."}],
+            "tools": [{
+                "name": "get_weather",
+                "description": "Weather for a city",
+                "input_schema": {"type": "object", "properties": {"city": {"type": "string"}}}
+            }],
+            "tool_choice": {"type": "tool", "name": "get_weather"},
+            "messages": [
+                {"role": "user", "content": "Weather in Paris?"},
+                {"role": "assistant", "content": [
+                    {"type": "thinking", "thinking": "Need the tool.", "signature": "x"},
+                    {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Paris"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": [{"type": "text", "text": "18C"}]},
+                    {"type": "text", "text": "And tomorrow?"}
+                ]}
+            ]
+        })))
+        .unwrap();
+
+        let Either::Left(messages) = &chat.messages else {
+            panic!("expected messages");
+        };
+        let roles: Vec<_> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["system", "user", "assistant", "tool", "user"]);
+
+        let assistant = &messages[2];
+        assert!(assistant.content.is_none());
+        let call = &assistant.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(call.id.as_deref(), Some("toolu_1"));
+        assert_eq!(call.function.arguments, r#"{"city":"Paris"}"#);
+
+        assert_eq!(messages[3].tool_call_id.as_deref(), Some("toolu_1"));
+        assert_eq!(
+            messages[3].content.as_ref().unwrap().to_text().unwrap(),
+            "18C"
+        );
+        assert!(matches!(chat.tool_choice, Some(ToolChoice::Tool(_))));
+        assert_eq!(chat.max_tokens, Some(256));
+    }
+
+    #[test]
+    fn rejects_server_tools() {
+        let err = to_chat_request(request(serde_json::json!({
+            "max_tokens": 16,
+            "tools": [{"type": "web_search_20250305", "name": "web_search"}],
+            "messages": [{"role": "user", "content": "hi"}]
+        })))
+        .unwrap_err();
+        assert!(err.to_string().contains("web_search_20250305"));
+    }
+
+    #[test]
+    fn any_tool_choice_forces_a_single_tool() {
+        let tool = serde_json::json!({"name": "get_weather", "input_schema": {"type": "object"}});
+        let chat = to_chat_request(request(serde_json::json!({
+            "max_tokens": 16,
+            "tools": [tool],
+            "tool_choice": {"type": "any"},
+            "messages": [{"role": "user", "content": "hi"}]
+        })))
+        .unwrap();
+        assert!(matches!(chat.tool_choice, Some(ToolChoice::Tool(_))));
+
+        let err = to_chat_request(request(serde_json::json!({
+            "max_tokens": 16,
+            "tools": [tool, {"name": "get_time", "input_schema": {"type": "object"}}],
+            "tool_choice": {"type": "any"},
+            "messages": [{"role": "user", "content": "hi"}]
+        })))
+        .unwrap_err();
+        assert!(err.to_string().contains("exactly one tool"));
+    }
+
+    #[test]
+    fn response_blocks_and_stop_reason() {
+        let response = ChatCompletionResponse {
+            id: "1".to_string(),
+            choices: vec![Choice {
+                finish_reason: "tool_calls".to_string(),
+                index: 0,
+                message: ResponseMessage {
+                    content: Some("Checking.".to_string()),
+                    role: "assistant".to_string(),
+                    tool_calls: Some(vec![ToolCallResponse {
+                        index: 0,
+                        id: "call_1".to_string(),
+                        tp: ToolCallType::Function,
+                        function: CalledFunction {
+                            name: "get_weather".to_string(),
+                            arguments: r#"{"city":"Paris"}"#.to_string(),
+                        },
+                    }]),
+                    reasoning_content: Some("The user wants weather.".to_string()),
+                },
+                logprobs: None,
+                stop_sequence: None,
+            }],
+            created: 0,
+            model: "qwen3".to_string(),
+            system_fingerprint: String::new(),
+            object: "chat.completion".to_string(),
+            usage: Usage {
+                completion_tokens: 7,
+                prompt_tokens: 20,
+                total_tokens: 27,
+                avg_tok_per_sec: 0.0,
+                avg_prompt_tok_per_sec: 0.0,
+                avg_compl_tok_per_sec: 0.0,
+                total_time_sec: 0.0,
+                total_prompt_time_sec: 0.0,
+                total_completion_time_sec: 0.0,
+                completion_tokens_details: None,
+            },
+            agentic_tool_calls: None,
+            files: None,
+            session_id: None,
+        };
+
+        let message = chat_response_to_message(&response, "msg_1".to_string());
+        assert_eq!(message.stop_reason, Some("tool_use"));
+        assert_eq!(
+            message.usage,
+            AnthropicUsage {
+                input_tokens: 20,
+                output_tokens: 7
+            }
+        );
+        assert!(matches!(
+            message.content[0],
+            AnthropicOutputBlock::Thinking { .. }
+        ));
+        assert_eq!(
+            message.content[2],
+            AnthropicOutputBlock::ToolUse {
+                id: "call_1".to_string(),
+                name: "get_weather".to_string(),
+                input: serde_json::json!({"city": "Paris"}),
+            }
+        );
+
+        let mut stopped = response.clone();
+        let choice = &mut stopped.choices[0];
+        choice.finish_reason = "stop".to_string();
+        choice.message.tool_calls = None;
+        choice.stop_sequence = Some("END".to_string());
+        let message = chat_response_to_message(&stopped, "msg_2".to_string());
+        assert_eq!(message.stop_reason, Some("stop_sequence"));
+        assert_eq!(message.stop_sequence.as_deref(), Some("END"));
+    }
+
+    #[test]
+    fn document_title_is_escaped() {
+        let part = user_part(AnthropicContentBlock::Document {
+            source: AnthropicSource::Text {
+                data: "a < b".to_string(),
+            },
+            title: Some(r#"x"><y"#.to_string()),
+        })
+        .unwrap();
+        let Either::Left(text) = &part["text"].0 else {
+            panic!("expected text");
+        };
+        assert_eq!(
+            text,
+            "<document title=\"x&quot;&gt;&lt;y\">\na &lt; b\n</document>"
+        );
+    }
+
+    #[test]
+    fn stream_event_names_match_types() {
+        let event = AnthropicStreamEvent::ContentBlockDelta {
+            index: 1,
+            delta: AnthropicDelta::TextDelta {
+                text: "hi".to_string(),
+            },
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], event.event_type());
+        assert_eq!(json["delta"]["type"], "text_delta");
+        assert_eq!(
+            serde_json::to_value(AnthropicStreamEvent::MessageStop).unwrap()["type"],
+            "message_stop"
+        );
+    }
+}
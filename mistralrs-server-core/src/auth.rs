@@ -0,0 +1,1034 @@
+//! ## API key authentication and per-key quotas.
+//!
+//! Keys are loaded from a JSON file (see [`ApiKeysConfig`]) or supplied by a custom
+//! [`ApiKeyProvider`]. Each key may be scoped to a set of models and to inference and/or
+//! admin routes, and may carry a request/token quota over a fixed window.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::types::SharedMistralRsState;
+
+/// Header accepted as an alternative to `Authorization: Bearer <key>`.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// `Sec-WebSocket-Protocol` prefix carrying a key, as used by OpenAI's realtime clients.
+pub const WEBSOCKET_KEY_PROTOCOL_PREFIX: &str = "openai-insecure-api-key.";
+
+const DEFAULT_QUOTA_WINDOW_SECS: u64 = 60;
+
+/// Which group of routes a key is allowed to call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// Generation, embeddings, files, responses and sessions.
+    Inference,
+    /// Model management (`/v1/models/{unload,reload,tune}`), `/re_isq`, `/metrics` and
+    /// `/v1/system/*`.
+    Admin,
+}
+
+/// Per-key request and token limits over a fixed window.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiKeyQuota {
+    /// Window length in seconds. Counters reset when the window elapses.
+    #[serde(default = "default_quota_window_secs")]
+    pub window_secs: u64,
+    /// Maximum number of requests per window.
+    #[serde(default)]
+    pub max_requests: Option<u64>,
+    /// Maximum number of tokens (prompt + completion, as reported in `usage.total_tokens`) per window.
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Inference => "inference",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+fn default_quota_window_secs() -> u64 {
+    DEFAULT_QUOTA_WINDOW_SECS
+}
+
+fn default_scopes() -> Vec<ApiKeyScope> {
+    vec![ApiKeyScope::Inference]
+}
+
+/// Everything the server knows about a key except the secret itself.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiKeyPolicy {
+    /// Identifier used in logs, quota accounting and the [`AuthenticatedKey`] extension.
+    pub name: String,
+    /// Route groups the key may call. Defaults to `["inference"]`.
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<ApiKeyScope>,
+    /// Model IDs the key may target. `None` allows every model.
+    #[serde(default)]
+    pub models: Option<Vec<String>>,
+    #[serde(default)]
+    pub quota: Option<ApiKeyQuota>,
+}
+
+/// A key entry in the API keys file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiKeyEntry {
+    pub key: String,
+    #[serde(flatten)]
+    pub policy: ApiKeyPolicy,
+}
+
+/// Contents of the API keys file.
+///
+/// ```json
+/// {
+///   "keys": [
+///     { "key": "sk-admin", "name": "ops", "scopes": ["admin", "inference"] },
+///     {
+///       "key": "sk-team-a",
+///       "name": "team-a",
+///       "models": ["qwen3-4b"],
+///       "quota": { "window_secs": 60, "max_requests": 120, "max_tokens": 200000 }
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ApiKeysConfig {
+    pub keys: Vec<ApiKeyEntry>,
+}
+
+impl ApiKeysConfig {
+    /// Load and validate the keys file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read API keys file {}", path.display()))?;
+        let config: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid API keys file {}", path.display()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.keys.is_empty() {
+            anyhow::bail!("API keys file must contain at least one key");
+        }
+        let mut names = std::collections::HashSet::new();
+        for entry in &self.keys {
+            if entry.key.is_empty() {
+                anyhow::bail!("API key `{}` has an empty secret", entry.policy.name);
+            }
+            if !names.insert(&entry.policy.name) {
+                anyhow::bail!("Duplicate API key name: {}", entry.policy.name);
+            }
+            if entry.policy.scopes.is_empty() {
+                anyhow::bail!("API key `{}` has no scopes", entry.policy.name);
+            }
+            if let Some(quota) = &entry.policy.quota {
+                if quota.window_secs == 0 {
+                    anyhow::bail!(
+                        "API key `{}`: quota window_secs must be > 0",
+                        entry.policy.name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Source of key policies. Implement this to back authentication with an external store.
+pub trait ApiKeyProvider: Send + Sync {
+    /// Returns the policy for a presented key, or `None` if the key is unknown.
+    fn lookup(&self, key: &str) -> Option<ApiKeyPolicy>;
+}
+
+impl ApiKeyProvider for ApiKeysConfig {
+    fn lookup(&self, key: &str) -> Option<ApiKeyPolicy> {
+        // Compare against every entry so lookup time doesn't depend on which key matched.
+        let mut found = None;
+        for entry in &self.keys {
+            if constant_time_eq(entry.key.as_bytes(), key.as_bytes()) {
+                found = Some(entry.policy.clone());
+            }
+        }
+        found
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Identity of the caller, inserted as a request extension after successful authentication.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedKey {
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// Models the key may use; `None` allows all of them.
+    pub models: Option<Vec<String>>,
+}
+
+impl AuthenticatedKey {
+    /// Whether the key may use `model`. Handlers that reach further models than the one in the
+    /// request (e.g. `/v1/realtime`) check them with this.
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.models
+            .as_ref()
+            .is_none_or(|allowed| allowed.iter().any(|m| m == model))
+    }
+
+    /// Whether the key may see a file or batch created by the key named `owner`. Admin keys
+    /// see everything, and resources without an owner (e.g. agent outputs) are shared.
+    pub fn can_access(&self, owner: Option<&str>) -> bool {
+        owner.is_none_or(|owner| owner == self.name) || self.scopes.contains(&ApiKeyScope::Admin)
+    }
+}
+
+/// Charges token usage to a key from outside the auth layer, for work whose usage never
+/// reaches an HTTP response body (batch lines, realtime sessions).
+#[derive(Clone)]
+pub struct TokenMeter {
+    auth: ApiKeyAuth,
+    policy: ApiKeyPolicy,
+}
+
+impl TokenMeter {
+    /// Fails once the key's token quota for the current window is used up.
+    pub fn check(&self) -> Result<(), AuthError> {
+        match &self.policy.quota {
+            Some(quota) => self
+                .auth
+                .quotas
+                .check_tokens(&self.policy.name, quota, Instant::now()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn charge(&self, tokens: u64) {
+        self.auth.record_tokens(&self.policy, tokens);
+    }
+
+    /// Charge the usage reported in a response body, if it has any.
+    pub fn charge_usage(&self, body: &Value) {
+        if let Some(tokens) = usage_total_tokens(body) {
+            self.charge(tokens);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct RequestKey {
+    key: AuthenticatedKey,
+    /// Set when the key has a token quota.
+    meter: Option<TokenMeter>,
+}
+
+tokio::task_local! {
+    static CURRENT_KEY: RequestKey;
+}
+
+/// The key that authenticated the request being handled, if any.
+pub fn authenticated_key() -> Option<AuthenticatedKey> {
+    CURRENT_KEY.try_with(|current| current.key.clone()).ok()
+}
+
+/// Name of the key that authenticated the request being handled, if any. Engine requests are
+/// attributed to it in the audit log (see [`crate::handler_core::send_request_with_model`]).
+pub fn authenticated_key_name() -> Option<String> {
+    CURRENT_KEY
+        .try_with(|current| current.key.name.clone())
+        .ok()
+}
+
+/// Token meter of the key that authenticated the request being handled, if the key has a
+/// token quota.
+pub fn token_meter() -> Option<TokenMeter> {
+    CURRENT_KEY
+        .try_with(|current| current.meter.clone())
+        .ok()
+        .flatten()
+}
+
+/// Keep the current key for `f`, a task spawned while handling the request.
+pub(crate) fn carry_key<F: Future>(f: F) -> impl Future<Output = F::Output> {
+    let key = CURRENT_KEY.try_with(Clone::clone).ok();
+    async move {
+        match key {
+            Some(key) => CURRENT_KEY.scope(key, f).await,
+            None => f.await,
+        }
+    }
+}
+
+/// Route classification used for scope checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteClass {
+    /// Never requires a key (`/health`, `/`).
+    Public,
+    Inference,
+    Admin,
+}
+
+impl RouteClass {
+    /// Classify a router-relative path.
+    pub fn of(path: &str) -> Self {
+        match path {
+            "/" | "/health" => Self::Public,
+            "/v1/models/unload" | "/v1/models/reload" | "/v1/models/tune" | "/re_isq"
+            | "/metrics" => Self::Admin,
+            p if p.starts_with("/v1/system/") => Self::Admin,
+            _ => Self::Inference,
+        }
+    }
+
+    /// Whether requests to `path` run on a model named in their JSON body (or, for the realtime
+    /// WebSocket handshake, a `model` query parameter). Keys limited to certain models must
+    /// name one on these routes. Multipart routes check their `model` field in the handler.
+    pub fn names_model(path: &str) -> bool {
+        matches!(
+            path,
+            "/v1/chat/completions"
+                | "/v1/completions"
+                | "/v1/messages"
+                | "/v1/embeddings"
+                | "/v1/rerank"
+                | "/v1/chat/count_tokens"
+                | "/tokenize"
+                | "/detokenize"
+                | "/v1/images/generations"
+                | "/v1/audio/speech"
+                | "/v1/responses"
+                | "/v1/realtime"
+                | "/api/chat"
+                | "/api/generate"
+                | "/api/embed"
+                | "/api/show"
+        )
+    }
+
+    fn required_scope(self) -> Option<ApiKeyScope> {
+        match self {
+            Self::Public => None,
+            Self::Inference => Some(ApiKeyScope::Inference),
+            Self::Admin => Some(ApiKeyScope::Admin),
+        }
+    }
+}
+
+/// Why a request was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    /// No key was presented (401).
+    MissingKey,
+    /// The key is unknown (401).
+    InvalidKey,
+    /// The key lacks the scope for this route (403).
+    ScopeDenied(ApiKeyScope),
+    /// The key may not use the requested model (403).
+    ModelDenied(String),
+    /// The key is limited to certain models and the request names none of them (403).
+    ModelRequired,
+    /// The key's request quota for the current window is used up (429).
+    RequestQuotaExceeded { retry_after: Duration },
+    /// The key's token quota for the current window is used up (429).
+    TokenQuotaExceeded { retry_after: Duration },
+}
+
+impl AuthError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::MissingKey | Self::InvalidKey => StatusCode::UNAUTHORIZED,
+            Self::ScopeDenied(_) | Self::ModelDenied(_) | Self::ModelRequired => {
+                StatusCode::FORBIDDEN
+            }
+            Self::RequestQuotaExceeded { .. } | Self::TokenQuotaExceeded { .. } => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::MissingKey => "missing_api_key",
+            Self::InvalidKey => "invalid_api_key",
+            Self::ScopeDenied(_) => "insufficient_scope",
+            Self::ModelDenied(_) | Self::ModelRequired => "model_not_allowed",
+            Self::RequestQuotaExceeded { .. } => "rate_limit_exceeded",
+            Self::TokenQuotaExceeded { .. } => "insufficient_quota",
+        }
+    }
+
+    pub(crate) fn message(&self) -> String {
+        match self {
+            Self::MissingKey => {
+                "Missing API key. Pass it as `Authorization: Bearer <key>`.".to_string()
+            }
+            Self::InvalidKey => "Invalid API key.".to_string(),
+            Self::ScopeDenied(scope) => {
+                format!("This API key does not have the `{}` scope.", scope.as_str())
+            }
+            Self::ModelDenied(model) => {
+                format!("This API key is not allowed to use model `{model}`.")
+            }
+            Self::ModelRequired => {
+                "This API key is limited to certain models; name one in the request.".to_string()
+            }
+            Self::RequestQuotaExceeded { .. } => {
+                "Request quota exceeded for this API key.".to_string()
+            }
+            Self::TokenQuotaExceeded { .. } => "Token quota exceeded for this API key.".to_string(),
+        }
+    }
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let error_type = match self.status() {
+            StatusCode::UNAUTHORIZED => "authentication_error",
+            StatusCode::FORBIDDEN => "permission_error",
+            _ => "rate_limit_error",
+        };
+        let body = serde_json::json!({
+            "error": {
+                "message": self.message(),
+                "type": error_type,
+                "code": self.code(),
+            }
+        });
+        let mut response = (self.status(), Json(body)).into_response();
+        match &self {
+            Self::MissingKey | Self::InvalidKey => {
+                response
+                    .headers_mut()
+                    .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            }
+            Self::RequestQuotaExceeded { retry_after }
+            | Self::TokenQuotaExceeded { retry_after } => {
+                let secs = retry_after.as_secs().max(1);
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            }
+            _ => {}
+        }
+        response
+    }
+}
+
+struct QuotaWindow {
+    started: Instant,
+    requests: u64,
+    tokens: u64,
+}
+
+impl QuotaWindow {
+    fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            requests: 0,
+            tokens: 0,
+        }
+    }
+
+    /// Reset the counters if the window has elapsed and return the time left in it.
+    fn roll(&mut self, window: Duration, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed >= window {
+            *self = Self::new(now);
+            window
+        } else {
+            window - elapsed
+        }
+    }
+}
+
+/// Tracks per-key usage against [`ApiKeyQuota`] limits.
+#[derive(Default)]
+struct QuotaTracker {
+    windows: Mutex<HashMap<String, QuotaWindow>>,
+}
+
+impl QuotaTracker {
+    /// Count a request against the key's quota, rejecting it if a limit is already reached.
+    fn admit(&self, name: &str, quota: &ApiKeyQuota, now: Instant) -> Result<(), AuthError> {
+        let window = Duration::from_secs(quota.window_secs);
+        let mut windows = self.windows.lock().unwrap();
+        let state = windows
+            .entry(name.to_string())
+            .or_insert_with(|| QuotaWindow::new(now));
+        let retry_after = state.roll(window, now);
+
+        if quota.max_requests.is_some_and(|max| state.requests >= max) {
+            return Err(AuthError::RequestQuotaExceeded { retry_after });
+        }
+        if quota.max_tokens.is_some_and(|max| state.tokens >= max) {
+            return Err(AuthError::TokenQuotaExceeded { retry_after });
+        }
+        state.requests += 1;
+        Ok(())
+    }
+
+    /// Like [`Self::admit`], but only checks the token limit and counts nothing.
+    fn check_tokens(&self, name: &str, quota: &ApiKeyQuota, now: Instant) -> Result<(), AuthError> {
+        let window = Duration::from_secs(quota.window_secs);
+        let mut windows = self.windows.lock().unwrap();
+        let Some(state) = windows.get_mut(name) else {
+            return Ok(());
+        };
+        let retry_after = state.roll(window, now);
+        if quota.max_tokens.is_some_and(|max| state.tokens >= max) {
+            return Err(AuthError::TokenQuotaExceeded { retry_after });
+        }
+        Ok(())
+    }
+
+    fn record_tokens(&self, name: &str, quota: &ApiKeyQuota, tokens: u64, now: Instant) {
+        let window = Duration::from_secs(quota.window_secs);
+        let mut windows = self.windows.lock().unwrap();
+        let state = windows
+            .entry(name.to_string())
+            .or_insert_with(|| QuotaWindow::new(now));
+        state.roll(window, now);
+        state.tokens = state.tokens.saturating_add(tokens);
+    }
+}
+
+/// API key authentication for the server router.
+///
+/// Attach it with
+/// [`MistralRsServerRouterBuilder::with_api_key_auth`](crate::mistralrs_server_router_builder::MistralRsServerRouterBuilder::with_api_key_auth).
+#[derive(Clone)]
+pub struct ApiKeyAuth {
+    provider: Arc<dyn ApiKeyProvider>,
+    quotas: Arc<QuotaTracker>,
+}
+
+impl ApiKeyAuth {
+    /// Authenticate with a custom key provider.
+    pub fn new(provider: Arc<dyn ApiKeyProvider>) -> Self {
+        Self {
+            provider,
+            quotas: Arc::new(QuotaTracker::default()),
+        }
+    }
+
+    /// Authenticate against the keys in a JSON file. See [`ApiKeysConfig`].
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(Arc::new(ApiKeysConfig::from_file(path)?)))
+    }
+
+    /// Resolve the presented key, check its scope and model access, and count the request
+    /// against its quota.
+    pub fn authorize(
+        &self,
+        key: Option<&str>,
+        route: RouteClass,
+        model: Option<&str>,
+    ) -> Result<Option<ApiKeyPolicy>, AuthError> {
+        let Some(required) = route.required_scope() else {
+            return Ok(None);
+        };
+        let key = key.ok_or(AuthError::MissingKey)?;
+        let policy = self.provider.lookup(key).ok_or(AuthError::InvalidKey)?;
+
+        if !policy.scopes.contains(&required) {
+            return Err(AuthError::ScopeDenied(required));
+        }
+        if let (Some(allowed), Some(model)) = (&policy.models, model) {
+            if !allowed.iter().any(|m| m == model) {
+                return Err(AuthError::ModelDenied(model.to_string()));
+            }
+        }
+        if let Some(quota) = &policy.quota {
+            self.quotas.admit(&policy.name, quota, Instant::now())?;
+        }
+        Ok(Some(policy))
+    }
+
+    /// Charge `tokens` to the key's token quota.
+    pub fn record_tokens(&self, policy: &ApiKeyPolicy, tokens: u64) {
+        if let Some(quota) = &policy.quota {
+            self.quotas
+                .record_tokens(&policy.name, quota, tokens, Instant::now());
+        }
+    }
+}
+
+/// Extract the key from `Authorization: Bearer <key>` or `x-api-key: <key>`. Browsers can't set
+/// headers on WebSocket handshakes, so a `Sec-WebSocket-Protocol` entry of the form
+/// `openai-insecure-api-key.<key>` is accepted too.
+pub fn extract_api_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(value) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+    {
+        let (scheme, key) = value.trim().split_once(' ')?;
+        return scheme
+            .eq_ignore_ascii_case("bearer")
+            .then_some(key.trim())
+            .filter(|k| !k.is_empty());
+    }
+    if let Some(key) = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()) {
+        return Some(key.trim()).filter(|k| !k.is_empty());
+    }
+    headers
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|v| v.to_str().ok())?
+        .split(',')
+        .find_map(|protocol| protocol.trim().strip_prefix(WEBSOCKET_KEY_PROTOCOL_PREFIX))
+        .filter(|k| !k.is_empty())
+}
+
+/// State for [`api_key_middleware`].
+#[derive(Clone)]
+pub(crate) struct AuthLayerState {
+    pub(crate) auth: ApiKeyAuth,
+    pub(crate) mistralrs: SharedMistralRsState,
+    pub(crate) max_body_limit: usize,
+}
+
+/// Axum middleware enforcing [`ApiKeyAuth`] on every route of the router.
+pub(crate) async fn api_key_middleware(
+    State(layer): State<AuthLayerState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = RouteClass::of(request.uri().path());
+    let names_model = RouteClass::names_model(request.uri().path());
+    if route == RouteClass::Public || request.method() == Method::OPTIONS {
+        return next.run(request).await;
+    }
+
+    let key = extract_api_key(request.headers()).map(str::to_string);
+    // Reject unknown keys before touching the body so unauthenticated callers can't make us
+    // buffer it.
+    let restricted = match key.as_deref().map(|k| layer.auth.provider.lookup(k)) {
+        None => return AuthError::MissingKey.into_response(),
+        Some(None) => return AuthError::InvalidKey.into_response(),
+        Some(Some(policy)) => policy.models.is_some(),
+    };
+
+    let (request, model) = match requested_model(request, &layer, names_model).await {
+        Ok(x) => x,
+        Err(response) => return response,
+    };
+    // Fail closed: a model-limited key can't reach a model route without naming a model it
+    // may use.
+    if restricted && names_model && model.is_none() {
+        return AuthError::ModelRequired.into_response();
+    }
+
+    let policy = match layer
+        .auth
+        .authorize(key.as_deref(), route, model.as_deref())
+    {
+        Ok(Some(policy)) => policy,
+        Ok(None) => return next.run(request).await,
+        Err(e) => return e.into_response(),
+    };
+
+    let key = AuthenticatedKey {
+        name: policy.name.clone(),
+        scopes: policy.scopes.clone(),
+        models: policy.models.clone(),
+    };
+    let mut request = request;
+    request.extensions_mut().insert(key.clone());
+
+    let metered = policy
+        .quota
+        .as_ref()
+        .is_some_and(|q| q.max_tokens.is_some());
+    let current = RequestKey {
+        key,
+        meter: metered.then(|| TokenMeter {
+            auth: layer.auth.clone(),
+            policy: policy.clone(),
+        }),
+    };
+    let response = CURRENT_KEY.scope(current, next.run(request)).await;
+    if metered {
+        charge_tokens(response, layer.auth, policy).await
+    } else {
+        response
+    }
+}
+
+/// Buffer a JSON body to read its `model`/`model_id` field, then rebuild the request.
+/// In a JSON body, `"default"` (or no model at all) resolves to the current default model.
+/// Other requests target a model only through a `model` query parameter, as WebSocket
+/// handshakes do; on routes that name a model, leaving it out also means the default.
+async fn requested_model(
+    request: Request,
+    layer: &AuthLayerState,
+    names_model: bool,
+) -> Result<(Request, Option<String>), Response> {
+    let is_json = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(is_json_content_type);
+
+    if !is_json {
+        let model = request.uri().query().and_then(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(name, _)| name == "model")
+                .map(|(_, model)| model.into_owned())
+        });
+        let model = match model {
+            Some(m) if m != "default" => Some(m),
+            None if !names_model => None,
+            _ => layer.mistralrs.get_default_model_id().ok().flatten(),
+        };
+        return Ok((request, model));
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = axum::body::to_bytes(body, layer.max_body_limit)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())?;
+    let model = serde_json::from_slice::<Value>(&bytes).ok().and_then(|v| {
+        v.get("model")
+            .or_else(|| v.get("model_id"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    });
+    let model = match model {
+        Some(m) if m != "default" => Some(m),
+        _ => layer.mistralrs.get_default_model_id().ok().flatten(),
+    };
+    let request = Request::from_parts(parts, Body::from(bytes));
+    Ok((request, model))
+}
+
+/// Whether axum's `Json` extractor accepts a body of this content type: `application/json` or
+/// any `application/*+json`. Bodies it would parse must not slip past the model check.
+fn is_json_content_type(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    let Some((kind, subtype)) = mime.split_once('/') else {
+        return false;
+    };
+    let subtype = subtype.to_ascii_lowercase();
+    kind.eq_ignore_ascii_case("application") && (subtype == "json" || subtype.ends_with("+json"))
+}
+
+/// Read `usage.total_tokens` from the response (JSON, SSE or NDJSON) and charge it to the key.
+async fn charge_tokens(response: Response, auth: ApiKeyAuth, policy: ApiKeyPolicy) -> Response {
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    if content_type.starts_with("application/json") {
+        let (parts, body) = response.into_parts();
+        let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        };
+        if let Some(tokens) = serde_json::from_slice::<Value>(&bytes)
+            .ok()
+            .as_ref()
+            .and_then(usage_total_tokens)
+        {
+            auth.record_tokens(&policy, tokens);
+        }
+        Response::from_parts(parts, Body::from(bytes))
+    } else if content_type.starts_with("text/event-stream")
+        || content_type.starts_with("application/x-ndjson")
+    {
+        let parse: fn(&Bytes) -> Option<u64> = if content_type.starts_with("text/event-stream") {
+            sse_total_tokens
+        } else {
+            ndjson_total_tokens
+        };
+        let (parts, body) = response.into_parts();
+        let mut charge = StreamTokenCharge {
+            auth,
+            policy,
+            tokens: 0,
+        };
+        let stream = body.into_data_stream().map(move |chunk| {
+            if let Some(tokens) = chunk.as_ref().ok().and_then(parse) {
+                charge.tokens = charge.tokens.max(tokens);
+            }
+            chunk
+        });
+        Response::from_parts(parts, Body::from_stream(stream))
+    } else {
+        response
+    }
+}
+
+/// Charges the largest `usage.total_tokens` seen in a stream once the stream is dropped,
+/// including when the client disconnects early.
+struct StreamTokenCharge {
+    auth: ApiKeyAuth,
+    policy: ApiKeyPolicy,
+    tokens: u64,
+}
+
+impl Drop for StreamTokenCharge {
+    fn drop(&mut self) {
+        if self.tokens > 0 {
+            self.auth.record_tokens(&self.policy, self.tokens);
+        }
+    }
+}
+
+fn usage_total_tokens(value: &Value) -> Option<u64> {
+    // Ollama reports counts at the top level of the final object.
+    if let Some(output) = value.get("eval_count").and_then(Value::as_u64) {
+        let input = value.get("prompt_eval_count").and_then(Value::as_u64);
+        return Some(input.unwrap_or(0) + output);
+    }
+    value
+        .get("usage")
+        .or_else(|| value.get("response").and_then(|r| r.get("usage")))
+        .and_then(|u| {
+            // Anthropic-style usage has no total.
+            u.get("total_tokens").and_then(Value::as_u64).or_else(|| {
+                let input = u.get("input_tokens").and_then(Value::as_u64);
+                let output = u.get("output_tokens").and_then(Value::as_u64);
+                (input.is_some() || output.is_some())
+                    .then(|| input.unwrap_or(0) + output.unwrap_or(0))
+            })
+        })
+}
+
+fn sse_total_tokens(chunk: &Bytes) -> Option<u64> {
+    let text = std::str::from_utf8(chunk).ok()?;
+    text.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .filter_map(|v| usage_total_tokens(&v))
+        .max()
+}
+
+fn ndjson_total_tokens(chunk: &Bytes) -> Option<u64> {
+    let text = std::str::from_utf8(chunk).ok()?;
+    text.lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|v| usage_total_tokens(&v))
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ApiKeysConfig {
+        serde_json::from_value(serde_json::json!({
+            "keys": [
+                { "key": "sk-admin", "name": "ops", "scopes": ["admin", "inference"] },
+                {
+                    "key": "sk-team",
+                    "name": "team",
+                    "models": ["qwen"],
+                    "quota": { "window_secs": 60, "max_requests": 2, "max_tokens": 100 }
+                }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn route_classification() {
+        assert_eq!(RouteClass::of("/health"), RouteClass::Public);
+        assert_eq!(RouteClass::of("/v1/models/unload"), RouteClass::Admin);
+        assert_eq!(RouteClass::of("/v1/system/doctor"), RouteClass::Admin);
+        assert_eq!(RouteClass::of("/re_isq"), RouteClass::Admin);
+        assert_eq!(RouteClass::of("/metrics"), RouteClass::Admin);
+        assert_eq!(
+            RouteClass::of("/v1/chat/completions"),
+            RouteClass::Inference
+        );
+        assert!(RouteClass::names_model("/v1/chat/completions"));
+        assert!(RouteClass::names_model("/v1/realtime"));
+        assert!(!RouteClass::names_model("/v1/models"));
+        assert!(!RouteClass::names_model("/v1/files"));
+    }
+
+    #[test]
+    fn json_content_types_match_axum() {
+        assert!(is_json_content_type("application/json"));
+        assert!(is_json_content_type("application/json; charset=utf-8"));
+        assert!(is_json_content_type("application/vnd.api+json"));
+        assert!(is_json_content_type("Application/JSON"));
+        assert!(!is_json_content_type("text/json"));
+        assert!(!is_json_content_type("application/x-ndjson"));
+        assert!(!is_json_content_type("multipart/form-data; boundary=x"));
+    }
+
+    #[test]
+    fn scopes_and_models_are_enforced() {
+        let auth = ApiKeyAuth::new(Arc::new(config()));
+
+        assert_eq!(
+            auth.authorize(None, RouteClass::Inference, None),
+            Err(AuthError::MissingKey)
+        );
+        assert_eq!(
+            auth.authorize(Some("nope"), RouteClass::Inference, None),
+            Err(AuthError::InvalidKey)
+        );
+        assert_eq!(
+            auth.authorize(Some("sk-team"), RouteClass::Admin, None),
+            Err(AuthError::ScopeDenied(ApiKeyScope::Admin))
+        );
+        assert_eq!(
+            auth.authorize(Some("sk-team"), RouteClass::Inference, Some("llama")),
+            Err(AuthError::ModelDenied("llama".to_string()))
+        );
+        assert!(auth
+            .authorize(Some("sk-admin"), RouteClass::Admin, Some("llama"))
+            .is_ok());
+        assert!(auth
+            .authorize(Some("anything"), RouteClass::Public, None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn quotas_reject_and_reset() {
+        let quota = ApiKeyQuota {
+            window_secs: 10,
+            max_requests: Some(2),
+            max_tokens: Some(100),
+        };
+        let tracker = QuotaTracker::default();
+        let start = Instant::now();
+
+        assert!(tracker.admit("k", &quota, start).is_ok());
+        assert!(tracker.admit("k", &quota, start).is_ok());
+        assert!(matches!(
+            tracker.admit("k", &quota, start + Duration::from_secs(4)),
+            Err(AuthError::RequestQuotaExceeded { retry_after }) if retry_after == Duration::from_secs(6)
+        ));
+
+        let later = start + Duration::from_secs(11);
+        tracker.record_tokens("k", &quota, 150, later);
+        assert!(matches!(
+            tracker.admit("k", &quota, later),
+            Err(AuthError::TokenQuotaExceeded { .. })
+        ));
+        assert!(tracker
+            .admit("k", &quota, later + Duration::from_secs(10))
+            .is_ok());
+    }
+
+    #[test]
+    fn token_check_counts_nothing() {
+        let quota = ApiKeyQuota {
+            window_secs: 10,
+            max_requests: Some(1),
+            max_tokens: Some(100),
+        };
+        let tracker = QuotaTracker::default();
+        let now = Instant::now();
+        assert!(tracker.check_tokens("k", &quota, now).is_ok());
+        tracker.record_tokens("k", &quota, 100, now);
+        assert!(matches!(
+            tracker.check_tokens("k", &quota, now),
+            Err(AuthError::TokenQuotaExceeded { .. })
+        ));
+        assert!(tracker
+            .check_tokens("k", &quota, now + Duration::from_secs(10))
+            .is_ok());
+        // Checks don't use up the request quota.
+        assert!(tracker
+            .admit("k", &quota, now + Duration::from_secs(10))
+            .is_ok());
+    }
+
+    #[test]
+    fn owned_resources_are_scoped_to_their_key() {
+        let key = |name: &str, scopes: Vec<ApiKeyScope>| AuthenticatedKey {
+            name: name.to_string(),
+            scopes,
+            models: None,
+        };
+        let app = key("app", vec![ApiKeyScope::Inference]);
+        assert!(app.can_access(Some("app")));
+        assert!(app.can_access(None));
+        assert!(!app.can_access(Some("other")));
+        assert!(key("ops", vec![ApiKeyScope::Admin]).can_access(Some("other")));
+    }
+
+    #[test]
+    fn key_extraction() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer sk-1".parse().unwrap());
+        assert_eq!(extract_api_key(&headers), Some("sk-1"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, "sk-2".parse().unwrap());
+        assert_eq!(extract_api_key(&headers), Some("sk-2"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Basic abc".parse().unwrap());
+        assert_eq!(extract_api_key(&headers), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::SEC_WEBSOCKET_PROTOCOL,
+            "realtime, openai-insecure-api-key.sk-3".parse().unwrap(),
+        );
+        assert_eq!(extract_api_key(&headers), Some("sk-3"));
+    }
+
+    #[test]
+    fn usage_is_read_from_sse_chunks() {
+        let chunk = Bytes::from_static(
+            b"data: {\"choices\":[],\"usage\":{\"total_tokens\":42}}\n\ndata: [DONE]\n\n",
+        );
+        assert_eq!(sse_total_tokens(&chunk), Some(42));
+
+        let chunk = Bytes::from_static(
+            b"event: message_delta\ndata: {\"type\":\"message_delta\",\"usage\":{\"input_tokens\":30,\"output_tokens\":12}}\n\n",
+        );
+        assert_eq!(sse_total_tokens(&chunk), Some(42));
+    }
+
+    #[test]
+    fn usage_is_read_from_ndjson_lines() {
+        let chunk = Bytes::from_static(
+            b"{\"done\":false,\"response\":\"hi\"}\n{\"done\":true,\"prompt_eval_count\":30,\"eval_count\":12}\n",
+        );
+        assert_eq!(ndjson_total_tokens(&chunk), Some(42));
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let mut cfg = config();
+        cfg.keys[1].policy.name = "ops".to_string();
+        assert!(cfg.validate().is_err());
+    }
+}
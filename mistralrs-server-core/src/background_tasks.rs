@@ -39,13 +39,15 @@ pub struct BackgroundTask {
     pub created_at: u64,
     /// Model name
     pub model: String,
+    /// Name of the API key that created the task
+    pub owner: Option<String>,
     /// Cancellation flag
     pub cancel_requested: bool,
 }
 
 impl BackgroundTask {
     /// Create a new background task
-    pub fn new(id: String, model: String) -> Self {
+    pub fn new(id: String, model: String, owner: Option<String>) -> Self {
         let created_at = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -56,6 +58,7 @@ impl BackgroundTask {
             state: BackgroundTaskState::Queued,
             created_at,
             model,
+            owner,
             cancel_requested: false,
         }
     }
@@ -64,6 +67,7 @@ impl BackgroundTask {
     pub fn to_response_resource(&self) -> ResponseResource {
         let mut resource =
             ResponseResource::new(self.id.clone(), self.model.clone(), self.created_at);
+        resource.owner = self.owner.clone();
 
         match &self.state {
             BackgroundTaskState::Queued => {
@@ -103,10 +107,10 @@ impl BackgroundTaskManager {
         }
     }
 
-    /// Create a new background task and return its ID
-    pub fn create_task(&self, model: String) -> String {
+    /// Create a new background task owned by the key named `owner` and return its ID
+    pub fn create_task(&self, model: String, owner: Option<String>) -> String {
         let id = format!("resp_{}", Uuid::new_v4());
-        let task = BackgroundTask::new(id.clone(), model);
+        let task = BackgroundTask::new(id.clone(), model, owner);
 
         let mut tasks = self.tasks.write().unwrap();
         tasks.insert(id.clone(), task);
@@ -233,6 +237,7 @@ impl Clone for BackgroundTask {
             state: self.state.clone(),
             created_at: self.created_at,
             model: self.model.clone(),
+            owner: self.owner.clone(),
             cancel_requested: self.cancel_requested,
         }
     }
@@ -254,7 +259,7 @@ mod tests {
     #[test]
     fn test_create_and_get_task() {
         let manager = BackgroundTaskManager::new();
-        let id = manager.create_task("test-model".to_string());
+        let id = manager.create_task("test-model".to_string(), None);
 
         let task = manager.get_task(&id).unwrap();
         assert_eq!(task.id, id);
@@ -264,7 +269,7 @@ mod tests {
     #[test]
     fn test_task_state_transitions() {
         let manager = BackgroundTaskManager::new();
-        let id = manager.create_task("test-model".to_string());
+        let id = manager.create_task("test-model".to_string(), None);
 
         // Move to in_progress
         assert!(manager.mark_in_progress(&id));
@@ -281,7 +286,7 @@ mod tests {
     #[test]
     fn test_cancel_task() {
         let manager = BackgroundTaskManager::new();
-        let id = manager.create_task("test-model".to_string());
+        let id = manager.create_task("test-model".to_string(), None);
 
         // Request cancellation
         assert!(manager.request_cancel(&id));
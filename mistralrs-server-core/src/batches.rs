@@ -0,0 +1,1029 @@
+//! ## OpenAI-compatible Batch API.
+//!
+//! `POST /v1/batches` takes the id of a JSONL file in the file store. Each line is one request
+//! (`{"custom_id", "method", "url", "body"}`) for `/v1/chat/completions`, `/v1/completions` or
+//! `/v1/embeddings`. Lines are run in the background through the same handlers as the HTTP
+//! routes, up to each model's scheduler `max_seqs` at once and at low scheduling priority unless
+//! the line sets `priority`, so interactive traffic goes first. Lines not started within the
+//! completion window are failed and the batch is `expired`. The results are written back to the
+//! file store as JSONL output and error files.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use indexmap::IndexMap;
+use mistralrs_core::{File as CoreFile, FileContent, FileSource, RequestPriority};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    auth::{authenticated_key, carry_key, token_meter, AuthenticatedKey, TokenMeter},
+    chat_completion::chatcompletions,
+    completions::completions,
+    embeddings::embeddings,
+    files::find_visible_file,
+    mistralrs_server_router_builder::AgenticDefaults,
+    openai::{ChatCompletionRequest, CompletionRequest, EmbeddingRequest},
+    types::{ExtractedMistralRsState, SharedMistralRsState},
+    util::validate_model_access,
+};
+
+/// The only completion window accepted, as with OpenAI.
+pub const COMPLETION_WINDOW: &str = "24h";
+
+/// Length of [`COMPLETION_WINDOW`].
+const COMPLETION_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+/// How long batch output and error files stay in the file store.
+pub const BATCH_FILE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Delay before a line rejected with 429 is dispatched again.
+const OVERLOADED_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// `FileSource::tool` recorded on batch output and error files.
+pub const BATCH_FILE_SOURCE: &str = "batch";
+
+/// Endpoints a batch may target.
+pub const SUPPORTED_ENDPOINTS: &[&str] =
+    &["/v1/chat/completions", "/v1/completions", "/v1/embeddings"];
+
+const DEFAULT_LIST_LIMIT: usize = 20;
+const MAX_LIST_LIMIT: usize = 100;
+
+/// Body of `POST /v1/batches`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreateBatchRequest {
+    pub input_file_id: String,
+    pub endpoint: String,
+    #[serde(default = "default_completion_window")]
+    pub completion_window: String,
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+fn default_completion_window() -> String {
+    COMPLETION_WINDOW.to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Validating,
+    Failed,
+    InProgress,
+    Finalizing,
+    Completed,
+    Expired,
+    Cancelling,
+    Cancelled,
+}
+
+impl BatchStatus {
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            Self::Failed | Self::Completed | Self::Expired | Self::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchRequestCounts {
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+/// A validation error for the batch as a whole (for example an unparseable input line).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchError {
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub param: Option<String>,
+    /// One-based line number in the input file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchErrors {
+    pub object: String,
+    pub data: Vec<BatchError>,
+}
+
+/// OpenAI batch object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Batch {
+    pub id: String,
+    pub object: String,
+    pub endpoint: String,
+    pub errors: Option<BatchErrors>,
+    pub input_file_id: String,
+    pub completion_window: String,
+    pub status: BatchStatus,
+    pub output_file_id: Option<String>,
+    pub error_file_id: Option<String>,
+    pub created_at: u64,
+    pub in_progress_at: Option<u64>,
+    pub expires_at: u64,
+    pub finalizing_at: Option<u64>,
+    pub completed_at: Option<u64>,
+    pub failed_at: Option<u64>,
+    pub expired_at: Option<u64>,
+    pub cancelling_at: Option<u64>,
+    pub cancelled_at: Option<u64>,
+    pub request_counts: BatchRequestCounts,
+    pub metadata: Option<Value>,
+    /// Name of the API key that created the batch. Other keys can't see it.
+    #[serde(skip)]
+    pub owner: Option<String>,
+}
+
+impl Batch {
+    fn new(request: &CreateBatchRequest, owner: Option<String>) -> Self {
+        let created_at = now_secs();
+        Self {
+            id: format!("batch_{}", Uuid::new_v4().simple()),
+            object: "batch".to_string(),
+            endpoint: request.endpoint.clone(),
+            errors: None,
+            input_file_id: request.input_file_id.clone(),
+            completion_window: request.completion_window.clone(),
+            status: BatchStatus::Validating,
+            output_file_id: None,
+            error_file_id: None,
+            created_at,
+            in_progress_at: None,
+            expires_at: created_at + COMPLETION_WINDOW_SECS,
+            finalizing_at: None,
+            completed_at: None,
+            failed_at: None,
+            expired_at: None,
+            cancelling_at: None,
+            cancelled_at: None,
+            request_counts: BatchRequestCounts::default(),
+            metadata: request.metadata.clone(),
+            owner,
+        }
+    }
+
+    /// When the batch reached its terminal status.
+    fn finished_at(&self) -> Option<u64> {
+        self.completed_at
+            .or(self.failed_at)
+            .or(self.expired_at)
+            .or(self.cancelled_at)
+    }
+
+    /// Whether `key` may see this batch. Everything is visible when authentication is off.
+    pub fn is_visible(&self, key: Option<&AuthenticatedKey>) -> bool {
+        key.is_none_or(|key| key.can_access(self.owner.as_deref()))
+    }
+}
+
+/// One line of the input file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BatchInputLine {
+    pub custom_id: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    pub body: Value,
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+struct BatchEntry {
+    batch: Batch,
+    cancel_requested: Arc<AtomicBool>,
+}
+
+/// In-memory registry of batches, newest last.
+#[derive(Default)]
+pub struct BatchManager {
+    batches: RwLock<IndexMap<String, BatchEntry>>,
+}
+
+impl BatchManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a batch, dropping finished batches whose result files have expired.
+    fn insert(&self, batch: Batch) -> Arc<AtomicBool> {
+        let cancel_requested = Arc::new(AtomicBool::new(false));
+        let mut batches = self.batches.write().unwrap();
+        let now = now_secs();
+        batches.retain(|_, e| {
+            e.batch
+                .finished_at()
+                .is_none_or(|t| t + BATCH_FILE_TTL.as_secs() > now)
+        });
+        batches.insert(
+            batch.id.clone(),
+            BatchEntry {
+                batch,
+                cancel_requested: cancel_requested.clone(),
+            },
+        );
+        cancel_requested
+    }
+
+    pub fn get(&self, id: &str) -> Option<Batch> {
+        self.batches
+            .read()
+            .unwrap()
+            .get(id)
+            .map(|e| e.batch.clone())
+    }
+
+    /// Newest first, starting after the batch with id `after`, skipping batches `key` can't see.
+    pub fn list(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+        key: Option<&AuthenticatedKey>,
+    ) -> (Vec<Batch>, bool) {
+        let batches = self.batches.read().unwrap();
+        let mut iter = batches
+            .values()
+            .rev()
+            .filter(|e| e.batch.is_visible(key))
+            .peekable();
+        if let Some(after) = after {
+            for entry in iter.by_ref() {
+                if entry.batch.id == after {
+                    break;
+                }
+            }
+        }
+        let page: Vec<Batch> = iter.by_ref().take(limit).map(|e| e.batch.clone()).collect();
+        let has_more = iter.peek().is_some();
+        (page, has_more)
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut Batch)) {
+        if let Some(entry) = self.batches.write().unwrap().get_mut(id) {
+            f(&mut entry.batch);
+        }
+    }
+
+    /// Flag a running batch for cancellation. Returns the updated batch, or the current status
+    /// if the batch has already finished.
+    pub fn request_cancel(&self, id: &str) -> Option<Result<Batch, BatchStatus>> {
+        let mut batches = self.batches.write().unwrap();
+        let entry = batches.get_mut(id)?;
+        if entry.batch.status.is_terminal() {
+            return Some(Err(entry.batch.status));
+        }
+        entry.cancel_requested.store(true, Ordering::Relaxed);
+        if entry.batch.status != BatchStatus::Cancelling {
+            entry.batch.status = BatchStatus::Cancelling;
+            entry.batch.cancelling_at = Some(now_secs());
+        }
+        Some(Ok(entry.batch.clone()))
+    }
+}
+
+static BATCH_MANAGER: std::sync::LazyLock<BatchManager> =
+    std::sync::LazyLock::new(BatchManager::new);
+
+/// Get the global batch manager
+pub fn get_batch_manager() -> &'static BatchManager {
+    &BATCH_MANAGER
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListBatchesQuery {
+    pub after: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// `POST /v1/batches`
+pub async fn create_batch(
+    State(state): ExtractedMistralRsState,
+    Extension(agentic_defaults): Extension<AgenticDefaults>,
+    key: Option<Extension<AuthenticatedKey>>,
+    Json(request): Json<CreateBatchRequest>,
+) -> Response {
+    if !SUPPORTED_ENDPOINTS.contains(&request.endpoint.as_str()) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Unsupported endpoint '{}'. Supported: {}.",
+                request.endpoint,
+                SUPPORTED_ENDPOINTS.join(", ")
+            ),
+            "invalid_endpoint",
+        );
+    }
+    if request.completion_window != COMPLETION_WINDOW {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("completion_window must be '{COMPLETION_WINDOW}'."),
+            "invalid_completion_window",
+        );
+    }
+    let Some(input) = find_visible_file(&state, &request.input_file_id, key.as_deref()) else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            &format!("File '{}' not found or expired", request.input_file_id),
+            "file_not_found",
+        );
+    };
+
+    let manager = get_batch_manager();
+    let mut batch = Batch::new(&request, key.map(|Extension(key)| key.name));
+
+    let lines = match file_text(&input).map(|text| parse_input(&text, &request.endpoint)) {
+        Ok(Ok(lines)) => lines,
+        Ok(Err(errors)) | Err(errors) => {
+            batch.status = BatchStatus::Failed;
+            batch.failed_at = Some(now_secs());
+            batch.errors = Some(BatchErrors {
+                object: "list".to_string(),
+                data: errors,
+            });
+            manager.insert(batch.clone());
+            return Json(batch).into_response();
+        }
+    };
+
+    batch.status = BatchStatus::InProgress;
+    batch.in_progress_at = Some(now_secs());
+    batch.request_counts.total = lines.len();
+    let cancel_requested = manager.insert(batch.clone());
+
+    tokio::spawn(carry_key(run_batch(
+        state,
+        agentic_defaults,
+        batch.id.clone(),
+        lines,
+        cancel_requested,
+    )));
+
+    Json(batch).into_response()
+}
+
+/// `GET /v1/batches/{batch_id}`
+pub async fn get_batch(
+    key: Option<Extension<AuthenticatedKey>>,
+    Path(batch_id): Path<String>,
+) -> Response {
+    match get_batch_manager()
+        .get(&batch_id)
+        .filter(|b| b.is_visible(key.as_deref()))
+    {
+        Some(batch) => Json(batch).into_response(),
+        None => batch_not_found(&batch_id),
+    }
+}
+
+/// `GET /v1/batches`
+pub async fn list_batches(
+    key: Option<Extension<AuthenticatedKey>>,
+    Query(query): Query<ListBatchesQuery>,
+) -> Response {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_LIST_LIMIT)
+        .clamp(1, MAX_LIST_LIMIT);
+    let (data, has_more) = get_batch_manager().list(query.after.as_deref(), limit, key.as_deref());
+    Json(serde_json::json!({
+        "object": "list",
+        "first_id": data.first().map(|b| b.id.clone()),
+        "last_id": data.last().map(|b| b.id.clone()),
+        "has_more": has_more,
+        "data": data,
+    }))
+    .into_response()
+}
+
+/// `POST /v1/batches/{batch_id}/cancel`
+pub async fn cancel_batch(
+    key: Option<Extension<AuthenticatedKey>>,
+    Path(batch_id): Path<String>,
+) -> Response {
+    let manager = get_batch_manager();
+    if !manager
+        .get(&batch_id)
+        .is_some_and(|b| b.is_visible(key.as_deref()))
+    {
+        return batch_not_found(&batch_id);
+    }
+    match manager.request_cancel(&batch_id) {
+        Some(Ok(batch)) => Json(batch).into_response(),
+        Some(Err(status)) => error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Cannot cancel a batch with status '{}'.",
+                serde_json::to_value(status)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default()
+            ),
+            "invalid_batch_status",
+        ),
+        None => batch_not_found(&batch_id),
+    }
+}
+
+/// Text body of an input file. Binary uploads are accepted if they decode as UTF-8.
+fn file_text(file: &CoreFile) -> Result<String, Vec<BatchError>> {
+    let invalid = |message: &str| {
+        vec![BatchError {
+            code: "invalid_input_file".to_string(),
+            message: message.to_string(),
+            param: Some("input_file_id".to_string()),
+            line: None,
+        }]
+    };
+    match &file.content {
+        FileContent::Text { text: Some(t), .. } => Ok(t.clone()),
+        FileContent::Binary {
+            data_base64: Some(b),
+        } => STANDARD
+            .decode(b)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| invalid("Input file is not UTF-8 JSONL.")),
+        FileContent::Text { text: None, .. } | FileContent::Binary { data_base64: None } => {
+            Err(invalid("Input file body is not available."))
+        }
+        FileContent::Error { message, .. } => Err(invalid(message)),
+    }
+}
+
+/// Parse and validate every line up front, so a malformed file fails the batch before any
+/// request runs.
+fn parse_input(text: &str, endpoint: &str) -> Result<Vec<BatchInputLine>, Vec<BatchError>> {
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    let mut custom_ids = std::collections::HashSet::new();
+
+    for (idx, raw) in text.lines().enumerate() {
+        if raw.trim().is_empty() {
+            continue;
+        }
+        let line_no = idx + 1;
+        let mut error = |code: &str, message: String, param: Option<&str>| {
+            errors.push(BatchError {
+                code: code.to_string(),
+                message,
+                param: param.map(str::to_string),
+                line: Some(line_no),
+            })
+        };
+        match serde_json::from_str::<BatchInputLine>(raw) {
+            Ok(line) => {
+                if !line.method.eq_ignore_ascii_case("POST") {
+                    error(
+                        "invalid_method",
+                        format!("Only POST is supported, got '{}'.", line.method),
+                        Some("method"),
+                    );
+                } else if line.url != endpoint {
+                    error(
+                        "mismatched_endpoint",
+                        format!(
+                            "Line url '{}' does not match the batch endpoint '{endpoint}'.",
+                            line.url
+                        ),
+                        Some("url"),
+                    );
+                } else if !line.body.is_object() {
+                    error(
+                        "invalid_body",
+                        "body must be a JSON object.".to_string(),
+                        Some("body"),
+                    );
+                } else if !custom_ids.insert(line.custom_id.clone()) {
+                    error(
+                        "duplicate_custom_id",
+                        format!("Duplicate custom_id '{}'.", line.custom_id),
+                        Some("custom_id"),
+                    );
+                } else {
+                    lines.push(line);
+                }
+            }
+            Err(e) => error("invalid_json_line", e.to_string(), None),
+        }
+    }
+
+    if lines.is_empty() && errors.is_empty() {
+        errors.push(BatchError {
+            code: "empty_file".to_string(),
+            message: "Input file contains no requests.".to_string(),
+            param: Some("input_file_id".to_string()),
+            line: None,
+        });
+    }
+    if errors.is_empty() {
+        Ok(lines)
+    } else {
+        Err(errors)
+    }
+}
+
+async fn run_batch(
+    state: SharedMistralRsState,
+    agentic_defaults: AgenticDefaults,
+    batch_id: String,
+    lines: Vec<BatchInputLine>,
+    cancel_requested: Arc<AtomicBool>,
+) {
+    let manager = get_batch_manager();
+    let Some(expires_at) = manager.get(&batch_id).map(|b| b.expires_at) else {
+        return;
+    };
+    // Submit as many lines per model as its scheduler can run at once and leave the rest of
+    // the back-pressure to admission control.
+    let mut permits: HashMap<String, Arc<Semaphore>> = HashMap::new();
+
+    let mut output = String::new();
+    let mut errors = String::new();
+    let mut record = |custom_id: &str, status: StatusCode, body: Value| {
+        let ok = status.is_success();
+        let target = if ok { &mut output } else { &mut errors };
+        target.push_str(&result_record(custom_id, status, body).to_string());
+        target.push('\n');
+        manager.update(&batch_id, |b| {
+            if ok {
+                b.request_counts.completed += 1;
+            } else {
+                b.request_counts.failed += 1;
+            }
+        });
+    };
+
+    let mut running = JoinSet::new();
+    let mut pending = lines.into_iter();
+    let mut expired = false;
+    while let Some(line) = pending.next() {
+        let model = line
+            .body
+            .get("model")
+            .and_then(Value::as_str)
+            .unwrap_or("default");
+        let permit = permits
+            .entry(model.to_string())
+            .or_insert_with(|| {
+                let model = Some(model).filter(|m| *m != "default");
+                Arc::new(Semaphore::new(state.max_seqs(model).unwrap_or(1).max(1)))
+            })
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("batch semaphore is never closed");
+        while let Some(Ok((custom_id, status, body))) = running.try_join_next() {
+            record(&custom_id, status, body);
+        }
+        if cancel_requested.load(Ordering::Relaxed) {
+            break;
+        }
+        if now_secs() >= expires_at {
+            expired = true;
+            for line in std::iter::once(line).chain(pending.by_ref()) {
+                let body = error_body(
+                    "The batch expired before this request was run.",
+                    "batch_expired",
+                );
+                record(&line.custom_id, StatusCode::REQUEST_TIMEOUT, body);
+            }
+            break;
+        }
+        let state = state.clone();
+        let agentic_defaults = agentic_defaults.clone();
+        let cancel_requested = cancel_requested.clone();
+        running.spawn(carry_key(async move {
+            let (status, body) = run_line(
+                &state,
+                &agentic_defaults,
+                &line,
+                &cancel_requested,
+                expires_at,
+            )
+            .await;
+            drop(permit);
+            (line.custom_id, status, body)
+        }));
+    }
+    while let Some(joined) = running.join_next().await {
+        if let Ok((custom_id, status, body)) = joined {
+            record(&custom_id, status, body);
+        }
+    }
+
+    let cancelled = cancel_requested.load(Ordering::Relaxed);
+    manager.update(&batch_id, |b| {
+        if !cancelled {
+            b.status = BatchStatus::Finalizing;
+        }
+        b.finalizing_at = Some(now_secs());
+    });
+
+    let owner = manager.get(&batch_id).and_then(|b| b.owner);
+    let output_file_id = store_result_file(&state, &batch_id, owner.clone(), "output", output);
+    let error_file_id = store_result_file(&state, &batch_id, owner, "errors", errors);
+
+    manager.update(&batch_id, |b| {
+        b.output_file_id = output_file_id;
+        b.error_file_id = error_file_id;
+        if cancelled {
+            b.status = BatchStatus::Cancelled;
+            b.cancelled_at = Some(now_secs());
+        } else if expired {
+            b.status = BatchStatus::Expired;
+            b.expired_at = Some(now_secs());
+        } else {
+            b.status = BatchStatus::Completed;
+            b.completed_at = Some(now_secs());
+        }
+    });
+}
+
+/// Run one line. Lines turned away by admission control or the key's token quota wait for room
+/// instead of failing, until the batch is cancelled or expires.
+async fn run_line(
+    state: &SharedMistralRsState,
+    agentic_defaults: &AgenticDefaults,
+    line: &BatchInputLine,
+    cancel_requested: &AtomicBool,
+    expires_at: u64,
+) -> (StatusCode, Value) {
+    loop {
+        match dispatch(state, agentic_defaults, line).await {
+            (StatusCode::TOO_MANY_REQUESTS, _)
+                if !cancel_requested.load(Ordering::Relaxed) && now_secs() < expires_at =>
+            {
+                tokio::time::sleep(OVERLOADED_RETRY_DELAY).await
+            }
+            result => break result,
+        }
+    }
+}
+
+/// One line of the output or error file.
+fn result_record(custom_id: &str, status: StatusCode, body: Value) -> Value {
+    let error = if status.is_success() {
+        Value::Null
+    } else {
+        serde_json::json!({
+            "code": error_code(&body, status),
+            "message": error_message(&body),
+        })
+    };
+    serde_json::json!({
+        "id": format!("batch_req_{}", Uuid::new_v4().simple()),
+        "custom_id": custom_id,
+        "response": {
+            "status_code": status.as_u16(),
+            "request_id": body.get("id").and_then(Value::as_str).unwrap_or_default(),
+            "body": body,
+        },
+        "error": error,
+    })
+}
+
+/// Run one line through the matching route handler and return its status and JSON body.
+async fn dispatch(
+    state: &SharedMistralRsState,
+    agentic_defaults: &AgenticDefaults,
+    line: &BatchInputLine,
+) -> (StatusCode, Value) {
+    // Lines skip the auth layer, so the key's model limits and token quota are applied here.
+    let model = line
+        .body
+        .get("model")
+        .and_then(Value::as_str)
+        .unwrap_or("default");
+    let meter = token_meter();
+    let allowed = validate_model_access(model, authenticated_key().as_ref(), state)
+        .and_then(|()| meter.as_ref().map_or(Ok(()), TokenMeter::check));
+    let response = if let Err(e) = allowed {
+        e.into_response()
+    } else {
+        match line.url.as_str() {
+            "/v1/chat/completions" => {
+                match serde_json::from_value::<ChatCompletionRequest>(line.body.clone()) {
+                    Ok(mut request) => {
+                        request.stream = Some(false);
+                        request.priority.get_or_insert(RequestPriority::Low);
+                        chatcompletions(
+                            State(state.clone()),
+                            Extension(agentic_defaults.clone()),
+                            Json(request),
+                        )
+                        .await
+                        .into_response()
+                    }
+                    Err(e) => return invalid_body(e),
+                }
+            }
+            "/v1/completions" => {
+                match serde_json::from_value::<CompletionRequest>(line.body.clone()) {
+                    Ok(mut request) => {
+                        request.stream = Some(false);
+                        request.priority.get_or_insert(RequestPriority::Low);
+                        completions(State(state.clone()), Json(request))
+                            .await
+                            .into_response()
+                    }
+                    Err(e) => return invalid_body(e),
+                }
+            }
+            "/v1/embeddings" => match serde_json::from_value::<EmbeddingRequest>(line.body.clone())
+            {
+                Ok(mut request) => {
+                    request.priority.get_or_insert(RequestPriority::Low);
+                    embeddings(State(state.clone()), Json(request))
+                        .await
+                        .into_response()
+                }
+                Err(e) => return invalid_body(e),
+            },
+            other => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    error_body(&format!("Unsupported url '{other}'."), "invalid_url"),
+                )
+            }
+        }
+    };
+
+    let status = response.status();
+    let body = match axum::body::to_bytes(response.into_body(), usize::MAX).await {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned())),
+        Err(e) => error_body(&e.to_string(), "internal_error"),
+    };
+    if let Some(meter) = &meter {
+        meter.charge_usage(&body);
+    }
+    (status, body)
+}
+
+fn invalid_body(e: serde_json::Error) -> (StatusCode, Value) {
+    (
+        StatusCode::BAD_REQUEST,
+        error_body(
+            &format!("Invalid request body: {e}"),
+            "invalid_request_body",
+        ),
+    )
+}
+
+/// The handler's `error.code` if it set one, otherwise the HTTP status.
+fn error_code(body: &Value, status: StatusCode) -> String {
+    body.get("error")
+        .and_then(|e| e.get("code"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("http_{}", status.as_u16()))
+}
+
+/// Best-effort error message from a handler's error body (`{"message"}` or `{"error":{"message"}}`).
+fn error_message(body: &Value) -> String {
+    body.get("error")
+        .and_then(|e| e.get("message").or(Some(e)))
+        .or_else(|| body.get("message"))
+        .and_then(|m| m.as_str().map(str::to_string))
+        .unwrap_or_else(|| body.to_string())
+}
+
+fn store_result_file(
+    state: &SharedMistralRsState,
+    batch_id: &str,
+    owner: Option<String>,
+    kind: &str,
+    jsonl: String,
+) -> Option<String> {
+    if jsonl.is_empty() {
+        return None;
+    }
+    let id = format!("file_{batch_id}_{kind}");
+    let file = CoreFile {
+        id: id.clone(),
+        name: format!("{batch_id}_{kind}.jsonl"),
+        format: Some("jsonl".to_string()),
+        mime_type: Some("application/jsonl".to_string()),
+        bytes: jsonl.len() as u64,
+        created_at: now_secs(),
+        purpose: None,
+        owner,
+        source: FileSource {
+            tool: BATCH_FILE_SOURCE.to_string(),
+            round: 0,
+            turn: 0,
+        },
+        content: FileContent::Text {
+            text: Some(jsonl),
+            preview: None,
+        },
+    };
+    match state.add_file(None, file, Some(BATCH_FILE_TTL)) {
+        Ok(()) => Some(id),
+        Err(e) => {
+            tracing::error!("Failed to store batch {kind} file for {batch_id}: {e}");
+            None
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn error_body(message: &str, code: &str) -> Value {
+    serde_json::json!({
+        "error": {
+            "message": message,
+            "type": "invalid_request_error",
+            "code": code,
+        }
+    })
+}
+
+fn error_response(status: StatusCode, message: &str, code: &str) -> Response {
+    (status, Json(error_body(message, code))).into_response()
+}
+
+fn batch_not_found(id: &str) -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        &format!("Batch '{id}' not found"),
+        "batch_not_found",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_request() -> CreateBatchRequest {
+        CreateBatchRequest {
+            input_file_id: "file_x".to_string(),
+            endpoint: "/v1/embeddings".to_string(),
+            completion_window: COMPLETION_WINDOW.to_string(),
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn parses_valid_input() {
+        let text = r#"{"custom_id":"a","method":"POST","url":"/v1/chat/completions","body":{"model":"default","messages":[]}}
+
+{"custom_id":"b","url":"/v1/chat/completions","body":{"model":"default","messages":[]}}
+"#;
+        let lines = parse_input(text, "/v1/chat/completions").unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].method, "POST");
+    }
+
+    #[test]
+    fn line_priority_is_parsed() {
+        let body = serde_json::json!({"model": "default", "prompt": "hi", "priority": "high"});
+        let request: CompletionRequest = serde_json::from_value(body).unwrap();
+        assert_eq!(request.priority, Some(RequestPriority::High));
+
+        let body = serde_json::json!({"model": "default", "prompt": "hi"});
+        let request: CompletionRequest = serde_json::from_value(body).unwrap();
+        assert_eq!(request.priority, None);
+    }
+
+    #[test]
+    fn reports_bad_lines_with_line_numbers() {
+        let text = r#"{"custom_id":"a","url":"/v1/completions","body":{}}
+not json
+{"custom_id":"a","url":"/v1/chat/completions","body":{}}
+{"custom_id":"a","url":"/v1/chat/completions","body":{}}"#;
+        let errors = parse_input(text, "/v1/chat/completions").unwrap_err();
+        let codes: Vec<_> = errors.iter().map(|e| (e.code.as_str(), e.line)).collect();
+        assert_eq!(
+            codes,
+            vec![
+                ("mismatched_endpoint", Some(1)),
+                ("invalid_json_line", Some(2)),
+                ("duplicate_custom_id", Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_input_is_rejected() {
+        let errors = parse_input("\n\n", "/v1/embeddings").unwrap_err();
+        assert_eq!(errors[0].code, "empty_file");
+    }
+
+    #[test]
+    fn list_is_newest_first_with_cursor() {
+        let manager = BatchManager::new();
+        let request = create_request();
+        let ids: Vec<String> = (0..3)
+            .map(|_| {
+                let batch = Batch::new(&request, None);
+                let id = batch.id.clone();
+                manager.insert(batch);
+                id
+            })
+            .collect();
+
+        let (page, has_more) = manager.list(None, 2, None);
+        assert_eq!(
+            page.iter().map(|b| &b.id).collect::<Vec<_>>(),
+            vec![&ids[2], &ids[1]]
+        );
+        assert!(has_more);
+
+        let (page, has_more) = manager.list(Some(&ids[1]), 2, None);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, ids[0]);
+        assert!(!has_more);
+    }
+
+    #[test]
+    fn list_skips_batches_of_other_keys() {
+        let manager = BatchManager::new();
+        let request = create_request();
+        let mine = Batch::new(&request, Some("app".to_string()));
+        let mine_id = mine.id.clone();
+        manager.insert(mine);
+        manager.insert(Batch::new(&request, Some("other".to_string())));
+
+        let key = AuthenticatedKey {
+            name: "app".to_string(),
+            scopes: vec![crate::auth::ApiKeyScope::Inference],
+            models: None,
+        };
+        let (page, has_more) = manager.list(None, 10, Some(&key));
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, mine_id);
+        assert!(!has_more);
+        assert_eq!(manager.list(None, 10, None).0.len(), 2);
+    }
+
+    #[test]
+    fn finished_batches_are_dropped_after_their_files_expire() {
+        let manager = BatchManager::new();
+        let request = create_request();
+        let mut old = Batch::new(&request, None);
+        old.status = BatchStatus::Completed;
+        old.completed_at = Some(now_secs() - BATCH_FILE_TTL.as_secs() - 1);
+        let old_id = old.id.clone();
+        manager.insert(old);
+        let mut recent = Batch::new(&request, None);
+        recent.status = BatchStatus::Expired;
+        recent.expired_at = Some(now_secs());
+        let recent_id = recent.id.clone();
+        manager.insert(recent);
+        assert!(manager.get(&old_id).is_some());
+
+        manager.insert(Batch::new(&request, None));
+        assert!(manager.get(&old_id).is_none());
+        assert!(manager.get(&recent_id).is_some());
+    }
+
+    #[test]
+    fn cancel_moves_to_cancelling_until_terminal() {
+        let manager = BatchManager::new();
+        let request = create_request();
+        let mut batch = Batch::new(&request, None);
+        batch.status = BatchStatus::InProgress;
+        let id = batch.id.clone();
+        let flag = manager.insert(batch);
+
+        let cancelled = manager.request_cancel(&id).unwrap().unwrap();
+        assert_eq!(cancelled.status, BatchStatus::Cancelling);
+        assert!(flag.load(Ordering::Relaxed));
+
+        manager.update(&id, |b| b.status = BatchStatus::Cancelled);
+        assert_eq!(
+            manager.request_cancel(&id).unwrap().unwrap_err(),
+            BatchStatus::Cancelled
+        );
+        assert!(manager.request_cancel("missing").is_none());
+    }
+}
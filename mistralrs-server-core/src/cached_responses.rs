@@ -1,9 +1,15 @@
 //! ## Response caching functionality for the Responses API.
+//!
+//! Responses live in memory by default. [`FileResponseCache`] keeps them on disk so
+//! `previous_response_id` chains survive a restart.
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::LazyLock;
-use std::sync::{Arc, RwLock};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock, RwLock};
+use std::time::{Duration, SystemTime};
 
 use crate::openai::Message;
 use crate::responses_types::ResponseResource;
@@ -24,12 +30,19 @@ pub trait ResponseCache: Send + Sync {
 
     /// Retrieve conversation history for a response
     fn get_conversation_history(&self, id: &str) -> Result<Option<Vec<Message>>>;
+
+    /// Store the input messages a response was generated from
+    fn store_input_items(&self, id: String, messages: Vec<Message>) -> Result<()>;
+
+    /// Retrieve the input messages a response was generated from
+    fn get_input_items(&self, id: &str) -> Result<Option<Vec<Message>>>;
 }
 
 /// In-memory implementation of ResponseCache
 pub struct InMemoryResponseCache {
     responses: Arc<RwLock<HashMap<String, ResponseResource>>>,
     conversation_histories: Arc<RwLock<HashMap<String, Vec<Message>>>>,
+    input_items: Arc<RwLock<HashMap<String, Vec<Message>>>>,
 }
 
 impl InMemoryResponseCache {
@@ -38,6 +51,7 @@ impl InMemoryResponseCache {
         Self {
             responses: Arc::new(RwLock::new(HashMap::new())),
             conversation_histories: Arc::new(RwLock::new(HashMap::new())),
+            input_items: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
@@ -62,18 +76,20 @@ impl ResponseCache for InMemoryResponseCache {
 
     fn delete_response(&self, id: &str) -> Result<bool> {
         // IMPORTANT: Lock ordering must be maintained to prevent deadlocks.
-        // Order: responses -> conversation_histories
+        // Order: responses -> conversation_histories -> input_items
         // All methods that acquire multiple locks must follow this order.
         //
         // We acquire all locks before any modifications to ensure atomicity.
         // The locks are released in reverse order when dropped at end of scope.
         let mut responses = self.responses.write().unwrap();
         let mut histories = self.conversation_histories.write().unwrap();
+        let mut input_items = self.input_items.write().unwrap();
 
         let response_removed = responses.remove(id).is_some();
         let history_removed = histories.remove(id).is_some();
+        let input_removed = input_items.remove(id).is_some();
 
-        Ok(response_removed || history_removed)
+        Ok(response_removed || history_removed || input_removed)
     }
 
     fn store_conversation_history(&self, id: String, messages: Vec<Message>) -> Result<()> {
@@ -86,17 +102,236 @@ impl ResponseCache for InMemoryResponseCache {
         let histories = self.conversation_histories.read().unwrap();
         Ok(histories.get(id).cloned())
     }
+
+    fn store_input_items(&self, id: String, messages: Vec<Message>) -> Result<()> {
+        let mut input_items = self.input_items.write().unwrap();
+        input_items.insert(id, messages);
+        Ok(())
+    }
+
+    fn get_input_items(&self, id: &str) -> Result<Option<Vec<Message>>> {
+        let input_items = self.input_items.read().unwrap();
+        Ok(input_items.get(id).cloned())
+    }
+}
+
+/// Everything stored for one response ID, written as `<id>.json`.
+#[derive(Default, Serialize, Deserialize)]
+struct StoredEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response: Option<ResponseResource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    conversation_history: Option<Vec<Message>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    input_items: Option<Vec<Message>>,
+}
+
+/// File-backed implementation of ResponseCache.
+///
+/// Each response ID is one JSON file in the cache directory. Entries expire `ttl` after their
+/// last write, and once there are more than `max_entries` the least recently written are removed.
+pub struct FileResponseCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+    max_entries: Option<usize>,
+    /// Last write time of every entry on disk. Also serializes all file access.
+    index: Mutex<HashMap<String, SystemTime>>,
+}
+
+impl FileResponseCache {
+    /// Open (or create) a cache in `dir`, indexing the entries already there.
+    pub fn new(
+        dir: impl Into<PathBuf>,
+        ttl: Option<Duration>,
+        max_entries: Option<usize>,
+    ) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create response cache dir {}", dir.display()))?;
+
+        let mut index = HashMap::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(id) = path.file_stem().and_then(|s| s.to_str()) {
+                    index.insert(id.to_string(), fs::metadata(&path)?.modified()?);
+                }
+            }
+        }
+
+        let cache = Self {
+            dir,
+            ttl,
+            max_entries,
+            index: Mutex::new(index),
+        };
+        cache.evict(&mut cache.index.lock().unwrap());
+        Ok(cache)
+    }
+
+    /// Path of the entry for `id`, or `None` if `id` is not a safe file name.
+    fn entry_path(&self, id: &str) -> Option<PathBuf> {
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid.then(|| self.dir.join(format!("{id}.json")))
+    }
+
+    fn is_expired(&self, written: SystemTime) -> bool {
+        self.ttl
+            .is_some_and(|ttl| written.elapsed().is_ok_and(|elapsed| elapsed > ttl))
+    }
+
+    fn read_entry(path: &Path) -> Result<Option<StoredEntry>> {
+        match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .with_context(|| format!("Corrupt response cache entry {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Read-modify-write the entry for `id`, then apply eviction.
+    fn update(&self, id: &str, f: impl FnOnce(&mut StoredEntry)) -> Result<()> {
+        let path = self
+            .entry_path(id)
+            .ok_or_else(|| anyhow!("Invalid response ID `{id}`."))?;
+        let mut index = self.index.lock().unwrap();
+
+        let mut entry = Self::read_entry(&path)?.unwrap_or_default();
+        f(&mut entry);
+        // Write then rename so a crash never leaves a half-written entry.
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(&entry)?)?;
+        fs::rename(&tmp, &path)?;
+
+        index.insert(id.to_string(), SystemTime::now());
+        self.evict(&mut index);
+        Ok(())
+    }
+
+    /// Read the entry for `id`, dropping it if it has expired.
+    fn read(&self, id: &str) -> Result<Option<StoredEntry>> {
+        let Some(path) = self.entry_path(id) else {
+            return Ok(None);
+        };
+        let mut index = self.index.lock().unwrap();
+        match index.get(id) {
+            None => return Ok(None),
+            Some(&written) if self.is_expired(written) => {
+                index.remove(id);
+                let _ = fs::remove_file(&path);
+                return Ok(None);
+            }
+            Some(_) => (),
+        }
+        Self::read_entry(&path)
+    }
+
+    /// Remove expired entries, then the oldest ones beyond `max_entries`.
+    fn evict(&self, index: &mut HashMap<String, SystemTime>) {
+        let mut evicted: Vec<String> = index
+            .iter()
+            .filter(|(_, written)| self.is_expired(**written))
+            .map(|(id, _)| id.clone())
+            .collect();
+        if let Some(max_entries) = self.max_entries {
+            let mut live: Vec<(&String, &SystemTime)> = index
+                .iter()
+                .filter(|(_, written)| !self.is_expired(**written))
+                .collect();
+            if live.len() > max_entries {
+                live.sort_by_key(|(_, written)| **written);
+                let excess = live.len() - max_entries;
+                evicted.extend(live.into_iter().take(excess).map(|(id, _)| id.clone()));
+            }
+        }
+        for id in evicted {
+            index.remove(&id);
+            if let Some(path) = self.entry_path(&id) {
+                if let Err(e) = fs::remove_file(&path) {
+                    tracing::warn!("Failed to evict {}: {e}", path.display());
+                }
+            }
+        }
+    }
 }
 
-/// Global response cache instance
-pub static RESPONSE_CACHE: LazyLock<Arc<dyn ResponseCache>> =
-    LazyLock::new(|| Arc::new(InMemoryResponseCache::new()));
+impl ResponseCache for FileResponseCache {
+    fn store_response(&self, id: String, response: ResponseResource) -> Result<()> {
+        self.update(&id, |entry| entry.response = Some(response))
+    }
+
+    fn get_response(&self, id: &str) -> Result<Option<ResponseResource>> {
+        Ok(self.read(id)?.and_then(|entry| entry.response))
+    }
+
+    fn delete_response(&self, id: &str) -> Result<bool> {
+        let Some(path) = self.entry_path(id) else {
+            return Ok(false);
+        };
+        let mut index = self.index.lock().unwrap();
+        let indexed = index.remove(id).is_some();
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(indexed),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store_conversation_history(&self, id: String, messages: Vec<Message>) -> Result<()> {
+        self.update(&id, |entry| entry.conversation_history = Some(messages))
+    }
+
+    fn get_conversation_history(&self, id: &str) -> Result<Option<Vec<Message>>> {
+        Ok(self.read(id)?.and_then(|entry| entry.conversation_history))
+    }
+
+    fn store_input_items(&self, id: String, messages: Vec<Message>) -> Result<()> {
+        self.update(&id, |entry| entry.input_items = Some(messages))
+    }
+
+    fn get_input_items(&self, id: &str) -> Result<Option<Vec<Message>>> {
+        Ok(self.read(id)?.and_then(|entry| entry.input_items))
+    }
+}
+
+/// Cache installed with [`set_response_cache`], or the in-memory default.
+static INSTALLED_CACHE: OnceLock<Arc<dyn ResponseCache>> = OnceLock::new();
+
+/// Global response cache instance. It resolves to the cache installed with
+/// [`set_response_cache`] on first use, so install custom caches before touching it.
+pub static RESPONSE_CACHE: LazyLock<Arc<dyn ResponseCache>> = LazyLock::new(|| {
+    INSTALLED_CACHE
+        .get_or_init(|| Arc::new(InMemoryResponseCache::new()))
+        .clone()
+});
+
+/// Install the global cache. Must run before the first request; the default is in-memory.
+pub fn set_response_cache(cache: Arc<dyn ResponseCache>) -> Result<()> {
+    INSTALLED_CACHE
+        .set(cache)
+        .map_err(|_| anyhow!("The response cache is already initialized."))
+}
 
 /// Helper function to get the global cache instance
 pub fn get_response_cache() -> Arc<dyn ResponseCache> {
     RESPONSE_CACHE.clone()
 }
 
+/// Run `call` against the global cache on a blocking thread. [`FileResponseCache`] reads and
+/// writes disk, which must not stall the async handlers.
+pub async fn with_response_cache<T, F>(call: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn ResponseCache) -> Result<T> + Send + 'static,
+{
+    let cache = get_response_cache();
+    tokio::task::spawn_blocking(move || call(&*cache)).await?
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -153,4 +388,48 @@ mod tests {
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap().len(), 1);
     }
+
+    #[test]
+    fn test_file_cache_persists_and_evicts() {
+        let dir =
+            std::env::temp_dir().join(format!("mistralrs-responses-{}", uuid::Uuid::new_v4()));
+        let response = |id: &str| {
+            ResponseResource::new(id.to_string(), "test-model".to_string(), 1234567890)
+                .with_status(ResponseStatus::Completed)
+        };
+
+        let cache = FileResponseCache::new(&dir, None, Some(2)).unwrap();
+        cache
+            .store_response("resp_a".to_string(), response("resp_a"))
+            .unwrap();
+        cache
+            .store_input_items("resp_a".to_string(), Vec::new())
+            .unwrap();
+        assert!(cache
+            .store_response("../escape".to_string(), response("x"))
+            .is_err());
+        drop(cache);
+
+        // A new instance sees entries written by the previous one.
+        let cache = FileResponseCache::new(&dir, None, Some(2)).unwrap();
+        assert_eq!(cache.get_response("resp_a").unwrap().unwrap().id, "resp_a");
+        assert!(cache.get_input_items("resp_a").unwrap().is_some());
+
+        // Writing past `max_entries` evicts the least recently written entry.
+        std::thread::sleep(Duration::from_millis(10));
+        cache
+            .store_response("resp_b".to_string(), response("resp_b"))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        cache
+            .store_response("resp_c".to_string(), response("resp_c"))
+            .unwrap();
+        assert!(cache.get_response("resp_a").unwrap().is_none());
+        assert!(cache.get_response("resp_c").unwrap().is_some());
+
+        assert!(cache.delete_response("resp_b").unwrap());
+        assert!(!cache.delete_response("resp_b").unwrap());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
@@ -33,14 +33,15 @@ use crate::{
         convert_stop_tokens, get_dry_sampling_params, handle_completion_error,
         BaseCompletionResponder,
     },
+    files::{resolve_attachment, Attachment},
     handler_core::{
-        create_response_channel, send_request_with_model, BaseJsonModelError, ErrorToResponse,
-        JsonError, ModelErrorMessage,
+        boxed_send_error, create_response_channel, overloaded_response, request_timeout,
+        send_request_with_model, BaseJsonModelError, ErrorToResponse, JsonError, ModelErrorMessage,
     },
     mistralrs_server_router_builder::AgenticDefaults,
     openai::{
-        ChatCompletionRequest, Grammar, JsonSchemaResponseFormat, MessageInnerContent,
-        ResponseFormat,
+        ChatCompletionRequest, Grammar, JsonSchemaResponseFormat, MessageInnerContent, Prediction,
+        ResponseFormat, StreamOptions,
     },
     streaming::{base_create_streamer, get_keep_alive_interval, BaseStreamer, DoneState},
     types::{ExtractedMistralRsState, OnChunkCallback, OnDoneCallback, SharedMistralRsState},
@@ -326,6 +327,15 @@ impl futures::Stream for ChatCompletionStreamer {
     ) -> Poll<Option<Self::Item>> {
         match self.done_state {
             DoneState::SendingDone => {
+                if let Some(mut chunk) = self.usage_chunk.take() {
+                    if let Some(on_chunk) = &self.on_chunk {
+                        chunk = on_chunk(chunk);
+                    }
+                    if self.store_chunks {
+                        self.chunks.push(chunk.clone());
+                    }
+                    return Poll::Ready(Some(Event::default().json_data(chunk)));
+                }
                 // https://platform.openai.com/docs/api-reference/completions/create
                 // If true, returns a stream of events that happen during the Run as server-sent events, terminating when the Run enters a terminal state with a data: [DONE] message.
                 self.done_state = DoneState::Done;
@@ -365,9 +375,11 @@ impl futures::Stream for ChatCompletionStreamer {
                     )))
                 }
                 Response::Chunk(mut response) => {
-                    if response.choices.iter().all(|x| x.finish_reason.is_some()) {
+                    let is_final = response.choices.iter().all(|x| x.finish_reason.is_some());
+                    if is_final {
                         self.done_state = DoneState::SendingDone;
                     }
+                    self.apply_stream_options(&mut response, is_final);
                     // Done now, just need to send the [DONE]
                     MistralRs::maybe_log_response(self.state.clone(), &response);
 
@@ -423,6 +435,7 @@ impl futures::Stream for ChatCompletionStreamer {
                 Response::CompletionChunk(_) => unreachable!(),
                 Response::ImageGeneration(_) => unreachable!(),
                 Response::Speech { .. } => unreachable!(),
+                Response::SpeechChunk { .. } => unreachable!(),
                 Response::Raw { .. } => unreachable!(),
                 Response::Embeddings { .. } => unreachable!(),
             },
@@ -445,10 +458,11 @@ impl IntoResponse for ChatCompletionResponder {
         match self {
             ChatCompletionResponder::Sse(s) => s.into_response(),
             ChatCompletionResponder::Json(s) => Json(s).into_response(),
-            ChatCompletionResponder::InternalError(e) => {
-                JsonError::new(sanitize_error_message(e.as_ref()))
-                    .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
-            }
+            ChatCompletionResponder::InternalError(e) => overloaded_response(e.downcast_ref())
+                .unwrap_or_else(|| {
+                    JsonError::new(sanitize_error_message(e.as_ref()))
+                        .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
+                }),
             ChatCompletionResponder::ValidationError(e) => {
                 JsonError::new(sanitize_error_message(e.as_ref()))
                     .to_response(http::StatusCode::UNPROCESSABLE_ENTITY)
@@ -497,12 +511,13 @@ pub async fn parse_request(
 
     let stop_toks = convert_stop_tokens(oairequest.stop_seqs);
 
-    let messages = match oairequest.messages {
+    let (messages, input_files) = match oairequest.messages {
         Either::Left(req_messages) => {
             let mut messages = Vec::new();
             let mut image_urls = Vec::new();
             let mut audio_urls = Vec::new();
             let mut video_urls = Vec::new();
+            let mut input_files = Vec::new();
             for message in req_messages {
                 let content = match message.content.as_deref() {
                     Some(content) => content.clone(),
@@ -585,7 +600,13 @@ pub async fn parse_request(
                         // If there is only one message, it is possible a text message
                         // found when rig is used as client. In this case, we need to check if
                         // the message is a text message or an image message.
-                        if image_messages.len() == 1 {
+                        // A lone `file` part has no text and goes through the multimodal path.
+                        let lone_file_part = image_messages.len() == 1
+                            && matches!(
+                                image_messages[0].get("type").map(|t| t.deref()),
+                                Some(Either::Left(x)) if x == "file"
+                            );
+                        if image_messages.len() == 1 && !lone_file_part {
                             if !image_messages[0].contains_key("text") {
                                 anyhow::bail!("Expected `text` key in input message.");
                             }
@@ -669,6 +690,29 @@ pub async fn parse_request(
                                             .clone(),
                                     });
                                 }
+                                Some(MessageInnerContent(Either::Left(x))) if x == "file" => {
+                                    let file_id = image_message
+                                        .get("file")
+                                        .as_ref()
+                                        .context("File sub-content must have `file` key.")?
+                                        .as_ref()
+                                        .right()
+                                        .context("File sub-content `file` key must be an object.")?
+                                        .get("file_id")
+                                        .context("File sub-content `file` object must have a `file_id` key.")?;
+                                    let (attachment, file) = resolve_attachment(
+                                        &state,
+                                        file_id,
+                                        oairequest.enable_code_execution,
+                                    )?;
+                                    items.push(match attachment {
+                                        Attachment::Image(image_url) => ContentPart::Image { image_url },
+                                        Attachment::Audio(audio_url) => ContentPart::Audio { audio_url },
+                                        Attachment::Video(video_url) => ContentPart::Video { video_url },
+                                        Attachment::Text(text) => ContentPart::Text { text },
+                                    });
+                                    input_files.push(file);
+                                }
                                 _ => anyhow::bail!("Expected array content sub-content to be of format {{`type`: `text`, `text`: ...}} and {{`type`: `url`, `image_url`: {{`url`: ...}}}}")
                             }
                         }
@@ -817,20 +861,26 @@ pub async fn parse_request(
                     videos.push(video);
                 }
 
-                RequestMessage::MultimodalChat {
-                    messages,
-                    images,
-                    audios,
-                    videos,
-                    enable_thinking: oairequest.enable_thinking,
-                    reasoning_effort,
-                }
+                (
+                    RequestMessage::MultimodalChat {
+                        messages,
+                        images,
+                        audios,
+                        videos,
+                        enable_thinking: oairequest.enable_thinking,
+                        reasoning_effort,
+                    },
+                    input_files,
+                )
             } else {
-                RequestMessage::Chat {
-                    messages,
-                    enable_thinking: oairequest.enable_thinking,
-                    reasoning_effort,
-                }
+                (
+                    RequestMessage::Chat {
+                        messages,
+                        enable_thinking: oairequest.enable_thinking,
+                        reasoning_effort,
+                    },
+                    input_files,
+                )
             }
         }
         Either::Right(prompt) => {
@@ -840,11 +890,14 @@ pub async fn parse_request(
             message_map.insert("role".to_string(), Either::Left("user".to_string()));
             message_map.insert("content".to_string(), Either::Left(prompt));
             messages.push(message_map);
-            RequestMessage::Chat {
-                messages,
-                enable_thinking: oairequest.enable_thinking,
-                reasoning_effort,
-            }
+            (
+                RequestMessage::Chat {
+                    messages,
+                    enable_thinking: oairequest.enable_thinking,
+                    reasoning_effort,
+                },
+                Vec::new(),
+            )
         }
     };
 
@@ -893,6 +946,7 @@ pub async fn parse_request(
                 logits_bias: oairequest.logit_bias,
                 n_choices: oairequest.n_choices,
                 dry_params,
+                seed: oairequest.seed,
             },
             response: tx,
             return_logprobs: oairequest.logprobs,
@@ -920,6 +974,13 @@ pub async fn parse_request(
                 Some(oairequest.model.clone())
             },
             truncate_sequence: oairequest.truncate_sequence.unwrap_or(false),
+            input_files: (!input_files.is_empty()).then_some(input_files),
+            priority: oairequest.priority.unwrap_or_default(),
+            timeout: request_timeout(oairequest.timeout)?,
+            trace_context: None,
+            user: None,
+            prediction: oairequest.prediction.as_ref().map(Prediction::text),
+            agentic_permit: None,
         })),
         is_streaming,
     ))
@@ -980,6 +1041,8 @@ pub async fn chatcompletions(
         Some(oairequest.model.clone())
     };
 
+    let stream_options = oairequest.stream_options.unwrap_or_default();
+
     // tool_dispatch_url is server-level only (not settable per-request via HTTP API) for security
     let (request, is_streaming) = match parse_request(
         oairequest,
@@ -996,11 +1059,17 @@ pub async fn chatcompletions(
     };
 
     if let Err(e) = send_request_with_model(&state, request, model_id.as_deref()).await {
-        return handle_error(state, e.into());
+        return handle_error(state, boxed_send_error(e));
     }
 
     if is_streaming {
-        ChatCompletionResponder::Sse(create_streamer(rx, state, None, None))
+        ChatCompletionResponder::Sse(create_streamer_with_options(
+            rx,
+            state,
+            stream_options,
+            None,
+            None,
+        ))
     } else {
         process_non_streaming_response(&mut rx, state).await
     }
@@ -1021,7 +1090,19 @@ pub fn create_streamer(
     on_chunk: Option<ChatCompletionOnChunkCallback>,
     on_done: Option<ChatCompletionOnDoneCallback>,
 ) -> Sse<KeepAliveStream<ChatCompletionStreamer>> {
-    let streamer = base_create_streamer(rx, state, on_chunk, on_done);
+    create_streamer_with_options(rx, state, StreamOptions::default(), on_chunk, on_done)
+}
+
+/// Creates a SSE streamer that reports usage as requested by `stream_options`.
+pub fn create_streamer_with_options(
+    rx: Receiver<Response>,
+    state: SharedMistralRsState,
+    stream_options: StreamOptions,
+    on_chunk: Option<ChatCompletionOnChunkCallback>,
+    on_done: Option<ChatCompletionOnDoneCallback>,
+) -> Sse<KeepAliveStream<ChatCompletionStreamer>> {
+    let mut streamer = base_create_streamer(rx, state, on_chunk, on_done);
+    streamer.stream_options = stream_options;
     let keep_alive_interval = get_keep_alive_interval();
 
     Sse::new(streamer)
@@ -1116,6 +1197,7 @@ pub fn match_responses(state: SharedMistralRsState, response: Response) -> ChatC
         Response::CompletionChunk(_) => unreachable!(),
         Response::ImageGeneration(_) => unreachable!(),
         Response::Speech { .. } => unreachable!(),
+        Response::SpeechChunk { .. } => unreachable!(),
         Response::Raw { .. } => unreachable!(),
         Response::Embeddings { .. } => unreachable!(),
         Response::AgenticToolCallProgress { .. } => unreachable!(),
@@ -13,10 +13,11 @@ use crate::{
         BaseCompletionResponder,
     },
     handler_core::{
-        base_process_non_streaming_response, create_response_channel, send_request,
-        BaseJsonModelError, ErrorToResponse, JsonError, ModelErrorMessage,
+        base_process_non_streaming_response, boxed_send_error, create_response_channel,
+        overloaded_response, request_timeout, send_request, BaseJsonModelError, ErrorToResponse,
+        JsonError, ModelErrorMessage,
     },
-    openai::{CompletionRequest, Grammar},
+    openai::{CompletionRequest, Grammar, Prediction, StreamOptions},
     streaming::{base_create_streamer, get_keep_alive_interval, BaseStreamer, DoneState},
     types::{ExtractedMistralRsState, OnChunkCallback, OnDoneCallback, SharedMistralRsState},
     util::{sanitize_error_message, validate_model_name},
@@ -92,6 +93,15 @@ impl futures::Stream for CompletionStreamer {
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         match self.done_state {
             DoneState::SendingDone => {
+                if let Some(mut chunk) = self.usage_chunk.take() {
+                    if let Some(on_chunk) = &self.on_chunk {
+                        chunk = on_chunk(chunk);
+                    }
+                    if self.store_chunks {
+                        self.chunks.push(chunk.clone());
+                    }
+                    return Poll::Ready(Some(Event::default().json_data(chunk)));
+                }
                 // https://platform.openai.com/docs/api-reference/completions/create
                 // If true, returns a stream of events that happen during the Run as server-sent events, terminating when the Run enters a terminal state with a data: [DONE] message.
                 self.done_state = DoneState::Done;
@@ -131,9 +141,11 @@ impl futures::Stream for CompletionStreamer {
                     )))
                 }
                 Response::CompletionChunk(mut response) => {
-                    if response.choices.iter().all(|x| x.finish_reason.is_some()) {
+                    let is_final = response.choices.iter().all(|x| x.finish_reason.is_some());
+                    if is_final {
                         self.done_state = DoneState::SendingDone;
                     }
+                    self.apply_stream_options(&mut response, is_final);
                     // Done now, just need to send the [DONE]
                     MistralRs::maybe_log_response(self.state.clone(), &response);
 
@@ -159,6 +171,7 @@ impl futures::Stream for CompletionStreamer {
                 Response::ImageGeneration(_) => unreachable!(),
                 Response::ModelError(_, _) => unreachable!(),
                 Response::Speech { .. } => unreachable!(),
+                Response::SpeechChunk { .. } => unreachable!(),
                 Response::Raw { .. } => unreachable!(),
                 Response::Embeddings { .. } => unreachable!(),
             },
@@ -182,10 +195,11 @@ impl IntoResponse for CompletionResponder {
         match self {
             CompletionResponder::Sse(s) => s.into_response(),
             CompletionResponder::Json(s) => Json(s).into_response(),
-            CompletionResponder::InternalError(e) => {
-                JsonError::new(sanitize_error_message(e.as_ref()))
-                    .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
-            }
+            CompletionResponder::InternalError(e) => overloaded_response(e.downcast_ref())
+                .unwrap_or_else(|| {
+                    JsonError::new(sanitize_error_message(e.as_ref()))
+                        .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
+                }),
             CompletionResponder::ValidationError(e) => {
                 JsonError::new(sanitize_error_message(e.as_ref()))
                     .to_response(http::StatusCode::UNPROCESSABLE_ENTITY)
@@ -229,6 +243,7 @@ pub fn parse_request(
                 text: oairequest.prompt,
                 echo_prompt: oairequest.echo_prompt,
                 best_of: oairequest.best_of,
+                fim_mode: oairequest.fim_mode.unwrap_or_default(),
             },
             sampling_params: SamplingParams {
                 temperature: oairequest.temperature,
@@ -244,6 +259,7 @@ pub fn parse_request(
                 logits_bias: oairequest.logit_bias,
                 n_choices: oairequest.n_choices,
                 dry_params,
+                seed: oairequest.seed,
             },
             response: tx,
             return_logprobs: oairequest.logprobs.is_some(),
@@ -277,6 +293,13 @@ pub fn parse_request(
             truncate_sequence: oairequest.truncate_sequence.unwrap_or(false),
             session_id: None,
             files: None,
+            input_files: None,
+            priority: oairequest.priority.unwrap_or_default(),
+            timeout: request_timeout(oairequest.timeout)?,
+            trace_context: None,
+            user: None,
+            prediction: oairequest.prediction.as_ref().map(Prediction::text),
+            agentic_permit: None,
         })),
         is_streaming,
     ))
@@ -296,17 +319,24 @@ pub async fn completions(
 ) -> CompletionResponder {
     let (tx, mut rx) = create_response_channel(None);
 
+    let stream_options = oairequest.stream_options.unwrap_or_default();
     let (request, is_streaming) = match parse_request(oairequest, state.clone(), tx) {
         Ok(x) => x,
         Err(e) => return handle_error(state, e.into()),
     };
 
     if let Err(e) = send_request(&state, request).await {
-        return handle_error(state, e.into());
+        return handle_error(state, boxed_send_error(e));
     }
 
     if is_streaming {
-        CompletionResponder::Sse(create_streamer(rx, state, None, None))
+        CompletionResponder::Sse(create_streamer_with_options(
+            rx,
+            state,
+            stream_options,
+            None,
+            None,
+        ))
     } else {
         process_non_streaming_response(&mut rx, state).await
     }
@@ -327,7 +357,19 @@ pub fn create_streamer(
     on_chunk: Option<CompletionOnChunkCallback>,
     on_done: Option<CompletionOnDoneCallback>,
 ) -> Sse<KeepAliveStream<CompletionStreamer>> {
-    let streamer = base_create_streamer(rx, state, on_chunk, on_done);
+    create_streamer_with_options(rx, state, StreamOptions::default(), on_chunk, on_done)
+}
+
+/// Creates a SSE streamer that reports usage as requested by `stream_options`.
+pub fn create_streamer_with_options(
+    rx: Receiver<Response>,
+    state: SharedMistralRsState,
+    stream_options: StreamOptions,
+    on_chunk: Option<CompletionOnChunkCallback>,
+    on_done: Option<CompletionOnDoneCallback>,
+) -> Sse<KeepAliveStream<CompletionStreamer>> {
+    let mut streamer = base_create_streamer(rx, state, on_chunk, on_done);
+    streamer.stream_options = stream_options;
     let keep_alive_interval = get_keep_alive_interval();
 
     Sse::new(streamer)
@@ -365,6 +407,7 @@ pub fn match_responses(state: SharedMistralRsState, response: Response) -> Compl
         Response::ModelError(_, _) => unreachable!(),
         Response::ImageGeneration(_) => unreachable!(),
         Response::Speech { .. } => unreachable!(),
+        Response::SpeechChunk { .. } => unreachable!(),
         Response::Raw { .. } => unreachable!(),
         Response::Embeddings { .. } => unreachable!(),
         Response::AgenticToolCallProgress { .. } => unreachable!(),
@@ -9,14 +9,15 @@ use axum::{
 use base64::{prelude::BASE64_STANDARD, Engine};
 use futures::future::join_all;
 use mistralrs_core::{
-    Constraint, MistralRs, NormalRequest, Request, RequestMessage, Response, SamplingParams,
+    Constraint, MistralRs, NormalRequest, Request, RequestMessage, RequestPriority, Response,
+    SamplingParams,
 };
 use tokio::sync::mpsc::Receiver;
 
 use crate::{
     handler_core::{
-        base_process_non_streaming_response, create_response_channel, send_request_with_model,
-        ErrorToResponse, JsonError,
+        base_process_non_streaming_response, create_response_channel, overloaded_response,
+        send_request_with_model, ErrorToResponse, JsonError,
     },
     openai::{
         EmbeddingData, EmbeddingEncodingFormat, EmbeddingInput, EmbeddingRequest,
@@ -33,20 +34,21 @@ pub enum EmbeddingResponder {
     ValidationError(AnyhowError),
 }
 
-struct EmbeddingWithUsage {
-    embedding: Vec<f32>,
-    prompt_tokens: usize,
-    total_tokens: usize,
+pub(crate) struct EmbeddingWithUsage {
+    pub(crate) embedding: Vec<f32>,
+    pub(crate) prompt_tokens: usize,
+    pub(crate) total_tokens: usize,
 }
 
 impl IntoResponse for EmbeddingResponder {
     fn into_response(self) -> axum::response::Response {
         match self {
             EmbeddingResponder::Json(s) => Json(s).into_response(),
-            EmbeddingResponder::InternalError(e) => {
-                JsonError::new(sanitize_error_message(e.root_cause()))
-                    .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
-            }
+            EmbeddingResponder::InternalError(e) => overloaded_response(e.downcast_ref())
+                .unwrap_or_else(|| {
+                    JsonError::new(sanitize_error_message(e.root_cause()))
+                        .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
+                }),
             EmbeddingResponder::ValidationError(e) => {
                 JsonError::new(sanitize_error_message(e.root_cause()))
                     .to_response(http::StatusCode::UNPROCESSABLE_ENTITY)
@@ -95,6 +97,7 @@ pub async fn embeddings(
         Some(oairequest.model.clone())
     };
 
+    let priority = oairequest.priority.unwrap_or_default();
     let encoding = oairequest.encoding_format.unwrap_or_default();
     let return_base64 = matches!(encoding, EmbeddingEncodingFormat::Base64);
 
@@ -113,6 +116,7 @@ pub async fn embeddings(
                         prompt,
                         model_override.as_deref(),
                         oairequest.truncate_sequence.unwrap_or(false),
+                        priority,
                     )
                     .await
                 }
@@ -156,6 +160,7 @@ pub async fn embeddings(
                         tokens,
                         model_override.as_deref(),
                         oairequest.truncate_sequence.unwrap_or(false),
+                        priority,
                     )
                     .await
                 }
@@ -238,11 +243,12 @@ fn normalize_inputs(input: EmbeddingInput) -> Result<Inputs> {
     }
 }
 
-async fn fetch_embedding(
+pub(crate) async fn fetch_embedding(
     state: SharedMistralRsState,
     prompt: String,
     model_id: Option<&str>,
     truncate_sequence: bool,
+    priority: RequestPriority,
 ) -> Result<EmbeddingWithUsage> {
     let (tx, mut rx) = create_response_channel(Some(1));
 
@@ -272,6 +278,13 @@ async fn fetch_embedding(
         truncate_sequence,
         session_id: None,
         files: None,
+        input_files: None,
+        priority,
+        timeout: None,
+        trace_context: None,
+        user: None,
+        prediction: None,
+        agentic_permit: None,
     }));
 
     send_request_with_model(&state, request, model_id)
@@ -286,6 +299,7 @@ async fn fetch_embedding_tokens(
     tokens: Vec<u32>,
     model_id: Option<&str>,
     truncate_sequence: bool,
+    priority: RequestPriority,
 ) -> Result<EmbeddingWithUsage> {
     let (tx, mut rx) = create_response_channel(Some(1));
 
@@ -315,6 +329,13 @@ async fn fetch_embedding_tokens(
         truncate_sequence,
         session_id: None,
         files: None,
+        input_files: None,
+        priority,
+        timeout: None,
+        trace_context: None,
+        user: None,
+        prediction: None,
+        agentic_permit: None,
     }));
 
     send_request_with_model(&state, request, model_id)
@@ -350,6 +371,7 @@ async fn process_embedding_response(
             | Response::CompletionModelError(_, _)
             | Response::ImageGeneration(_)
             | Response::Speech { .. }
+            | Response::SpeechChunk { .. }
             | Response::Raw { .. }
             | Response::AgenticToolCallProgress { .. }
             | Response::AgenticToolApprovalRequired { .. }
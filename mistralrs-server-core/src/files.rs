@@ -1,18 +1,43 @@
-//! OpenAI-compatible Files endpoints. Read/list/delete only; files arrive via agentic tool calls, not uploads.
+//! OpenAI-compatible Files endpoints. Files come from agentic tool calls, batch results, or
+//! multipart uploads (`POST /v1/files`). Uploaded ids can be referenced from chat messages with a
+//! `{"type": "file", "file": {"file_id": ...}}` content part.
 
+use std::{path::Path as FsPath, sync::Arc, time::Duration};
+
+use anyhow::Context;
 use axum::{
-    extract::{Path, State},
+    extract::{multipart::Field, Multipart, Path, State},
     http::{header, StatusCode},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use base64::{engine::general_purpose::STANDARD, Engine};
 use mistralrs_core::{File as CoreFile, FileContent};
 use serde::Serialize;
 
-use crate::types::{ExtractedMistralRsState, SharedMistralRsState};
+use crate::{
+    auth::{authenticated_key, AuthenticatedKey},
+    types::{ExtractedMistralRsState, SharedMistralRsState},
+};
 
 const PURPOSE: &str = "agent_output";
+const BATCH_OUTPUT_PURPOSE: &str = "batch_output";
+
+/// Purposes accepted by `POST /v1/files`.
+pub const UPLOAD_PURPOSES: &[&str] = &[
+    "assistants",
+    "batch",
+    "evals",
+    "fine-tune",
+    "user_data",
+    "vision",
+];
+
+/// Largest accepted upload. Files are held in memory by the engine's file store.
+pub const MAX_UPLOAD_BYTES: usize = 100 * 1024 * 1024;
+
+/// Uploads outlive agent outputs so they can be referenced across requests.
+pub const UPLOAD_FILE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
 
 /// OpenAI file metadata + mistral.rs extensions (`format`, `mime_type`, `source`, `truncated`).
 #[derive(Serialize)]
@@ -22,7 +47,7 @@ pub struct FileMetadata {
     pub bytes: u64,
     pub created_at: u64,
     pub filename: String,
-    pub purpose: &'static str,
+    pub purpose: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub format: Option<String>,
     pub mime_type: String,
@@ -38,8 +63,26 @@ pub struct SourceMeta {
     pub turn: usize,
 }
 
-pub async fn get_file(State(state): ExtractedMistralRsState, Path(id): Path<String>) -> Response {
-    match state.find_file(&id) {
+/// Whether `key` may see `file`. Everything is visible when authentication is off.
+pub(crate) fn is_visible(file: &CoreFile, key: Option<&AuthenticatedKey>) -> bool {
+    key.is_none_or(|key| key.can_access(file.owner.as_deref()))
+}
+
+/// Look up a file the caller may see. Files owned by other keys are reported as missing.
+pub(crate) fn find_visible_file(
+    state: &SharedMistralRsState,
+    id: &str,
+    key: Option<&AuthenticatedKey>,
+) -> Option<Arc<CoreFile>> {
+    state.find_file(id).filter(|f| is_visible(f, key))
+}
+
+pub async fn get_file(
+    State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
+    Path(id): Path<String>,
+) -> Response {
+    match find_visible_file(&state, &id, key.as_deref()) {
         Some(f) => Json(metadata(&f)).into_response(),
         None => not_found(&id),
     }
@@ -47,23 +90,160 @@ pub async fn get_file(State(state): ExtractedMistralRsState, Path(id): Path<Stri
 
 pub async fn get_file_content(
     State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
     Path(id): Path<String>,
 ) -> Response {
-    serve_bytes(state, &id).unwrap_or_else(|(code, msg)| {
+    serve_bytes(state, &id, key.as_deref()).unwrap_or_else(|(code, msg)| {
         (code, [(header::CONTENT_TYPE, "application/json")], msg).into_response()
     })
 }
 
-pub async fn list_files(State(state): ExtractedMistralRsState) -> Response {
-    let data: Vec<FileMetadata> = state.list_files().iter().map(|f| metadata(f)).collect();
+pub async fn list_files(
+    State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
+) -> Response {
+    let data: Vec<FileMetadata> = state
+        .list_files()
+        .iter()
+        .filter(|f| is_visible(f, key.as_deref()))
+        .map(|f| metadata(f))
+        .collect();
     Json(serde_json::json!({ "object": "list", "data": data })).into_response()
 }
 
+/// `POST /v1/files`. Multipart form with a `file` part and a `purpose` field.
+pub async fn upload_file(
+    State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
+    mut multipart: Multipart,
+) -> Response {
+    let mut purpose = None;
+    let mut upload = None;
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return error_response(e.status(), &e.body_text(), "invalid_multipart"),
+        };
+        let field_name = field.name().map(str::to_string);
+        match field_name.as_deref() {
+            Some("purpose") => match field.text().await {
+                Ok(text) => purpose = Some(text.trim().to_string()),
+                Err(e) => return error_response(e.status(), &e.body_text(), "invalid_multipart"),
+            },
+            Some("file") => match read_file_part(field, MAX_UPLOAD_BYTES).await {
+                Ok(part) => upload = Some(part),
+                Err((status, message)) => {
+                    let code = if status == StatusCode::PAYLOAD_TOO_LARGE {
+                        "file_too_large"
+                    } else {
+                        "invalid_multipart"
+                    };
+                    return error_response(status, &message, code);
+                }
+            },
+            // OpenAI clients may send extra fields (e.g. `expires_after`); ignore them.
+            _ => {}
+        }
+    }
+
+    let Some(purpose) = purpose else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "Missing required field 'purpose'.",
+            "missing_purpose",
+        );
+    };
+    if !UPLOAD_PURPOSES.contains(&purpose.as_str()) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Invalid purpose '{purpose}'. Expected one of: {}.",
+                UPLOAD_PURPOSES.join(", ")
+            ),
+            "invalid_purpose",
+        );
+    }
+    let Some(UploadedPart {
+        name,
+        content_type,
+        data,
+    }) = upload
+    else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "Missing required field 'file'.",
+            "missing_file",
+        );
+    };
+    if data.is_empty() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "Uploaded file is empty.",
+            "empty_file",
+        );
+    }
+
+    let mut file = CoreFile::from_upload(&name, content_type.as_deref(), &data, purpose);
+    file.owner = key.map(|Extension(key)| key.name);
+    let meta = metadata(&file);
+    if let Err(e) = state.add_file(None, file, Some(UPLOAD_FILE_TTL)) {
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &e.to_string(),
+            "internal_error",
+        );
+    }
+    Json(meta).into_response()
+}
+
+/// A file part read from a multipart form.
+pub(crate) struct UploadedPart {
+    /// Final path component of the part's filename.
+    pub(crate) name: String,
+    pub(crate) content_type: Option<String>,
+    pub(crate) data: Vec<u8>,
+}
+
+/// Read a multipart file part into memory. Fails with 413 once it exceeds `limit` bytes.
+pub(crate) async fn read_file_part(
+    mut field: Field<'_>,
+    limit: usize,
+) -> Result<UploadedPart, (StatusCode, String)> {
+    // Keep only the final path component; the name is reused in the code-execution workdir.
+    let name = field
+        .file_name()
+        .and_then(|n| FsPath::new(n).file_name())
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "upload".to_string());
+    let content_type = field.content_type().map(str::to_string);
+    let mut data = Vec::new();
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| (e.status(), e.body_text()))?
+    {
+        if data.len() + chunk.len() > limit {
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("File exceeds the {limit} byte upload limit."),
+            ));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(UploadedPart {
+        name,
+        content_type,
+        data,
+    })
+}
+
 pub async fn delete_file(
     State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
     Path(id): Path<String>,
 ) -> Response {
-    if !state.remove_file(&id) {
+    if find_visible_file(&state, &id, key.as_deref()).is_none() || !state.remove_file(&id) {
         return not_found(&id);
     }
     Json(serde_json::json!({
@@ -81,7 +261,13 @@ fn metadata(f: &CoreFile) -> FileMetadata {
         bytes: f.bytes,
         created_at: f.created_at,
         filename: f.name.clone(),
-        purpose: PURPOSE,
+        purpose: match &f.purpose {
+            Some(purpose) => purpose.clone(),
+            None if f.source.tool == crate::batches::BATCH_FILE_SOURCE => {
+                BATCH_OUTPUT_PURPOSE.to_string()
+            }
+            None => PURPOSE.to_string(),
+        },
         format: f.format.clone(),
         mime_type: f
             .mime_type
@@ -96,8 +282,12 @@ fn metadata(f: &CoreFile) -> FileMetadata {
     }
 }
 
-fn serve_bytes(state: SharedMistralRsState, id: &str) -> Result<Response, (StatusCode, String)> {
-    let file = state.find_file(id).ok_or_else(|| {
+fn serve_bytes(
+    state: SharedMistralRsState,
+    id: &str,
+    key: Option<&AuthenticatedKey>,
+) -> Result<Response, (StatusCode, String)> {
+    let file = find_visible_file(&state, id, key).ok_or_else(|| {
         (
             StatusCode::NOT_FOUND,
             json_error("file not found or expired"),
@@ -154,15 +344,123 @@ fn serve_bytes(state: SharedMistralRsState, id: &str) -> Result<Response, (Statu
         .into_response())
 }
 
+/// How an uploaded file referenced from a chat message reaches the model.
+pub(crate) enum Attachment {
+    /// Data URL for an image content part.
+    Image(String),
+    /// Data URL for an audio content part.
+    Audio(String),
+    /// Data URL for a video content part.
+    Video(String),
+    /// The body of a text file, or a note describing a binary file the model can only reach
+    /// through tools.
+    Text(String),
+}
+
+/// Resolve a `file` content part. The file itself is also returned so the request can hand it
+/// to the agentic loop as an input.
+pub(crate) fn resolve_attachment(
+    state: &SharedMistralRsState,
+    file_id: &str,
+    code_execution: bool,
+) -> anyhow::Result<(Attachment, CoreFile)> {
+    let file = find_visible_file(state, file_id, authenticated_key().as_ref())
+        .with_context(|| format!("File '{file_id}' not found or expired"))?;
+    if let FileContent::Error { message, .. } = &file.content {
+        anyhow::bail!("File '{file_id}' is unavailable: {message}");
+    }
+    let mime = file
+        .mime_type
+        .clone()
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    let data_url = file
+        .binary_data()
+        .map(|b64| format!("data:{mime};base64,{b64}"));
+
+    let attachment = match data_url {
+        Some(url) if file.is_image() => Attachment::Image(url),
+        Some(url) if file.is_audio() => Attachment::Audio(url),
+        Some(url) if file.is_video() => Attachment::Video(url),
+        _ => match file.as_text() {
+            Some(text) => Attachment::Text(inline_text(&file, text, code_execution)),
+            None if file.is_truncated() => {
+                anyhow::bail!("File '{file_id}' body is not available")
+            }
+            None => {
+                let location = if code_execution {
+                    format!(
+                        " It is available as `{}` in the code execution working directory.",
+                        file.name
+                    )
+                } else {
+                    String::new()
+                };
+                Attachment::Text(format!(
+                    "[Attached file: {} ({mime}, {} bytes, id={}).{location}]",
+                    file.name, file.bytes, file.id
+                ))
+            }
+        },
+    };
+    Ok((attachment, (*file).clone()))
+}
+
+/// Max bytes of an attached text file inlined into the prompt, about 16k tokens. Longer files
+/// are cut with a visible note.
+const ATTACHMENT_INLINE_BYTES: usize = 64 * 1024;
+
+/// Wrap a text file for the prompt, cut to [`ATTACHMENT_INLINE_BYTES`].
+fn inline_text(file: &CoreFile, text: &str, code_execution: bool) -> String {
+    let shown = CoreFile::truncate_utf8(text, ATTACHMENT_INLINE_BYTES);
+    let note = if shown.len() < text.len() {
+        let location = if code_execution {
+            format!(
+                " The full file is `{}` in the code execution working directory.",
+                file.name
+            )
+        } else {
+            String::new()
+        };
+        format!(
+            "\n[Truncated: showing the first {} of {} bytes.{location}]",
+            shown.len(),
+            text.len()
+        )
+    } else {
+        String::new()
+    };
+    format!(
+        "\n<file name=\"{}\" id=\"{}\">\n{shown}{note}\n</file>\n",
+        escape_attr(&file.name),
+        escape_attr(&file.id)
+    )
+}
+
+fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 fn not_found(id: &str) -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        &format!("File '{id}' not found or expired"),
+        "file_not_found",
+    )
+}
+
+fn error_response(status: StatusCode, message: &str, code: &str) -> Response {
     let body = serde_json::json!({
         "error": {
-            "message": format!("File '{id}' not found or expired"),
+            "message": message,
             "type": "invalid_request_error",
-            "code": "file_not_found",
+            "code": code,
         }
     });
-    (StatusCode::NOT_FOUND, Json(body)).into_response()
+    (status, Json(body)).into_response()
 }
 
 fn json_error(msg: &str) -> String {
@@ -207,3 +505,28 @@ fn percent_encode_filename(name: &str) -> String {
     }
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_text_is_capped_and_escaped() {
+        let text = "é".repeat(ATTACHMENT_INLINE_BYTES);
+        let file = CoreFile::from_upload(
+            "a\"><b>.txt",
+            Some("text/plain"),
+            text.as_bytes(),
+            "user_data",
+        );
+        let inlined = inline_text(&file, &text, false);
+        assert!(inlined.contains("name=\"a&quot;&gt;&lt;b&gt;.txt\""));
+        assert!(inlined.contains(&format!(
+            "[Truncated: showing the first {ATTACHMENT_INLINE_BYTES} of {} bytes.]",
+            text.len()
+        )));
+
+        let inlined = inline_text(&file, "short", false);
+        assert!(inlined.contains("\nshort\n</file>"));
+    }
+}
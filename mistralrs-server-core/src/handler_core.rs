@@ -1,12 +1,18 @@
 //! Core functionality for handlers.
 
+use std::time::Duration;
+
 use anyhow::{Context, Result};
-use axum::{extract::Json, http::StatusCode, response::IntoResponse};
-use mistralrs_core::{Request, Response};
+use axum::{
+    extract::Json,
+    http::{header::RETRY_AFTER, HeaderValue, StatusCode},
+    response::IntoResponse,
+};
+use mistralrs_core::{current_trace_context, MistralRsError, Request, Response};
 use serde::Serialize;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 
-use crate::types::SharedMistralRsState;
+use crate::{auth::authenticated_key_name, types::SharedMistralRsState};
 
 /// Default buffer size for the response channel used in streaming operations.
 ///
@@ -93,9 +99,29 @@ pub async fn send_request(state: &SharedMistralRsState, request: Request) -> Res
 
 pub async fn send_request_with_model(
     state: &SharedMistralRsState,
-    request: Request,
+    mut request: Request,
     model_id: Option<&str>,
 ) -> Result<()> {
+    // Record the request under the HTTP span set up by `telemetry::trace_http_request`, and
+    // attribute it to the API key that authenticated it.
+    if let Request::Normal(request) = &mut request {
+        if request.trace_context.is_none() {
+            request.trace_context = current_trace_context();
+        }
+        // The key name also decides who owns tool files and sessions, so it always wins.
+        if let Some(name) = authenticated_key_name() {
+            request.user = Some(name);
+        }
+    }
+
+    // Returned without context so handlers can downcast it and answer 429. Other failures are
+    // reported by `get_sender` below.
+    if let Err(e @ MistralRsError::Overloaded { .. }) =
+        state.check_admission(&mut request, model_id)
+    {
+        return Err(e.into());
+    }
+
     let sender = state
         .get_sender(model_id)
         .context("mistral.rs sender not available.")?;
@@ -106,7 +132,69 @@ pub async fn send_request_with_model(
         .context("Failed to send request to model pipeline")
 }
 
+/// Boxes an error from [`send_request`], keeping admission rejections downcastable to
+/// [`MistralRsError`].
+pub(crate) fn boxed_send_error(e: anyhow::Error) -> Box<dyn std::error::Error + Send + Sync> {
+    if overloaded(e.downcast_ref()).is_some() {
+        if let Ok(e) = e.downcast::<MistralRsError>() {
+            return Box::new(e);
+        }
+    }
+    e.into()
+}
+
+/// The reason and retry delay of an admission rejection.
+pub(crate) fn overloaded(e: Option<&MistralRsError>) -> Option<(&str, Duration)> {
+    match e? {
+        MistralRsError::Overloaded {
+            reason,
+            retry_after,
+            ..
+        } => Some((reason, *retry_after)),
+        _ => None,
+    }
+}
+
+/// Sets `Retry-After` on `response`, in whole seconds.
+pub(crate) fn with_retry_after(
+    mut response: axum::response::Response,
+    retry_after: Duration,
+) -> axum::response::Response {
+    let secs = retry_after.as_secs().max(1);
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(secs));
+    response
+}
+
+/// The `429 Too Many Requests` response for an admission rejection, if `e` is one. Uses the
+/// OpenAI error shape with code `queue_full`.
+pub(crate) fn overloaded_response(e: Option<&MistralRsError>) -> Option<axum::response::Response> {
+    let (reason, retry_after) = overloaded(e)?;
+    let body = serde_json::json!({
+        "error": {
+            "message": reason,
+            "type": "rate_limit_error",
+            "code": "queue_full",
+        }
+    });
+    let response = (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response();
+    Some(with_retry_after(response, retry_after))
+}
+
+/// Converts a request's `timeout` in seconds into the engine's per-request deadline.
+pub(crate) fn request_timeout(secs: Option<f64>) -> Result<Option<Duration>> {
+    secs.map(|secs| {
+        Duration::try_from_secs_f64(secs)
+            .map_err(|_| anyhow::anyhow!("`timeout` must be a non-negative number of seconds."))
+    })
+    .transpose()
+}
+
 /// Generic function to process non-streaming responses.
+///
+/// Client disconnects need no handling here: the server drops the handler future, and with it
+/// `rx`, so the engine sees a closed response channel and cancels the sequence on its next step.
 pub(crate) async fn base_process_non_streaming_response<R, M, E>(
     rx: &mut Receiver<Response>,
     state: SharedMistralRsState,
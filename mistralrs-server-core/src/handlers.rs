@@ -4,17 +4,20 @@ use anyhow::Result;
 use axum::extract::Path;
 use axum::extract::{Json, State};
 use axum::http::StatusCode;
+use axum::Extension;
 use mistralrs_core::{
     auto_tune, collect_system_info, parse_isq_value, run_doctor, AutoDeviceMapParams,
     AutoTuneRequest, AutoTuneResult, MistralRs, MistralRsError, ModelDType, ModelSelected,
-    ModelStatus as CoreModelStatus, Request, SerializedSession, TokenSource, TuneProfile,
+    ModelStatus as CoreModelStatus, QueueDepth, Request, SerializedSession, SessionInfo,
+    TokenSource, TuneProfile,
 };
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use crate::{
+    auth::AuthenticatedKey,
     openai::{ModelObject, ModelObjects},
-    types::ExtractedMistralRsState,
+    types::{ExtractedMistralRsState, SharedMistralRsState},
 };
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema)]
@@ -165,6 +168,9 @@ pub struct ModelStatusResponse {
     /// Error message when status indicates an error condition
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Queue depth of a loaded model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue: Option<QueueDepth>,
 }
 
 #[utoipa::path(
@@ -187,6 +193,7 @@ pub async fn unload_model(
             model_id,
             status: ModelStatus::Unloaded,
             error: None,
+            queue: None,
         }),
         Err(e) => {
             let (status, error) = match &e {
@@ -199,6 +206,7 @@ pub async fn unload_model(
                 model_id,
                 status,
                 error,
+                queue: None,
             })
         }
     }
@@ -224,6 +232,7 @@ pub async fn reload_model(
             model_id,
             status: ModelStatus::Loaded,
             error: None,
+            queue: None,
         }),
         Err(e) => {
             let (status, error) = match &e {
@@ -239,6 +248,7 @@ pub async fn reload_model(
                 model_id,
                 status,
                 error,
+                queue: None,
             })
         }
     }
@@ -266,21 +276,28 @@ pub async fn get_model_status(
                 CoreModelStatus::Unloaded => ModelStatus::Unloaded,
                 CoreModelStatus::Reloading => ModelStatus::Reloading,
             };
+            let queue = match core_status {
+                CoreModelStatus::Loaded => state.queue_depth(Some(&model_id)).ok(),
+                _ => None,
+            };
             Json(ModelStatusResponse {
                 model_id,
                 status,
                 error: None,
+                queue,
             })
         }
         Ok(None) => Json(ModelStatusResponse {
             model_id,
             status: ModelStatus::NotFound,
             error: None,
+            queue: None,
         }),
         Err(e) => Json(ModelStatusResponse {
             model_id,
             status: ModelStatus::InternalError,
             error: Some(e.to_string()),
+            queue: None,
         }),
     }
 }
@@ -389,7 +406,40 @@ pub async fn tune_model(
         .map_err(|err| err.to_string())
 }
 
-/// GET `/v1/sessions/{session_id}`. 404 if the session doesn't exist.
+/// Response body of `GET /v1/sessions`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionList {
+    pub object: &'static str,
+    pub data: Vec<SessionInfo>,
+}
+
+/// GET `/v1/sessions`. Sessions the caller may see, in memory or on disk, most recently updated
+/// first.
+#[utoipa::path(
+    get,
+    tag = "Mistral.rs",
+    path = "/v1/sessions",
+    responses((status = 200, description = "Stored agentic sessions", body = SessionList))
+)]
+pub async fn list_sessions(
+    State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
+) -> Result<Json<SessionList>, (StatusCode, String)> {
+    on_session_store(&state, |state| state.list_sessions(None))
+        .await?
+        .map(|sessions| {
+            Json(SessionList {
+                object: "list",
+                data: sessions
+                    .into_iter()
+                    .filter(|info| session_visible(info.owner.as_deref(), key.as_deref()))
+                    .collect(),
+            })
+        })
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// GET `/v1/sessions/{session_id}`. 404 if the session doesn't exist or belongs to another key.
 #[utoipa::path(
     get,
     tag = "Mistral.rs",
@@ -402,19 +452,58 @@ pub async fn tune_model(
 )]
 pub async fn get_session(
     State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
     Path(session_id): Path<String>,
 ) -> Result<Json<SerializedSession>, (StatusCode, String)> {
-    match state.export_session(None, &session_id) {
-        Ok(Some(session)) => Ok(Json(session)),
-        Ok(None) => Err((
-            StatusCode::NOT_FOUND,
-            format!("Session {session_id} not found"),
-        )),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
-    }
+    find_session(&state, &session_id, key.as_deref())
+        .await?
+        .map(Json)
+        .ok_or_else(|| session_not_found(&session_id))
 }
 
-/// PUT `/v1/sessions/{session_id}`. Replaces any existing session.
+/// Run a session call on a blocking thread, since it may read or write the storage backend.
+async fn on_session_store<T, F>(
+    state: &SharedMistralRsState,
+    call: F,
+) -> Result<T, (StatusCode, String)>
+where
+    T: Send + 'static,
+    F: FnOnce(&MistralRs) -> T + Send + 'static,
+{
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || call(&state))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Whether `key` may see a session owned by `owner`. Everything is visible when authentication
+/// is off.
+fn session_visible(owner: Option<&str>, key: Option<&AuthenticatedKey>) -> bool {
+    key.is_none_or(|key| key.can_access(owner))
+}
+
+/// Export a session the caller may see. Sessions of other keys are reported as missing.
+async fn find_session(
+    state: &SharedMistralRsState,
+    session_id: &str,
+    key: Option<&AuthenticatedKey>,
+) -> Result<Option<SerializedSession>, (StatusCode, String)> {
+    let id = session_id.to_string();
+    on_session_store(state, move |state| state.export_session(None, &id))
+        .await?
+        .map(|session| session.filter(|s| session_visible(s.owner.as_deref(), key)))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+fn session_not_found(session_id: &str) -> (StatusCode, String) {
+    (
+        StatusCode::NOT_FOUND,
+        format!("Session {session_id} not found"),
+    )
+}
+
+/// PUT `/v1/sessions/{session_id}`. Replaces any existing session. The imported session and its
+/// files belong to the calling key; another key's session can't be replaced.
 #[utoipa::path(
     put,
     tag = "Mistral.rs",
@@ -424,20 +513,39 @@ pub async fn get_session(
     responses(
         (status = 200, description = "Session imported"),
         (status = 400, description = "Invalid session payload"),
+        (status = 404, description = "Session belongs to another key"),
     )
 )]
 pub async fn put_session(
     State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
     Path(session_id): Path<String>,
-    Json(session): Json<SerializedSession>,
+    Json(mut session): Json<SerializedSession>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    state
-        .import_session(None, session_id, session)
-        .map(|()| StatusCode::OK)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+    if let Some(Extension(key)) = &key {
+        let id = session_id.clone();
+        if on_session_store(&state, move |state| state.export_session(None, &id))
+            .await?
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .is_some_and(|existing| !key.can_access(existing.owner.as_deref()))
+        {
+            return Err(session_not_found(&session_id));
+        }
+        session.owner = Some(key.name.clone());
+        for file in &mut session.files {
+            file.owner = Some(key.name.clone());
+        }
+    }
+    on_session_store(&state, move |state| {
+        state.import_session(None, session_id, session)
+    })
+    .await?
+    .map(|()| StatusCode::OK)
+    .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
 }
 
-/// DELETE `/v1/sessions/{session_id}`. Idempotent: returns 200 either way.
+/// DELETE `/v1/sessions/{session_id}`. Idempotent: returns 200 either way. Sessions of other
+/// keys are left alone.
 #[utoipa::path(
     delete,
     tag = "Mistral.rs",
@@ -447,10 +555,17 @@ pub async fn put_session(
 )]
 pub async fn delete_session(
     State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
     Path(session_id): Path<String>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    state
-        .delete_session(None, &session_id)
+    if find_session(&state, &session_id, key.as_deref())
+        .await?
+        .is_none()
+    {
+        return Ok(StatusCode::OK);
+    }
+    on_session_store(&state, move |state| state.delete_session(None, &session_id))
+        .await?
         .map(|_| StatusCode::OK)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
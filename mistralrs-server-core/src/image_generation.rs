@@ -1,29 +1,57 @@
-//! ## Image generation functionality and route handler.
+//! ## Image generation functionality and route handlers.
+//!
+//! `POST /v1/images/generations` is text-to-image. `POST /v1/images/edits` and
+//! `POST /v1/images/variations` take a multipart image upload and denoise from it instead of
+//! from pure noise, repainting only the masked region when a mask is given.
 
 use std::{error::Error, sync::Arc};
 
 use anyhow::Result;
 use axum::{
-    extract::{Json, State},
-    http::{self},
-    response::IntoResponse,
+    extract::{Json, Multipart, State},
+    http::{self, StatusCode},
+    response::{IntoResponse, Response as AxumResponse},
+    Extension,
 };
+use image::{DynamicImage, GenericImageView};
 use mistralrs_core::{
-    Constraint, DiffusionGenerationParams, ImageGenerationResponse, MistralRs, NormalRequest,
-    Request, RequestMessage, Response, SamplingParams,
+    Constraint, DiffusionGenerationParams, DiffusionImageInput, ImageGenerationResponse,
+    ImageGenerationResponseFormat, MistralRs, ModelCategory, NormalRequest, Request,
+    RequestMessage, RequestPriority, Response, SamplingParams,
 };
 use tokio::sync::mpsc::{Receiver, Sender};
 
 use crate::{
+    auth::AuthenticatedKey,
+    files::{read_file_part, MAX_UPLOAD_BYTES},
     handler_core::{
-        base_process_non_streaming_response, create_response_channel, send_request,
-        ErrorToResponse, JsonError,
+        base_process_non_streaming_response, boxed_send_error, create_response_channel,
+        overloaded_response, send_request, ErrorToResponse, JsonError,
     },
     openai::ImageGenerationRequest,
     types::{ExtractedMistralRsState, SharedMistralRsState},
-    util::{sanitize_error_message, validate_model_name},
+    util::{sanitize_error_message, validate_model_access, validate_model_name},
 };
 
+/// Longest side of the output when an edit or variation does not specify `size`.
+const MAX_DEFAULT_SIDE: u32 = 1024;
+
+/// Output sides are rounded down to a multiple of this, matching the FLUX latent packing.
+const SIZE_MULTIPLE: u32 = 16;
+
+/// Default `strength` for unmasked edits: enough to follow the prompt, while keeping the
+/// composition of the source.
+const DEFAULT_EDIT_STRENGTH: f64 = 0.75;
+
+/// Default `strength` for variations, which have no prompt to steer towards.
+const DEFAULT_VARIATION_STRENGTH: f64 = 0.6;
+
+/// Most images one request may ask for. They are denoised as a single batch.
+pub const MAX_IMAGES_PER_REQUEST: usize = 10;
+
+/// Most denoising steps one request may ask for.
+pub const MAX_STEPS: usize = 100;
+
 /// Represents different types of image generation responses.
 pub enum ImageGenerationResponder {
     Json(ImageGenerationResponse),
@@ -36,10 +64,11 @@ impl IntoResponse for ImageGenerationResponder {
     fn into_response(self) -> axum::response::Response {
         match self {
             ImageGenerationResponder::Json(s) => Json(s).into_response(),
-            ImageGenerationResponder::InternalError(e) => {
-                JsonError::new(sanitize_error_message(e.as_ref()))
-                    .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
-            }
+            ImageGenerationResponder::InternalError(e) => overloaded_response(e.downcast_ref())
+                .unwrap_or_else(|| {
+                    JsonError::new(sanitize_error_message(e.as_ref()))
+                        .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
+                }),
             ImageGenerationResponder::ValidationError(e) => {
                 JsonError::new(sanitize_error_message(e.as_ref()))
                     .to_response(http::StatusCode::UNPROCESSABLE_ENTITY)
@@ -62,16 +91,64 @@ pub fn parse_request(
 
     // Validate that the requested model matches the loaded model
     validate_model_name(&oairequest.model, state.clone())?;
+    validate_num_images(oairequest.n_choices).map_err(anyhow::Error::msg)?;
+    validate_steps(oairequest.steps).map_err(anyhow::Error::msg)?;
+
+    let generation_params = DiffusionGenerationParams {
+        height: oairequest.height,
+        width: oairequest.width,
+        num_steps: oairequest.steps,
+        guidance_scale: oairequest.guidance_scale,
+        seed: oairequest.seed,
+        negative_prompt: oairequest.negative_prompt,
+        true_cfg_scale: oairequest.true_cfg_scale,
+        num_images: oairequest.n_choices,
+        image_input: None,
+    };
+    Ok(build_request(
+        &state,
+        oairequest.model,
+        oairequest.prompt,
+        oairequest.response_format,
+        generation_params,
+        tx,
+    ))
+}
+
+fn validate_num_images(n: usize) -> std::result::Result<(), String> {
+    if (1..=MAX_IMAGES_PER_REQUEST).contains(&n) {
+        Ok(())
+    } else {
+        Err(format!(
+            "n must be between 1 and {MAX_IMAGES_PER_REQUEST}, got {n}."
+        ))
+    }
+}
+
+fn validate_steps(steps: Option<usize>) -> std::result::Result<(), String> {
+    match steps {
+        Some(steps) if !(1..=MAX_STEPS).contains(&steps) => Err(format!(
+            "steps must be between 1 and {MAX_STEPS}, got {steps}."
+        )),
+        _ => Ok(()),
+    }
+}
 
-    Ok(Request::Normal(Box::new(NormalRequest {
+/// Builds the engine request shared by generations, edits and variations.
+fn build_request(
+    state: &MistralRs,
+    model: String,
+    prompt: String,
+    format: ImageGenerationResponseFormat,
+    generation_params: DiffusionGenerationParams,
+    tx: Sender<Response>,
+) -> Request {
+    Request::Normal(Box::new(NormalRequest {
         id: state.next_request_id(),
         messages: RequestMessage::ImageGeneration {
-            prompt: oairequest.prompt,
-            format: oairequest.response_format,
-            generation_params: DiffusionGenerationParams {
-                height: oairequest.height,
-                width: oairequest.width,
-            },
+            prompt,
+            format,
+            generation_params,
             save_file: None,
         },
         sampling_params: SamplingParams::deterministic(),
@@ -93,15 +170,18 @@ pub fn parse_request(
         agent_approval_notifier: None,
         max_tool_rounds: None,
         tool_dispatch_url: None,
-        model_id: if oairequest.model == "default" {
-            None
-        } else {
-            Some(oairequest.model.clone())
-        },
+        model_id: (model != "default").then_some(model),
         truncate_sequence: false,
         session_id: None,
         files: None,
-    })))
+        input_files: None,
+        priority: RequestPriority::Normal,
+        timeout: None,
+        trace_context: None,
+        user: None,
+        prediction: None,
+        agentic_permit: None,
+    }))
 }
 
 /// Image generation endpoint handler.
@@ -115,19 +195,303 @@ pub fn parse_request(
 pub async fn image_generation(
     State(state): ExtractedMistralRsState,
     Json(oairequest): Json<ImageGenerationRequest>,
-) -> ImageGenerationResponder {
+) -> AxumResponse {
+    if let Err(message) =
+        validate_num_images(oairequest.n_choices).and_then(|()| validate_steps(oairequest.steps))
+    {
+        return JsonError::new(message).to_response(StatusCode::BAD_REQUEST);
+    }
+
     let (tx, mut rx) = create_response_channel(None);
 
     let request = match parse_request(oairequest, state.clone(), tx) {
         Ok(x) => x,
-        Err(e) => return handle_error(state, e.into()),
+        Err(e) => return handle_error(state, e.into()).into_response(),
     };
 
     if let Err(e) = send_request(&state, request).await {
-        return handle_error(state, e.into());
+        return handle_error(state, boxed_send_error(e)).into_response();
     }
 
-    process_non_streaming_response(&mut rx, state).await
+    process_non_streaming_response(&mut rx, state)
+        .await
+        .into_response()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ImageEditTask {
+    Edit,
+    Variation,
+}
+
+/// A parsed `/v1/images/edits` or `/v1/images/variations` upload.
+struct ImageEditRequest {
+    model: String,
+    prompt: Option<String>,
+    image: DynamicImage,
+    mask: Option<DynamicImage>,
+    response_format: ImageGenerationResponseFormat,
+    /// Output `(width, height)`; the source size when not given.
+    size: Option<(usize, usize)>,
+    strength: Option<f64>,
+    n: usize,
+    steps: Option<usize>,
+    guidance_scale: Option<f64>,
+    seed: Option<u64>,
+    negative_prompt: Option<String>,
+    true_cfg_scale: Option<f64>,
+}
+
+/// Image editing endpoint handler: img2img from `image`, or inpainting when `mask` is given or
+/// `image` has transparent pixels.
+pub async fn image_edits(
+    State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
+    multipart: Multipart,
+) -> AxumResponse {
+    edit_image(
+        state,
+        key.map(|Extension(key)| key),
+        multipart,
+        ImageEditTask::Edit,
+    )
+    .await
+}
+
+/// Image variation endpoint handler. `prompt` is optional here.
+pub async fn image_variations(
+    State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
+    multipart: Multipart,
+) -> AxumResponse {
+    edit_image(
+        state,
+        key.map(|Extension(key)| key),
+        multipart,
+        ImageEditTask::Variation,
+    )
+    .await
+}
+
+async fn edit_image(
+    state: SharedMistralRsState,
+    key: Option<AuthenticatedKey>,
+    multipart: Multipart,
+    task: ImageEditTask,
+) -> AxumResponse {
+    let request = match parse_multipart(multipart).await {
+        Ok(request) => request,
+        Err((status, message)) => return JsonError::new(message).to_response(status),
+    };
+    if let Err(e) = validate_model_name(&request.model, state.clone()) {
+        return JsonError::new(e.to_string()).to_response(StatusCode::BAD_REQUEST);
+    }
+    if let Err(e) = validate_model_access(&request.model, key.as_ref(), &state) {
+        return e.into_response();
+    }
+    let model_id = (request.model != "default").then(|| request.model.clone());
+    if !matches!(
+        state.get_model_category(model_id.as_deref()),
+        Ok(ModelCategory::Diffusion)
+    ) {
+        return JsonError::new("The model does not support image editing.".to_string())
+            .to_response(StatusCode::BAD_REQUEST);
+    }
+    let prompt = match (task, request.prompt) {
+        (_, Some(prompt)) => prompt,
+        (ImageEditTask::Variation, None) => String::new(),
+        (ImageEditTask::Edit, None) => {
+            return JsonError::new("Missing required field 'prompt'.".to_string())
+                .to_response(StatusCode::BAD_REQUEST)
+        }
+    };
+    if let Some(mask) = &request.mask {
+        if mask.dimensions() != request.image.dimensions() {
+            return JsonError::new("The mask must have the same dimensions as the image.".into())
+                .to_response(StatusCode::BAD_REQUEST);
+        }
+    }
+    if let Err(message) =
+        validate_num_images(request.n).and_then(|()| validate_steps(request.steps))
+    {
+        return JsonError::new(message).to_response(StatusCode::BAD_REQUEST);
+    }
+    if let Some(strength) = request.strength {
+        if !(0.0..=1.0).contains(&strength) {
+            return JsonError::new(format!("strength must be between 0 and 1, got {strength}."))
+                .to_response(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let (width, height) = request
+        .size
+        .unwrap_or_else(|| default_size(request.image.dimensions()));
+    let mut image_input = DiffusionImageInput {
+        image: request.image,
+        mask: request.mask,
+        strength: 1.0,
+    };
+    // Inpainting regenerates the masked region from scratch by default; the rest of the image
+    // is kept regardless of strength.
+    image_input.strength =
+        request
+            .strength
+            .unwrap_or_else(|| match (image_input.repaint_mask().is_some(), task) {
+                (true, _) => 1.0,
+                (false, ImageEditTask::Edit) => DEFAULT_EDIT_STRENGTH,
+                (false, ImageEditTask::Variation) => DEFAULT_VARIATION_STRENGTH,
+            });
+    let generation_params = DiffusionGenerationParams {
+        height,
+        width,
+        num_steps: request.steps,
+        guidance_scale: request.guidance_scale,
+        seed: request.seed,
+        negative_prompt: request.negative_prompt,
+        true_cfg_scale: request.true_cfg_scale,
+        num_images: request.n,
+        image_input: Some(image_input),
+    };
+
+    let (tx, mut rx) = create_response_channel(None);
+    let request = build_request(
+        &state,
+        request.model,
+        prompt,
+        request.response_format,
+        generation_params,
+        tx,
+    );
+    if let Err(e) = send_request(&state, request).await {
+        return handle_error(state, boxed_send_error(e)).into_response();
+    }
+    process_non_streaming_response(&mut rx, state)
+        .await
+        .into_response()
+}
+
+async fn parse_multipart(
+    mut multipart: Multipart,
+) -> std::result::Result<ImageEditRequest, (StatusCode, String)> {
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, message);
+    let mut model = None;
+    let mut prompt = None;
+    let mut image = None;
+    let mut mask = None;
+    let mut response_format = ImageGenerationResponseFormat::Url;
+    let mut size = None;
+    let mut strength = None;
+    let mut n = 1;
+    let mut steps = None;
+    let mut guidance_scale = None;
+    let mut seed = None;
+    let mut negative_prompt = None;
+    let mut true_cfg_scale = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| (e.status(), e.body_text()))?
+    {
+        let name = field.name().map(str::to_string);
+        if matches!(name.as_deref(), Some("image" | "image[]" | "mask")) {
+            let part = read_file_part(field, MAX_UPLOAD_BYTES).await?;
+            let decoded = image::load_from_memory(&part.data)
+                .map_err(|e| bad_request(format!("Could not decode {}: {e}", part.name)))?;
+            if name.as_deref() == Some("mask") {
+                mask = Some(decoded);
+            } else if image.is_none() {
+                // Only the first of several `image[]` parts is used.
+                image = Some(decoded);
+            }
+            continue;
+        }
+        let value = field
+            .text()
+            .await
+            .map_err(|e| (e.status(), e.body_text()))?;
+        let value = value.trim().to_string();
+        match name.as_deref() {
+            Some("model") => model = Some(value),
+            Some("prompt") if !value.is_empty() => prompt = Some(value),
+            Some("response_format") => {
+                response_format = match value.as_str() {
+                    "url" | "Url" => ImageGenerationResponseFormat::Url,
+                    "b64_json" | "B64Json" => ImageGenerationResponseFormat::B64Json,
+                    other => {
+                        return Err(bad_request(format!(
+                            "Invalid response_format '{other}'. Expected url or b64_json."
+                        )))
+                    }
+                }
+            }
+            Some("size") => size = parse_size(&value).map_err(bad_request)?,
+            Some("strength") => strength = Some(parse_field("strength", &value)?),
+            Some("n") => n = parse_field("n", &value)?,
+            Some("steps" | "num_inference_steps") => steps = Some(parse_field("steps", &value)?),
+            Some("guidance_scale") => guidance_scale = Some(parse_field("guidance_scale", &value)?),
+            Some("seed") => seed = Some(parse_field("seed", &value)?),
+            Some("negative_prompt") if !value.is_empty() => negative_prompt = Some(value),
+            Some("true_cfg_scale") => true_cfg_scale = Some(parse_field("true_cfg_scale", &value)?),
+            // `quality`, `user` and similar are accepted and ignored.
+            _ => {}
+        }
+    }
+
+    let image = image.ok_or_else(|| bad_request("Missing required field 'image'.".to_string()))?;
+    Ok(ImageEditRequest {
+        model: model.unwrap_or_else(|| "default".to_string()),
+        prompt,
+        image,
+        mask,
+        response_format,
+        size,
+        strength,
+        n,
+        steps,
+        guidance_scale,
+        seed,
+        negative_prompt,
+        true_cfg_scale,
+    })
+}
+
+fn parse_field<T: std::str::FromStr>(
+    name: &str,
+    value: &str,
+) -> std::result::Result<T, (StatusCode, String)> {
+    value.parse().map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid {name} '{value}'."),
+        )
+    })
+}
+
+/// Parses `size` as `WIDTHxHEIGHT`. `auto` keeps the source size.
+fn parse_size(value: &str) -> std::result::Result<Option<(usize, usize)>, String> {
+    if value.is_empty() || value == "auto" {
+        return Ok(None);
+    }
+    let invalid = || format!("Invalid size '{value}'. Expected WIDTHxHEIGHT, e.g. 1024x1024.");
+    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+    let width = width.parse::<usize>().map_err(|_| invalid())?;
+    let height = height.parse::<usize>().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+    Ok(Some((width, height)))
+}
+
+/// Output size for a source image: its own size, scaled down to fit [`MAX_DEFAULT_SIDE`] and
+/// rounded down to a multiple of [`SIZE_MULTIPLE`].
+fn default_size((width, height): (u32, u32)) -> (usize, usize) {
+    let scale = (MAX_DEFAULT_SIDE as f64 / width.max(height).max(1) as f64).min(1.0);
+    let fit = |side: u32| {
+        let side = (side as f64 * scale) as u32;
+        (side / SIZE_MULTIPLE).max(1) * SIZE_MULTIPLE
+    };
+    (fit(width) as usize, fit(height) as usize)
 }
 
 /// Helper function to handle image generation errors and logging them.
@@ -175,6 +539,7 @@ pub fn match_responses(
         Response::Done(_) => unreachable!(),
         Response::ModelError(_, _) => unreachable!(),
         Response::Speech { .. } => unreachable!(),
+        Response::SpeechChunk { .. } => unreachable!(),
         Response::Raw { .. } => unreachable!(),
         Response::Embeddings { .. } => unreachable!(),
         Response::AgenticToolCallProgress { .. } => unreachable!(),
@@ -182,3 +547,33 @@ pub fn match_responses(
         Response::File(_) => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{default_size, parse_size, validate_steps};
+
+    #[test]
+    fn size_parsing() {
+        assert_eq!(parse_size("1024x768"), Ok(Some((1024, 768))));
+        assert_eq!(parse_size("auto"), Ok(None));
+        assert!(parse_size("1024").is_err());
+        assert!(parse_size("0x512").is_err());
+    }
+
+    #[test]
+    fn default_size_fits_and_rounds() {
+        assert_eq!(default_size((512, 512)), (512, 512));
+        assert_eq!(default_size((2048, 1024)), (1024, 512));
+        assert_eq!(default_size((1000, 601)), (992, 592));
+        assert_eq!(default_size((4, 4)), (16, 16));
+    }
+
+    #[test]
+    fn steps_are_bounded() {
+        assert!(validate_steps(None).is_ok());
+        assert!(validate_steps(Some(1)).is_ok());
+        assert!(validate_steps(Some(100)).is_ok());
+        assert!(validate_steps(Some(0)).is_err());
+        assert!(validate_steps(Some(101)).is_err());
+    }
+}
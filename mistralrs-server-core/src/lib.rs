@@ -219,8 +219,11 @@
 //! }
 //! ```
 
+pub mod anthropic;
 pub mod approvals;
+pub mod auth;
 pub mod background_tasks;
+pub mod batches;
 pub mod cached_responses;
 pub mod chat_completion;
 mod completion_core;
@@ -230,14 +233,21 @@ pub mod files;
 pub mod handler_core;
 mod handlers;
 pub mod image_generation;
+pub mod metrics;
 pub mod mistralrs_for_server_builder;
 pub mod mistralrs_server_router_builder;
+pub mod ollama;
 pub mod openai;
 pub mod openapi_doc;
+pub mod realtime;
+pub mod rerank;
 pub mod responses;
 pub mod responses_types;
 pub mod speech_generation;
 pub mod streaming;
+pub mod telemetry;
+pub mod tokenize;
+pub mod transcription;
 pub mod types;
 pub mod util;
 pub mod video;
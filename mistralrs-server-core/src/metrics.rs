@@ -0,0 +1,262 @@
+//! ## Prometheus metrics.
+//!
+//! `GET /metrics` renders every loaded model's [`EngineMetrics`] in the Prometheus text
+//! exposition format (version 0.0.4), labelled by `model`.
+
+use std::fmt::Write;
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use mistralrs_core::{EngineMetrics, HistogramSnapshot};
+
+use crate::types::ExtractedMistralRsState;
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Prometheus scrape endpoint.
+pub async fn metrics(State(state): ExtractedMistralRsState) -> impl IntoResponse {
+    let mut model_ids = match state.list_models() {
+        Ok(ids) => ids,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+    model_ids.sort();
+
+    let per_model: Vec<(String, EngineMetrics)> = model_ids
+        .into_iter()
+        .filter_map(|id| {
+            let logger = state.get_logger(Some(&id)).ok()?;
+            Some((id, logger.metrics()))
+        })
+        .collect();
+
+    (
+        [(header::CONTENT_TYPE, CONTENT_TYPE)],
+        render_metrics(&per_model),
+    )
+        .into_response()
+}
+
+/// Render per-model metrics. Each metric family is emitted once with one sample per model.
+pub fn render_metrics(per_model: &[(String, EngineMetrics)]) -> String {
+    let mut out = String::new();
+
+    let counters: [(&str, &str, fn(&EngineMetrics) -> Option<f64>); 5] = [
+        (
+            "mistralrs_tokens_processed_total",
+            "Prompt and generated tokens processed by the engine.",
+            |m| Some(m.tokens_processed_total as f64),
+        ),
+        (
+            "mistralrs_sequences_total",
+            "Sequences admitted to the scheduler.",
+            |m| Some(m.sequences_total as f64),
+        ),
+        (
+            "mistralrs_prefix_cache_hits_total",
+            "Sequences that reused a cached prefix.",
+            |m| Some(m.prefix_cache_hits_total as f64),
+        ),
+        (
+            "mistralrs_encoder_cache_hits_total",
+            "Multimodal encoder cache hits.",
+            |m| m.encoder_cache.map(|(hits, _)| hits as f64),
+        ),
+        (
+            "mistralrs_encoder_cache_misses_total",
+            "Multimodal encoder cache misses.",
+            |m| m.encoder_cache.map(|(_, misses)| misses as f64),
+        ),
+    ];
+    for (name, help, value) in counters {
+        write_scalar_family(&mut out, name, help, "counter", per_model, value);
+    }
+
+    let gauges: [(&str, &str, fn(&EngineMetrics) -> Option<f64>); 5] = [
+        (
+            "mistralrs_sequences_running",
+            "Sequences currently running.",
+            |m| Some(m.num_running as f64),
+        ),
+        (
+            "mistralrs_sequences_waiting",
+            "Sequences waiting to be scheduled.",
+            |m| Some(m.num_waiting as f64),
+        ),
+        (
+            "mistralrs_waiting_prompt_tokens",
+            "Prompt tokens summed over the waiting sequences.",
+            |m| Some(m.waiting_prompt_tokens as f64),
+        ),
+        (
+            "mistralrs_agentic_loops_running",
+            "Agentic tool loops currently running.",
+            |m| Some(m.agentic_loops as f64),
+        ),
+        (
+            "mistralrs_kv_cache_usage_ratio",
+            "Fraction of paged KV cache blocks in use.",
+            |m| m.kv_cache_usage,
+        ),
+    ];
+    for (name, help, value) in gauges {
+        write_scalar_family(&mut out, name, help, "gauge", per_model, value);
+    }
+
+    let histograms: [(&str, &str, fn(&EngineMetrics) -> &HistogramSnapshot); 4] = [
+        (
+            "mistralrs_time_to_first_token_seconds",
+            "Time from request arrival to the first generated token.",
+            |m| &m.time_to_first_token_seconds,
+        ),
+        (
+            "mistralrs_inter_token_latency_seconds",
+            "Time between consecutive generated tokens of a sequence.",
+            |m| &m.inter_token_latency_seconds,
+        ),
+        (
+            "mistralrs_request_latency_seconds",
+            "Time from request arrival to the sequence finishing.",
+            |m| &m.request_latency_seconds,
+        ),
+        (
+            "mistralrs_agentic_tool_rounds",
+            "Tool rounds run by each agentic request.",
+            |m| &m.agentic_tool_rounds,
+        ),
+    ];
+    for (name, help, value) in histograms {
+        write_histogram_family(&mut out, name, help, per_model, value);
+    }
+
+    out
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn write_scalar_family(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    kind: &str,
+    per_model: &[(String, EngineMetrics)],
+    value: fn(&EngineMetrics) -> Option<f64>,
+) {
+    let samples: Vec<_> = per_model
+        .iter()
+        .filter_map(|(id, m)| value(m).map(|v| (id, v)))
+        .collect();
+    if samples.is_empty() {
+        return;
+    }
+    write_header(out, name, help, kind);
+    for (id, v) in samples {
+        let _ = writeln!(out, "{name}{{model=\"{}\"}} {}", escape_label(id), v);
+    }
+}
+
+fn write_histogram_family(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    per_model: &[(String, EngineMetrics)],
+    value: fn(&EngineMetrics) -> &HistogramSnapshot,
+) {
+    if per_model.is_empty() {
+        return;
+    }
+    write_header(out, name, help, "histogram");
+    for (id, m) in per_model {
+        let h = value(m);
+        let model = escape_label(id);
+        for (bound, count) in h.bounds.iter().zip(&h.cumulative_counts) {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{model=\"{model}\",le=\"{bound}\"}} {count}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{model=\"{model}\",le=\"+Inf\"}} {}",
+            h.count
+        );
+        let _ = writeln!(out, "{name}_sum{{model=\"{model}\"}} {}", h.sum);
+        let _ = writeln!(out, "{name}_count{{model=\"{model}\"}} {}", h.count);
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram(bounds: &[f64], cumulative_counts: &[u64], sum: f64) -> HistogramSnapshot {
+        HistogramSnapshot {
+            bounds: bounds.to_vec(),
+            cumulative_counts: cumulative_counts.to_vec(),
+            count: *cumulative_counts.last().unwrap_or(&0) + 1,
+            sum,
+        }
+    }
+
+    fn sample_metrics() -> EngineMetrics {
+        EngineMetrics {
+            tokens_processed_total: 120,
+            sequences_total: 3,
+            prefix_cache_hits_total: 1,
+            num_running: 2,
+            num_waiting: 0,
+            waiting_prompt_tokens: 0,
+            agentic_loops: 0,
+            encoder_cache: None,
+            kv_cache_usage: Some(0.25),
+            time_to_first_token_seconds: histogram(&[0.1, 1.0], &[1, 2], 1.5),
+            inter_token_latency_seconds: histogram(&[0.01], &[4], 0.2),
+            request_latency_seconds: histogram(&[1.0], &[0], 3.0),
+            agentic_tool_rounds: histogram(&[0.0, 1.0], &[0, 0], 2.0),
+        }
+    }
+
+    #[test]
+    fn renders_prometheus_text() {
+        let text = render_metrics(&[("qwen\"3".to_string(), sample_metrics())]);
+
+        assert!(text.contains("# TYPE mistralrs_tokens_processed_total counter"));
+        assert!(text.contains("mistralrs_tokens_processed_total{model=\"qwen\\\"3\"} 120"));
+        assert!(text.contains("mistralrs_kv_cache_usage_ratio{model=\"qwen\\\"3\"} 0.25"));
+        assert!(text.contains(
+            "mistralrs_time_to_first_token_seconds_bucket{model=\"qwen\\\"3\",le=\"0.1\"} 1"
+        ));
+        assert!(text.contains(
+            "mistralrs_time_to_first_token_seconds_bucket{model=\"qwen\\\"3\",le=\"+Inf\"} 3"
+        ));
+        assert!(text.contains("mistralrs_time_to_first_token_seconds_count{model=\"qwen\\\"3\"} 3"));
+        // No encoder cache: the family is omitted entirely.
+        assert!(!text.contains("mistralrs_encoder_cache_hits_total"));
+    }
+
+    #[test]
+    fn families_are_declared_once_across_models() {
+        let text = render_metrics(&[
+            ("a".to_string(), sample_metrics()),
+            ("b".to_string(), sample_metrics()),
+        ]);
+        assert_eq!(
+            text.matches("# TYPE mistralrs_request_latency_seconds histogram")
+                .count(),
+            1
+        );
+        assert!(text.contains("mistralrs_sequences_running{model=\"b\"} 2"));
+    }
+}
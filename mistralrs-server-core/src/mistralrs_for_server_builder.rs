@@ -250,6 +250,15 @@ pub struct MistralRsForServerBuilder {
 
     /// Python code execution configuration
     code_exec_config: Option<mistralrs_core::CodeExecutionConfig>,
+
+    /// Durable storage for agentic sessions, shared by all models
+    session_storage: Option<Arc<dyn mistralrs_core::SessionStorage>>,
+
+    /// Queue bounds applied to each model
+    admission_limits: mistralrs_core::AdmissionLimits,
+
+    /// Audit log shared by all models
+    audit_log: Option<Arc<mistralrs_core::AuditLog>>,
 }
 
 impl Default for MistralRsForServerBuilder {
@@ -286,6 +295,9 @@ impl Default for MistralRsForServerBuilder {
             mtp_config: defaults::MTP_CONFIG,
             disable_eos_stop: false,
             code_exec_config: None,
+            session_storage: None,
+            admission_limits: mistralrs_core::AdmissionLimits::default(),
+            audit_log: None,
         }
     }
 }
@@ -649,6 +661,45 @@ impl MistralRsForServerBuilder {
         self
     }
 
+    /// Sets the storage backend that persists agentic sessions.
+    pub fn with_session_storage(
+        mut self,
+        storage: Arc<dyn mistralrs_core::SessionStorage>,
+    ) -> Self {
+        self.session_storage = Some(storage);
+        self
+    }
+
+    /// Sets the agentic session storage backend if provided.
+    pub fn with_session_storage_optional(
+        mut self,
+        storage: Option<Arc<dyn mistralrs_core::SessionStorage>>,
+    ) -> Self {
+        self.session_storage = storage;
+        self
+    }
+
+    /// Sets the admission limits applied to each model. Requests over a limit get a 429.
+    pub fn with_admission_limits(mut self, limits: mistralrs_core::AdmissionLimits) -> Self {
+        self.admission_limits = limits;
+        self
+    }
+
+    /// Sets the audit log that records requests, tool calls and approval decisions.
+    pub fn with_audit_log(mut self, audit_log: Arc<mistralrs_core::AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Sets the audit log if provided.
+    pub fn with_audit_log_optional(
+        mut self,
+        audit_log: Option<Arc<mistralrs_core::AuditLog>>,
+    ) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
     /// Builds the configured mistral.rs instance.
     ///
     /// ### Examples
@@ -789,6 +840,16 @@ impl MistralRsForServerBuilder {
             builder = builder.with_code_execution(code_exec_config);
         }
 
+        if let Some(session_storage) = self.session_storage {
+            builder = builder.with_session_storage(session_storage);
+        }
+
+        builder = builder.with_admission_limits(self.admission_limits);
+
+        if let Some(audit_log) = self.audit_log {
+            builder = builder.with_audit_log(audit_log);
+        }
+
         let mistralrs = builder.build().await;
 
         Ok(mistralrs)
@@ -936,6 +997,16 @@ impl MistralRsForServerBuilder {
             builder = builder.with_code_execution(code_exec_config);
         }
 
+        if let Some(session_storage) = self.session_storage.clone() {
+            builder = builder.with_session_storage(session_storage);
+        }
+
+        builder = builder.with_admission_limits(self.admission_limits);
+
+        if let Some(audit_log) = self.audit_log.clone() {
+            builder = builder.with_audit_log(audit_log);
+        }
+
         let mistralrs = builder.build().await;
 
         if let Some(alias) = first_model.alias.as_ref() {
@@ -1024,6 +1095,9 @@ impl MistralRsForServerBuilder {
                 search_embedding_model,
                 search_callback: self.search_callback.clone(),
                 tool_callbacks: HashMap::new(),
+                session_storage: self.session_storage.clone(),
+                admission_limits: self.admission_limits,
+                audit_log: self.audit_log.clone(),
             };
 
             let mut add_model_config = mistralrs_core::AddModelConfig::new(engine_config);
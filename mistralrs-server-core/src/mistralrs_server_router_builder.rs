@@ -1,12 +1,16 @@
 //! ## mistral.rs server router builder.
 
+use std::sync::Arc;
+
 use anyhow::Result;
 use axum::{
     extract::DefaultBodyLimit,
-    http::{self, Method},
+    http::{self, HeaderName, Method},
+    middleware,
     routing::{get, post},
     Extension, Router,
 };
+use mistralrs_core::{ffmpeg_available, ModelCategory};
 use tower_http::cors::{AllowOrigin, CorsLayer};
 #[cfg(feature = "swagger-ui")]
 use utoipa_swagger_ui::SwaggerUi;
@@ -14,18 +18,31 @@ use utoipa_swagger_ui::SwaggerUi;
 #[cfg(feature = "swagger-ui")]
 use crate::openapi_doc::get_openapi_doc;
 use crate::{
+    anthropic::messages,
     approvals::{resolve_agent_approval, ApprovalBroker},
+    auth::{api_key_middleware, ApiKeyAuth, AuthLayerState, API_KEY_HEADER},
+    batches::{cancel_batch, create_batch, get_batch, list_batches},
+    cached_responses::{set_response_cache, ResponseCache},
     chat_completion::chatcompletions,
     completions::completions,
     embeddings::embeddings,
-    files::{delete_file, get_file, get_file_content, list_files},
+    files::{delete_file, get_file, get_file_content, list_files, upload_file, MAX_UPLOAD_BYTES},
     handlers::{
-        delete_session, get_model_status, get_session, health, models, put_session, re_isq,
-        reload_model, system_doctor, system_info, tune_model, unload_model,
+        delete_session, get_model_status, get_session, health, list_sessions, models, put_session,
+        re_isq, reload_model, system_doctor, system_info, tune_model, unload_model,
+    },
+    image_generation::{image_edits, image_generation, image_variations},
+    metrics::metrics,
+    ollama::ollama_router,
+    realtime::realtime,
+    rerank::rerank,
+    responses::{
+        cancel_response, create_response, delete_response, get_response, list_input_items,
     },
-    image_generation::image_generation,
-    responses::{cancel_response, create_response, delete_response, get_response},
     speech_generation::speech_generation,
+    telemetry::{trace_http_request, TRACEPARENT_HEADER},
+    tokenize::{count_tokens, detokenize, tokenize},
+    transcription::{transcriptions, translations},
     types::SharedMistralRsState,
 };
 
@@ -88,6 +105,12 @@ pub struct MistralRsServerRouterBuilder {
     max_body_limit: Option<usize>,
     /// Server-level agentic defaults
     agentic_defaults: AgenticDefaults,
+    /// Optional API key authentication
+    api_key_auth: Option<ApiKeyAuth>,
+    /// Whether to include the Ollama-compatible `/api/*` routes
+    include_ollama_routes: bool,
+    /// Optional Responses API cache, installed globally on build
+    response_cache: Option<Arc<dyn ResponseCache>>,
 }
 
 impl Default for MistralRsServerRouterBuilder {
@@ -102,6 +125,9 @@ impl Default for MistralRsServerRouterBuilder {
             allowed_origins: None,
             max_body_limit: None,
             agentic_defaults: AgenticDefaults::default(),
+            api_key_auth: None,
+            include_ollama_routes: false,
+            response_cache: None,
         }
     }
 }
@@ -210,6 +236,50 @@ impl MistralRsServerRouterBuilder {
         self
     }
 
+    /// Requires an API key on every route except `/` and `/health`.
+    ///
+    /// Keys are checked for route scope (inference vs. admin), allowed models, and quota.
+    /// See [`crate::auth`] for the key file format.
+    pub fn with_api_key_auth(mut self, auth: ApiKeyAuth) -> Self {
+        self.api_key_auth = Some(auth);
+        self
+    }
+
+    /// Enables API key authentication if provided.
+    pub fn with_api_key_auth_optional(mut self, auth: Option<ApiKeyAuth>) -> Self {
+        if let Some(auth) = auth {
+            self = self.with_api_key_auth(auth);
+        }
+        self
+    }
+
+    /// Configures whether to include the Ollama-compatible routes.
+    ///
+    /// When enabled, `/api/chat`, `/api/generate`, `/api/embed`, `/api/tags`, `/api/show` and
+    /// `/api/version` are served alongside the OpenAI routes. Disabled by default.
+    /// See [`crate::ollama`].
+    pub fn with_ollama_routes(mut self, include_ollama_routes: bool) -> Self {
+        self.include_ollama_routes = include_ollama_routes;
+        self
+    }
+
+    /// Sets the cache for stored Responses API objects, in memory by default.
+    ///
+    /// The cache is process-wide, so only one router per process may set it.
+    /// See [`crate::cached_responses::FileResponseCache`] for a durable backend.
+    pub fn with_response_cache(mut self, cache: Arc<dyn ResponseCache>) -> Self {
+        self.response_cache = Some(cache);
+        self
+    }
+
+    /// Sets the Responses API cache if provided.
+    pub fn with_response_cache_optional(mut self, cache: Option<Arc<dyn ResponseCache>>) -> Self {
+        if let Some(cache) = cache {
+            self = self.with_response_cache(cache);
+        }
+        self
+    }
+
     /// Builds the configured axum router.
     ///
     /// ### Examples
@@ -227,12 +297,20 @@ impl MistralRsServerRouterBuilder {
             anyhow::anyhow!("`mistralrs` instance must be set. Use `with_mistralrs`.")
         })?;
 
+        if let Some(cache) = self.response_cache {
+            set_response_cache(cache)?;
+        }
+
+        warn_if_speech_without_ffmpeg(&mistralrs);
+
         #[allow(unused_mut)]
         let mut router = init_router(
             mistralrs,
             self.allowed_origins,
             self.max_body_limit,
             self.agentic_defaults,
+            self.api_key_auth,
+            self.include_ollama_routes,
         )?;
 
         #[cfg(feature = "swagger-ui")]
@@ -249,15 +327,34 @@ impl MistralRsServerRouterBuilder {
     }
 }
 
+/// Speech output in mp3, opus and aac needs the `ffmpeg` binary at request time, so say so
+/// once at startup rather than on the first failed request.
+fn warn_if_speech_without_ffmpeg(state: &SharedMistralRsState) {
+    let has_speech_model = state.list_models().unwrap_or_default().iter().any(|model| {
+        matches!(
+            state.get_model_category(Some(model.as_str())),
+            Ok(ModelCategory::Speech)
+        )
+    });
+    if has_speech_model && !ffmpeg_available() {
+        tracing::warn!(
+            "ffmpeg is not on PATH: /v1/audio/speech will only serve wav, flac and pcm. Install \
+             ffmpeg for mp3, opus and aac output."
+        );
+    }
+}
+
 /// Initializes and configures the underlying axum router with MistralRs API endpoints.
 ///
 /// This function creates a router with all the necessary API endpoints,
-/// CORS configuration, and body size limits.
+/// CORS configuration, body size limits, and optional API key authentication.
 fn init_router(
     state: SharedMistralRsState,
     allowed_origins: Option<Vec<String>>,
     max_body_limit: Option<usize>,
     agentic_defaults: AgenticDefaults,
+    api_key_auth: Option<ApiKeyAuth>,
+    include_ollama_routes: bool,
 ) -> Result<Router> {
     let allow_origin = if let Some(origins) = allowed_origins {
         let parsed_origins: Result<Vec<_>, _> = origins.into_iter().map(|o| o.parse()).collect();
@@ -271,16 +368,26 @@ fn init_router(
     };
 
     let router_max_body_limit = max_body_limit.unwrap_or(DEFAULT_MAX_BODY_LIMIT);
+    // Leave room for the multipart framing around a maximum-size upload.
+    let upload_body_limit = router_max_body_limit.max(MAX_UPLOAD_BYTES + MB_TO_B);
 
     let cors_layer = CorsLayer::new()
         .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-        .allow_headers([http::header::CONTENT_TYPE, http::header::AUTHORIZATION])
+        .allow_headers([
+            http::header::CONTENT_TYPE,
+            http::header::AUTHORIZATION,
+            HeaderName::from_static(API_KEY_HEADER),
+            HeaderName::from_static(TRACEPARENT_HEADER),
+        ])
         .allow_origin(allow_origin);
 
-    let router = Router::new()
+    let mut router = Router::new()
         .route("/v1/chat/completions", post(chatcompletions))
         .route("/v1/completions", post(completions))
+        .route("/v1/messages", post(messages))
         .route("/v1/embeddings", post(embeddings))
+        .route("/v1/rerank", post(rerank))
+        .route("/v1/chat/count_tokens", post(count_tokens))
         .route("/v1/models", get(models))
         .route("/v1/models/unload", post(unload_model))
         .route("/v1/models/reload", post(reload_model))
@@ -289,13 +396,44 @@ fn init_router(
         .route("/v1/system/info", get(system_info))
         .route("/v1/system/doctor", post(system_doctor))
         .route("/health", get(health))
+        .route("/metrics", get(metrics))
         .route("/", get(health))
         .route("/re_isq", post(re_isq))
+        .route("/tokenize", post(tokenize))
+        .route("/detokenize", post(detokenize))
         .route("/v1/images/generations", post(image_generation))
-        .route("/v1/files", get(list_files))
+        .route(
+            "/v1/images/edits",
+            post(image_edits).layer(DefaultBodyLimit::max(upload_body_limit)),
+        )
+        .route(
+            "/v1/images/variations",
+            post(image_variations).layer(DefaultBodyLimit::max(upload_body_limit)),
+        )
+        .route(
+            "/v1/files",
+            get(list_files)
+                .post(upload_file)
+                .layer(DefaultBodyLimit::max(upload_body_limit)),
+        )
         .route("/v1/files/{id}", get(get_file).delete(delete_file))
         .route("/v1/files/{id}/content", get(get_file_content))
-        .route("/v1/audio/speech", post(speech_generation))
+        .route("/v1/batches", post(create_batch).get(list_batches))
+        .route("/v1/batches/{batch_id}", get(get_batch))
+        .route("/v1/batches/{batch_id}/cancel", post(cancel_batch))
+        .route(
+            "/v1/audio/speech",
+            post(speech_generation).layer(DefaultBodyLimit::max(upload_body_limit)),
+        )
+        .route("/v1/realtime", get(realtime))
+        .route(
+            "/v1/audio/transcriptions",
+            post(transcriptions).layer(DefaultBodyLimit::max(upload_body_limit)),
+        )
+        .route(
+            "/v1/audio/translations",
+            post(translations).layer(DefaultBodyLimit::max(upload_body_limit)),
+        )
         .route(
             "/v1/agent/approvals/{approval_id}",
             post(resolve_agent_approval),
@@ -306,10 +444,36 @@ fn init_router(
             get(get_response).delete(delete_response),
         )
         .route("/v1/responses/{response_id}/cancel", post(cancel_response))
+        .route(
+            "/v1/responses/{response_id}/input_items",
+            get(list_input_items),
+        )
+        .route("/v1/sessions", get(list_sessions))
         .route(
             "/v1/sessions/{session_id}",
             get(get_session).put(put_session).delete(delete_session),
-        )
+        );
+
+    if include_ollama_routes {
+        router = router.merge(ollama_router());
+    }
+
+    // Inside the CORS layer so rejections still carry CORS headers.
+    if let Some(auth) = api_key_auth {
+        router = router.layer(middleware::from_fn_with_state(
+            AuthLayerState {
+                auth,
+                mistralrs: state.clone(),
+                max_body_limit: router_max_body_limit,
+            },
+            api_key_middleware,
+        ));
+    }
+
+    // Outside auth so rejected requests are traced as well.
+    router = router.layer(middleware::from_fn(trace_http_request));
+
+    let router = router
         .layer(cors_layer)
         .layer(DefaultBodyLimit::max(router_max_body_limit))
         .layer(Extension(agentic_defaults.approval_broker.clone()))
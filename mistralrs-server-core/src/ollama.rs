@@ -0,0 +1,1049 @@
+//! ## Ollama-compatible API.
+//!
+//! Optional routes (`/api/chat`, `/api/generate`, `/api/embed`, `/api/tags`, `/api/show`,
+//! `/api/version`) for clients that only speak Ollama. Requests are translated into the
+//! OpenAI request types and run through the same pipelines as the `/v1` routes; streaming
+//! responses are newline-delimited JSON. Enable with
+//! [`MistralRsServerRouterBuilder::with_ollama_routes`](crate::mistralrs_server_router_builder::MistralRsServerRouterBuilder::with_ollama_routes).
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    convert::Infallible,
+    hash::{Hash, Hasher},
+};
+
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Json, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response as AxumResponse},
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use either::Either;
+use futures::future::join_all;
+use mistralrs_core::{
+    ChatCompletionResponse, CompletionResponse, MistralRs, ModelCategory, RequestPriority,
+    Response, Tool, ToolCallResponse, ToolType, Usage,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc::Receiver;
+
+use crate::{
+    chat_completion::parse_request as parse_chat_request,
+    completions::parse_request as parse_completion_request,
+    embeddings::fetch_embedding,
+    handler_core::{
+        create_response_channel, overloaded, send_request_with_model, with_retry_after,
+    },
+    openai::{
+        ChatCompletionRequest, CompletionRequest, FunctionCalled, Grammar,
+        JsonSchemaResponseFormat, Message, MessageContent, ResponseFormat, StopTokens, ToolCall,
+    },
+    types::{ExtractedMistralRsState, SharedMistralRsState},
+    util::sanitize_error_message,
+};
+
+/// Version reported by `/api/version`. Some clients refuse to talk to servers older than the
+/// Ollama release that introduced a feature they use, so this tracks the API surface we cover.
+pub const OLLAMA_API_VERSION: &str = "0.9.0";
+
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// The Ollama routes, to be merged into the main router.
+pub fn ollama_router() -> Router<SharedMistralRsState> {
+    Router::new()
+        .route("/api/chat", post(chat))
+        .route("/api/generate", post(generate))
+        .route("/api/embed", post(embed))
+        .route("/api/tags", get(tags))
+        .route("/api/show", post(show))
+        .route("/api/version", get(version))
+}
+
+/// Sampling options. Unknown options (`num_ctx`, `num_gpu`, ...) are ignored.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OllamaOptions {
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub top_k: Option<usize>,
+    pub min_p: Option<f64>,
+    /// Maximum tokens to generate. Negative values mean no limit.
+    pub num_predict: Option<i64>,
+    pub stop: Option<Vec<String>>,
+    pub repeat_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub seed: Option<u64>,
+}
+
+impl OllamaOptions {
+    fn max_tokens(&self) -> Option<usize> {
+        self.num_predict
+            .and_then(|n| usize::try_from(n).ok())
+            .filter(|n| *n > 0)
+    }
+}
+
+/// `think`: a boolean, or an effort level for models that support one.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OllamaThink {
+    Enabled(bool),
+    Effort(String),
+}
+
+fn thinking_params(think: Option<OllamaThink>) -> (Option<bool>, Option<String>) {
+    match think {
+        None => (None, None),
+        Some(OllamaThink::Enabled(enabled)) => (Some(enabled), None),
+        Some(OllamaThink::Effort(effort)) => (Some(true), Some(effort)),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaFunctionCall {
+    pub name: String,
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaToolCall {
+    pub function: OllamaFunctionCall,
+}
+
+impl From<&ToolCallResponse> for OllamaToolCall {
+    fn from(call: &ToolCallResponse) -> Self {
+        Self {
+            function: OllamaFunctionCall {
+                name: call.function.name.clone(),
+                arguments: serde_json::from_str(&call.function.arguments)
+                    .unwrap_or_else(|_| Value::String(call.function.arguments.clone())),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+    /// Base64-encoded images, without a data URL prefix.
+    #[serde(default)]
+    pub images: Option<Vec<String>>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<OllamaToolCall>>,
+    /// Name of the tool a `tool` message answers.
+    #[serde(default)]
+    pub tool_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaChatRequest {
+    pub model: String,
+    #[serde(default)]
+    pub messages: Vec<OllamaMessage>,
+    #[serde(default)]
+    pub tools: Option<Vec<Tool>>,
+    /// `"json"` or a JSON schema.
+    #[serde(default)]
+    pub format: Option<Value>,
+    #[serde(default)]
+    pub options: OllamaOptions,
+    #[serde(default)]
+    pub stream: Option<bool>,
+    #[serde(default)]
+    pub think: Option<OllamaThink>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaGenerateRequest {
+    pub model: String,
+    #[serde(default)]
+    pub prompt: String,
+    #[serde(default)]
+    pub suffix: Option<String>,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub images: Option<Vec<String>>,
+    #[serde(default)]
+    pub format: Option<Value>,
+    #[serde(default)]
+    pub options: OllamaOptions,
+    #[serde(default)]
+    pub stream: Option<bool>,
+    /// Skip the chat template and send `prompt` as-is.
+    #[serde(default)]
+    pub raw: bool,
+    #[serde(default)]
+    pub think: Option<OllamaThink>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OllamaEmbedInput {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaEmbedRequest {
+    pub model: String,
+    pub input: OllamaEmbedInput,
+    #[serde(default)]
+    pub truncate: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaShowRequest {
+    #[serde(alias = "name")]
+    pub model: String,
+}
+
+/// Timing and token counts attached to the final object of a response.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct OllamaStats {
+    pub done_reason: String,
+    /// Durations are in nanoseconds.
+    pub total_duration: u64,
+    pub load_duration: u64,
+    pub prompt_eval_count: usize,
+    pub prompt_eval_duration: u64,
+    pub eval_count: usize,
+    pub eval_duration: u64,
+}
+
+fn nanos(secs: f32) -> u64 {
+    (secs.max(0.0) as f64 * 1e9) as u64
+}
+
+impl OllamaStats {
+    fn new(finish_reason: &str, usage: Option<&Usage>) -> Self {
+        let done_reason = match finish_reason {
+            "length" => "length",
+            _ => "stop",
+        }
+        .to_string();
+        match usage {
+            Some(usage) => Self {
+                done_reason,
+                total_duration: nanos(usage.total_time_sec),
+                load_duration: 0,
+                prompt_eval_count: usage.prompt_tokens,
+                prompt_eval_duration: nanos(usage.total_prompt_time_sec),
+                eval_count: usage.completion_tokens,
+                eval_duration: nanos(usage.total_completion_time_sec),
+            },
+            // Completion chunks carry no usage.
+            None => Self {
+                done_reason,
+                total_duration: 0,
+                load_duration: 0,
+                prompt_eval_count: 0,
+                prompt_eval_duration: 0,
+                eval_count: 0,
+                eval_duration: 0,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaResponseMessage {
+    pub role: &'static str,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<OllamaToolCall>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaChatResponse {
+    pub model: String,
+    pub created_at: String,
+    pub message: OllamaResponseMessage,
+    pub done: bool,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<OllamaStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaGenerateResponse {
+    pub model: String,
+    pub created_at: String,
+    pub response: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
+    pub done: bool,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<OllamaStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaEmbedResponse {
+    pub model: String,
+    pub embeddings: Vec<Vec<f32>>,
+    pub prompt_eval_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaModelDetails {
+    pub parent_model: String,
+    pub format: String,
+    pub family: String,
+    pub families: Vec<String>,
+    pub parameter_size: String,
+    pub quantization_level: String,
+}
+
+impl OllamaModelDetails {
+    fn unknown() -> Self {
+        Self {
+            parent_model: String::new(),
+            format: String::new(),
+            family: String::new(),
+            families: Vec::new(),
+            parameter_size: String::new(),
+            quantization_level: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaModel {
+    pub name: String,
+    pub model: String,
+    pub modified_at: String,
+    pub size: u64,
+    pub digest: String,
+    pub details: OllamaModelDetails,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaTags {
+    pub models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaShowResponse {
+    pub modelfile: String,
+    pub parameters: String,
+    pub template: String,
+    pub details: OllamaModelDetails,
+    pub model_info: serde_json::Map<String, Value>,
+    pub capabilities: Vec<&'static str>,
+    pub modified_at: String,
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn timestamp(secs: u64) -> String {
+    DateTime::<Utc>::from_timestamp(secs as i64, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Ollama errors are `{"error": "..."}`, both as a body and as a stream line.
+fn error_response(status: StatusCode, message: impl Into<String>) -> AxumResponse {
+    (
+        status,
+        axum::Json(serde_json::json!({ "error": message.into() })),
+    )
+        .into_response()
+}
+
+/// Ollama model names carry a tag (`llama3:latest`). Accept a bare `:latest` suffix on ids
+/// that don't have one; everything else is passed through for normal validation.
+fn resolve_model(state: &SharedMistralRsState, name: &str) -> String {
+    if name.is_empty() {
+        return "default".to_string();
+    }
+    if state.model_exists(name).unwrap_or(false) {
+        return name.to_string();
+    }
+    match name.strip_suffix(":latest") {
+        Some(stripped) if state.model_exists(stripped).unwrap_or(false) => stripped.to_string(),
+        _ => name.to_string(),
+    }
+}
+
+fn model_id(model: &str) -> Option<String> {
+    (model != "default").then(|| model.to_string())
+}
+
+/// `format` is either `"json"` or a JSON schema.
+fn format_schema(format: Option<Value>) -> Option<Value> {
+    match format? {
+        Value::String(s) if s == "json" => Some(serde_json::json!({ "type": "object" })),
+        Value::String(_) | Value::Null => None,
+        schema => Some(schema),
+    }
+}
+
+fn response_format(format: Option<Value>) -> Option<ResponseFormat> {
+    format_schema(format).map(|schema| ResponseFormat::JsonSchema {
+        json_schema: JsonSchemaResponseFormat {
+            name: "ollama_format".to_string(),
+            schema,
+        },
+    })
+}
+
+fn image_urls(images: Option<Vec<String>>) -> Vec<String> {
+    images
+        .unwrap_or_default()
+        .into_iter()
+        .map(|data| {
+            if data.starts_with("data:") {
+                data
+            } else {
+                // The decoder sniffs the real format; the declared type only has to parse.
+                format!("data:image/png;base64,{data}")
+            }
+        })
+        .collect()
+}
+
+fn user_message(content: String, images: Option<Vec<String>>) -> Message {
+    let images = image_urls(images);
+    let content = if images.is_empty() {
+        MessageContent::from_text(content)
+    } else {
+        let mut parts: Vec<_> = images
+            .into_iter()
+            .map(MessageContent::image_url_part)
+            .collect();
+        parts.push(MessageContent::text_part(content));
+        MessageContent::from_parts(parts)
+    };
+    Message {
+        content: Some(content),
+        role: "user".to_string(),
+        name: None,
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
+fn convert_message(message: OllamaMessage) -> Message {
+    if message.role == "user" {
+        return user_message(message.content, message.images);
+    }
+    let tool_calls = message.tool_calls.map(|calls| {
+        calls
+            .into_iter()
+            .map(|call| ToolCall {
+                id: None,
+                tp: ToolType::Function,
+                function: FunctionCalled {
+                    name: call.function.name,
+                    arguments: match call.function.arguments {
+                        Value::String(s) => s,
+                        other => other.to_string(),
+                    },
+                },
+            })
+            .collect::<Vec<_>>()
+    });
+    let content = if message.content.is_empty() && tool_calls.is_some() {
+        None
+    } else {
+        Some(MessageContent::from_text(message.content))
+    };
+    Message {
+        content,
+        role: message.role,
+        name: message.tool_name,
+        tool_calls,
+        tool_call_id: None,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn chat_request(
+    model: String,
+    messages: Vec<Message>,
+    tools: Option<Vec<Tool>>,
+    format: Option<Value>,
+    options: OllamaOptions,
+    stream: bool,
+    think: Option<OllamaThink>,
+) -> ChatCompletionRequest {
+    let (enable_thinking, reasoning_effort) = thinking_params(think);
+    ChatCompletionRequest {
+        messages: Either::Left(messages),
+        model,
+        logit_bias: None,
+        logprobs: false,
+        top_logprobs: None,
+        max_tokens: options.max_tokens(),
+        n_choices: 1,
+        presence_penalty: options.presence_penalty,
+        frequency_penalty: options.frequency_penalty,
+        seed: options.seed,
+        repetition_penalty: options.repeat_penalty,
+        stop_seqs: options.stop.map(StopTokens::Multi),
+        temperature: options.temperature,
+        top_p: options.top_p,
+        stream: Some(stream),
+        stream_options: None,
+        tools,
+        tool_choice: None,
+        response_format: response_format(format),
+        web_search_options: None,
+        prediction: None,
+        enable_code_execution: false,
+        agent_permission: None,
+        code_execution_permission: None,
+        session_id: None,
+        max_tool_rounds: None,
+        top_k: options.top_k,
+        grammar: None,
+        min_p: options.min_p,
+        dry_multiplier: None,
+        dry_base: None,
+        dry_allowed_length: None,
+        dry_sequence_breakers: None,
+        enable_thinking,
+        truncate_sequence: None,
+        reasoning_effort,
+        files: None,
+        priority: None,
+        timeout: None,
+    }
+}
+
+/// Which Ollama object a response is rendered as.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Chat,
+    Generate,
+}
+
+fn render_chat(
+    shape: Shape,
+    model: &str,
+    content: String,
+    thinking: Option<String>,
+    tool_calls: Option<&[ToolCallResponse]>,
+    stats: Option<OllamaStats>,
+) -> Value {
+    let thinking = thinking.filter(|t| !t.is_empty());
+    let done = stats.is_some();
+    let value = match shape {
+        Shape::Chat => serde_json::to_value(OllamaChatResponse {
+            model: model.to_string(),
+            created_at: now(),
+            message: OllamaResponseMessage {
+                role: "assistant",
+                content,
+                thinking,
+                tool_calls: tool_calls
+                    .filter(|calls| !calls.is_empty())
+                    .map(|calls| calls.iter().map(OllamaToolCall::from).collect()),
+            },
+            done,
+            stats,
+        }),
+        Shape::Generate => serde_json::to_value(OllamaGenerateResponse {
+            model: model.to_string(),
+            created_at: now(),
+            response: content,
+            thinking,
+            done,
+            stats,
+        }),
+    };
+    value.unwrap_or_default()
+}
+
+fn render_chat_done(shape: Shape, model: &str, response: &ChatCompletionResponse) -> Value {
+    let Some(choice) = response.choices.first() else {
+        return render_chat(
+            shape,
+            model,
+            String::new(),
+            None,
+            None,
+            Some(OllamaStats::new("stop", Some(&response.usage))),
+        );
+    };
+    render_chat(
+        shape,
+        model,
+        choice.message.content.clone().unwrap_or_default(),
+        choice.message.reasoning_content.clone(),
+        choice.message.tool_calls.as_deref(),
+        Some(OllamaStats::new(
+            &choice.finish_reason,
+            Some(&response.usage),
+        )),
+    )
+}
+
+fn render_completion_done(model: &str, response: &CompletionResponse) -> Value {
+    let (text, finish_reason) = response
+        .choices
+        .first()
+        .map(|c| (c.text.clone(), c.finish_reason.as_str()))
+        .unwrap_or_default();
+    render_chat(
+        Shape::Generate,
+        model,
+        text,
+        None,
+        None,
+        Some(OllamaStats::new(finish_reason, Some(&response.usage))),
+    )
+}
+
+/// Translate one engine response into a stream line. `None` skips the response; the bool
+/// marks the last line.
+fn stream_line(shape: Shape, model: &str, response: Response) -> Option<(Value, bool)> {
+    match response {
+        Response::Chunk(chunk) => {
+            let choice = chunk.choices.first()?;
+            let stats = choice
+                .finish_reason
+                .as_deref()
+                .map(|reason| OllamaStats::new(reason, chunk.usage.as_ref()));
+            let done = stats.is_some();
+            let line = render_chat(
+                shape,
+                model,
+                choice.delta.content.clone().unwrap_or_default(),
+                choice.delta.reasoning_content.clone(),
+                choice.delta.tool_calls.as_deref(),
+                stats,
+            );
+            Some((line, done))
+        }
+        Response::CompletionChunk(chunk) => {
+            let choice = chunk.choices.first()?;
+            let stats = choice
+                .finish_reason
+                .as_deref()
+                .map(|reason| OllamaStats::new(reason, None));
+            let done = stats.is_some();
+            let line = render_chat(shape, model, choice.text.clone(), None, None, stats);
+            Some((line, done))
+        }
+        Response::Done(response) => Some((render_chat_done(shape, model, &response), true)),
+        Response::CompletionDone(response) => {
+            Some((render_completion_done(model, &response), true))
+        }
+        Response::ModelError(msg, _) | Response::CompletionModelError(msg, _) => {
+            Some((serde_json::json!({ "error": msg }), true))
+        }
+        Response::ValidationError(e) | Response::InternalError(e) => Some((
+            serde_json::json!({ "error": sanitize_error_message(e.as_ref()) }),
+            true,
+        )),
+        _ => None,
+    }
+}
+
+struct LineStream {
+    rx: Receiver<Response>,
+    shape: Shape,
+    model: String,
+}
+
+fn ndjson_response(rx: Receiver<Response>, shape: Shape, model: String) -> AxumResponse {
+    let lines =
+        futures::stream::unfold(Some(LineStream { rx, shape, model }), |stream| async move {
+            let mut stream = stream?;
+            loop {
+                let response = stream.rx.recv().await?;
+                if let Some((line, done)) = stream_line(stream.shape, &stream.model, response) {
+                    let next = (!done).then_some(stream);
+                    return Some((Ok::<_, Infallible>(format!("{line}\n")), next));
+                }
+            }
+        });
+    (
+        [(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+/// Wait for the final response of a non-streaming request.
+async fn collect_response(
+    state: SharedMistralRsState,
+    mut rx: Receiver<Response>,
+    shape: Shape,
+    model: &str,
+) -> AxumResponse {
+    while let Some(response) = rx.recv().await {
+        match response {
+            Response::Done(ref done) => {
+                MistralRs::maybe_log_response(state, done);
+                return axum::Json(render_chat_done(shape, model, done)).into_response();
+            }
+            Response::CompletionDone(ref done) => {
+                MistralRs::maybe_log_response(state, done);
+                return axum::Json(render_completion_done(model, done)).into_response();
+            }
+            Response::ModelError(msg, _) | Response::CompletionModelError(msg, _) => {
+                return error_response(StatusCode::INTERNAL_SERVER_ERROR, msg)
+            }
+            Response::ValidationError(e) => {
+                return error_response(StatusCode::BAD_REQUEST, sanitize_error_message(e.as_ref()))
+            }
+            Response::InternalError(e) => {
+                MistralRs::maybe_log_error(state, &*e);
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    sanitize_error_message(e.as_ref()),
+                );
+            }
+            _ => continue,
+        }
+    }
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "The engine closed the response channel.",
+    )
+}
+
+async fn dispatch(
+    state: SharedMistralRsState,
+    parsed: Result<(mistralrs_core::Request, bool)>,
+    rx: Receiver<Response>,
+    shape: Shape,
+    model: String,
+) -> AxumResponse {
+    let (request, is_streaming) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    if let Err(e) = send_request_with_model(&state, request, model_id(&model).as_deref()).await {
+        MistralRs::maybe_log_error(state, &*e);
+        if let Some((reason, retry_after)) = overloaded(e.downcast_ref()) {
+            return with_retry_after(
+                error_response(StatusCode::TOO_MANY_REQUESTS, reason),
+                retry_after,
+            );
+        }
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            sanitize_error_message(&*e),
+        );
+    }
+    if is_streaming {
+        ndjson_response(rx, shape, model)
+    } else {
+        collect_response(state, rx, shape, &model).await
+    }
+}
+
+/// `POST /api/chat`.
+pub async fn chat(
+    State(state): ExtractedMistralRsState,
+    Json(request): Json<OllamaChatRequest>,
+) -> AxumResponse {
+    let model = resolve_model(&state, &request.model);
+    let messages = request.messages.into_iter().map(convert_message).collect();
+    let chat = chat_request(
+        model.clone(),
+        messages,
+        request.tools,
+        request.format,
+        request.options,
+        request.stream.unwrap_or(true),
+        request.think,
+    );
+
+    let (tx, rx) = create_response_channel(None);
+    let parsed = parse_chat_request(chat, state.clone(), tx, None, None, None).await;
+    dispatch(state, parsed, rx, Shape::Chat, model).await
+}
+
+/// `POST /api/generate`. `raw` prompts and prompts with a `suffix` skip the chat template.
+pub async fn generate(
+    State(state): ExtractedMistralRsState,
+    Json(request): Json<OllamaGenerateRequest>,
+) -> AxumResponse {
+    let model = resolve_model(&state, &request.model);
+    let stream = request.stream.unwrap_or(true);
+
+    // An empty prompt is how Ollama clients preload a model.
+    if request.prompt.is_empty() && request.images.as_ref().is_none_or(Vec::is_empty) {
+        return axum::Json(serde_json::json!({
+            "model": request.model,
+            "created_at": now(),
+            "response": "",
+            "done": true,
+            "done_reason": "load",
+        }))
+        .into_response();
+    }
+
+    let (tx, rx) = create_response_channel(None);
+    let parsed = if request.raw || request.suffix.is_some() {
+        let options = request.options;
+        let completion = CompletionRequest {
+            model: model.clone(),
+            prompt: request.prompt,
+            best_of: None,
+            echo_prompt: false,
+            presence_penalty: options.presence_penalty,
+            frequency_penalty: options.frequency_penalty,
+            seed: options.seed,
+            logit_bias: None,
+            logprobs: None,
+            max_tokens: options.max_tokens(),
+            n_choices: 1,
+            stop_seqs: options.stop.map(StopTokens::Multi),
+            stream: Some(stream),
+            stream_options: None,
+            temperature: options.temperature,
+            top_p: options.top_p,
+            suffix: request.suffix,
+            _user: None,
+            tools: None,
+            tool_choice: None,
+            fim_mode: None,
+            prediction: None,
+            top_k: options.top_k,
+            grammar: format_schema(request.format).map(Grammar::JsonSchema),
+            min_p: options.min_p,
+            repetition_penalty: options.repeat_penalty,
+            dry_multiplier: None,
+            dry_base: None,
+            dry_allowed_length: None,
+            dry_sequence_breakers: None,
+            truncate_sequence: None,
+            priority: None,
+            timeout: None,
+        };
+        parse_completion_request(completion, state.clone(), tx)
+    } else {
+        let mut messages = Vec::new();
+        if let Some(system) = request.system {
+            messages.push(Message {
+                content: Some(MessageContent::from_text(system)),
+                role: "system".to_string(),
+                name: None,
+                tool_calls: None,
+                tool_call_id: None,
+            });
+        }
+        messages.push(user_message(request.prompt, request.images));
+        let chat = chat_request(
+            model.clone(),
+            messages,
+            None,
+            request.format,
+            request.options,
+            stream,
+            request.think,
+        );
+        parse_chat_request(chat, state.clone(), tx, None, None, None).await
+    };
+    dispatch(state, parsed, rx, Shape::Generate, model).await
+}
+
+/// `POST /api/embed`.
+pub async fn embed(
+    State(state): ExtractedMistralRsState,
+    Json(request): Json<OllamaEmbedRequest>,
+) -> AxumResponse {
+    let model = resolve_model(&state, &request.model);
+    if let Err(e) = crate::util::validate_model_name(&model, state.clone()) {
+        return error_response(StatusCode::BAD_REQUEST, e.to_string());
+    }
+    let inputs = match request.input {
+        OllamaEmbedInput::Single(input) => vec![input],
+        OllamaEmbedInput::Multiple(inputs) => inputs,
+    };
+    let model_id = model_id(&model);
+    let truncate = request.truncate.unwrap_or(true);
+
+    let results = join_all(inputs.into_iter().map(|input| {
+        fetch_embedding(
+            state.clone(),
+            input,
+            model_id.as_deref(),
+            truncate,
+            RequestPriority::Normal,
+        )
+    }))
+    .await;
+
+    let mut embeddings = Vec::with_capacity(results.len());
+    let mut prompt_eval_count = 0;
+    for result in results {
+        match result {
+            Ok(result) => {
+                prompt_eval_count += result.prompt_tokens;
+                embeddings.push(result.embedding);
+            }
+            Err(e) => {
+                MistralRs::maybe_log_error(state, &*e);
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    sanitize_error_message(&*e),
+                );
+            }
+        }
+    }
+    axum::Json(OllamaEmbedResponse {
+        model: request.model,
+        embeddings,
+        prompt_eval_count,
+    })
+    .into_response()
+}
+
+/// Stable pseudo-digest so clients that key caches on `digest` see one entry per model.
+fn digest(name: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// `GET /api/tags`: every registered model, loaded or not.
+pub async fn tags(State(state): ExtractedMistralRsState) -> AxumResponse {
+    let models = match state.list_models_with_status() {
+        Ok(models) => models,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    let modified_at = timestamp(state.get_creation_time());
+    let models = models
+        .into_iter()
+        .map(|(id, _)| OllamaModel {
+            name: id.clone(),
+            model: id.clone(),
+            modified_at: modified_at.clone(),
+            size: 0,
+            digest: digest(&id),
+            details: OllamaModelDetails::unknown(),
+        })
+        .collect();
+    axum::Json(OllamaTags { models }).into_response()
+}
+
+fn capabilities(category: &ModelCategory) -> Vec<&'static str> {
+    match category {
+        ModelCategory::Text => vec!["completion", "tools"],
+        ModelCategory::Multimodal { .. } => vec!["completion", "tools", "vision"],
+        ModelCategory::Embedding => vec!["embedding"],
+        ModelCategory::Diffusion | ModelCategory::Audio | ModelCategory::Speech => Vec::new(),
+    }
+}
+
+/// `POST /api/show`. mistral.rs has no Modelfile, so only capabilities are filled in.
+pub async fn show(
+    State(state): ExtractedMistralRsState,
+    Json(request): Json<OllamaShowRequest>,
+) -> AxumResponse {
+    let model = resolve_model(&state, &request.model);
+    let category = match state.get_model_category(model_id(&model).as_deref()) {
+        Ok(category) => category,
+        Err(e) => return error_response(StatusCode::NOT_FOUND, e.to_string()),
+    };
+    axum::Json(OllamaShowResponse {
+        modelfile: String::new(),
+        parameters: String::new(),
+        template: String::new(),
+        details: OllamaModelDetails::unknown(),
+        model_info: serde_json::Map::new(),
+        capabilities: capabilities(&category),
+        modified_at: timestamp(state.get_creation_time()),
+    })
+    .into_response()
+}
+
+/// `GET /api/version`.
+pub async fn version() -> AxumResponse {
+    axum::Json(serde_json::json!({ "version": OLLAMA_API_VERSION })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_tool_history() {
+        let message: OllamaMessage = serde_json::from_value(serde_json::json!({
+            "role": "assistant",
+            "content": "",
+            "tool_calls": [{"function": {"name": "get_weather", "arguments": {"city": "Paris"}}}]
+        }))
+        .unwrap();
+        let message = convert_message(message);
+        assert!(message.content.is_none());
+        let call = &message.tool_calls.unwrap()[0];
+        assert_eq!(call.function.arguments, r#"{"city":"Paris"}"#);
+
+        let tool: OllamaMessage = serde_json::from_value(serde_json::json!({
+            "role": "tool", "content": "18C", "tool_name": "get_weather"
+        }))
+        .unwrap();
+        assert_eq!(convert_message(tool).name.as_deref(), Some("get_weather"));
+    }
+
+    #[test]
+    fn user_images_become_data_urls() {
+        let message = user_message(
+            "What is this?".to_string(),
+            Some(vec!["iVBORw0K".to_string()]),
+        );
+        let Some(content) = message.content else {
+            panic!("expected content");
+        };
+        let Either::Right(parts) = &*content else {
+            panic!("expected parts");
+        };
+        assert_eq!(parts.len(), 2);
+        let Either::Right(image_url) = &*parts[0]["image_url"] else {
+            panic!("expected image_url object");
+        };
+        assert_eq!(image_url["url"], "data:image/png;base64,iVBORw0K");
+    }
+
+    #[test]
+    fn options_and_format() {
+        let options: OllamaOptions =
+            serde_json::from_value(serde_json::json!({"num_predict": -1, "num_ctx": 8192}))
+                .unwrap();
+        assert_eq!(options.max_tokens(), None);
+        assert!(matches!(
+            response_format(Some(Value::String("json".to_string()))),
+            Some(ResponseFormat::JsonSchema { .. })
+        ));
+        assert!(response_format(Some(Value::String(String::new()))).is_none());
+    }
+
+    #[test]
+    fn final_line_carries_stats() {
+        let stats = OllamaStats::new("length", None);
+        let line = render_chat(
+            Shape::Generate,
+            "m",
+            "hi".to_string(),
+            None,
+            None,
+            Some(stats),
+        );
+        assert_eq!(line["done"], true);
+        assert_eq!(line["done_reason"], "length");
+        assert_eq!(line["response"], "hi");
+        assert!(line.get("thinking").is_none());
+
+        let line = render_chat(Shape::Chat, "m", "hi".to_string(), None, None, None);
+        assert_eq!(line["done"], false);
+        assert!(line.get("done_reason").is_none());
+        assert_eq!(line["message"]["content"], "hi");
+    }
+}
@@ -4,8 +4,8 @@ use std::{collections::HashMap, ops::Deref};
 
 use either::Either;
 use mistralrs_core::{
-    AgentPermission, CodeExecutionPermission, ImageGenerationResponseFormat, LlguidanceGrammar,
-    Tool, ToolChoice, ToolType, WebSearchOptions,
+    AgentPermission, CodeExecutionPermission, FimMode, ImageGenerationResponseFormat,
+    LlguidanceGrammar, RequestPriority, Tool, ToolChoice, ToolType, WebSearchOptions,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -158,6 +158,22 @@ impl MessageContent {
         part
     }
 
+    /// Create a content part referencing a file uploaded through `POST /v1/files`
+    pub fn file_part(file_id: String) -> HashMap<String, MessageInnerContent> {
+        let mut part = HashMap::new();
+        part.insert(
+            "type".to_string(),
+            MessageInnerContent(Either::Left("file".to_string())),
+        );
+        let mut file_obj = HashMap::new();
+        file_obj.insert("file_id".to_string(), file_id);
+        part.insert(
+            "file".to_string(),
+            MessageInnerContent(Either::Right(file_obj)),
+        );
+        part
+    }
+
     /// Extract text from MessageContent
     pub fn to_text(&self) -> Option<String> {
         match &self.0 {
@@ -299,6 +315,11 @@ fn default_false() -> bool {
     false
 }
 
+/// Default value helper
+fn default_true() -> bool {
+    true
+}
+
 /// Default value helper
 fn default_1usize() -> usize {
     1
@@ -531,6 +552,53 @@ pub enum ResponseFormat {
     },
 }
 
+/// Streaming options following OpenAI's `stream_options`.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, ToSchema)]
+pub struct StreamOptions {
+    /// Send one more chunk before `[DONE]` with empty `choices` and the request's usage.
+    #[serde(default)]
+    pub include_usage: bool,
+    /// Attach cumulative usage and timing to every chunk.
+    #[serde(default)]
+    pub continuous_usage_stats: bool,
+}
+
+/// Predicted output following OpenAI's `prediction`: text the response is expected to
+/// largely repeat, such as a file being edited.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Prediction {
+    Content { content: PredictionContent },
+}
+
+impl Prediction {
+    /// The predicted text, with any text parts joined.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Content {
+                content: PredictionContent::Text(text),
+            } => text.clone(),
+            Self::Content {
+                content: PredictionContent::Parts(parts),
+            } => parts.iter().map(|part| part.text.as_str()).collect(),
+        }
+    }
+}
+
+/// Predicted content, either a string or an array of text parts.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum PredictionContent {
+    Text(String),
+    Parts(Vec<PredictionTextPart>),
+}
+
+/// One text part of predicted content.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct PredictionTextPart {
+    pub text: String,
+}
+
 /// Chat completion request following OpenAI's specification
 #[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct ChatCompletionRequest {
@@ -561,6 +629,11 @@ pub struct ChatCompletionRequest {
     pub presence_penalty: Option<f32>,
     #[schema(example = json!(Option::None::<f32>))]
     pub frequency_penalty: Option<f32>,
+    /// Seed for reproducible sampling. Repeated requests with the same seed and parameters
+    /// return the same output while `system_fingerprint` is unchanged.
+    #[schema(example = json!(Option::None::<u64>))]
+    #[serde(default)]
+    pub seed: Option<u64>,
     #[schema(example = json!(Option::None::<f32>))]
     pub repetition_penalty: Option<f32>,
     #[serde(rename = "stop")]
@@ -572,6 +645,8 @@ pub struct ChatCompletionRequest {
     pub top_p: Option<f64>,
     #[schema(example = true)]
     pub stream: Option<bool>,
+    #[schema(example = json!(Option::None::<StreamOptions>))]
+    pub stream_options: Option<StreamOptions>,
     #[schema(example = json!(Option::None::<Vec<Tool>>))]
     pub tools: Option<Vec<Tool>>,
     #[schema(example = json!(Option::None::<ToolChoice>))]
@@ -580,6 +655,11 @@ pub struct ChatCompletionRequest {
     pub response_format: Option<ResponseFormat>,
     #[schema(example = json!(Option::None::<WebSearchOptions>))]
     pub web_search_options: Option<WebSearchOptions>,
+    /// Expected output. Matching spans are verified in one forward pass instead of being
+    /// generated token by token.
+    #[schema(example = json!(Option::None::<Prediction>))]
+    #[serde(default)]
+    pub prediction: Option<Prediction>,
     /// Enable Python code execution tools for this request.
     #[serde(default)]
     pub enable_code_execution: bool,
@@ -622,6 +702,15 @@ pub struct ChatCompletionRequest {
     #[schema(example = json!(Option::None::<bool>))]
     #[serde(default)]
     pub truncate_sequence: Option<bool>,
+    /// Scheduling class: "low", "normal" (default) or "high".
+    #[schema(example = json!(Option::None::<RequestPriority>))]
+    #[serde(default)]
+    pub priority: Option<RequestPriority>,
+    /// Seconds the request may run, tool rounds included. When exceeded, generation stops with
+    /// `finish_reason: "canceled"`.
+    #[schema(example = json!(Option::None::<f64>))]
+    #[serde(default)]
+    pub timeout: Option<f64>,
 }
 
 /// Function for ChatCompletionRequest.messages Schema generation to handle `Either`
@@ -690,6 +779,11 @@ pub struct CompletionRequest {
     pub presence_penalty: Option<f32>,
     #[schema(example = json!(Option::None::<f32>))]
     pub frequency_penalty: Option<f32>,
+    /// Seed for reproducible sampling. Repeated requests with the same seed and parameters
+    /// return the same output while `system_fingerprint` is unchanged.
+    #[schema(example = json!(Option::None::<u64>))]
+    #[serde(default)]
+    pub seed: Option<u64>,
     #[schema(example = json!(Option::None::<HashMap<u32, f32>>))]
     pub logit_bias: Option<HashMap<u32, f32>>,
     #[schema(example = json!(Option::None::<usize>))]
@@ -705,10 +799,15 @@ pub struct CompletionRequest {
     #[schema(example = json!(Option::None::<StopTokens>))]
     pub stop_seqs: Option<StopTokens>,
     pub stream: Option<bool>,
+    #[schema(example = json!(Option::None::<StreamOptions>))]
+    pub stream_options: Option<StreamOptions>,
     #[schema(example = 0.7)]
     pub temperature: Option<f64>,
     #[schema(example = json!(Option::None::<f64>))]
     pub top_p: Option<f64>,
+    /// Text that follows the completion. For code models with fill-in-the-middle tokens the
+    /// model generates the text between `prompt` and `suffix`; otherwise the suffix is
+    /// appended to the output.
     #[schema(example = json!(Option::None::<String>))]
     pub suffix: Option<String>,
     #[serde(rename = "user")]
@@ -719,6 +818,14 @@ pub struct CompletionRequest {
     pub tool_choice: Option<ToolChoice>,
 
     // mistral.rs additional
+    /// Fill-in-the-middle token order when `suffix` is set: "psm" (default) or "spm".
+    #[schema(example = json!(Option::None::<FimMode>))]
+    #[serde(default)]
+    pub fim_mode: Option<FimMode>,
+    /// Expected output, as in chat completions' `prediction`.
+    #[schema(example = json!(Option::None::<Prediction>))]
+    #[serde(default)]
+    pub prediction: Option<Prediction>,
     #[schema(example = json!(Option::None::<usize>))]
     pub top_k: Option<usize>,
     #[schema(example = json!(Option::None::<Grammar>))]
@@ -738,6 +845,15 @@ pub struct CompletionRequest {
     #[schema(example = json!(Option::None::<bool>))]
     #[serde(default)]
     pub truncate_sequence: Option<bool>,
+    /// Scheduling class: "low", "normal" (default) or "high".
+    #[schema(example = json!(Option::None::<RequestPriority>))]
+    #[serde(default)]
+    pub priority: Option<RequestPriority>,
+    /// Seconds the request may run, tool rounds included. When exceeded, generation stops with
+    /// `finish_reason: "canceled"`.
+    #[schema(example = json!(Option::None::<f64>))]
+    #[serde(default)]
+    pub timeout: Option<f64>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -840,6 +956,10 @@ pub struct EmbeddingRequest {
     #[schema(example = json!(Option::None::<bool>))]
     #[serde(default)]
     pub truncate_sequence: Option<bool>,
+    /// Scheduling class: "low", "normal" (default) or "high".
+    #[schema(example = json!(Option::None::<RequestPriority>))]
+    #[serde(default)]
+    pub priority: Option<RequestPriority>,
 }
 
 #[derive(Debug, Clone, Serialize, ToSchema)]
@@ -910,6 +1030,146 @@ pub struct EmbeddingResponse {
     pub usage: EmbeddingUsage,
 }
 
+/// A document to rerank: plain text or an object with a `text` field.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum RerankDocument {
+    Text(String),
+    Object { text: String },
+}
+
+impl RerankDocument {
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Text(text) | Self::Object { text } => text,
+        }
+    }
+}
+
+/// Rerank request (Cohere/Jina-compatible)
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct RerankRequest {
+    #[schema(example = "default")]
+    #[serde(default = "default_model")]
+    pub model: String,
+    #[schema(example = "What is the capital of France?")]
+    pub query: String,
+    #[schema(example = json!(["Paris is the capital of France.", "Berlin is in Germany."]))]
+    pub documents: Vec<RerankDocument>,
+    /// Return only the `top_n` highest-scoring documents.
+    #[schema(example = json!(Option::None::<usize>))]
+    #[serde(default)]
+    pub top_n: Option<usize>,
+    /// Include the document text in each result.
+    #[schema(example = true)]
+    #[serde(default = "default_true")]
+    pub return_documents: bool,
+
+    // mistral.rs additional
+    /// Task instruction for instruction-following cross-encoders such as Qwen3-Reranker.
+    #[schema(example = json!(Option::None::<String>))]
+    #[serde(default)]
+    pub instruction: Option<String>,
+    #[schema(example = json!(Option::None::<bool>))]
+    #[serde(default)]
+    pub truncate_sequence: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RerankResultDocument {
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RerankResult {
+    pub index: usize,
+    pub relevance_score: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document: Option<RerankResultDocument>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RerankUsage {
+    pub prompt_tokens: usize,
+    pub total_tokens: usize,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RerankResponse {
+    pub id: String,
+    pub model: String,
+    pub results: Vec<RerankResult>,
+    pub usage: RerankUsage,
+}
+
+/// Tokenize a raw prompt
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct TokenizeCompletionRequest {
+    #[schema(example = "default")]
+    #[serde(default = "default_model")]
+    pub model: String,
+    #[schema(example = "Hello, world!")]
+    pub prompt: String,
+    #[schema(example = true)]
+    #[serde(default = "default_true")]
+    pub add_special_tokens: bool,
+}
+
+/// Tokenize chat messages after applying the chat template. Accepts any
+/// `/v1/chat/completions` body.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct TokenizeChatRequest {
+    #[serde(flatten)]
+    pub request: ChatCompletionRequest,
+    #[schema(example = true)]
+    #[serde(default = "default_true")]
+    pub add_generation_prompt: bool,
+    #[schema(example = true)]
+    #[serde(default = "default_true")]
+    pub add_special_tokens: bool,
+}
+
+/// Tokenize request: either a `prompt` or chat `messages`
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum TokenizeRequest {
+    Completion(TokenizeCompletionRequest),
+    Chat(TokenizeChatRequest),
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TokenizeResponse {
+    pub count: usize,
+    /// The model's maximum sequence length, if known.
+    pub max_model_len: Option<usize>,
+    pub tokens: Vec<u32>,
+}
+
+/// Detokenize request
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct DetokenizeRequest {
+    #[schema(example = "default")]
+    #[serde(default = "default_model")]
+    pub model: String,
+    #[schema(example = json!([9906, 11, 1917, 0]))]
+    pub tokens: Vec<u32>,
+    #[schema(example = false)]
+    #[serde(default = "default_false")]
+    pub skip_special_tokens: bool,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DetokenizeResponse {
+    pub prompt: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CountTokensResponse {
+    pub input_tokens: usize,
+    /// The model's maximum sequence length, if known.
+    pub max_model_len: Option<usize>,
+}
+
 /// Image generation request
 #[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct ImageGenerationRequest {
@@ -930,6 +1190,21 @@ pub struct ImageGenerationRequest {
     #[serde(default = "default_1280usize")]
     #[schema(example = 1280)]
     pub width: usize,
+    /// Denoising steps, 1 to 100. Defaults to the model's own.
+    #[serde(alias = "num_inference_steps")]
+    #[schema(example = json!(Option::None::<usize>))]
+    pub steps: Option<usize>,
+    /// Distilled guidance strength, for FLUX.1-dev.
+    #[schema(example = json!(Option::None::<f64>))]
+    pub guidance_scale: Option<f64>,
+    /// Seed for the initial noise. Image `i` of the request uses `seed + i`.
+    #[schema(example = json!(Option::None::<u64>))]
+    pub seed: Option<u64>,
+    #[schema(example = json!(Option::None::<String>))]
+    pub negative_prompt: Option<String>,
+    /// Classifier-free guidance scale used with `negative_prompt`.
+    #[schema(example = json!(Option::None::<f64>))]
+    pub true_cfg_scale: Option<f64>,
 }
 
 /// Audio format options for speech generation responses.
@@ -987,7 +1262,27 @@ pub struct SpeechGenerationRequest {
     // `voice` and `instructions` are ignored.
     /// The desired audio format for the generated speech.
     #[schema(example = "mp3")]
+    #[serde(default)]
     pub response_format: AudioResponseFormat,
+    /// Stream audio while it is generated: `audio` sends the encoded bytes as a chunked body,
+    /// `sse` sends them base64-encoded in server-sent events. Omit for a single response.
+    #[serde(default)]
+    pub stream_format: Option<SpeechStreamFormat>,
+    /// Recording of the voice to clone, as a URL, data URL or file path on the server.
+    /// `multipart/form-data` requests may upload it instead. Requires `reference_text`.
+    #[serde(default)]
+    pub reference_audio: Option<String>,
+    /// Transcript of the reference audio, with the same speaker tags as `input`.
+    #[serde(default)]
+    pub reference_text: Option<String>,
+}
+
+/// How `/v1/audio/speech` streams audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeechStreamFormat {
+    Audio,
+    Sse,
 }
 
 /// Helper type for messages field in ResponsesCreateRequest
@@ -1058,6 +1353,11 @@ pub struct ResponsesCreateRequest {
     pub presence_penalty: Option<f32>,
     #[schema(example = json!(Option::None::<f32>))]
     pub frequency_penalty: Option<f32>,
+    /// Seed for reproducible sampling. Repeated requests with the same seed and parameters
+    /// return the same output while `system_fingerprint` is unchanged.
+    #[schema(example = json!(Option::None::<u64>))]
+    #[serde(default)]
+    pub seed: Option<u64>,
     #[serde(rename = "stop")]
     #[schema(example = json!(Option::None::<StopTokens>))]
     pub stop_seqs: Option<StopTokens>,
@@ -1120,6 +1420,15 @@ pub struct ResponsesCreateRequest {
     /// Valid values: "low", "medium", "high"
     #[schema(example = json!(Option::None::<String>))]
     pub reasoning_effort: Option<String>,
+    /// Scheduling class: "low", "normal" (default) or "high".
+    #[schema(example = json!(Option::None::<RequestPriority>))]
+    #[serde(default)]
+    pub priority: Option<RequestPriority>,
+    /// Seconds the request may run, tool rounds included. When exceeded, generation stops with
+    /// `finish_reason: "canceled"`.
+    #[schema(example = json!(Option::None::<f64>))]
+    #[serde(default)]
+    pub timeout: Option<f64>,
 }
 
 /// Response object
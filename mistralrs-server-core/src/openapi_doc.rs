@@ -9,22 +9,31 @@ use crate::{
     handlers::{__path_health, __path_models, __path_re_isq, ReIsqRequest},
     image_generation::__path_image_generation,
     openai::{
-        AudioResponseFormat, ChatCompletionRequest, CompletionRequest, EmbeddingData,
-        EmbeddingEncodingFormat, EmbeddingInput, EmbeddingRequest, EmbeddingResponse,
-        EmbeddingUsage, EmbeddingVector, FunctionCalled, Grammar, ImageGenerationRequest,
-        JsonSchemaResponseFormat, Message, MessageContent, MessageInnerContent, ModelObject,
-        ModelObjects, ResponseFormat, ResponsesAnnotation, ResponsesChunk, ResponsesContent,
-        ResponsesCreateRequest, ResponsesDelta, ResponsesDeltaContent, ResponsesDeltaOutput,
-        ResponsesError, ResponsesIncompleteDetails, ResponsesInputTokensDetails, ResponsesMessages,
-        ResponsesObject, ResponsesOutput, ResponsesOutputTokensDetails, ResponsesUsage,
-        SpeechGenerationRequest, StopTokens, ToolCall,
+        AudioResponseFormat, ChatCompletionRequest, CompletionRequest, CountTokensResponse,
+        DetokenizeRequest, DetokenizeResponse, EmbeddingData, EmbeddingEncodingFormat,
+        EmbeddingInput, EmbeddingRequest, EmbeddingResponse, EmbeddingUsage, EmbeddingVector,
+        FunctionCalled, Grammar, ImageGenerationRequest, JsonSchemaResponseFormat, Message,
+        MessageContent, MessageInnerContent, ModelObject, ModelObjects, Prediction,
+        PredictionContent, PredictionTextPart, RerankDocument, RerankRequest, RerankResponse,
+        RerankResult, RerankResultDocument, RerankUsage, ResponseFormat, ResponsesAnnotation,
+        ResponsesChunk, ResponsesContent, ResponsesCreateRequest, ResponsesDelta,
+        ResponsesDeltaContent, ResponsesDeltaOutput, ResponsesError, ResponsesIncompleteDetails,
+        ResponsesInputTokensDetails, ResponsesMessages, ResponsesObject, ResponsesOutput,
+        ResponsesOutputTokensDetails, ResponsesUsage, SpeechGenerationRequest, SpeechStreamFormat,
+        StopTokens, StreamOptions, TokenizeChatRequest, TokenizeCompletionRequest, TokenizeRequest,
+        TokenizeResponse, ToolCall,
+    },
+    rerank::__path_rerank,
+    responses::{
+        __path_create_response, __path_delete_response, __path_get_response,
+        __path_list_input_items,
     },
-    responses::{__path_create_response, __path_delete_response, __path_get_response},
     speech_generation::__path_speech_generation,
+    tokenize::{__path_count_tokens, __path_detokenize, __path_tokenize},
 };
 use mistralrs_core::{
-    ApproximateUserLocation, Function, ImageGenerationResponseFormat, SearchContextSize, Tool,
-    ToolChoice, ToolType, WebSearchOptions, WebSearchUserLocation,
+    ApproximateUserLocation, FimMode, Function, ImageGenerationResponseFormat, RequestPriority,
+    SearchContextSize, Tool, ToolChoice, ToolType, WebSearchOptions, WebSearchUserLocation,
 };
 
 /// This is used to generate the OpenAPI docs.
@@ -69,12 +78,15 @@ use mistralrs_core::{
 pub fn get_openapi_doc(base_path: Option<&str>) -> utoipa::openapi::OpenApi {
     #[derive(OpenApi)]
     #[openapi(
-        paths(models, health, chatcompletions, completions, embeddings, re_isq, image_generation, speech_generation, create_response, get_response, delete_response),
+        paths(models, health, chatcompletions, completions, embeddings, rerank, tokenize, detokenize, count_tokens, re_isq, image_generation, speech_generation, create_response, get_response, delete_response, list_input_items),
         components(schemas(
             ApproximateUserLocation,
             AudioResponseFormat,
             ChatCompletionRequest,
             CompletionRequest,
+            CountTokensResponse,
+            DetokenizeRequest,
+            DetokenizeResponse,
             EmbeddingData,
             EmbeddingEncodingFormat,
             EmbeddingInput,
@@ -82,6 +94,7 @@ pub fn get_openapi_doc(base_path: Option<&str>) -> utoipa::openapi::OpenApi {
             EmbeddingResponse,
             EmbeddingUsage,
             EmbeddingVector,
+            FimMode,
             Function,
             FunctionCalled,
             Grammar,
@@ -93,11 +106,21 @@ pub fn get_openapi_doc(base_path: Option<&str>) -> utoipa::openapi::OpenApi {
             MessageInnerContent,
             ModelObject,
             ModelObjects,
+            Prediction,
+            PredictionContent,
+            PredictionTextPart,
             ReIsqRequest,
+            RerankDocument,
+            RerankRequest,
+            RerankResponse,
+            RerankResult,
+            RerankResultDocument,
+            RerankUsage,
             ResponseFormat,
             ResponsesAnnotation,
             ResponsesChunk,
             ResponsesContent,
+            RequestPriority,
             ResponsesCreateRequest,
             ResponsesDelta,
             ResponsesDeltaContent,
@@ -112,7 +135,13 @@ pub fn get_openapi_doc(base_path: Option<&str>) -> utoipa::openapi::OpenApi {
             ResponsesUsage,
             SearchContextSize,
             SpeechGenerationRequest,
+            SpeechStreamFormat,
             StopTokens,
+            StreamOptions,
+            TokenizeChatRequest,
+            TokenizeCompletionRequest,
+            TokenizeRequest,
+            TokenizeResponse,
             Tool,
             ToolCall,
             ToolChoice,
@@ -0,0 +1,1783 @@
+//! ## Realtime voice and text sessions over WebSocket.
+//!
+//! `GET /v1/realtime?model=<id>` upgrades to a WebSocket speaking a subset of OpenAI's Realtime
+//! API. The client streams base64 PCM16 audio (or sends text items); each committed audio turn
+//! is transcribed by an audio-capable multimodal model such as Voxtral or Gemma 3n, the reply is
+//! generated by the session's text model and, when audio output is enabled, every finished
+//! sentence is synthesized by a speech model (Dia) and streamed back as `response.audio.delta`
+//! events while the rest of the reply is still being generated.
+//!
+//! With server voice activity detection (the default), a turn is committed when the speaker
+//! pauses, and speech starting while a response is being generated cancels it (barge-in).
+
+use std::{
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response as AxumResponse},
+    Extension,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use either::Either;
+use futures::{SinkExt, StreamExt};
+use indexmap::IndexMap;
+use mistralrs_core::{
+    speech_utils::Sample, AudioInput, Constraint, MessageContent, ModelCategory, NormalRequest,
+    Request, RequestMessage, RequestPriority, Response, SamplingParams, Usage,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use tokio::{
+    sync::{mpsc, Notify},
+    task::{AbortHandle, JoinHandle},
+};
+use uuid::Uuid;
+
+use crate::{
+    auth::{token_meter, AuthenticatedKey, TokenMeter},
+    handler_core::{create_response_channel, send_request_with_model, ErrorToResponse, JsonError},
+    transcription::transcribe_audio,
+    types::SharedMistralRsState,
+    util::{sanitize_error_message, validate_model_name},
+};
+
+/// Subprotocol selected when the client offers it, as OpenAI's SDKs do.
+pub const REALTIME_SUBPROTOCOL: &str = "realtime";
+
+/// Sample rate of PCM16 audio in both directions unless the session sets another.
+pub const DEFAULT_SAMPLE_RATE: u32 = 24_000;
+
+/// Audio carried by one `response.audio.delta` event.
+const AUDIO_DELTA_MS: usize = 100;
+
+/// Committing less audio than this is rejected, as it can't hold a word.
+const MIN_COMMIT_MS: usize = 100;
+
+/// Largest audio payload one `input_audio_buffer.append` may carry, as in OpenAI's API.
+const MAX_APPEND_BYTES: usize = 15 * 1024 * 1024;
+
+/// Longest uncommitted audio the input buffer holds. Appends beyond it are rejected until the
+/// client commits or clears the buffer.
+const MAX_BUFFER_MS: u64 = 5 * 60 * 1000;
+
+/// Server events waiting for the WebSocket writer before senders wait.
+const EVENT_BUFFER: usize = 256;
+
+/// Transcriptions and responses a session may have running or waiting. Further commits and
+/// response requests are rejected until one finishes.
+const MAX_QUEUED_TURNS: usize = 8;
+
+/// Length of the frames the voice activity detector classifies.
+const VAD_FRAME_MS: u64 = 20;
+
+/// Voiced audio needed before speech counts as started, so clicks and pops don't interrupt.
+const VAD_MIN_SPEECH_MS: u64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct RealtimeQuery {
+    /// Text model generating the replies. Defaults to the default model.
+    pub model: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Modality {
+    Text,
+    Audio,
+}
+
+/// Which model transcribes committed audio, and hints passed to it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputAudioTranscription {
+    /// An audio-capable multimodal model. Defaults to the session model if it accepts audio,
+    /// otherwise the first loaded multimodal model.
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+/// Server-side voice activity detection settings.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TurnDetection {
+    /// Only `server_vad` is supported.
+    #[serde(rename = "type", default = "default_turn_detection_type")]
+    pub kind: String,
+    /// RMS level (0.0-1.0 of full scale) above which a 20 ms frame counts as speech.
+    #[serde(default = "default_vad_threshold")]
+    pub threshold: f32,
+    /// Audio kept from before speech started, so the first syllable isn't clipped.
+    #[serde(default = "default_prefix_padding_ms")]
+    pub prefix_padding_ms: u64,
+    /// Silence that ends a turn.
+    #[serde(default = "default_silence_duration_ms")]
+    pub silence_duration_ms: u64,
+    /// Start a response when a turn ends.
+    #[serde(default = "default_true")]
+    pub create_response: bool,
+    /// Cancel the in-progress response when the user starts speaking.
+    #[serde(default = "default_true")]
+    pub interrupt_response: bool,
+}
+
+fn default_turn_detection_type() -> String {
+    "server_vad".to_string()
+}
+
+fn default_vad_threshold() -> f32 {
+    0.02
+}
+
+fn default_prefix_padding_ms() -> u64 {
+    300
+}
+
+fn default_silence_duration_ms() -> u64 {
+    500
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for TurnDetection {
+    fn default() -> Self {
+        Self {
+            kind: default_turn_detection_type(),
+            threshold: default_vad_threshold(),
+            prefix_padding_ms: default_prefix_padding_ms(),
+            silence_duration_ms: default_silence_duration_ms(),
+            create_response: true,
+            interrupt_response: true,
+        }
+    }
+}
+
+/// Session settings, as reported in `session.created` and `session.updated`.
+#[derive(Clone, Debug, Serialize)]
+pub struct RealtimeSession {
+    pub id: String,
+    pub object: &'static str,
+    pub model: String,
+    pub modalities: Vec<Modality>,
+    pub instructions: Option<String>,
+    /// Dia speaker tag used for synthesized speech: `S1` or `S2`.
+    pub voice: String,
+    pub input_audio_format: String,
+    pub output_audio_format: String,
+    pub input_sample_rate: u32,
+    pub output_sample_rate: u32,
+    pub input_audio_transcription: InputAudioTranscription,
+    /// Speech model used for audio output. Defaults to the first loaded speech model.
+    pub speech_model: Option<String>,
+    /// `None` disables voice activity detection; turns are then committed by the client.
+    pub turn_detection: Option<TurnDetection>,
+    pub temperature: f64,
+    pub max_response_output_tokens: Option<usize>,
+}
+
+impl RealtimeSession {
+    fn new(model: String) -> Self {
+        Self {
+            id: format!("sess_{}", Uuid::new_v4().simple()),
+            object: "realtime.session",
+            model,
+            modalities: vec![Modality::Text, Modality::Audio],
+            instructions: None,
+            voice: "S1".to_string(),
+            input_audio_format: "pcm16".to_string(),
+            output_audio_format: "pcm16".to_string(),
+            input_sample_rate: DEFAULT_SAMPLE_RATE,
+            output_sample_rate: DEFAULT_SAMPLE_RATE,
+            input_audio_transcription: InputAudioTranscription::default(),
+            speech_model: None,
+            turn_detection: Some(TurnDetection::default()),
+            temperature: 0.8,
+            max_response_output_tokens: None,
+        }
+    }
+
+    /// Apply the fields present in a `session.update`, validating the result as a whole.
+    fn apply(&mut self, update: SessionUpdate) -> Result<(), String> {
+        let mut next = self.clone();
+        if let Some(model) = update.model {
+            next.model = model;
+        }
+        if let Some(modalities) = update.modalities {
+            if !modalities.contains(&Modality::Text) {
+                return Err("`modalities` must include \"text\".".to_string());
+            }
+            next.modalities = modalities;
+        }
+        if let Some(instructions) = update.instructions {
+            next.instructions = instructions;
+        }
+        if let Some(voice) = update.voice {
+            next.voice = voice;
+        }
+        if let Some(format) = update.input_audio_format {
+            next.input_audio_format = format;
+        }
+        if let Some(format) = update.output_audio_format {
+            next.output_audio_format = format;
+        }
+        if let Some(rate) = update.input_sample_rate {
+            next.input_sample_rate = rate;
+        }
+        if let Some(rate) = update.output_sample_rate {
+            next.output_sample_rate = rate;
+        }
+        if let Some(transcription) = update.input_audio_transcription {
+            next.input_audio_transcription = transcription.unwrap_or_default();
+        }
+        if let Some(speech_model) = update.speech_model {
+            next.speech_model = speech_model;
+        }
+        if let Some(turn_detection) = update.turn_detection {
+            next.turn_detection = turn_detection;
+        }
+        if let Some(temperature) = update.temperature {
+            next.temperature = temperature;
+        }
+        if let Some(max_tokens) = update.max_response_output_tokens {
+            next.max_response_output_tokens = max_tokens;
+        }
+
+        if !matches!(next.voice.as_str(), "S1" | "S2") {
+            return Err(format!(
+                "Unsupported voice '{}'. Expected S1 or S2.",
+                next.voice
+            ));
+        }
+        for format in [&next.input_audio_format, &next.output_audio_format] {
+            if format != "pcm16" {
+                return Err(format!(
+                    "Unsupported audio format '{format}'. Only pcm16 is supported."
+                ));
+            }
+        }
+        if next.input_sample_rate == 0 || next.output_sample_rate == 0 {
+            return Err("Sample rates must be positive.".to_string());
+        }
+        if let Some(turn_detection) = &next.turn_detection {
+            if turn_detection.kind != "server_vad" {
+                return Err(format!(
+                    "Unsupported turn_detection type '{}'. Only server_vad is supported.",
+                    turn_detection.kind
+                ));
+            }
+            if !(0.0..=1.0).contains(&turn_detection.threshold) {
+                return Err("turn_detection.threshold must be between 0.0 and 1.0.".to_string());
+            }
+        }
+        if !(0.0..=2.0).contains(&next.temperature) {
+            return Err("temperature must be between 0.0 and 2.0.".to_string());
+        }
+        *self = next;
+        Ok(())
+    }
+
+    fn audio_output(&self) -> bool {
+        self.modalities.contains(&Modality::Audio)
+    }
+}
+
+/// Fields of a `session.update`. Nullable settings use a nested `Option` so that `null` can
+/// clear them while an absent field leaves them unchanged.
+#[derive(Debug, Default, Deserialize)]
+struct SessionUpdate {
+    model: Option<String>,
+    modalities: Option<Vec<Modality>>,
+    #[serde(default, deserialize_with = "nullable")]
+    instructions: Option<Option<String>>,
+    voice: Option<String>,
+    input_audio_format: Option<String>,
+    output_audio_format: Option<String>,
+    input_sample_rate: Option<u32>,
+    output_sample_rate: Option<u32>,
+    #[serde(default, deserialize_with = "nullable")]
+    input_audio_transcription: Option<Option<InputAudioTranscription>>,
+    #[serde(default, deserialize_with = "nullable")]
+    speech_model: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    turn_detection: Option<Option<TurnDetection>>,
+    temperature: Option<f64>,
+    #[serde(default, deserialize_with = "nullable")]
+    max_response_output_tokens: Option<Option<usize>>,
+}
+
+fn nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Events sent by the client.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum ClientEvent {
+    #[serde(rename = "session.update")]
+    SessionUpdate { session: SessionUpdate },
+    #[serde(rename = "input_audio_buffer.append")]
+    InputAudioBufferAppend { audio: String },
+    #[serde(rename = "input_audio_buffer.commit")]
+    InputAudioBufferCommit,
+    #[serde(rename = "input_audio_buffer.clear")]
+    InputAudioBufferClear,
+    #[serde(rename = "conversation.item.create")]
+    ConversationItemCreate { item: ClientItem },
+    #[serde(rename = "response.create")]
+    ResponseCreate {
+        #[serde(default)]
+        response: Option<ResponseOverrides>,
+    },
+    #[serde(rename = "response.cancel")]
+    ResponseCancel,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientItem {
+    #[serde(rename = "type", default = "default_item_type")]
+    kind: String,
+    role: String,
+    #[serde(default)]
+    content: Vec<ClientContent>,
+}
+
+fn default_item_type() -> String {
+    "message".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientContent {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+/// Per-response settings taking precedence over the session's.
+#[derive(Debug, Default, Deserialize)]
+struct ResponseOverrides {
+    modalities: Option<Vec<Modality>>,
+    instructions: Option<String>,
+}
+
+/// A realtime protocol error, reported to the client as an `error` event.
+#[derive(Debug)]
+struct EventError {
+    kind: &'static str,
+    message: String,
+}
+
+impl EventError {
+    fn invalid(message: impl Into<String>) -> Self {
+        Self {
+            kind: "invalid_request_error",
+            message: message.into(),
+        }
+    }
+
+    fn rate_limit(message: impl Into<String>) -> Self {
+        Self {
+            kind: "rate_limit_error",
+            message: message.into(),
+        }
+    }
+
+    fn server(e: &anyhow::Error) -> Self {
+        Self {
+            kind: "server_error",
+            message: sanitize_error_message(&**e),
+        }
+    }
+}
+
+/// Sends server events to the writer task, stamping each with an `event_id`. Sending waits while
+/// [`EVENT_BUFFER`] events are pending, so a client that reads slowly slows the session down
+/// instead of growing its backlog.
+#[derive(Clone)]
+struct Events(mpsc::Sender<Value>);
+
+impl Events {
+    async fn emit(&self, mut event: Value) {
+        event["event_id"] = Value::String(format!("event_{}", Uuid::new_v4().simple()));
+        let _ = self.0.send(event).await;
+    }
+
+    async fn error(&self, error: EventError, client_event_id: Option<&str>) {
+        self.emit(error_event(error, client_event_id)).await;
+    }
+}
+
+fn error_event(error: EventError, client_event_id: Option<&str>) -> Value {
+    json!({
+        "type": "error",
+        "error": {
+            "type": error.kind,
+            "message": error.message,
+            "event_id": client_event_id,
+        },
+    })
+}
+
+/// One turn of the conversation. Audio turns have no text until they are transcribed.
+#[derive(Clone, Debug)]
+struct ConversationItem {
+    id: String,
+    role: String,
+    text: Option<String>,
+}
+
+/// State shared between the connection and the tasks it spawns for each turn.
+struct Shared {
+    state: SharedMistralRsState,
+    key: Option<AuthenticatedKey>,
+    /// Charged with each turn's usage when the key has a token quota.
+    meter: Option<TokenMeter>,
+    events: Events,
+    conversation: Mutex<Vec<ConversationItem>>,
+    /// Cancels the response being generated, if any.
+    active_response: Mutex<Option<Arc<Notify>>>,
+}
+
+impl Shared {
+    fn user(&self) -> Option<String> {
+        self.key.as_ref().map(|key| key.name.clone())
+    }
+
+    fn check_model(&self, model: &str) -> Result<(), EventError> {
+        match &self.key {
+            Some(key) if !key.allows_model(model) => Err(EventError::invalid(format!(
+                "API key is not allowed to use model '{model}'."
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails once the key's token quota is used up, so no further responses are generated.
+    fn check_quota(&self) -> Result<(), EventError> {
+        match &self.meter {
+            Some(meter) => meter
+                .check()
+                .map_err(|e| EventError::rate_limit(e.message())),
+            None => Ok(()),
+        }
+    }
+
+    fn charge(&self, tokens: usize) {
+        if let Some(meter) = &self.meter {
+            meter.charge(tokens as u64);
+        }
+    }
+
+    fn push_item(&self, item: ConversationItem) -> Option<String> {
+        let mut conversation = self.conversation.lock().unwrap();
+        let previous = conversation.last().map(|item| item.id.clone());
+        conversation.push(item);
+        previous
+    }
+}
+
+/// Realtime WebSocket endpoint.
+#[utoipa::path(
+    get,
+    tag = "Mistral.rs",
+    path = "/v1/realtime",
+    params(("model" = Option<String>, Query, description = "Text model generating replies")),
+    responses((status = 101, description = "Switching to the realtime WebSocket protocol"))
+)]
+pub async fn realtime(
+    State(state): State<SharedMistralRsState>,
+    Query(query): Query<RealtimeQuery>,
+    key: Option<Extension<AuthenticatedKey>>,
+    ws: WebSocketUpgrade,
+) -> AxumResponse {
+    let model = match query.model.filter(|m| m != "default") {
+        Some(model) => model,
+        None => match state.get_default_model_id() {
+            Ok(Some(model)) => model,
+            _ => {
+                return JsonError::new("No model is loaded.".to_string())
+                    .to_response(StatusCode::SERVICE_UNAVAILABLE)
+            }
+        },
+    };
+    if let Err(e) = validate_model_name(&model, state.clone()) {
+        return JsonError::new(e.to_string()).to_response(StatusCode::BAD_REQUEST);
+    }
+    if !matches!(
+        state.get_model_category(Some(model.as_str())),
+        Ok(ModelCategory::Text | ModelCategory::Multimodal { .. })
+    ) {
+        return JsonError::new(format!("Model '{model}' does not generate text."))
+            .to_response(StatusCode::BAD_REQUEST);
+    }
+
+    let key = key.map(|Extension(key)| key);
+    if key.as_ref().is_some_and(|key| !key.allows_model(&model)) {
+        return JsonError::new(format!("API key is not allowed to use model '{model}'."))
+            .to_response(StatusCode::FORBIDDEN);
+    }
+    // The session outlives the request, so its usage is charged through the meter rather than
+    // from a response body.
+    let meter = token_meter();
+    ws.protocols([REALTIME_SUBPROTOCOL])
+        .on_upgrade(move |socket| run_session(socket, state, key, meter, model))
+        .into_response()
+}
+
+async fn run_session(
+    socket: WebSocket,
+    state: SharedMistralRsState,
+    key: Option<AuthenticatedKey>,
+    meter: Option<TokenMeter>,
+    model: String,
+) {
+    let (mut sink, mut stream) = socket.split();
+    let (events_tx, mut events_rx) = mpsc::channel::<Value>(EVENT_BUFFER);
+    let writer = tokio::spawn(async move {
+        while let Some(event) = events_rx.recv().await {
+            if sink
+                .send(Message::Text(event.to_string().into()))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+        let _ = sink.close().await;
+    });
+
+    let mut session = Session::new(
+        Shared {
+            state,
+            key,
+            meter,
+            events: Events(events_tx),
+            conversation: Mutex::new(Vec::new()),
+            active_response: Mutex::new(None),
+        },
+        model,
+    );
+    session.emit(json!({
+        "type": "session.created",
+        "session": session.config,
+    }));
+    session.flush().await;
+
+    while let Some(Ok(message)) = stream.next().await {
+        let text = match message {
+            Message::Text(text) => text.as_str().to_string(),
+            Message::Binary(bytes) => match String::from_utf8(bytes.to_vec()) {
+                Ok(text) => text,
+                Err(_) => {
+                    session.error(EventError::invalid("Events must be UTF-8 JSON."), None);
+                    session.flush().await;
+                    continue;
+                }
+            },
+            Message::Close(_) => break,
+            Message::Ping(_) | Message::Pong(_) => continue,
+        };
+        let value = match serde_json::from_str::<Value>(&text) {
+            Ok(value) => value,
+            Err(e) => {
+                session.error(EventError::invalid(format!("Invalid JSON: {e}")), None);
+                session.flush().await;
+                continue;
+            }
+        };
+        let event_id = value
+            .get("event_id")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let result = serde_json::from_value::<ClientEvent>(value)
+            .map_err(|e| EventError::invalid(format!("Invalid event: {e}")))
+            .and_then(|event| session.handle(event));
+        if let Err(e) = result {
+            session.error(e, event_id.as_deref());
+        }
+        session.flush().await;
+    }
+
+    // Aborting the turn tasks drops their response channels, which cancels their sequences.
+    // Queued ones never started.
+    for job in session.jobs.drain(..) {
+        job.abort();
+    }
+    drop(session);
+    let _ = writer.await;
+}
+
+/// Connection state owned by the task reading client events.
+struct Session {
+    shared: Arc<Shared>,
+    config: RealtimeSession,
+    /// Uncommitted mono audio at the session's input sample rate.
+    buffer: Vec<f32>,
+    vad: Option<VoiceActivityDetector>,
+    /// Turn tasks still running, aborted when the connection closes. At most
+    /// [`MAX_QUEUED_TURNS`], counting `queued`.
+    jobs: Vec<AbortHandle>,
+    /// Events from handling the current client event, sent by [`Session::flush`].
+    outbox: Vec<Value>,
+    /// Turn tasks from handling the current client event. They start once its events are sent,
+    /// so their own events come after them.
+    queued: Vec<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    /// The last turn task; each one waits for its predecessor so the conversation stays in
+    /// order.
+    tail: Option<JoinHandle<()>>,
+}
+
+impl Session {
+    fn new(shared: Shared, model: String) -> Self {
+        let config = RealtimeSession::new(model);
+        let vad = config
+            .turn_detection
+            .clone()
+            .map(|td| VoiceActivityDetector::new(td, config.input_sample_rate));
+        Self {
+            shared: Arc::new(shared),
+            config,
+            buffer: Vec::new(),
+            vad,
+            jobs: Vec::new(),
+            outbox: Vec::new(),
+            queued: Vec::new(),
+            tail: None,
+        }
+    }
+
+    fn emit(&mut self, event: Value) {
+        self.outbox.push(event);
+    }
+
+    fn error(&mut self, error: EventError, client_event_id: Option<&str>) {
+        self.emit(error_event(error, client_event_id));
+    }
+
+    /// Send the queued events, then start the queued turn tasks.
+    async fn flush(&mut self) {
+        for event in mem::take(&mut self.outbox) {
+            self.shared.events.emit(event).await;
+        }
+        for job in mem::take(&mut self.queued) {
+            let previous = self.tail.take();
+            let handle = tokio::spawn(async move {
+                if let Some(previous) = previous {
+                    let _ = previous.await;
+                }
+                job.await;
+            });
+            self.jobs.push(handle.abort_handle());
+            self.tail = Some(handle);
+        }
+    }
+
+    fn handle(&mut self, event: ClientEvent) -> Result<(), EventError> {
+        match event {
+            ClientEvent::SessionUpdate { session } => self.update(session),
+            ClientEvent::InputAudioBufferAppend { audio } => self.append(&audio),
+            ClientEvent::InputAudioBufferCommit => self.commit(),
+            ClientEvent::InputAudioBufferClear => {
+                self.buffer.clear();
+                if let Some(vad) = &mut self.vad {
+                    vad.reset();
+                }
+                self.emit(json!({ "type": "input_audio_buffer.cleared" }));
+                Ok(())
+            }
+            ClientEvent::ConversationItemCreate { item } => self.create_item(item),
+            ClientEvent::ResponseCreate { response } => {
+                self.shared.check_quota()?;
+                self.create_response(response.unwrap_or_default())
+            }
+            ClientEvent::ResponseCancel => {
+                if self.cancel_response() {
+                    Ok(())
+                } else {
+                    Err(EventError::invalid("There is no response in progress."))
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, update: SessionUpdate) -> Result<(), EventError> {
+        let mut config = self.config.clone();
+        config.apply(update).map_err(EventError::invalid)?;
+
+        let state = &self.shared.state;
+        if config.model != self.config.model {
+            validate_model_name(&config.model, state.clone())
+                .map_err(|e| EventError::invalid(e.to_string()))?;
+            self.shared.check_model(&config.model)?;
+        }
+        if let Some(model) = &config.input_audio_transcription.model {
+            if !matches!(
+                state.get_model_category(Some(model.as_str())),
+                Ok(ModelCategory::Multimodal { .. })
+            ) {
+                return Err(EventError::invalid(format!(
+                    "Model '{model}' does not accept audio input."
+                )));
+            }
+            self.shared.check_model(model)?;
+        }
+        if let Some(model) = &config.speech_model {
+            if !matches!(
+                state.get_model_category(Some(model.as_str())),
+                Ok(ModelCategory::Speech)
+            ) {
+                return Err(EventError::invalid(format!(
+                    "Model '{model}' is not a speech model."
+                )));
+            }
+            self.shared.check_model(model)?;
+        }
+
+        if config.input_sample_rate != self.config.input_sample_rate
+            || config.turn_detection != self.config.turn_detection
+        {
+            self.buffer.clear();
+            self.vad = config
+                .turn_detection
+                .clone()
+                .map(|td| VoiceActivityDetector::new(td, config.input_sample_rate));
+        }
+        self.config = config;
+        self.emit(json!({
+            "type": "session.updated",
+            "session": self.config,
+        }));
+        Ok(())
+    }
+
+    fn append(&mut self, audio: &str) -> Result<(), EventError> {
+        let samples = decode_append(audio, self.buffer.len(), self.config.input_sample_rate)?;
+        self.buffer.extend_from_slice(&samples);
+
+        let Some(vad) = &mut self.vad else {
+            return Ok(());
+        };
+        let vad_events = vad.push(&samples);
+        let speaking = vad.speaking();
+        let TurnDetection {
+            prefix_padding_ms,
+            create_response,
+            interrupt_response,
+            ..
+        } = vad.config;
+        for event in vad_events {
+            match event {
+                VadEvent::SpeechStarted { audio_start_ms } => {
+                    self.emit(json!({
+                        "type": "input_audio_buffer.speech_started",
+                        "audio_start_ms": audio_start_ms,
+                    }));
+                    if interrupt_response {
+                        self.cancel_response();
+                    }
+                }
+                VadEvent::SpeechStopped { audio_end_ms } => {
+                    self.emit(json!({
+                        "type": "input_audio_buffer.speech_stopped",
+                        "audio_end_ms": audio_end_ms,
+                    }));
+                    self.commit()?;
+                    if create_response {
+                        self.shared.check_quota()?;
+                        self.create_response(ResponseOverrides::default())?;
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        // Between turns, keep only the padding that may precede the next one.
+        if !speaking {
+            let keep = ms_to_samples(prefix_padding_ms, self.config.input_sample_rate);
+            if self.buffer.len() > keep {
+                self.buffer.drain(..self.buffer.len() - keep);
+            }
+        }
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<(), EventError> {
+        let min_samples = ms_to_samples(MIN_COMMIT_MS as u64, self.config.input_sample_rate);
+        if self.buffer.len() < min_samples {
+            return Err(EventError::invalid(format!(
+                "Audio buffer too small to commit: expected at least {MIN_COMMIT_MS} ms of audio."
+            )));
+        }
+        let model = self.transcription_model()?;
+        self.check_turn_capacity()?;
+        let audio = AudioInput {
+            samples: mem::take(&mut self.buffer),
+            sample_rate: self.config.input_sample_rate,
+            channels: 1,
+        };
+        if let Some(vad) = &mut self.vad {
+            vad.reset();
+        }
+
+        let item = ConversationItem {
+            id: new_item_id(),
+            role: "user".to_string(),
+            text: None,
+        };
+        let item_id = item.id.clone();
+        let previous_item_id = self.shared.push_item(item);
+        self.emit(json!({
+            "type": "input_audio_buffer.committed",
+            "previous_item_id": previous_item_id,
+            "item_id": item_id,
+        }));
+        self.emit(json!({
+            "type": "conversation.item.created",
+            "previous_item_id": previous_item_id,
+            "item": item_json(&item_id, "user", "in_progress", "input_audio", None),
+        }));
+
+        let shared = self.shared.clone();
+        let options = self.config.input_audio_transcription.clone();
+        self.spawn(async move {
+            transcribe_turn(shared, model, item_id, audio, options).await;
+        });
+        Ok(())
+    }
+
+    fn create_item(&mut self, item: ClientItem) -> Result<(), EventError> {
+        if item.kind != "message" {
+            return Err(EventError::invalid(format!(
+                "Unsupported item type '{}'. Only message items are supported.",
+                item.kind
+            )));
+        }
+        if !matches!(item.role.as_str(), "user" | "assistant" | "system") {
+            return Err(EventError::invalid(format!(
+                "Unsupported role '{}'.",
+                item.role
+            )));
+        }
+        let mut text = String::new();
+        for part in item.content {
+            match (part.kind.as_str(), part.text) {
+                ("input_text" | "text", Some(part)) => text.push_str(&part),
+                (kind, _) => {
+                    return Err(EventError::invalid(format!(
+                        "Unsupported content type '{kind}'. Send text parts, and stream audio \
+                         with input_audio_buffer.append."
+                    )))
+                }
+            }
+        }
+
+        let content_type = if item.role == "assistant" {
+            "text"
+        } else {
+            "input_text"
+        };
+        let item = ConversationItem {
+            id: new_item_id(),
+            role: item.role,
+            text: Some(text),
+        };
+        let created = item_json(
+            &item.id,
+            &item.role,
+            "completed",
+            content_type,
+            item.text.as_deref(),
+        );
+        let previous_item_id = self.shared.push_item(item);
+        self.emit(json!({
+            "type": "conversation.item.created",
+            "previous_item_id": previous_item_id,
+            "item": created,
+        }));
+        Ok(())
+    }
+
+    fn create_response(&mut self, overrides: ResponseOverrides) -> Result<(), EventError> {
+        self.check_turn_capacity()?;
+        let mut config = self.config.clone();
+        if let Some(modalities) = overrides.modalities {
+            config.modalities = modalities;
+        }
+        if let Some(instructions) = overrides.instructions {
+            config.instructions = Some(instructions);
+        }
+        let speech_model = if config.audio_output() {
+            Some(self.speech_model()?)
+        } else {
+            None
+        };
+        let shared = self.shared.clone();
+        self.spawn(async move {
+            respond(shared, config, speech_model).await;
+        });
+        Ok(())
+    }
+
+    /// Cancel the response being generated. Returns whether there was one.
+    fn cancel_response(&self) -> bool {
+        match self.shared.active_response.lock().unwrap().as_ref() {
+            Some(cancel) => {
+                cancel.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run `job` after every job spawned before it, once the current events are sent.
+    fn spawn(&mut self, job: impl Future<Output = ()> + Send + 'static) {
+        self.queued.push(Box::pin(job));
+    }
+
+    /// Fails when [`MAX_QUEUED_TURNS`] turn tasks are already running or waiting.
+    fn check_turn_capacity(&mut self) -> Result<(), EventError> {
+        self.jobs.retain(|job| !job.is_finished());
+        if self.jobs.len() + self.queued.len() >= MAX_QUEUED_TURNS {
+            return Err(EventError::rate_limit(format!(
+                "{MAX_QUEUED_TURNS} turns are already pending; wait for one to finish."
+            )));
+        }
+        Ok(())
+    }
+
+    fn transcription_model(&self) -> Result<String, EventError> {
+        let state = &self.shared.state;
+        let model = match &self.config.input_audio_transcription.model {
+            Some(model) => model.clone(),
+            None if matches!(
+                state.get_model_category(Some(self.config.model.as_str())),
+                Ok(ModelCategory::Multimodal { .. })
+            ) =>
+            {
+                self.config.model.clone()
+            }
+            None => first_model(state, |category| {
+                matches!(category, ModelCategory::Multimodal { .. })
+            })
+            .ok_or_else(|| {
+                EventError::invalid(
+                    "No audio-capable model is loaded to transcribe input audio. Load one such \
+                     as Voxtral or Gemma 3n.",
+                )
+            })?,
+        };
+        self.shared.check_model(&model)?;
+        Ok(model)
+    }
+
+    fn speech_model(&self) -> Result<String, EventError> {
+        let model = match &self.config.speech_model {
+            Some(model) => model.clone(),
+            None => first_model(&self.shared.state, |category| {
+                matches!(category, ModelCategory::Speech)
+            })
+            .ok_or_else(|| {
+                EventError::invalid(
+                    "No speech model is loaded for audio output. Load one such as Dia, or set \
+                     modalities to [\"text\"].",
+                )
+            })?,
+        };
+        self.shared.check_model(&model)?;
+        Ok(model)
+    }
+}
+
+/// The first loaded model whose category matches.
+fn first_model(
+    state: &SharedMistralRsState,
+    matches: impl Fn(&ModelCategory) -> bool,
+) -> Option<String> {
+    state.list_models().ok()?.into_iter().find(|model| {
+        state
+            .get_model_category(Some(model.as_str()))
+            .is_ok_and(|category| matches(&category))
+    })
+}
+
+async fn transcribe_turn(
+    shared: Arc<Shared>,
+    model: String,
+    item_id: String,
+    audio: AudioInput,
+    options: InputAudioTranscription,
+) {
+    let result = transcribe_audio(
+        &shared.state,
+        &model,
+        audio,
+        options.language,
+        options.prompt,
+    )
+    .await;
+    match result {
+        Ok((transcript, usage)) => {
+            shared.charge(usage.as_ref().map_or(0, |usage| usage.total_tokens));
+            if let Some(item) = shared
+                .conversation
+                .lock()
+                .unwrap()
+                .iter_mut()
+                .find(|item| item.id == item_id)
+            {
+                item.text = Some(transcript.clone());
+            }
+            let mut event = json!({
+                "type": "conversation.item.input_audio_transcription.completed",
+                "item_id": item_id,
+                "content_index": 0,
+                "transcript": transcript,
+            });
+            if let Some(usage) = usage {
+                event["usage"] = json!(usage);
+            }
+            shared.events.emit(event).await;
+        }
+        Err(e) => {
+            shared
+                .events
+                .emit(json!({
+                    "type": "conversation.item.input_audio_transcription.failed",
+                    "item_id": item_id,
+                    "content_index": 0,
+                    "error": {
+                        "type": "transcription_error",
+                        "message": sanitize_error_message(&*e),
+                    },
+                }))
+                .await
+        }
+    }
+}
+
+/// How a response ended.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ResponseStatus {
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+impl ResponseStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Cancelled => "cancelled",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+async fn respond(shared: Arc<Shared>, config: RealtimeSession, speech_model: Option<String>) {
+    let response_id = format!("resp_{}", Uuid::new_v4().simple());
+    let item_id = new_item_id();
+    let events = shared.events.clone();
+    let (content_type, delta_event) = if speech_model.is_some() {
+        ("audio", "response.audio_transcript.delta")
+    } else {
+        ("text", "response.text.delta")
+    };
+
+    let cancel = Arc::new(Notify::new());
+    *shared.active_response.lock().unwrap() = Some(cancel.clone());
+
+    events
+        .emit(json!({
+            "type": "response.created",
+            "response": {
+                "id": response_id,
+                "object": "realtime.response",
+                "status": "in_progress",
+                "output": [],
+                "usage": Value::Null,
+            },
+        }))
+        .await;
+    events
+        .emit(json!({
+            "type": "response.output_item.added",
+            "response_id": response_id,
+            "output_index": 0,
+            "item": item_json(&item_id, "assistant", "in_progress", content_type, None),
+        }))
+        .await;
+
+    // Finished sentences are voiced while the rest of the reply is generated.
+    let mut speech = speech_model.map(|model| {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let synth = SpeechTarget {
+            shared: shared.clone(),
+            model,
+            voice: config.voice.clone(),
+            sample_rate: config.output_sample_rate,
+            response_id: response_id.clone(),
+            item_id: item_id.clone(),
+        };
+        (tx, tokio::spawn(synth.run(rx)))
+    });
+
+    let mut text = String::new();
+    let mut pending = String::new();
+    let mut usage = None;
+    let mut status = ResponseStatus::Completed;
+
+    let generation = async {
+        let mut rx = start_generation(&shared, &config).await?;
+        while let Some(response) = rx.recv().await {
+            match response {
+                Response::Chunk(chunk) => {
+                    if chunk.usage.is_some() {
+                        usage = chunk.usage;
+                    }
+                    let Some(choice) = chunk.choices.first() else {
+                        continue;
+                    };
+                    let delta = choice.delta.content.as_deref().unwrap_or_default();
+                    if !delta.is_empty() {
+                        text.push_str(delta);
+                        events
+                            .emit(json!({
+                                "type": delta_event,
+                                "response_id": response_id,
+                                "item_id": item_id,
+                                "output_index": 0,
+                                "content_index": 0,
+                                "delta": delta,
+                            }))
+                            .await;
+                        if let Some((sentences, _)) = &speech {
+                            pending.push_str(delta);
+                            for sentence in drain_sentences(&mut pending) {
+                                let _ = sentences.send(sentence);
+                            }
+                        }
+                    }
+                    if choice.finish_reason.is_some() {
+                        break;
+                    }
+                }
+                Response::ModelError(message, _) | Response::CompletionModelError(message, _) => {
+                    anyhow::bail!(message)
+                }
+                Response::InternalError(e) | Response::ValidationError(e) => anyhow::bail!(e),
+                _ => continue,
+            }
+        }
+        // Voice whatever trails the last sentence break, then wait for the audio to finish.
+        if let Some((sentences, synth)) = speech.take() {
+            let rest = pending.trim();
+            if !rest.is_empty() {
+                let _ = sentences.send(rest.to_string());
+            }
+            drop(sentences);
+            synth.await??;
+        }
+        Ok::<_, anyhow::Error>(())
+    };
+
+    let result = tokio::select! {
+        result = generation => result,
+        _ = cancel.notified() => {
+            status = ResponseStatus::Cancelled;
+            Ok(())
+        }
+    };
+    if let Some((_, synth)) = speech {
+        synth.abort();
+    }
+    *shared.active_response.lock().unwrap() = None;
+    if let Some(usage) = &usage {
+        shared.charge(usage.total_tokens);
+    }
+    if let Err(e) = result {
+        status = ResponseStatus::Failed;
+        events.error(EventError::server(&e), None).await;
+    }
+
+    if content_type == "audio" {
+        events
+            .emit(json!({
+                "type": "response.audio.done",
+                "response_id": response_id,
+                "item_id": item_id,
+                "output_index": 0,
+                "content_index": 0,
+            }))
+            .await;
+        events
+            .emit(json!({
+                "type": "response.audio_transcript.done",
+                "response_id": response_id,
+                "item_id": item_id,
+                "output_index": 0,
+                "content_index": 0,
+                "transcript": text,
+            }))
+            .await;
+    } else {
+        events
+            .emit(json!({
+                "type": "response.text.done",
+                "response_id": response_id,
+                "item_id": item_id,
+                "output_index": 0,
+                "content_index": 0,
+                "text": text,
+            }))
+            .await;
+    }
+    let item_status = match status {
+        ResponseStatus::Completed => "completed",
+        _ => "incomplete",
+    };
+    let item = item_json(
+        &item_id,
+        "assistant",
+        item_status,
+        content_type,
+        Some(&text),
+    );
+    events
+        .emit(json!({
+            "type": "response.output_item.done",
+            "response_id": response_id,
+            "output_index": 0,
+            "item": item,
+        }))
+        .await;
+
+    // A cut-off reply stays in the conversation as far as it got.
+    if !text.is_empty() {
+        shared.push_item(ConversationItem {
+            id: item_id,
+            role: "assistant".to_string(),
+            text: Some(text),
+        });
+    }
+    events
+        .emit(json!({
+            "type": "response.done",
+            "response": {
+                "id": response_id,
+                "object": "realtime.response",
+                "status": status.as_str(),
+                "output": [item],
+                "usage": usage.map(|usage: Usage| json!({
+                    "input_tokens": usage.prompt_tokens,
+                    "output_tokens": usage.completion_tokens,
+                    "total_tokens": usage.total_tokens,
+                })),
+            },
+        }))
+        .await;
+}
+
+/// Send the conversation so far to the session's text model as a streaming chat request.
+async fn start_generation(
+    shared: &Shared,
+    config: &RealtimeSession,
+) -> Result<mpsc::Receiver<Response>> {
+    let mut messages = Vec::new();
+    if let Some(instructions) = config.instructions.as_ref().filter(|i| !i.is_empty()) {
+        messages.push(chat_message("system", instructions.clone()));
+    }
+    let conversation = shared.conversation.lock().unwrap().clone();
+    // Audio turns that failed to transcribe are left out.
+    for item in &conversation {
+        if let Some(text) = item.text.as_ref().filter(|t| !t.is_empty()) {
+            messages.push(chat_message(&item.role, text.clone()));
+        }
+    }
+
+    let mut sampling_params = SamplingParams::deterministic();
+    if config.temperature > 0.0 {
+        sampling_params.temperature = Some(config.temperature);
+        sampling_params.top_k = None;
+    }
+    sampling_params.max_len = config.max_response_output_tokens;
+
+    let (tx, rx) = create_response_channel(None);
+    let request = Request::Normal(Box::new(NormalRequest {
+        id: shared.state.next_request_id(),
+        messages: RequestMessage::Chat {
+            messages,
+            enable_thinking: Some(false),
+            reasoning_effort: None,
+        },
+        sampling_params,
+        response: tx,
+        return_logprobs: false,
+        is_streaming: true,
+        suffix: None,
+        constraint: Constraint::None,
+        tool_choice: None,
+        tools: None,
+        logits_processors: None,
+        return_raw_logits: false,
+        web_search_options: None,
+        enable_code_execution: false,
+        code_execution_permission: None,
+        code_execution_approval_notifier: None,
+        agent_permission: None,
+        agent_approval_handler: None,
+        agent_approval_notifier: None,
+        max_tool_rounds: None,
+        tool_dispatch_url: None,
+        model_id: Some(config.model.clone()),
+        truncate_sequence: true,
+        session_id: None,
+        files: None,
+        input_files: None,
+        priority: RequestPriority::High,
+        timeout: None,
+        trace_context: None,
+        user: shared.user(),
+        prediction: None,
+        agentic_permit: None,
+    }));
+    send_request_with_model(&shared.state, request, Some(&config.model)).await?;
+    Ok(rx)
+}
+
+fn chat_message(role: &str, text: String) -> IndexMap<String, MessageContent> {
+    let mut message = IndexMap::new();
+    message.insert("role".to_string(), Either::Left(role.to_string()));
+    message.insert("content".to_string(), Either::Left(text));
+    message
+}
+
+/// Where synthesized speech for one response goes.
+struct SpeechTarget {
+    shared: Arc<Shared>,
+    model: String,
+    voice: String,
+    sample_rate: u32,
+    response_id: String,
+    item_id: String,
+}
+
+impl SpeechTarget {
+    /// Synthesize sentences in order, streaming each as `response.audio.delta` events.
+    async fn run(self, mut sentences: mpsc::UnboundedReceiver<String>) -> Result<()> {
+        let chunk_bytes = ms_to_samples(AUDIO_DELTA_MS as u64, self.sample_rate) * 2;
+        while let Some(sentence) = sentences.recv().await {
+            let audio = self.synthesize(sentence).await?;
+            let mono = AudioInput {
+                samples: audio.to_mono(),
+                sample_rate: audio.sample_rate,
+                channels: 1,
+            }
+            .resample(self.sample_rate);
+            let pcm: Vec<u8> = mono
+                .samples
+                .iter()
+                .flat_map(|sample| sample.to_i16().to_le_bytes())
+                .collect();
+            for chunk in pcm.chunks(chunk_bytes.max(2)) {
+                self.shared
+                    .events
+                    .emit(json!({
+                        "type": "response.audio.delta",
+                        "response_id": self.response_id,
+                        "item_id": self.item_id,
+                        "output_index": 0,
+                        "content_index": 0,
+                        "delta": STANDARD.encode(chunk),
+                    }))
+                    .await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn synthesize(&self, text: String) -> Result<AudioInput> {
+        let (tx, mut rx) = create_response_channel(None);
+        let request = Request::Normal(Box::new(NormalRequest {
+            id: self.shared.state.next_request_id(),
+            messages: RequestMessage::SpeechGeneration {
+                prompt: format!("[{}] {text}", self.voice),
+                reference: None,
+            },
+            sampling_params: SamplingParams::deterministic(),
+            response: tx,
+            return_logprobs: false,
+            is_streaming: false,
+            suffix: None,
+            constraint: Constraint::None,
+            tool_choice: None,
+            tools: None,
+            logits_processors: None,
+            return_raw_logits: false,
+            web_search_options: None,
+            enable_code_execution: false,
+            code_execution_permission: None,
+            code_execution_approval_notifier: None,
+            agent_permission: None,
+            agent_approval_handler: None,
+            agent_approval_notifier: None,
+            max_tool_rounds: None,
+            tool_dispatch_url: None,
+            model_id: Some(self.model.clone()),
+            truncate_sequence: false,
+            session_id: None,
+            files: None,
+            input_files: None,
+            priority: RequestPriority::High,
+            timeout: None,
+            trace_context: None,
+            user: self.shared.user(),
+            prediction: None,
+            agentic_permit: None,
+        }));
+        send_request_with_model(&self.shared.state, request, Some(&self.model)).await?;
+
+        match rx.recv().await {
+            Some(Response::Speech {
+                pcm,
+                rate,
+                channels,
+            }) => Ok(AudioInput {
+                samples: pcm.to_vec(),
+                sample_rate: rate as u32,
+                channels: channels as u16,
+            }),
+            Some(Response::ModelError(message, _)) => anyhow::bail!(message),
+            Some(Response::InternalError(e) | Response::ValidationError(e)) => anyhow::bail!(e),
+            Some(_) => anyhow::bail!("Unexpected response from speech model."),
+            None => anyhow::bail!("Speech model stopped without a response."),
+        }
+    }
+}
+
+/// Split finished sentences off the front of `pending`, leaving the unfinished rest.
+fn drain_sentences(pending: &mut String) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = pending.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let ends = match c {
+            '\n' => true,
+            '.' | '!' | '?' => chars.peek().is_some_and(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if ends {
+            let end = i + c.len_utf8();
+            let sentence = pending[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence.to_string());
+            }
+            start = end;
+        }
+    }
+    pending.drain(..start);
+    sentences
+}
+
+/// Little-endian PCM16 to samples in [-1.0, 1.0).
+fn decode_pcm16(bytes: &[u8]) -> Option<Vec<f32>> {
+    if bytes.len() % 2 != 0 {
+        return None;
+    }
+    Some(
+        bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+    )
+}
+
+fn ms_to_samples(ms: u64, sample_rate: u32) -> usize {
+    (ms * sample_rate as u64 / 1000) as usize
+}
+
+/// Decode the audio of an `input_audio_buffer.append` event, rejecting it if it is too large or
+/// would grow a buffer already holding `buffered` samples past [`MAX_BUFFER_MS`].
+fn decode_append(audio: &str, buffered: usize, sample_rate: u32) -> Result<Vec<f32>, EventError> {
+    let bytes = STANDARD
+        .decode(audio)
+        .map_err(|e| EventError::invalid(format!("Invalid base64 audio: {e}")))?;
+    if bytes.len() > MAX_APPEND_BYTES {
+        return Err(EventError::invalid(format!(
+            "Audio chunk of {} bytes exceeds the {MAX_APPEND_BYTES} byte limit per append.",
+            bytes.len()
+        )));
+    }
+    let samples = decode_pcm16(&bytes)
+        .ok_or_else(|| EventError::invalid("PCM16 audio must have an even byte length."))?;
+    if buffered + samples.len() > ms_to_samples(MAX_BUFFER_MS, sample_rate) {
+        return Err(EventError::invalid(format!(
+            "Input audio buffer is full: it holds at most {} s of audio. Commit or clear it \
+             before appending more.",
+            MAX_BUFFER_MS / 1000
+        )));
+    }
+    Ok(samples)
+}
+
+fn new_item_id() -> String {
+    format!("item_{}", Uuid::new_v4().simple())
+}
+
+fn item_json(id: &str, role: &str, status: &str, content_type: &str, text: Option<&str>) -> Value {
+    let content = match content_type {
+        "input_audio" | "audio" => json!({ "type": content_type, "transcript": text }),
+        _ => json!({ "type": content_type, "text": text }),
+    };
+    json!({
+        "id": id,
+        "object": "realtime.item",
+        "type": "message",
+        "status": status,
+        "role": role,
+        "content": [content],
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum VadEvent {
+    SpeechStarted { audio_start_ms: u64 },
+    SpeechStopped { audio_end_ms: u64 },
+}
+
+/// Energy-based voice activity detection: 20 ms frames whose RMS exceeds the threshold are
+/// voiced. Offsets are in milliseconds of audio since the detector was last reset.
+struct VoiceActivityDetector {
+    config: TurnDetection,
+    frame_len: usize,
+    frame: Vec<f32>,
+    elapsed_ms: u64,
+    /// Start of the current run of voiced frames, while speech isn't confirmed yet.
+    voiced_since: Option<u64>,
+    speaking: bool,
+    silence_ms: u64,
+}
+
+impl VoiceActivityDetector {
+    fn new(config: TurnDetection, sample_rate: u32) -> Self {
+        Self {
+            config,
+            frame_len: ms_to_samples(VAD_FRAME_MS, sample_rate).max(1),
+            frame: Vec::new(),
+            elapsed_ms: 0,
+            voiced_since: None,
+            speaking: false,
+            silence_ms: 0,
+        }
+    }
+
+    fn speaking(&self) -> bool {
+        self.speaking || self.voiced_since.is_some()
+    }
+
+    fn reset(&mut self) {
+        self.frame.clear();
+        self.elapsed_ms = 0;
+        self.voiced_since = None;
+        self.speaking = false;
+        self.silence_ms = 0;
+    }
+
+    fn push(&mut self, samples: &[f32]) -> Vec<VadEvent> {
+        let mut events = Vec::new();
+        for &sample in samples {
+            self.frame.push(sample);
+            if self.frame.len() < self.frame_len {
+                continue;
+            }
+            let rms =
+                (self.frame.iter().map(|s| s * s).sum::<f32>() / self.frame.len() as f32).sqrt();
+            self.frame.clear();
+            let frame_start = self.elapsed_ms;
+            self.elapsed_ms += VAD_FRAME_MS;
+            let voiced = rms > self.config.threshold;
+
+            if self.speaking {
+                if voiced {
+                    self.silence_ms = 0;
+                } else {
+                    self.silence_ms += VAD_FRAME_MS;
+                    if self.silence_ms >= self.config.silence_duration_ms {
+                        self.speaking = false;
+                        self.silence_ms = 0;
+                        events.push(VadEvent::SpeechStopped {
+                            audio_end_ms: self.elapsed_ms - self.config.silence_duration_ms,
+                        });
+                    }
+                }
+            } else if voiced {
+                let since = *self.voiced_since.get_or_insert(frame_start);
+                if self.elapsed_ms - since >= VAD_MIN_SPEECH_MS {
+                    self.voiced_since = None;
+                    self.speaking = true;
+                    events.push(VadEvent::SpeechStarted {
+                        audio_start_ms: since,
+                    });
+                }
+            } else {
+                self.voiced_since = None;
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(ms: u64, amplitude: f32, sample_rate: u32) -> Vec<f32> {
+        (0..ms_to_samples(ms, sample_rate))
+            .map(|i| if i % 2 == 0 { amplitude } else { -amplitude })
+            .collect()
+    }
+
+    #[test]
+    fn vad_detects_turns() {
+        let rate = 16_000;
+        let mut vad = VoiceActivityDetector::new(TurnDetection::default(), rate);
+        assert!(vad.push(&tone(200, 0.0, rate)).is_empty());
+        // A click shorter than the minimum speech length is ignored.
+        assert!(vad.push(&tone(40, 0.5, rate)).is_empty());
+        assert!(vad.push(&tone(100, 0.0, rate)).is_empty());
+
+        assert_eq!(
+            vad.push(&tone(300, 0.5, rate)),
+            vec![VadEvent::SpeechStarted {
+                audio_start_ms: 340
+            }]
+        );
+        // Pauses shorter than `silence_duration_ms` don't end the turn.
+        assert!(vad.push(&tone(200, 0.0, rate)).is_empty());
+        assert!(vad.push(&tone(100, 0.5, rate)).is_empty());
+        assert_eq!(
+            vad.push(&tone(600, 0.0, rate)),
+            vec![VadEvent::SpeechStopped { audio_end_ms: 940 }]
+        );
+        assert!(!vad.speaking());
+    }
+
+    #[test]
+    fn session_update_is_partial_and_validated() {
+        let mut session = RealtimeSession::new("qwen".to_string());
+        let update: SessionUpdate = serde_json::from_value(json!({
+            "instructions": "Be brief.",
+            "turn_detection": { "type": "server_vad", "silence_duration_ms": 800 },
+        }))
+        .unwrap();
+        session.apply(update).unwrap();
+        assert_eq!(session.instructions.as_deref(), Some("Be brief."));
+        let turn_detection = session.turn_detection.clone().unwrap();
+        assert_eq!(turn_detection.silence_duration_ms, 800);
+        assert_eq!(turn_detection.prefix_padding_ms, 300);
+        assert_eq!(session.voice, "S1");
+
+        let update: SessionUpdate =
+            serde_json::from_value(json!({ "turn_detection": null, "instructions": null }))
+                .unwrap();
+        session.apply(update).unwrap();
+        assert!(session.turn_detection.is_none());
+        assert!(session.instructions.is_none());
+
+        let update: SessionUpdate =
+            serde_json::from_value(json!({ "voice": "alloy", "temperature": 0.2 })).unwrap();
+        assert!(session.apply(update).is_err());
+        assert_eq!(session.temperature, 0.8);
+    }
+
+    #[test]
+    fn client_events_parse() {
+        let event: ClientEvent = serde_json::from_value(json!({
+            "event_id": "e1",
+            "type": "input_audio_buffer.commit",
+        }))
+        .unwrap();
+        assert!(matches!(event, ClientEvent::InputAudioBufferCommit));
+
+        let event: ClientEvent = serde_json::from_value(json!({
+            "type": "conversation.item.create",
+            "item": {
+                "type": "message",
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "Hi" }],
+            },
+        }))
+        .unwrap();
+        let ClientEvent::ConversationItemCreate { item } = event else {
+            panic!("expected conversation.item.create");
+        };
+        assert_eq!(item.role, "user");
+        assert_eq!(item.content[0].text.as_deref(), Some("Hi"));
+
+        let event: ClientEvent =
+            serde_json::from_value(json!({ "type": "response.create" })).unwrap();
+        assert!(matches!(
+            event,
+            ClientEvent::ResponseCreate { response: None }
+        ));
+    }
+
+    #[test]
+    fn sentences_are_split_at_boundaries() {
+        let mut pending = "Hello there. How are you? I'm fi".to_string();
+        assert_eq!(
+            drain_sentences(&mut pending),
+            vec!["Hello there.", "How are you?"]
+        );
+        assert_eq!(pending, " I'm fi");
+        pending.push_str("ne, 3.5 times over!");
+        assert!(drain_sentences(&mut pending).is_empty());
+        pending.push('\n');
+        assert_eq!(
+            drain_sentences(&mut pending),
+            vec!["I'm fine, 3.5 times over!"]
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn pcm16_decoding() {
+        let bytes = [0x00, 0x80, 0xff, 0x7f, 0x00, 0x00];
+        assert_eq!(
+            decode_pcm16(&bytes).unwrap(),
+            vec![-1.0, 32767.0 / 32768.0, 0.0]
+        );
+        assert!(decode_pcm16(&bytes[..3]).is_none());
+    }
+
+    #[test]
+    fn appends_are_capped() {
+        let rate = 16_000;
+        let chunk = STANDARD.encode([0u8; 3200]);
+        assert_eq!(decode_append(&chunk, 0, rate).unwrap().len(), 1600);
+
+        // The buffer may fill up to the limit but not past it.
+        let limit = ms_to_samples(MAX_BUFFER_MS, rate);
+        assert!(decode_append(&chunk, limit - 1600, rate).is_ok());
+        let full = decode_append(&chunk, limit - 1599, rate).unwrap_err();
+        assert_eq!(full.kind, "invalid_request_error");
+
+        let oversized = STANDARD.encode(vec![0u8; MAX_APPEND_BYTES + 2]);
+        assert!(decode_append(&oversized, 0, rate).is_err());
+    }
+}
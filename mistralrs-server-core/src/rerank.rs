@@ -0,0 +1,263 @@
+//! Document reranking endpoint.
+//!
+//! Cross-encoder models score each query/document pair directly. Any other embedding model is
+//! used as a bi-encoder: the query and the documents are embedded separately and ranked by
+//! cosine similarity.
+
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, Context, Error as AnyhowError, Result};
+use axum::{
+    extract::{Json, State},
+    http,
+    response::IntoResponse,
+};
+use futures::future::join_all;
+use mistralrs_core::{
+    cosine_similarity, CrossEncoderFormat, MistralRs, ModelCategory, RequestPriority,
+};
+use uuid::Uuid;
+
+use crate::{
+    embeddings::{fetch_embedding, EmbeddingWithUsage},
+    handler_core::{overloaded_response, ErrorToResponse, JsonError},
+    openai::{
+        RerankDocument, RerankRequest, RerankResponse, RerankResult, RerankResultDocument,
+        RerankUsage,
+    },
+    types::{ExtractedMistralRsState, SharedMistralRsState},
+    util::{sanitize_error_message, validate_model_name},
+};
+
+/// Represents different types of rerank responses.
+pub enum RerankResponder {
+    Json(RerankResponse),
+    InternalError(AnyhowError),
+    ValidationError(AnyhowError),
+}
+
+impl IntoResponse for RerankResponder {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            RerankResponder::Json(s) => Json(s).into_response(),
+            RerankResponder::InternalError(e) => overloaded_response(e.downcast_ref())
+                .unwrap_or_else(|| {
+                    JsonError::new(sanitize_error_message(e.root_cause()))
+                        .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
+                }),
+            RerankResponder::ValidationError(e) => {
+                JsonError::new(sanitize_error_message(e.root_cause()))
+                    .to_response(http::StatusCode::UNPROCESSABLE_ENTITY)
+            }
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    tag = "Mistral.rs",
+    path = "/v1/rerank",
+    request_body = RerankRequest,
+    responses((status = 200, description = "Documents ranked by relevance", body = RerankResponse))
+)]
+pub async fn rerank(
+    State(state): ExtractedMistralRsState,
+    Json(request): Json<RerankRequest>,
+) -> RerankResponder {
+    let repr = serde_json::to_string(&request).expect("Serialization of rerank request failed.");
+    MistralRs::maybe_log_request(state.clone(), repr);
+
+    if let Err(e) = validate_model_name(&request.model, state.clone()) {
+        return RerankResponder::ValidationError(e);
+    }
+    if request.documents.is_empty() {
+        return RerankResponder::ValidationError(anyhow!(
+            "documents must contain at least one entry."
+        ));
+    }
+
+    let model_id = (request.model != "default").then(|| request.model.clone());
+    match state.get_model_category(model_id.as_deref()) {
+        Ok(ModelCategory::Embedding) => (),
+        Ok(_) => {
+            return RerankResponder::ValidationError(anyhow!(
+                "Model `{}` is not an embedding or reranker model.",
+                request.model
+            ))
+        }
+        Err(e) => return RerankResponder::ValidationError(e.into()),
+    }
+    let cross_encoder = match state.cross_encoder_format(model_id.as_deref()) {
+        Ok(format) => format,
+        Err(e) => return RerankResponder::InternalError(e.into()),
+    };
+
+    let truncate_sequence = request.truncate_sequence.unwrap_or(false);
+    let scored = match cross_encoder {
+        Some(format) => {
+            score_pairs(
+                state.clone(),
+                format,
+                &request,
+                model_id.as_deref(),
+                truncate_sequence,
+            )
+            .await
+        }
+        None => {
+            score_by_similarity(
+                state.clone(),
+                &request,
+                model_id.as_deref(),
+                truncate_sequence,
+            )
+            .await
+        }
+    };
+    let (scores, prompt_tokens) = match scored {
+        Ok(scored) => scored,
+        Err(e) => {
+            MistralRs::maybe_log_error(state, e.as_ref());
+            return RerankResponder::InternalError(e);
+        }
+    };
+
+    let results = rank(&scores, request.top_n)
+        .into_iter()
+        .map(|(index, relevance_score)| RerankResult {
+            index,
+            relevance_score,
+            document: request.return_documents.then(|| RerankResultDocument {
+                text: request.documents[index].text().to_string(),
+            }),
+        })
+        .collect();
+
+    let response = RerankResponse {
+        id: format!("rerank-{}", Uuid::new_v4()),
+        model: request.model,
+        results,
+        usage: RerankUsage {
+            prompt_tokens,
+            total_tokens: prompt_tokens,
+        },
+    };
+
+    MistralRs::maybe_log_response(state, &response);
+
+    RerankResponder::Json(response)
+}
+
+/// Score each document with a cross-encoder. Returns the scores and the prompt tokens used.
+async fn score_pairs(
+    state: SharedMistralRsState,
+    format: CrossEncoderFormat,
+    request: &RerankRequest,
+    model_id: Option<&str>,
+    truncate_sequence: bool,
+) -> Result<(Vec<f32>, usize)> {
+    let futures = request.documents.iter().map(|document| {
+        let prompt = format.format_pair(
+            &request.query,
+            document.text(),
+            request.instruction.as_deref(),
+        );
+        fetch_embedding(
+            state.clone(),
+            prompt,
+            model_id,
+            truncate_sequence,
+            RequestPriority::Normal,
+        )
+    });
+
+    let mut scores = Vec::with_capacity(request.documents.len());
+    let mut prompt_tokens = 0usize;
+    for result in join_all(futures).await {
+        let EmbeddingWithUsage {
+            embedding,
+            prompt_tokens: tokens,
+            ..
+        } = result?;
+        scores.push(
+            embedding
+                .first()
+                .copied()
+                .context("Cross-encoder returned an empty score.")?,
+        );
+        prompt_tokens = prompt_tokens.saturating_add(tokens);
+    }
+    Ok((scores, prompt_tokens))
+}
+
+/// Score each document by cosine similarity between its embedding and the query's.
+async fn score_by_similarity(
+    state: SharedMistralRsState,
+    request: &RerankRequest,
+    model_id: Option<&str>,
+    truncate_sequence: bool,
+) -> Result<(Vec<f32>, usize)> {
+    let prompts = std::iter::once(request.query.as_str())
+        .chain(request.documents.iter().map(RerankDocument::text));
+    let futures = prompts.map(|prompt| {
+        fetch_embedding(
+            state.clone(),
+            prompt.to_string(),
+            model_id,
+            truncate_sequence,
+            RequestPriority::Normal,
+        )
+    });
+
+    let mut embeddings = Vec::with_capacity(request.documents.len() + 1);
+    let mut prompt_tokens = 0usize;
+    for result in join_all(futures).await {
+        let result = result?;
+        prompt_tokens = prompt_tokens.saturating_add(result.prompt_tokens);
+        embeddings.push(result.embedding);
+    }
+
+    let (query, documents) = embeddings
+        .split_first()
+        .context("Missing query embedding.")?;
+    let scores = documents
+        .iter()
+        .map(|document| cosine_similarity(query, document))
+        .collect();
+    Ok((scores, prompt_tokens))
+}
+
+/// Document indices with their scores, best first, truncated to `top_n`.
+fn rank(scores: &[f32], top_n: Option<usize>) -> Vec<(usize, f32)> {
+    let mut ranked: Vec<(usize, f32)> = scores.iter().copied().enumerate().collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    if let Some(top_n) = top_n {
+        ranked.truncate(top_n);
+    }
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_best_first_and_truncates() {
+        let ranked = rank(&[0.1, 0.9, 0.5], Some(2));
+        assert_eq!(ranked, vec![(1, 0.9), (2, 0.5)]);
+        assert_eq!(rank(&[0.3], Some(5)).len(), 1);
+    }
+
+    #[test]
+    fn documents_accept_strings_and_objects() {
+        let request: RerankRequest = serde_json::from_value(serde_json::json!({
+            "query": "capital of France",
+            "documents": ["Paris", {"text": "Berlin"}]
+        }))
+        .unwrap();
+        assert_eq!(request.model, "default");
+        assert!(request.return_documents);
+        let texts: Vec<_> = request.documents.iter().map(RerankDocument::text).collect();
+        assert_eq!(texts, ["Paris", "Berlin"]);
+    }
+}
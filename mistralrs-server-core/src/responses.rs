@@ -12,17 +12,18 @@ use std::{
 
 use anyhow::Result;
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, Path, Query, State},
     http::{self, StatusCode},
     response::{
         sse::{Event, KeepAlive, KeepAliveStream},
         IntoResponse, Sse,
     },
+    Extension,
 };
 use either::Either;
 use mistralrs_core::{ChatCompletionResponse, MistralRs, Request, Response};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use tokio::sync::mpsc::{Receiver, Sender};
 use utoipa::{
     openapi::{schema::SchemaType, ArrayBuilder, ObjectBuilder, OneOfBuilder, RefOr, Schema, Type},
@@ -31,13 +32,14 @@ use utoipa::{
 use uuid::Uuid;
 
 use crate::{
+    auth::{authenticated_key, authenticated_key_name, carry_key, AuthenticatedKey},
     background_tasks::get_background_task_manager,
-    cached_responses::get_response_cache,
+    cached_responses::{get_response_cache, with_response_cache},
     chat_completion::parse_request as parse_chat_request,
     completion_core::{handle_completion_error, BaseCompletionResponder},
     handler_core::{
-        create_response_channel, send_request_with_model, BaseJsonModelError, ErrorToResponse,
-        JsonError, ModelErrorMessage,
+        boxed_send_error, create_response_channel, overloaded_response, send_request_with_model,
+        BaseJsonModelError, ErrorToResponse, JsonError, ModelErrorMessage,
     },
     openai::{ChatCompletionRequest, Message, MessageContent, ToolCall},
     responses_types::{
@@ -215,10 +217,13 @@ fn convert_input_items_to_messages(items: Vec<InputItem>) -> Vec<Message> {
                                     filename,
                                 } => {
                                     has_non_text_content = true;
-                                    // Files are typically handled as text descriptions or references
-                                    let file_ref = if let Some(id) = file_id {
-                                        format!("[File reference: {}]", id)
-                                    } else if let Some(data) = file_data {
+                                    // Uploaded files are resolved by the chat parser; anything
+                                    // else is only described in text.
+                                    if let Some(id) = file_id {
+                                        content_parts.push(MessageContent::file_part(id));
+                                        continue;
+                                    }
+                                    let file_ref = if let Some(data) = file_data {
                                         let name =
                                             filename.unwrap_or_else(|| "unnamed_file".to_string());
                                         format!(
@@ -398,6 +403,8 @@ pub struct RequestContext {
     pub store: Option<bool>,
     /// Whether request runs in background
     pub background: Option<bool>,
+    /// Name of the API key that sent the request
+    pub owner: Option<String>,
 }
 
 /// Include options for response content.
@@ -816,6 +823,7 @@ impl OpenResponsesStreamer {
         resource.max_tool_calls = self.request_context.max_tool_calls;
         resource.store = self.request_context.store;
         resource.background = self.request_context.background;
+        resource.owner = self.request_context.owner.clone();
 
         resource
     }
@@ -873,11 +881,17 @@ impl futures::Stream for OpenResponsesStreamer {
                 return Poll::Ready(Some(Ok(Event::default().data("[DONE]"))));
             }
             DoneState::Done => {
-                // Store conversation history if needed
+                // Store the final response and conversation history if needed
                 if self.store {
-                    if let Some(history) = self.conversation_history.take() {
-                        let cache = get_response_cache();
-                        let mut history = history;
+                    // The stored response carries the owner checked by the retrieval routes.
+                    let completed = self.events.iter().rev().find_map(|event| match event {
+                        OpenResponsesStreamEvent::ResponseCompleted { response, .. } => {
+                            Some(response.clone())
+                        }
+                        _ => None,
+                    });
+                    let histories = self.conversation_history.take().map(|input| {
+                        let mut history = input.clone();
 
                         // Add assistant's response
                         if !self.accumulated_text.is_empty() {
@@ -891,12 +905,20 @@ impl futures::Stream for OpenResponsesStreamer {
                                 tool_call_id: None,
                             });
                         }
-
-                        let _ = cache.store_conversation_history(
-                            self.streaming_state.response_id.clone(),
-                            history,
-                        );
-                    }
+                        (input, history)
+                    });
+                    let id = self.streaming_state.response_id.clone();
+                    let cache = get_response_cache();
+                    // The stream can't wait here, so the writes run in the background.
+                    tokio::task::spawn_blocking(move || {
+                        if let Some(response) = completed {
+                            let _ = cache.store_response(id.clone(), response);
+                        }
+                        if let Some((input, history)) = histories {
+                            let _ = cache.store_input_items(id.clone(), input);
+                            let _ = cache.store_conversation_history(id, history);
+                        }
+                    });
                 }
 
                 if let Some(on_done) = &self.on_done {
@@ -1210,10 +1232,11 @@ impl IntoResponse for OpenResponsesResponder {
         match self {
             OpenResponsesResponder::Sse(s) => s.into_response(),
             OpenResponsesResponder::Json(s) => Json(s).into_response(),
-            OpenResponsesResponder::InternalError(e) => {
-                JsonError::new(sanitize_error_message(e.as_ref()))
-                    .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
-            }
+            OpenResponsesResponder::InternalError(e) => overloaded_response(e.downcast_ref())
+                .unwrap_or_else(|| {
+                    JsonError::new(sanitize_error_message(e.as_ref()))
+                        .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
+                }),
             OpenResponsesResponder::ValidationError(e) => {
                 JsonError::new(sanitize_error_message(e.as_ref()))
                     .to_response(http::StatusCode::UNPROCESSABLE_ENTITY)
@@ -1315,6 +1338,7 @@ fn chat_response_to_response_resource(
     resource.max_tool_calls = request_ctx.max_tool_calls;
     resource.store = request_ctx.store;
     resource.background = request_ctx.background;
+    resource.owner = request_ctx.owner.clone();
 
     resource
 }
@@ -1364,15 +1388,26 @@ async fn parse_openresponses_request(
         max_tool_calls: oairequest.max_tool_calls,
         store: oairequest.store,
         background: oairequest.background,
+        owner: authenticated_key_name(),
     };
 
     // Extract include config before consuming oairequest
     let include_config = IncludeConfig::new(oairequest.include.clone());
 
     // If previous_response_id is provided, get the full conversation history from cache
-    let previous_messages = if let Some(prev_id) = &oairequest.previous_response_id {
-        let cache = get_response_cache();
-        cache.get_conversation_history(prev_id)?
+    let previous_messages = if let Some(prev_id) = oairequest.previous_response_id.clone() {
+        let key = authenticated_key();
+        with_response_cache(move |cache| {
+            // Another key's conversation can't be continued, or its history read through the
+            // reply.
+            if let Some(previous) = cache.get_response(&prev_id)? {
+                if !is_visible(&previous, key.as_ref()) {
+                    anyhow::bail!("Previous response '{prev_id}' not found");
+                }
+            }
+            cache.get_conversation_history(&prev_id)
+        })
+        .await?
     } else {
         None
     };
@@ -1472,15 +1507,18 @@ async fn parse_openresponses_request(
         n_choices: oairequest.n_choices,
         presence_penalty: oairequest.presence_penalty,
         frequency_penalty: oairequest.frequency_penalty,
+        seed: oairequest.seed,
         repetition_penalty: oairequest.repetition_penalty,
         stop_seqs: oairequest.stop_seqs,
         temperature: oairequest.temperature,
         top_p: oairequest.top_p,
         stream: oairequest.stream,
+        stream_options: None,
         tools: oairequest.tools,
         tool_choice: oairequest.tool_choice,
         response_format,
         web_search_options: oairequest.web_search_options,
+        prediction: None,
         enable_code_execution: false,
         agent_permission: None,
         code_execution_permission: None,
@@ -1497,6 +1535,8 @@ async fn parse_openresponses_request(
         truncate_sequence,
         reasoning_effort,
         files: None,
+        priority: oairequest.priority,
+        timeout: oairequest.timeout,
     };
 
     let (request, is_streaming) =
@@ -1540,10 +1580,11 @@ pub async fn create_response(
     // Handle background processing
     if background {
         let task_manager = get_background_task_manager();
-        let task_id = task_manager.create_task(model_name.clone());
+        let owner = authenticated_key_name();
+        let task_id = task_manager.create_task(model_name.clone(), owner.clone());
 
         // Return immediately with queued response
-        let response = ResponseResource::new(
+        let mut response = ResponseResource::new(
             task_id.clone(),
             model_name,
             SystemTime::now()
@@ -1553,11 +1594,12 @@ pub async fn create_response(
         )
         .with_status(ResponseStatus::Queued)
         .with_metadata(metadata.clone().unwrap_or(Value::Null));
+        response.owner = owner;
 
         // Spawn background task
         let state_clone = state.clone();
         let metadata_clone = metadata.clone();
-        tokio::spawn(async move {
+        tokio::spawn(carry_key(async move {
             let (bg_tx, mut bg_rx) = create_response_channel(None);
 
             let (request, _, conversation_history, _include_config, request_context) =
@@ -1601,23 +1643,13 @@ pub async fn create_response(
 
                     // Store if requested
                     if store {
-                        let cache = get_response_cache();
-                        let _ = cache.store_response(task_id.clone(), response.clone());
-
-                        if let Some(mut history) = conversation_history {
-                            for choice in &chat_resp.choices {
-                                if let Some(content) = &choice.message.content {
-                                    history.push(Message {
-                                        content: Some(MessageContent::from_text(content.clone())),
-                                        role: choice.message.role.clone(),
-                                        name: None,
-                                        tool_calls: None,
-                                        tool_call_id: None,
-                                    });
-                                }
-                            }
-                            let _ = cache.store_conversation_history(task_id.clone(), history);
-                        }
+                        store_completed(
+                            task_id.clone(),
+                            response.clone(),
+                            &chat_resp,
+                            conversation_history,
+                        )
+                        .await;
                     }
 
                     task_manager.mark_completed(&task_id, response);
@@ -1645,7 +1677,7 @@ pub async fn create_response(
                     );
                 }
             }
-        });
+        }));
 
         return OpenResponsesResponder::Json(response);
     }
@@ -1657,7 +1689,7 @@ pub async fn create_response(
         };
 
     if let Err(e) = send_request_with_model(&state, request, model_id.as_deref()).await {
-        return handle_error(state, e.into());
+        return handle_error(state, boxed_send_error(e));
     }
 
     if is_streaming {
@@ -1699,23 +1731,13 @@ pub async fn create_response(
 
                 // Store if requested
                 if store {
-                    let cache = get_response_cache();
-                    let _ = cache.store_response(request_id.clone(), response.clone());
-
-                    if let Some(mut history) = conversation_history {
-                        for choice in &chat_resp.choices {
-                            if let Some(content) = &choice.message.content {
-                                history.push(Message {
-                                    content: Some(MessageContent::from_text(content.clone())),
-                                    role: choice.message.role.clone(),
-                                    name: None,
-                                    tool_calls: None,
-                                    tool_call_id: None,
-                                });
-                            }
-                        }
-                        let _ = cache.store_conversation_history(request_id, history);
-                    }
+                    store_completed(
+                        request_id,
+                        response.clone(),
+                        &chat_resp,
+                        conversation_history,
+                    )
+                    .await;
                 }
 
                 OpenResponsesResponder::Json(response)
@@ -1731,8 +1753,10 @@ pub async fn create_response(
                 response.status = ResponseStatus::Failed;
 
                 if store {
-                    let cache = get_response_cache();
-                    let _ = cache.store_response(request_id.clone(), response.clone());
+                    let stored = response.clone();
+                    let _ =
+                        with_response_cache(move |cache| cache.store_response(request_id, stored))
+                            .await;
                 }
 
                 OpenResponsesResponder::ModelError(msg.to_string(), response)
@@ -1746,6 +1770,39 @@ pub async fn create_response(
     }
 }
 
+/// Store a finished response, its input items, and the conversation extended with the reply.
+async fn store_completed(
+    id: String,
+    response: ResponseResource,
+    chat_resp: &ChatCompletionResponse,
+    conversation_history: Option<Vec<Message>>,
+) {
+    let histories = conversation_history.map(|input| {
+        let mut history = input.clone();
+        for choice in &chat_resp.choices {
+            if let Some(content) = &choice.message.content {
+                history.push(Message {
+                    content: Some(MessageContent::from_text(content.clone())),
+                    role: choice.message.role.clone(),
+                    name: None,
+                    tool_calls: None,
+                    tool_call_id: None,
+                });
+            }
+        }
+        (input, history)
+    });
+    let _ = with_response_cache(move |cache| {
+        let _ = cache.store_response(id.clone(), response);
+        if let Some((input, history)) = histories {
+            let _ = cache.store_input_items(id.clone(), input);
+            let _ = cache.store_conversation_history(id, history);
+        }
+        Ok(())
+    })
+    .await;
+}
+
 /// Get response by ID endpoint
 #[utoipa::path(
     get,
@@ -1756,20 +1813,12 @@ pub async fn create_response(
 )]
 pub async fn get_response(
     State(_state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
     Path(response_id): Path<String>,
 ) -> impl IntoResponse {
-    // First check background tasks
-    let task_manager = get_background_task_manager();
-    if let Some(response) = task_manager.get_response(&response_id) {
-        return (StatusCode::OK, Json(response)).into_response();
-    }
-
-    // Then check cache
-    let cache = get_response_cache();
-    match cache.get_response(&response_id) {
+    match find_visible_response(&response_id, key.as_deref()).await {
         Ok(Some(response)) => (StatusCode::OK, Json(response)).into_response(),
-        Ok(None) => JsonError::new(format!("Response with ID '{response_id}' not found"))
-            .to_response(StatusCode::NOT_FOUND),
+        Ok(None) => response_not_found(&response_id),
         Err(e) => JsonError::new(format!(
             "Error retrieving response: {}",
             sanitize_error_message(&*e)
@@ -1778,6 +1827,32 @@ pub async fn get_response(
     }
 }
 
+/// Whether `key` may see `response`. Everything is visible when authentication is off.
+fn is_visible(response: &ResponseResource, key: Option<&AuthenticatedKey>) -> bool {
+    key.is_none_or(|key| key.can_access(response.owner.as_deref()))
+}
+
+/// Look up a response, background or stored, that the caller may see. Responses created by
+/// other keys are reported as missing.
+async fn find_visible_response(
+    response_id: &str,
+    key: Option<&AuthenticatedKey>,
+) -> Result<Option<ResponseResource>> {
+    let response = match get_background_task_manager().get_response(response_id) {
+        Some(response) => Some(response),
+        None => {
+            let id = response_id.to_string();
+            with_response_cache(move |cache| cache.get_response(&id)).await?
+        }
+    };
+    Ok(response.filter(|response| is_visible(response, key)))
+}
+
+fn response_not_found(response_id: &str) -> axum::response::Response {
+    JsonError::new(format!("Response with ID '{response_id}' not found"))
+        .to_response(StatusCode::NOT_FOUND)
+}
+
 /// Delete response by ID endpoint
 #[utoipa::path(
     delete,
@@ -1788,15 +1863,28 @@ pub async fn get_response(
 )]
 pub async fn delete_response(
     State(_state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
     Path(response_id): Path<String>,
 ) -> impl IntoResponse {
+    match find_visible_response(&response_id, key.as_deref()).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return response_not_found(&response_id),
+        Err(e) => {
+            return JsonError::new(format!(
+                "Error deleting response: {}",
+                sanitize_error_message(&*e)
+            ))
+            .to_response(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+
     // Delete from background tasks
     let task_manager = get_background_task_manager();
     let task_deleted = task_manager.delete_task(&response_id);
 
     // Delete from cache
-    let cache = get_response_cache();
-    match cache.delete_response(&response_id) {
+    let id = response_id.clone();
+    match with_response_cache(move |cache| cache.delete_response(&id)).await {
         Ok(cache_deleted) => {
             if task_deleted || cache_deleted {
                 (
@@ -1821,6 +1909,178 @@ pub async fn delete_response(
     }
 }
 
+/// Default and maximum page sizes for `GET /v1/responses/{id}/input_items`.
+const DEFAULT_INPUT_ITEMS_LIMIT: usize = 20;
+const MAX_INPUT_ITEMS_LIMIT: usize = 100;
+
+/// Query parameters for listing a response's input items
+#[derive(Debug, Deserialize)]
+pub struct ListInputItemsQuery {
+    /// List items after this item ID
+    pub after: Option<String>,
+    /// Number of items to return, 1 to 100
+    pub limit: Option<usize>,
+    /// `asc` or `desc` (default)
+    pub order: Option<String>,
+}
+
+/// Convert one content part of a chat message into a Responses input content part.
+fn input_content_part(
+    part: &HashMap<String, crate::openai::MessageInnerContent>,
+    text_type: &str,
+) -> Option<Value> {
+    let field = |key: &str| part.get(key).map(|value| &**value);
+    let Some(Either::Left(part_type)) = field("type") else {
+        return None;
+    };
+    match (part_type.as_str(), field(part_type)) {
+        ("text", Some(Either::Left(text))) => Some(json!({"type": text_type, "text": text})),
+        ("image_url", Some(Either::Left(url))) => {
+            Some(json!({"type": "input_image", "image_url": url}))
+        }
+        ("image_url", Some(Either::Right(image))) => Some(json!({
+            "type": "input_image",
+            "image_url": image.get("url"),
+            "detail": image.get("detail"),
+        })),
+        ("input_audio", Some(Either::Right(audio))) => {
+            Some(json!({"type": "input_audio", "input_audio": audio}))
+        }
+        ("file", Some(Either::Right(file))) => {
+            Some(json!({"type": "input_file", "file_id": file.get("file_id")}))
+        }
+        _ => None,
+    }
+}
+
+/// Convert the stored input messages of `response_id` into Responses API input items.
+fn input_items_from_messages(response_id: &str, messages: &[Message]) -> Vec<Value> {
+    let mut items = Vec::new();
+    let mut n = 0;
+    let mut next_id = || {
+        n += 1;
+        format!("item_{response_id}_{n}")
+    };
+    for message in messages {
+        if message.role == "tool" {
+            items.push(json!({
+                "type": "function_call_output",
+                "id": next_id(),
+                "call_id": message.tool_call_id,
+                "output": message.content.as_ref().and_then(|c| c.to_text()).unwrap_or_default(),
+                "status": "completed",
+            }));
+            continue;
+        }
+
+        let text_type = if message.role == "assistant" {
+            "output_text"
+        } else {
+            "input_text"
+        };
+        let content: Vec<Value> = match message.content.as_deref() {
+            Some(Either::Left(text)) => vec![json!({"type": text_type, "text": text})],
+            Some(Either::Right(parts)) => parts
+                .iter()
+                .filter_map(|part| input_content_part(part, text_type))
+                .collect(),
+            None => Vec::new(),
+        };
+        if !content.is_empty() {
+            items.push(json!({
+                "type": "message",
+                "id": next_id(),
+                "role": message.role,
+                "status": "completed",
+                "content": content,
+            }));
+        }
+        for call in message.tool_calls.iter().flatten() {
+            items.push(json!({
+                "type": "function_call",
+                "id": next_id(),
+                "call_id": call.id,
+                "name": call.function.name,
+                "arguments": call.function.arguments,
+                "status": "completed",
+            }));
+        }
+    }
+    items
+}
+
+/// List input items endpoint
+#[utoipa::path(
+    get,
+    tag = "Mistral.rs",
+    path = "/v1/responses/{response_id}/input_items",
+    params(
+        ("response_id" = String, Path, description = "The ID of the response"),
+        ("after" = Option<String>, Query, description = "List items after this item ID"),
+        ("limit" = Option<usize>, Query, description = "Number of items to return, 1 to 100"),
+        ("order" = Option<String>, Query, description = "`asc` or `desc` (default)"),
+    ),
+    responses((status = 200, description = "List of input items"))
+)]
+pub async fn list_input_items(
+    State(_state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
+    Path(response_id): Path<String>,
+    Query(query): Query<ListInputItemsQuery>,
+) -> impl IntoResponse {
+    let lookup = match find_visible_response(&response_id, key.as_deref()).await {
+        Ok(Some(_)) => {
+            let id = response_id.clone();
+            with_response_cache(move |cache| cache.get_input_items(&id)).await
+        }
+        other => other.map(|_| None),
+    };
+    let messages = match lookup {
+        Ok(Some(messages)) => messages,
+        Ok(None) => return response_not_found(&response_id),
+        Err(e) => {
+            return JsonError::new(format!(
+                "Error retrieving input items: {}",
+                sanitize_error_message(&*e)
+            ))
+            .to_response(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    };
+
+    let mut items = input_items_from_messages(&response_id, &messages);
+    match query.order.as_deref().unwrap_or("desc") {
+        "asc" => (),
+        "desc" => items.reverse(),
+        other => {
+            return JsonError::new(format!("order must be `asc` or `desc`, got `{other}`"))
+                .to_response(StatusCode::UNPROCESSABLE_ENTITY)
+        }
+    }
+
+    let start = match &query.after {
+        Some(after) => items
+            .iter()
+            .position(|item| item["id"] == after.as_str())
+            .map_or(items.len(), |i| i + 1),
+        None => 0,
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_INPUT_ITEMS_LIMIT)
+        .clamp(1, MAX_INPUT_ITEMS_LIMIT);
+    let data: Vec<Value> = items.iter().skip(start).take(limit).cloned().collect();
+    let has_more = start + data.len() < items.len();
+
+    Json(json!({
+        "object": "list",
+        "first_id": data.first().map(|item| item["id"].clone()),
+        "last_id": data.last().map(|item| item["id"].clone()),
+        "has_more": has_more,
+        "data": data,
+    }))
+    .into_response()
+}
+
 /// Cancel response endpoint
 #[utoipa::path(
     post,
@@ -1831,11 +2091,15 @@ pub async fn delete_response(
 )]
 pub async fn cancel_response(
     State(_state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
     Path(response_id): Path<String>,
 ) -> impl IntoResponse {
     let task_manager = get_background_task_manager();
+    let visible = task_manager
+        .get_response(&response_id)
+        .is_some_and(|response| is_visible(&response, key.as_deref()));
 
-    if task_manager.request_cancel(&response_id) {
+    if visible && task_manager.request_cancel(&response_id) {
         task_manager.mark_cancelled(&response_id);
 
         if let Some(response) = task_manager.get_response(&response_id) {
@@ -221,6 +221,9 @@ pub struct ResponseResource {
     /// Whether request runs in background
     #[serde(skip_serializing_if = "Option::is_none")]
     pub background: Option<bool>,
+    /// Name of the API key that created the response. Other keys can't read or continue it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
 }
 
 impl ResponseResource {
@@ -257,6 +260,7 @@ impl ResponseResource {
             max_tool_calls: None,
             store: None,
             background: None,
+            owner: None,
         }
     }
 
@@ -1,30 +1,44 @@
 //! ## Speech generation functionality and route handler.
+//!
+//! `POST /v1/audio/speech` takes a JSON body, or `multipart/form-data` with the same fields
+//! when the reference audio for voice cloning is uploaded rather than linked.
 
-use std::{error::Error, sync::Arc};
+use std::{convert::Infallible, error::Error, sync::Arc, time::Duration};
 
 use anyhow::Result;
 use axum::{
-    body::Bytes,
-    extract::{Json, State},
+    body::{Body, Bytes},
+    extract::{FromRequest, Json, Multipart, State},
     http::{self, HeaderMap, HeaderValue, StatusCode},
-    response::IntoResponse,
+    response::{
+        sse::{Event, KeepAlive},
+        IntoResponse, Sse,
+    },
 };
+use base64::{engine::general_purpose::STANDARD, Engine};
 use mistralrs_core::{
-    speech_utils::{self, Sample},
-    Constraint, MistralRs, NormalRequest, Request, RequestMessage, Response, SamplingParams,
+    encode_audio, AudioEncoder, AudioFormat, AudioInput, Constraint, MistralRs, NormalRequest,
+    Request, RequestMessage, RequestPriority, Response, SamplingParams, SpeechReference,
 };
-use tokio::sync::mpsc::{Receiver, Sender};
+use serde_json::Value;
+use tokio::sync::mpsc::{self, Receiver, Sender};
 
 use crate::{
+    auth::AuthenticatedKey,
+    files::{read_file_part, MAX_UPLOAD_BYTES},
     handler_core::{
-        base_process_non_streaming_response, create_response_channel, send_request,
-        ErrorToResponse, JsonError,
+        base_process_non_streaming_response, boxed_send_error, create_response_channel,
+        overloaded_response, send_request, ErrorToResponse, JsonError,
     },
-    openai::{AudioResponseFormat, SpeechGenerationRequest},
+    openai::{AudioResponseFormat, SpeechGenerationRequest, SpeechStreamFormat},
+    streaming::get_keep_alive_interval,
     types::SharedMistralRsState,
-    util::{sanitize_error_message, validate_model_name},
+    util::{parse_audio_url, sanitize_error_message, validate_model_access, validate_model_name},
 };
 
+/// Sample format of `pcm` output.
+const PCM_FORMAT: &str = "s16le";
+
 /// Represents different types of speech generation responses.
 pub enum SpeechGenerationResponder {
     InternalError(Box<dyn Error>),
@@ -36,10 +50,11 @@ impl IntoResponse for SpeechGenerationResponder {
     /// Converts the speech generation responder into an HTTP response.
     fn into_response(self) -> axum::response::Response {
         match self {
-            SpeechGenerationResponder::InternalError(e) => {
-                JsonError::new(sanitize_error_message(e.as_ref()))
-                    .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
-            }
+            SpeechGenerationResponder::InternalError(e) => overloaded_response(e.downcast_ref())
+                .unwrap_or_else(|| {
+                    JsonError::new(sanitize_error_message(e.as_ref()))
+                        .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
+                }),
             SpeechGenerationResponder::ValidationError(e) => {
                 JsonError::new(sanitize_error_message(e.as_ref()))
                     .to_response(http::StatusCode::UNPROCESSABLE_ENTITY)
@@ -55,9 +70,10 @@ impl IntoResponse for SpeechGenerationResponder {
 /// request format used by mistral.rs.
 pub fn parse_request(
     oairequest: SpeechGenerationRequest,
+    reference: Option<SpeechReference>,
     state: Arc<MistralRs>,
     tx: Sender<Response>,
-) -> Result<(Request, AudioResponseFormat)> {
+) -> Result<(Request, AudioResponseFormat, Option<SpeechStreamFormat>)> {
     let repr = serde_json::to_string(&oairequest).expect("Serialization of request failed.");
     MistralRs::maybe_log_request(state.clone(), repr);
 
@@ -68,11 +84,12 @@ pub fn parse_request(
         id: state.next_request_id(),
         messages: RequestMessage::SpeechGeneration {
             prompt: oairequest.input,
+            reference,
         },
         sampling_params: SamplingParams::deterministic(),
         response: tx,
         return_logprobs: false,
-        is_streaming: false,
+        is_streaming: oairequest.stream_format.is_some(),
         suffix: None,
         constraint: Constraint::None,
         tool_choice: None,
@@ -96,9 +113,20 @@ pub fn parse_request(
         truncate_sequence: false,
         session_id: None,
         files: None,
+        input_files: None,
+        priority: RequestPriority::Normal,
+        timeout: None,
+        trace_context: None,
+        user: None,
+        prediction: None,
+        agentic_permit: None,
     }));
 
-    Ok((request, oairequest.response_format))
+    Ok((
+        request,
+        oairequest.response_format,
+        oairequest.stream_format,
+    ))
 }
 
 /// Speech generation endpoint handler.
@@ -111,30 +139,307 @@ pub fn parse_request(
 )]
 pub async fn speech_generation(
     State(state): State<Arc<MistralRs>>,
-    Json(oairequest): Json<SpeechGenerationRequest>,
+    request: axum::extract::Request,
 ) -> SpeechGenerationResponder {
-    let (tx, mut rx) = create_response_channel(None);
+    let key = request.extensions().get::<AuthenticatedKey>().cloned();
+    let is_multipart = request
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"));
+    let (oairequest, upload) = if is_multipart {
+        let multipart = match Multipart::from_request(request, &state).await {
+            Ok(multipart) => multipart,
+            Err(e) => return SpeechGenerationResponder::RawResponse(e.into_response()),
+        };
+        match parse_multipart(multipart).await {
+            Ok(x) => x,
+            Err((status, message)) => {
+                return SpeechGenerationResponder::RawResponse(
+                    JsonError::new(message).to_response(status),
+                )
+            }
+        }
+    } else {
+        match Json::<SpeechGenerationRequest>::from_request(request, &state).await {
+            Ok(Json(oairequest)) => (oairequest, None),
+            Err(e) => return SpeechGenerationResponder::RawResponse(e.into_response()),
+        }
+    };
+
+    // The auth layer can't see the model in a multipart form.
+    if let Err(e) = validate_model_access(&oairequest.model, key.as_ref(), &state) {
+        return SpeechGenerationResponder::RawResponse(e.into_response());
+    }
 
-    let (request, response_format) = match parse_request(oairequest, state.clone(), tx) {
-        Ok(x) => x,
-        Err(e) => return handle_error(state, e.into()),
+    let reference = match resolve_reference(&oairequest, upload).await {
+        Ok(reference) => reference,
+        Err(e) => return SpeechGenerationResponder::ValidationError(e.into()),
     };
 
-    // Validate response format here
-    if !matches!(
+    let (tx, mut rx) = create_response_channel(None);
+
+    let (request, response_format, stream_format) =
+        match parse_request(oairequest, reference, state.clone(), tx) {
+            Ok(x) => x,
+            Err(e) => return handle_error(state, e.into()),
+        };
+
+    if let Err(e) = send_request(&state, request).await {
+        return handle_error(state, boxed_send_error(e));
+    }
+
+    match stream_format {
+        Some(stream_format) => stream_speech(rx, state, response_format, stream_format).await,
+        None => process_non_streaming_response(&mut rx, state, response_format).await,
+    }
+}
+
+/// Reads a multipart request. `reference_audio` may be an uploaded file or, like every other
+/// field, text.
+async fn parse_multipart(
+    mut multipart: Multipart,
+) -> std::result::Result<(SpeechGenerationRequest, Option<AudioInput>), (StatusCode, String)> {
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, message);
+    let mut model = None;
+    let mut input = None;
+    let mut response_format = AudioResponseFormat::default();
+    let mut stream_format = None;
+    let mut reference_audio = None;
+    let mut reference_text = None;
+    let mut upload = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| (e.status(), e.body_text()))?
+    {
+        let name = field.name().map(str::to_string);
+        if name.as_deref() == Some("reference_audio") && field.file_name().is_some() {
+            let part = read_file_part(field, MAX_UPLOAD_BYTES).await?;
+            let decoded = AudioInput::from_bytes(&part.data)
+                .map_err(|e| bad_request(format!("Could not decode {}: {e}", part.name)))?;
+            upload = Some(decoded);
+            continue;
+        }
+        let value = field
+            .text()
+            .await
+            .map_err(|e| (e.status(), e.body_text()))?;
+        let value = value.trim().to_string();
+        match name.as_deref() {
+            Some("model") => model = Some(value),
+            Some("input") => input = Some(value),
+            Some("response_format") => {
+                response_format = serde_json::from_value(Value::String(value.clone()))
+                    .map_err(|_| bad_request(format!("Invalid response_format '{value}'.")))?
+            }
+            Some("stream_format") if !value.is_empty() => {
+                stream_format = Some(
+                    serde_json::from_value(Value::String(value.clone()))
+                        .map_err(|_| bad_request(format!("Invalid stream_format '{value}'.")))?,
+                )
+            }
+            Some("reference_audio") if !value.is_empty() => reference_audio = Some(value),
+            Some("reference_text") if !value.is_empty() => reference_text = Some(value),
+            // `voice`, `instructions` and similar are accepted and ignored.
+            _ => {}
+        }
+    }
+
+    let input = input.ok_or_else(|| bad_request("Missing required field 'input'.".to_string()))?;
+    let oairequest = SpeechGenerationRequest {
+        model: model.unwrap_or_else(|| "default".to_string()),
+        input,
         response_format,
-        AudioResponseFormat::Wav | AudioResponseFormat::Pcm
-    ) {
-        return SpeechGenerationResponder::ValidationError(Box::new(JsonError::new(
-            "Only support wav/pcm response format.".to_string(),
-        )));
+        stream_format,
+        reference_audio,
+        reference_text,
+    };
+    Ok((oairequest, upload))
+}
+
+/// The voice to clone, if the request has one. An uploaded file takes the place of
+/// `reference_audio`.
+async fn resolve_reference(
+    oairequest: &SpeechGenerationRequest,
+    upload: Option<AudioInput>,
+) -> Result<Option<SpeechReference>> {
+    let has_audio = upload.is_some() || oairequest.reference_audio.is_some();
+    let Some(transcript) = &oairequest.reference_text else {
+        if has_audio {
+            anyhow::bail!("`reference_audio` requires `reference_text`, its transcript.");
+        }
+        return Ok(None);
+    };
+    let audio = match (upload, &oairequest.reference_audio) {
+        (Some(audio), _) => audio,
+        (None, Some(url)) => parse_audio_url(url).await?,
+        (None, None) => anyhow::bail!("`reference_text` requires `reference_audio`."),
+    };
+    if audio.samples.is_empty() {
+        anyhow::bail!("The reference audio contains no samples.");
     }
+    Ok(Some(SpeechReference {
+        audio,
+        transcript: transcript.clone(),
+    }))
+}
 
-    if let Err(e) = send_request(&state, request).await {
-        return handle_error(state, e.into());
+fn audio_format(format: AudioResponseFormat) -> AudioFormat {
+    match format {
+        AudioResponseFormat::Mp3 => AudioFormat::Mp3,
+        AudioResponseFormat::Opus => AudioFormat::Opus,
+        AudioResponseFormat::Aac => AudioFormat::Aac,
+        AudioResponseFormat::Flac => AudioFormat::Flac,
+        AudioResponseFormat::Wav => AudioFormat::Wav,
+        AudioResponseFormat::Pcm => AudioFormat::Pcm,
     }
+}
+
+/// Stream a speech request as it is generated.
+///
+/// The first response is awaited before answering so that failures, including a missing
+/// encoder, still produce an error status. Encoding then runs on a blocking thread, since the
+/// compressed formats pipe through `ffmpeg`.
+async fn stream_speech(
+    mut rx: Receiver<Response>,
+    state: SharedMistralRsState,
+    response_format: AudioResponseFormat,
+    stream_format: SpeechStreamFormat,
+) -> SpeechGenerationResponder {
+    let (first, rate, channels) = loop {
+        let Some(response) = rx.recv().await else {
+            return handle_error(state, "Speech generation ended without a response.".into());
+        };
+        match &response {
+            Response::SpeechChunk { rate, channels, .. }
+            | Response::Speech { rate, channels, .. } => {
+                let (rate, channels) = (*rate, *channels);
+                break (response, rate, channels);
+            }
+            Response::AgenticToolCallProgress { .. } | Response::File(_) => continue,
+            _ => return match_responses(state, response, response_format),
+        }
+    };
+    let content_type = response_format.audio_content_type(rate, channels, PCM_FORMAT);
+    let encoder =
+        match AudioEncoder::new(audio_format(response_format), rate as u32, channels as u16) {
+            Ok(encoder) => encoder,
+            Err(e) => return handle_error(state, e.into()),
+        };
 
-    process_non_streaming_response(&mut rx, state, response_format).await
+    let (out_tx, out_rx) = mpsc::channel(16);
+    tokio::task::spawn_blocking(move || encode_stream(state, first, rx, encoder, out_tx));
+
+    let response = match stream_format {
+        SpeechStreamFormat::Audio => {
+            let body = futures::stream::unfold(out_rx, |mut rx| async move {
+                let item = rx.recv().await?;
+                Some((item.map(Bytes::from).map_err(std::io::Error::other), rx))
+            });
+            (
+                [(http::header::CONTENT_TYPE, content_type)],
+                Body::from_stream(body),
+            )
+                .into_response()
+        }
+        SpeechStreamFormat::Sse => {
+            let events = futures::stream::unfold(Some(out_rx), |rx| async move {
+                let mut rx = rx?;
+                let (event, next) = match rx.recv().await {
+                    Some(Ok(bytes)) => (
+                        serde_json::json!({
+                            "type": "speech.audio.delta",
+                            "audio": STANDARD.encode(bytes),
+                        }),
+                        Some(rx),
+                    ),
+                    Some(Err(message)) => (
+                        serde_json::json!({ "type": "error", "error": { "message": message } }),
+                        None,
+                    ),
+                    None => (serde_json::json!({ "type": "speech.audio.done" }), None),
+                };
+                Some((
+                    Ok::<_, Infallible>(Event::default().data(event.to_string())),
+                    next,
+                ))
+            });
+            Sse::new(events)
+                .keep_alive(
+                    KeepAlive::new().interval(Duration::from_millis(get_keep_alive_interval())),
+                )
+                .into_response()
+        }
+    };
+    SpeechGenerationResponder::RawResponse(response)
+}
+
+/// Encode streamed chunks until the final `Speech` response, sending the bytes to `out`.
+/// Failures are sent as a sanitized message and end the stream.
+fn encode_stream(
+    state: SharedMistralRsState,
+    first: Response,
+    mut rx: Receiver<Response>,
+    mut encoder: AudioEncoder,
+    out: mpsc::Sender<Result<Vec<u8>, String>>,
+) {
+    let mut next = Some(first);
+    let mut streamed = false;
+    loop {
+        let Some(response) = next.take().or_else(|| rx.blocking_recv()) else {
+            let _ = out.blocking_send(Err(
+                "Speech generation ended without a final response.".to_string()
+            ));
+            return;
+        };
+        let encoded = match response {
+            Response::SpeechChunk { pcm, .. } => {
+                streamed = true;
+                encoder.push(&pcm)
+            }
+            Response::Speech { pcm, .. } => {
+                // Every sample has already been streamed, unless the model does not stream.
+                let tail = if streamed {
+                    Ok(Vec::new())
+                } else {
+                    encoder.push(&pcm)
+                };
+                let finished = tail.and_then(|mut bytes| {
+                    bytes.extend(encoder.finish()?);
+                    Ok(bytes)
+                });
+                let _ = match finished {
+                    Ok(bytes) => out.blocking_send(Ok(bytes)),
+                    Err(e) => out.blocking_send(Err(sanitize_error_message(&*e))),
+                };
+                return;
+            }
+            Response::InternalError(e) | Response::ValidationError(e) => {
+                MistralRs::maybe_log_error(state, &*e);
+                let _ = out.blocking_send(Err(sanitize_error_message(e.as_ref())));
+                return;
+            }
+            Response::ModelError(msg, _) | Response::CompletionModelError(msg, _) => {
+                let _ = out.blocking_send(Err(msg));
+                return;
+            }
+            _ => continue,
+        };
+        match encoded {
+            Ok(bytes) if bytes.is_empty() => {}
+            Ok(bytes) => {
+                if out.blocking_send(Ok(bytes)).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                let _ = out.blocking_send(Err(sanitize_error_message(&*e)));
+                return;
+            }
+        }
+    }
 }
 
 /// Helper function to handle speech generation errors and logging them.
@@ -191,38 +496,28 @@ pub fn match_responses(
             rate,
             channels,
         } => {
-            let pcm_endianness = "s16le";
-
-            let content_type = response_format.audio_content_type(rate, channels, pcm_endianness);
+            let content_type = response_format.audio_content_type(rate, channels, PCM_FORMAT);
             let mut headers = HeaderMap::new();
             headers.insert(
                 http::header::CONTENT_TYPE,
                 HeaderValue::from_str(&content_type).unwrap(),
             );
 
-            let encoded = match response_format {
-                AudioResponseFormat::Pcm => {
-                    let samples: &[f32] = &pcm;
-                    let mut buf = Vec::with_capacity(samples.len() * std::mem::size_of::<i64>());
-                    for &sample in samples {
-                        buf.extend_from_slice(&sample.to_i16().to_le_bytes());
-                    }
-                    buf
-                }
-                AudioResponseFormat::Wav => {
-                    // Write WAV data into an in-memory buffer
-                    let mut buf = Vec::new();
-                    speech_utils::write_pcm_as_wav(&mut buf, &pcm, rate as u32, channels as u16)
-                        .unwrap();
-                    buf
-                }
-                _ => unreachable!("Should be validated above."),
+            let encoded = match encode_audio(
+                &pcm,
+                rate as u32,
+                channels as u16,
+                audio_format(response_format),
+            ) {
+                Ok(encoded) => encoded,
+                Err(e) => return handle_error(state, e.into()),
             };
 
             let bytes = Bytes::from(encoded);
 
             SpeechGenerationResponder::RawResponse((StatusCode::OK, headers, bytes).into_response())
         }
+        Response::SpeechChunk { .. } => unreachable!(),
         Response::Raw { .. } => unreachable!(),
         Response::Embeddings { .. } => unreachable!(),
         Response::AgenticToolCallProgress { .. } => unreachable!(),
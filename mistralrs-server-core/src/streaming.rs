@@ -2,10 +2,10 @@
 
 use std::env;
 
-use mistralrs_core::Response;
+use mistralrs_core::{ChatCompletionChunkResponse, CompletionChunkResponse, Response, Usage};
 use tokio::sync::mpsc::Receiver;
 
-use crate::types::SharedMistralRsState;
+use crate::{openai::StreamOptions, types::SharedMistralRsState};
 
 /// Default keep-alive interval for Server-Sent Events (SSE) streams in milliseconds.
 pub const DEFAULT_KEEP_ALIVE_INTERVAL_MS: u64 = 10_000;
@@ -39,6 +39,68 @@ pub struct BaseStreamer<R, C, D> {
     pub on_chunk: Option<C>,
     /// Optional callback to execute when streaming completes
     pub on_done: Option<D>,
+    /// How usage is reported in the stream
+    pub stream_options: StreamOptions,
+    /// Usage-only chunk waiting to be sent before `[DONE]`
+    pub usage_chunk: Option<R>,
+}
+
+/// A streamed chunk that carries usage.
+pub trait UsageChunk: Clone {
+    /// The chunk's usage field.
+    fn usage_mut(&mut self) -> &mut Option<Usage>;
+
+    /// A copy of this chunk with no choices, used for the trailing usage chunk.
+    fn usage_only(&self) -> Self;
+}
+
+impl UsageChunk for ChatCompletionChunkResponse {
+    fn usage_mut(&mut self) -> &mut Option<Usage> {
+        &mut self.usage
+    }
+
+    fn usage_only(&self) -> Self {
+        Self {
+            choices: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+impl UsageChunk for CompletionChunkResponse {
+    fn usage_mut(&mut self) -> &mut Option<Usage> {
+        &mut self.usage
+    }
+
+    fn usage_only(&self) -> Self {
+        Self {
+            choices: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Apply `options` to a chunk from the engine, which always carries cumulative usage. Returns the
+/// usage-only chunk to send after the final one, if requested.
+///
+/// By default only the final chunk keeps its usage. With `include_usage`, usage moves to a
+/// separate trailing chunk, and with `continuous_usage_stats` every chunk keeps it.
+fn shape_usage<R: UsageChunk>(options: StreamOptions, chunk: &mut R, is_final: bool) -> Option<R> {
+    let usage_chunk = (options.include_usage && is_final).then(|| chunk.usage_only());
+    let keep = options.continuous_usage_stats || (is_final && !options.include_usage);
+    if !keep {
+        *chunk.usage_mut() = None;
+    }
+    usage_chunk
+}
+
+impl<R: UsageChunk, C, D> BaseStreamer<R, C, D> {
+    /// Shape the usage on `chunk` according to this stream's `stream_options`.
+    pub(crate) fn apply_stream_options(&mut self, chunk: &mut R, is_final: bool) {
+        if let Some(usage_chunk) = shape_usage(self.stream_options, chunk, is_final) {
+            self.usage_chunk = Some(usage_chunk);
+        }
+    }
 }
 
 /// Generic function to create a SSE streamer with optional callbacks.
@@ -58,6 +120,8 @@ pub(crate) fn base_create_streamer<R, C, D>(
         chunks: Vec::new(),
         on_chunk,
         on_done,
+        stream_options: StreamOptions::default(),
+        usage_chunk: None,
     }
 }
 
@@ -72,3 +136,63 @@ pub fn get_keep_alive_interval() -> u64 {
         })
         .unwrap_or(DEFAULT_KEEP_ALIVE_INTERVAL_MS)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk() -> CompletionChunkResponse {
+        CompletionChunkResponse {
+            id: "0".to_string(),
+            choices: Vec::new(),
+            created: 0,
+            model: "default".to_string(),
+            system_fingerprint: "local".to_string(),
+            object: "text_completion".to_string(),
+            usage: Some(Usage {
+                completion_tokens: 2,
+                prompt_tokens: 3,
+                total_tokens: 5,
+                avg_tok_per_sec: 0.0,
+                avg_prompt_tok_per_sec: 0.0,
+                avg_compl_tok_per_sec: 0.0,
+                total_time_sec: 0.0,
+                total_prompt_time_sec: 0.0,
+                total_completion_time_sec: 0.0,
+                completion_tokens_details: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn usage_only_on_final_chunk_by_default() {
+        let options = StreamOptions::default();
+        let mut c = chunk();
+        assert!(shape_usage(options, &mut c, false).is_none());
+        assert!(c.usage.is_none());
+
+        let mut c = chunk();
+        assert!(shape_usage(options, &mut c, true).is_none());
+        assert!(c.usage.is_some());
+    }
+
+    #[test]
+    fn include_usage_moves_usage_to_trailing_chunk() {
+        let options = StreamOptions {
+            include_usage: true,
+            continuous_usage_stats: false,
+        };
+        let mut c = chunk();
+        let trailing = shape_usage(options, &mut c, true).unwrap();
+        assert!(c.usage.is_none());
+        assert_eq!(trailing.usage.unwrap().total_tokens, 5);
+
+        let options = StreamOptions {
+            include_usage: true,
+            continuous_usage_stats: true,
+        };
+        let mut c = chunk();
+        assert!(shape_usage(options, &mut c, false).is_none());
+        assert!(c.usage.is_some());
+    }
+}
@@ -0,0 +1,60 @@
+//! ## Distributed tracing for HTTP requests.
+//!
+//! [`trace_http_request`] records a server span for each request, joining the caller's trace
+//! when a W3C `traceparent` header is present. Engine requests sent while handling it are
+//! recorded under that span (see [`crate::handler_core::send_request_with_model`]). Nothing is
+//! recorded unless an exporter was set up with [`mistralrs_core::init_otlp_exporter`].
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use mistralrs_core::{tracing_enabled, with_trace_context, TraceContext, TraceSpan};
+
+/// W3C trace context header.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Middleware recording a server span per request.
+///
+/// For streaming responses the span ends once the stream starts; generation itself is covered
+/// by the engine's sequence spans.
+pub async fn trace_http_request(request: Request, next: Next) -> Response {
+    if !tracing_enabled() {
+        return next.run(request).await;
+    }
+
+    let parent = request
+        .headers()
+        .get(TRACEPARENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceContext::from_traceparent);
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|route| route.as_str().to_string());
+
+    let mut span = TraceSpan::server(
+        format!("{method} {}", route.as_deref().unwrap_or(&path)),
+        parent,
+    );
+    span.set_attribute("http.request.method", method);
+    span.set_attribute("url.path", path);
+    if let Some(route) = route {
+        span.set_attribute("http.route", route);
+    }
+
+    let response = match span.context() {
+        Some(context) => with_trace_context(context, next.run(request)).await,
+        None => next.run(request).await,
+    };
+
+    let status = response.status();
+    span.set_attribute("http.response.status_code", i64::from(status.as_u16()));
+    if status.is_server_error() {
+        span.set_error(status.to_string());
+    }
+    response
+}
@@ -0,0 +1,288 @@
+//! Tokenization endpoints.
+//!
+//! `/tokenize` and `/detokenize` expose the model's tokenizer, and `/v1/chat/count_tokens`
+//! counts the prompt tokens a chat completion request would use. Chat messages go through the
+//! same parsing and chat template as `/v1/chat/completions`, so counts match generation.
+
+use anyhow::{anyhow, bail, Error as AnyhowError, Result};
+use axum::{
+    extract::{Json, State},
+    http,
+    response::IntoResponse,
+};
+use either::Either;
+use mistralrs_core::{
+    DetokenizationRequest, MistralRs, Request, RequestMessage, TokenizationRequest,
+};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::{
+    chat_completion::parse_request as parse_chat_request,
+    handler_core::{create_response_channel, send_request_with_model, ErrorToResponse, JsonError},
+    openai::{
+        ChatCompletionRequest, CountTokensResponse, DetokenizeRequest, DetokenizeResponse,
+        TokenizeRequest, TokenizeResponse,
+    },
+    types::{ExtractedMistralRsState, SharedMistralRsState},
+    util::{sanitize_error_message, validate_model_name},
+};
+
+/// Represents different types of tokenization responses.
+pub enum TokenizationResponder {
+    Tokenize(TokenizeResponse),
+    Detokenize(DetokenizeResponse),
+    CountTokens(CountTokensResponse),
+    InternalError(AnyhowError),
+    ValidationError(AnyhowError),
+}
+
+impl IntoResponse for TokenizationResponder {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            TokenizationResponder::Tokenize(s) => Json(s).into_response(),
+            TokenizationResponder::Detokenize(s) => Json(s).into_response(),
+            TokenizationResponder::CountTokens(s) => Json(s).into_response(),
+            TokenizationResponder::InternalError(e) => {
+                JsonError::new(sanitize_error_message(e.root_cause()))
+                    .to_response(http::StatusCode::INTERNAL_SERVER_ERROR)
+            }
+            TokenizationResponder::ValidationError(e) => {
+                JsonError::new(sanitize_error_message(e.root_cause()))
+                    .to_response(http::StatusCode::UNPROCESSABLE_ENTITY)
+            }
+        }
+    }
+}
+
+fn requested_model_id(model: &str) -> Option<String> {
+    (model != "default").then(|| model.to_string())
+}
+
+/// Build a tokenization request that templates `request` exactly as a chat completion would.
+async fn chat_tokenization_request(
+    state: SharedMistralRsState,
+    request: ChatCompletionRequest,
+    add_generation_prompt: bool,
+    add_special_tokens: bool,
+    response: Sender<anyhow::Result<Vec<u32>>>,
+) -> Result<TokenizationRequest> {
+    // The normal request is only inspected, never sent.
+    let (tx, _rx) = create_response_channel(Some(1));
+    let (Request::Normal(normal), _) =
+        parse_chat_request(request, state, tx, None, None, None).await?
+    else {
+        bail!("Chat request did not produce a normal request.");
+    };
+    let (messages, enable_thinking, reasoning_effort) = match normal.messages {
+        RequestMessage::Chat {
+            messages,
+            enable_thinking,
+            reasoning_effort,
+        }
+        | RequestMessage::MultimodalChat {
+            messages,
+            enable_thinking,
+            reasoning_effort,
+            ..
+        } => (messages, enable_thinking, reasoning_effort),
+        _ => bail!("Chat request did not produce chat messages."),
+    };
+    Ok(TokenizationRequest {
+        text: Either::Left(messages),
+        tools: normal.tools,
+        add_generation_prompt,
+        add_special_tokens,
+        enable_thinking,
+        reasoning_effort,
+        response,
+    })
+}
+
+/// Send a tokenizer request to the engine and wait for its result.
+async fn dispatch<T>(
+    state: &SharedMistralRsState,
+    request: Request,
+    model_id: Option<&str>,
+    mut rx: Receiver<anyhow::Result<T>>,
+) -> std::result::Result<T, TokenizationResponder> {
+    if let Err(e) = send_request_with_model(state, request, model_id).await {
+        MistralRs::maybe_log_error(state.clone(), &*e);
+        return Err(TokenizationResponder::InternalError(e));
+    }
+    match rx.recv().await {
+        // Engine-side failures are template or tokenizer errors caused by the input.
+        Some(result) => result.map_err(TokenizationResponder::ValidationError),
+        None => Err(TokenizationResponder::InternalError(anyhow!(
+            "No response received from the model."
+        ))),
+    }
+}
+
+/// Tokenize chat messages for `model`, returning the tokens.
+async fn tokenize_chat(
+    state: &SharedMistralRsState,
+    request: ChatCompletionRequest,
+    add_generation_prompt: bool,
+    add_special_tokens: bool,
+) -> std::result::Result<Vec<u32>, TokenizationResponder> {
+    let model_id = requested_model_id(&request.model);
+    let (tx, rx) = channel(1);
+    let request = chat_tokenization_request(
+        state.clone(),
+        request,
+        add_generation_prompt,
+        add_special_tokens,
+        tx,
+    )
+    .await
+    .map_err(TokenizationResponder::ValidationError)?;
+    dispatch(state, Request::Tokenize(request), model_id.as_deref(), rx).await
+}
+
+#[utoipa::path(
+    post,
+    tag = "Mistral.rs",
+    path = "/tokenize",
+    request_body = TokenizeRequest,
+    responses((status = 200, description = "Token ids", body = TokenizeResponse))
+)]
+pub async fn tokenize(
+    State(state): ExtractedMistralRsState,
+    Json(request): Json<TokenizeRequest>,
+) -> TokenizationResponder {
+    let (tokens, model_id) = match request {
+        TokenizeRequest::Completion(request) => {
+            let repr =
+                serde_json::to_string(&request).expect("Serialization of tokenize request failed.");
+            MistralRs::maybe_log_request(state.clone(), repr);
+            if let Err(e) = validate_model_name(&request.model, state.clone()) {
+                return TokenizationResponder::ValidationError(e);
+            }
+
+            let model_id = requested_model_id(&request.model);
+            let (tx, rx) = channel(1);
+            let tokenize_request = Request::Tokenize(TokenizationRequest {
+                text: Either::Right(request.prompt),
+                tools: None,
+                add_generation_prompt: false,
+                add_special_tokens: request.add_special_tokens,
+                enable_thinking: None,
+                reasoning_effort: None,
+                response: tx,
+            });
+            (
+                dispatch(&state, tokenize_request, model_id.as_deref(), rx).await,
+                model_id,
+            )
+        }
+        TokenizeRequest::Chat(request) => {
+            let model_id = requested_model_id(&request.request.model);
+            (
+                tokenize_chat(
+                    &state,
+                    request.request,
+                    request.add_generation_prompt,
+                    request.add_special_tokens,
+                )
+                .await,
+                model_id,
+            )
+        }
+    };
+    let tokens = match tokens {
+        Ok(tokens) => tokens,
+        Err(responder) => return responder,
+    };
+
+    TokenizationResponder::Tokenize(TokenizeResponse {
+        count: tokens.len(),
+        max_model_len: state
+            .max_sequence_length(model_id.as_deref())
+            .ok()
+            .flatten(),
+        tokens,
+    })
+}
+
+#[utoipa::path(
+    post,
+    tag = "Mistral.rs",
+    path = "/detokenize",
+    request_body = DetokenizeRequest,
+    responses((status = 200, description = "Decoded text", body = DetokenizeResponse))
+)]
+pub async fn detokenize(
+    State(state): ExtractedMistralRsState,
+    Json(request): Json<DetokenizeRequest>,
+) -> TokenizationResponder {
+    let repr =
+        serde_json::to_string(&request).expect("Serialization of detokenize request failed.");
+    MistralRs::maybe_log_request(state.clone(), repr);
+    if let Err(e) = validate_model_name(&request.model, state.clone()) {
+        return TokenizationResponder::ValidationError(e);
+    }
+
+    let model_id = requested_model_id(&request.model);
+    let (tx, rx) = channel(1);
+    let detokenize_request = Request::Detokenize(DetokenizationRequest {
+        tokens: request.tokens,
+        skip_special_tokens: request.skip_special_tokens,
+        response: tx,
+    });
+    match dispatch(&state, detokenize_request, model_id.as_deref(), rx).await {
+        Ok(prompt) => TokenizationResponder::Detokenize(DetokenizeResponse { prompt }),
+        Err(responder) => responder,
+    }
+}
+
+#[utoipa::path(
+    post,
+    tag = "Mistral.rs",
+    path = "/v1/chat/count_tokens",
+    request_body = ChatCompletionRequest,
+    responses((status = 200, description = "Prompt token count", body = CountTokensResponse))
+)]
+pub async fn count_tokens(
+    State(state): ExtractedMistralRsState,
+    Json(request): Json<ChatCompletionRequest>,
+) -> TokenizationResponder {
+    let model_id = requested_model_id(&request.model);
+    match tokenize_chat(&state, request, true, true).await {
+        Ok(tokens) => TokenizationResponder::CountTokens(CountTokensResponse {
+            input_tokens: tokens.len(),
+            max_model_len: state
+                .max_sequence_length(model_id.as_deref())
+                .ok()
+                .flatten(),
+        }),
+        Err(responder) => responder,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_request_accepts_prompt_or_messages() {
+        let request: TokenizeRequest =
+            serde_json::from_value(serde_json::json!({"prompt": "Hello"})).unwrap();
+        assert!(matches!(
+            request,
+            TokenizeRequest::Completion(ref r) if r.prompt == "Hello" && r.add_special_tokens
+        ));
+
+        let request: TokenizeRequest = serde_json::from_value(serde_json::json!({
+            "model": "default",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "add_generation_prompt": false
+        }))
+        .unwrap();
+        let TokenizeRequest::Chat(request) = request else {
+            panic!("expected a chat tokenize request");
+        };
+        assert!(!request.add_generation_prompt);
+        assert!(request.add_special_tokens);
+        assert!(matches!(request.request.messages, Either::Left(ref m) if m.len() == 1));
+    }
+}
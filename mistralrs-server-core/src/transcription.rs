@@ -0,0 +1,745 @@
+//! ## Speech-to-text functionality and route handlers.
+//!
+//! `POST /v1/audio/transcriptions` and `POST /v1/audio/translations` take a multipart audio
+//! upload and run it through an audio-capable multimodal model as a chat request. Long
+//! recordings are split into chunks of at most [`CHUNK_SECONDS`], cut at pauses where possible;
+//! each chunk becomes one segment in `verbose_json`, `srt` and `vtt` output.
+
+use std::{convert::Infallible, fmt::Write, time::Duration};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Multipart, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, KeepAlive},
+        IntoResponse, Response as AxumResponse, Sse,
+    },
+    Extension, Json,
+};
+use either::Either;
+use indexmap::IndexMap;
+use mistralrs_core::{
+    AudioInput, Constraint, MistralRs, ModelCategory, NormalRequest, Request, RequestMessage,
+    RequestPriority, Response, SamplingParams, Usage,
+};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc::{self, Sender};
+
+use crate::{
+    auth::AuthenticatedKey,
+    files::{read_file_part, MAX_UPLOAD_BYTES},
+    handler_core::{
+        create_response_channel, overloaded_response, send_request_with_model, ErrorToResponse,
+        JsonError,
+    },
+    streaming::get_keep_alive_interval,
+    types::{ExtractedMistralRsState, SharedMistralRsState},
+    util::{sanitize_error_message, validate_model_access, validate_model_name},
+};
+
+/// Longest audio span sent to the model in one request.
+pub const CHUNK_SECONDS: f32 = 30.0;
+
+/// How far back from a chunk's hard limit to look for a pause to cut at.
+const CUT_SEARCH_SECONDS: f32 = 5.0;
+
+/// Characters of the previous chunk's transcript passed as context to the next one.
+const CONTEXT_TAIL_CHARS: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpeechTask {
+    Transcribe,
+    Translate,
+}
+
+impl SpeechTask {
+    fn as_str(self) -> &'static str {
+        match self {
+            SpeechTask::Transcribe => "transcribe",
+            SpeechTask::Translate => "translate",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TranscriptionResponseFormat {
+    Json,
+    Text,
+    Srt,
+    Vtt,
+    VerboseJson,
+}
+
+impl std::str::FromStr for TranscriptionResponseFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Self::Json),
+            "text" => Ok(Self::Text),
+            "srt" => Ok(Self::Srt),
+            "vtt" => Ok(Self::Vtt),
+            "verbose_json" => Ok(Self::VerboseJson),
+            other => anyhow::bail!(
+                "Invalid response_format '{other}'. Expected json, text, srt, vtt or verbose_json."
+            ),
+        }
+    }
+}
+
+/// A parsed transcription or translation request.
+struct SpeechToTextRequest {
+    audio: AudioInput,
+    model: String,
+    language: Option<String>,
+    prompt: Option<String>,
+    response_format: TranscriptionResponseFormat,
+    temperature: Option<f64>,
+    stream: bool,
+}
+
+/// One chunk of the recording and its transcript.
+#[derive(Clone, Debug, Serialize)]
+pub struct TranscriptionSegment {
+    pub id: usize,
+    /// Offset of the chunk in the recording, in frames.
+    pub seek: usize,
+    pub start: f32,
+    pub end: f32,
+    pub text: String,
+}
+
+/// Token usage, summed over chunks. Omitted from responses when the model reported none.
+#[derive(Clone, Debug, Serialize)]
+pub struct TranscriptionUsage {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    pub total_tokens: usize,
+}
+
+impl TranscriptionUsage {
+    fn add(total: &mut Option<Self>, usage: &Usage) {
+        let total = total.get_or_insert(Self {
+            kind: "tokens",
+            input_tokens: 0,
+            output_tokens: 0,
+            total_tokens: 0,
+        });
+        total.input_tokens += usage.prompt_tokens;
+        total.output_tokens += usage.completion_tokens;
+        total.total_tokens += usage.total_tokens;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TranscriptionResponse {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TranscriptionUsage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerboseTranscriptionResponse {
+    pub task: &'static str,
+    pub language: String,
+    pub duration: f32,
+    pub text: String,
+    pub segments: Vec<TranscriptionSegment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TranscriptionUsage>,
+}
+
+/// OpenAI-compatible transcription endpoint handler.
+pub async fn transcriptions(
+    State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
+    multipart: Multipart,
+) -> AxumResponse {
+    speech_to_text(
+        state,
+        key.map(|Extension(key)| key),
+        multipart,
+        SpeechTask::Transcribe,
+    )
+    .await
+}
+
+/// OpenAI-compatible translation endpoint handler. Output is always English.
+pub async fn translations(
+    State(state): ExtractedMistralRsState,
+    key: Option<Extension<AuthenticatedKey>>,
+    multipart: Multipart,
+) -> AxumResponse {
+    speech_to_text(
+        state,
+        key.map(|Extension(key)| key),
+        multipart,
+        SpeechTask::Translate,
+    )
+    .await
+}
+
+async fn speech_to_text(
+    state: SharedMistralRsState,
+    key: Option<AuthenticatedKey>,
+    multipart: Multipart,
+    task: SpeechTask,
+) -> AxumResponse {
+    let request = match parse_multipart(multipart).await {
+        Ok(request) => request,
+        Err((status, message)) => return JsonError::new(message).to_response(status),
+    };
+    if let Err(e) = validate_model_name(&request.model, state.clone()) {
+        return JsonError::new(e.to_string()).to_response(StatusCode::BAD_REQUEST);
+    }
+    if let Err(e) = validate_model_access(&request.model, key.as_ref(), &state) {
+        return e.into_response();
+    }
+    let model_id = (request.model != "default").then(|| request.model.clone());
+    if !matches!(
+        state.get_model_category(model_id.as_deref()),
+        Ok(ModelCategory::Multimodal { .. })
+    ) {
+        return JsonError::new("The model does not accept audio input.".to_string())
+            .to_response(StatusCode::BAD_REQUEST);
+    }
+
+    if request.stream {
+        if !matches!(
+            request.response_format,
+            TranscriptionResponseFormat::Json | TranscriptionResponseFormat::Text
+        ) {
+            return JsonError::new(
+                "Streaming is only supported with the json and text response formats.".to_string(),
+            )
+            .to_response(StatusCode::BAD_REQUEST);
+        }
+        return stream_transcript(state, request, model_id, task);
+    }
+
+    let duration = request.audio.duration_secs();
+    match run_chunks(&state, &request, model_id.as_deref(), task, None).await {
+        Ok((segments, usage)) => render(&request, task, duration, segments, usage),
+        Err(e) => {
+            MistralRs::maybe_log_error(state, &*e);
+            overloaded_response(e.downcast_ref()).unwrap_or_else(|| {
+                JsonError::new(sanitize_error_message(&*e))
+                    .to_response(StatusCode::INTERNAL_SERVER_ERROR)
+            })
+        }
+    }
+}
+
+async fn parse_multipart(
+    mut multipart: Multipart,
+) -> std::result::Result<SpeechToTextRequest, (StatusCode, String)> {
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, message);
+    let mut audio = None;
+    let mut model = None;
+    let mut language = None;
+    let mut prompt = None;
+    let mut response_format = TranscriptionResponseFormat::Json;
+    let mut temperature = None;
+    let mut stream = false;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| (e.status(), e.body_text()))?
+    {
+        let name = field.name().map(str::to_string);
+        if name.as_deref() == Some("file") {
+            let part = read_file_part(field, MAX_UPLOAD_BYTES).await?;
+            let decoded = AudioInput::from_bytes(&part.data)
+                .map_err(|e| bad_request(format!("Could not decode audio file: {e}")))?;
+            audio = Some(decoded);
+            continue;
+        }
+        let value = field
+            .text()
+            .await
+            .map_err(|e| (e.status(), e.body_text()))?;
+        let value = value.trim().to_string();
+        match name.as_deref() {
+            Some("model") => model = Some(value),
+            Some("language") if !value.is_empty() => language = Some(value),
+            Some("prompt") if !value.is_empty() => prompt = Some(value),
+            Some("response_format") => {
+                response_format = value.parse().map_err(|e| bad_request(format!("{e}")))?
+            }
+            Some("temperature") => {
+                temperature = Some(
+                    value
+                        .parse::<f64>()
+                        .map_err(|_| bad_request(format!("Invalid temperature '{value}'.")))?,
+                )
+            }
+            Some("stream") => stream = value == "true",
+            // `timestamp_granularities[]`, `include[]` and similar are accepted and ignored.
+            _ => {}
+        }
+    }
+
+    let audio = audio.ok_or_else(|| bad_request("Missing required field 'file'.".to_string()))?;
+    if audio.samples.is_empty() {
+        return Err(bad_request("Audio file contains no samples.".to_string()));
+    }
+    Ok(SpeechToTextRequest {
+        audio,
+        model: model.unwrap_or_else(|| "default".to_string()),
+        language,
+        prompt,
+        response_format,
+        temperature,
+        stream,
+    })
+}
+
+fn stream_transcript(
+    state: SharedMistralRsState,
+    request: SpeechToTextRequest,
+    model_id: Option<String>,
+    task: SpeechTask,
+) -> AxumResponse {
+    let (event_tx, event_rx) = mpsc::channel::<Event>(64);
+    tokio::spawn(async move {
+        let (delta_tx, mut delta_rx) = mpsc::channel::<String>(64);
+        let forward_tx = event_tx.clone();
+        let forward = tokio::spawn(async move {
+            while let Some(delta) = delta_rx.recv().await {
+                let event = serde_json::json!({ "type": "transcript.text.delta", "delta": delta });
+                if forward_tx
+                    .send(Event::default().data(event.to_string()))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let result = run_chunks(&state, &request, model_id.as_deref(), task, Some(delta_tx)).await;
+        let _ = forward.await;
+        if event_tx.is_closed() {
+            return;
+        }
+        let event = match result {
+            Ok((segments, usage)) => {
+                let mut event = serde_json::json!({
+                    "type": "transcript.text.done",
+                    "text": join_segments(&segments),
+                });
+                if let Some(usage) = usage {
+                    event["usage"] = serde_json::json!(usage);
+                }
+                event
+            }
+            Err(e) => {
+                MistralRs::maybe_log_error(state, &*e);
+                serde_json::json!({
+                    "type": "error",
+                    "error": { "message": sanitize_error_message(&*e) },
+                })
+            }
+        };
+        let _ = event_tx
+            .send(Event::default().data(event.to_string()))
+            .await;
+    });
+
+    let stream = futures::stream::unfold(event_rx, |mut rx| async move {
+        rx.recv()
+            .await
+            .map(|event| (Ok::<_, Infallible>(event), rx))
+    });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::new().interval(Duration::from_millis(get_keep_alive_interval())))
+        .into_response()
+}
+
+/// Transcribe a recording already in memory, e.g. a committed `/v1/realtime` audio buffer.
+pub(crate) async fn transcribe_audio(
+    state: &SharedMistralRsState,
+    model_id: &str,
+    audio: AudioInput,
+    language: Option<String>,
+    prompt: Option<String>,
+) -> Result<(String, Option<TranscriptionUsage>)> {
+    let request = SpeechToTextRequest {
+        audio,
+        model: model_id.to_string(),
+        language,
+        prompt,
+        response_format: TranscriptionResponseFormat::Json,
+        temperature: None,
+        stream: false,
+    };
+    let (segments, usage) = run_chunks(
+        state,
+        &request,
+        Some(model_id),
+        SpeechTask::Transcribe,
+        None,
+    )
+    .await?;
+    Ok((join_segments(&segments), usage))
+}
+
+/// Transcribe every chunk in order. With `deltas`, text is forwarded as the model produces it.
+async fn run_chunks(
+    state: &SharedMistralRsState,
+    request: &SpeechToTextRequest,
+    model_id: Option<&str>,
+    task: SpeechTask,
+    deltas: Option<Sender<String>>,
+) -> Result<(Vec<TranscriptionSegment>, Option<TranscriptionUsage>)> {
+    let sample_rate = request.audio.sample_rate as f32;
+    let mut segments: Vec<TranscriptionSegment> = Vec::new();
+    let mut usage = None;
+
+    for (id, range) in request
+        .audio
+        .chunk_ranges(CHUNK_SECONDS, CUT_SEARCH_SECONDS)
+        .into_iter()
+        .enumerate()
+    {
+        let context = match segments.last() {
+            Some(previous) => Some(tail(&previous.text, CONTEXT_TAIL_CHARS)),
+            None => request.prompt.clone(),
+        };
+        let instruction = instruction(task, request.language.as_deref(), context.as_deref());
+        let chunk = request.audio.slice_frames(range.clone());
+
+        if id > 0 {
+            if let Some(deltas) = &deltas {
+                send_delta(deltas, " ".to_string()).await?;
+            }
+        }
+        let (text, chunk_usage) = transcribe_chunk(
+            state,
+            model_id,
+            chunk,
+            instruction,
+            request.temperature,
+            deltas.as_ref(),
+        )
+        .await
+        .with_context(|| format!("Failed to transcribe audio chunk {id}"))?;
+        if let Some(chunk_usage) = chunk_usage {
+            TranscriptionUsage::add(&mut usage, &chunk_usage);
+        }
+        segments.push(TranscriptionSegment {
+            id,
+            seek: range.start,
+            start: range.start as f32 / sample_rate,
+            end: range.end as f32 / sample_rate,
+            text: text.trim().to_string(),
+        });
+    }
+    Ok((segments, usage))
+}
+
+/// Forward a delta, failing once the client has gone so no further chunks are transcribed.
+async fn send_delta(deltas: &Sender<String>, delta: String) -> Result<()> {
+    deltas
+        .send(delta)
+        .await
+        .map_err(|_| anyhow::anyhow!("Client disconnected"))
+}
+
+async fn transcribe_chunk(
+    state: &SharedMistralRsState,
+    model_id: Option<&str>,
+    audio: AudioInput,
+    instruction: String,
+    temperature: Option<f64>,
+    deltas: Option<&Sender<String>>,
+) -> Result<(String, Option<Usage>)> {
+    let text = match state.get_model_category(model_id)? {
+        ModelCategory::Multimodal { prefixer } => prefixer.prefix_audio(vec![0], &instruction),
+        _ => instruction,
+    };
+
+    let mut audio_part = IndexMap::new();
+    audio_part.insert("type".to_string(), Value::String("audio".to_string()));
+    let mut text_part = IndexMap::new();
+    text_part.insert("type".to_string(), Value::String("text".to_string()));
+    text_part.insert("text".to_string(), Value::String(text));
+    let mut message = IndexMap::new();
+    message.insert("role".to_string(), Either::Left("user".to_string()));
+    message.insert(
+        "content".to_string(),
+        Either::Right(vec![audio_part, text_part]),
+    );
+
+    let mut sampling_params = SamplingParams::deterministic();
+    if let Some(temperature) = temperature.filter(|t| *t > 0.0) {
+        sampling_params.temperature = Some(temperature);
+        sampling_params.top_k = None;
+    }
+
+    let (tx, mut rx) = create_response_channel(None);
+    let request = Request::Normal(Box::new(NormalRequest {
+        id: state.next_request_id(),
+        messages: RequestMessage::MultimodalChat {
+            messages: vec![message],
+            images: Vec::new(),
+            audios: vec![audio],
+            videos: Vec::new(),
+            enable_thinking: Some(false),
+            reasoning_effort: None,
+        },
+        sampling_params,
+        response: tx,
+        return_logprobs: false,
+        is_streaming: deltas.is_some(),
+        suffix: None,
+        constraint: Constraint::None,
+        tool_choice: None,
+        tools: None,
+        logits_processors: None,
+        return_raw_logits: false,
+        web_search_options: None,
+        enable_code_execution: false,
+        code_execution_permission: None,
+        code_execution_approval_notifier: None,
+        agent_permission: None,
+        agent_approval_handler: None,
+        agent_approval_notifier: None,
+        max_tool_rounds: None,
+        tool_dispatch_url: None,
+        model_id: model_id.map(str::to_string),
+        truncate_sequence: false,
+        session_id: None,
+        files: None,
+        input_files: None,
+        priority: RequestPriority::Normal,
+        timeout: None,
+        trace_context: None,
+        user: None,
+        prediction: None,
+        agentic_permit: None,
+    }));
+    send_request_with_model(state, request, model_id).await?;
+
+    let mut text = String::new();
+    while let Some(response) = rx.recv().await {
+        match response {
+            Response::Chunk(chunk) => {
+                let Some(choice) = chunk.choices.first() else {
+                    continue;
+                };
+                if let Some(delta) = &choice.delta.content {
+                    text.push_str(delta);
+                    if let Some(deltas) = deltas {
+                        // Returning drops `rx`, which stops the engine request.
+                        send_delta(deltas, delta.clone()).await?;
+                    }
+                }
+                if choice.finish_reason.is_some() {
+                    return Ok((text, chunk.usage));
+                }
+            }
+            Response::Done(done) => {
+                let content = done
+                    .choices
+                    .first()
+                    .and_then(|c| c.message.content.clone())
+                    .unwrap_or_default();
+                return Ok((content, Some(done.usage)));
+            }
+            Response::ModelError(message, _) | Response::CompletionModelError(message, _) => {
+                anyhow::bail!(message)
+            }
+            Response::InternalError(e) | Response::ValidationError(e) => anyhow::bail!(e),
+            _ => continue,
+        }
+    }
+    // Streams can end without a chunk carrying `finish_reason`.
+    Ok((text, None))
+}
+
+fn instruction(task: SpeechTask, language: Option<&str>, context: Option<&str>) -> String {
+    let mut out = match task {
+        SpeechTask::Transcribe => {
+            "Transcribe the audio verbatim. Reply with the transcript only.".to_string()
+        }
+        SpeechTask::Translate => {
+            "Translate the speech in the audio into English. Reply with the translation only."
+                .to_string()
+        }
+    };
+    if let Some(language) = language {
+        let _ = write!(out, " The spoken language is '{language}'.");
+    }
+    if let Some(context) = context {
+        let _ = write!(
+            out,
+            "\nFor context, the audio continues from: \"{context}\""
+        );
+    }
+    out
+}
+
+/// The last `max_chars` characters of `text`.
+fn tail(text: &str, max_chars: usize) -> String {
+    let skip = text.chars().count().saturating_sub(max_chars);
+    text.chars().skip(skip).collect()
+}
+
+fn join_segments(segments: &[TranscriptionSegment]) -> String {
+    segments
+        .iter()
+        .map(|s| s.text.as_str())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn render(
+    request: &SpeechToTextRequest,
+    task: SpeechTask,
+    duration: f32,
+    segments: Vec<TranscriptionSegment>,
+    usage: Option<TranscriptionUsage>,
+) -> AxumResponse {
+    let text = join_segments(&segments);
+    match request.response_format {
+        TranscriptionResponseFormat::Json => {
+            Json(TranscriptionResponse { text, usage }).into_response()
+        }
+        TranscriptionResponseFormat::VerboseJson => {
+            let language = match task {
+                SpeechTask::Translate => "english".to_string(),
+                SpeechTask::Transcribe => request
+                    .language
+                    .clone()
+                    .unwrap_or_else(|| "unknown".to_string()),
+            };
+            Json(VerboseTranscriptionResponse {
+                task: task.as_str(),
+                language,
+                duration,
+                text,
+                segments,
+                usage,
+            })
+            .into_response()
+        }
+        TranscriptionResponseFormat::Text => {
+            ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], text).into_response()
+        }
+        TranscriptionResponseFormat::Srt => (
+            [(header::CONTENT_TYPE, "application/x-subrip; charset=utf-8")],
+            to_srt(&segments),
+        )
+            .into_response(),
+        TranscriptionResponseFormat::Vtt => (
+            [(header::CONTENT_TYPE, "text/vtt; charset=utf-8")],
+            to_vtt(&segments),
+        )
+            .into_response(),
+    }
+}
+
+/// `HH:MM:SS{sep}mmm`.
+fn timestamp(secs: f32, sep: char) -> String {
+    let total_ms = (secs.max(0.0) * 1000.0).round() as u64;
+    let (h, rem) = (total_ms / 3_600_000, total_ms % 3_600_000);
+    let (m, rem) = (rem / 60_000, rem % 60_000);
+    let (s, ms) = (rem / 1000, rem % 1000);
+    format!("{h:02}:{m:02}:{s:02}{sep}{ms:03}")
+}
+
+pub fn to_srt(segments: &[TranscriptionSegment]) -> String {
+    let mut out = String::new();
+    for (i, seg) in segments.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "{}\n{} --> {}\n{}\n",
+            i + 1,
+            timestamp(seg.start, ','),
+            timestamp(seg.end, ','),
+            seg.text
+        );
+    }
+    out
+}
+
+pub fn to_vtt(segments: &[TranscriptionSegment]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for seg in segments {
+        let _ = writeln!(
+            out,
+            "{} --> {}\n{}\n",
+            timestamp(seg.start, '.'),
+            timestamp(seg.end, '.'),
+            seg.text
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments() -> Vec<TranscriptionSegment> {
+        vec![
+            TranscriptionSegment {
+                id: 0,
+                seek: 0,
+                start: 0.0,
+                end: 29.5,
+                text: "Hello there.".to_string(),
+            },
+            TranscriptionSegment {
+                id: 1,
+                seek: 472_000,
+                start: 29.5,
+                end: 3725.25,
+                text: "General Kenobi.".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn srt_output() {
+        assert_eq!(
+            to_srt(&segments()),
+            "1\n00:00:00,000 --> 00:00:29,500\nHello there.\n\n\
+             2\n00:00:29,500 --> 01:02:05,250\nGeneral Kenobi.\n\n"
+        );
+    }
+
+    #[test]
+    fn vtt_output() {
+        assert_eq!(
+            to_vtt(&segments()),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:29.500\nHello there.\n\n\
+             00:00:29.500 --> 01:02:05.250\nGeneral Kenobi.\n\n"
+        );
+    }
+
+    #[test]
+    fn response_format_parsing() {
+        assert_eq!(
+            "verbose_json"
+                .parse::<TranscriptionResponseFormat>()
+                .unwrap(),
+            TranscriptionResponseFormat::VerboseJson
+        );
+        assert!("mp3".parse::<TranscriptionResponseFormat>().is_err());
+    }
+
+    #[test]
+    fn context_tail_is_char_safe() {
+        assert_eq!(tail("héllo wörld", 5), "wörld");
+        assert_eq!(tail("hi", 5), "hi");
+        assert_eq!(join_segments(&segments()), "Hello there. General Kenobi.");
+    }
+}
@@ -1,5 +1,6 @@
 //! ## General utilities.
 
+use crate::auth::{AuthError, AuthenticatedKey};
 use image::DynamicImage;
 use mistralrs_core::AudioInput;
 use mistralrs_core::MistralRs;
@@ -178,6 +179,30 @@ pub fn validate_model_name(
     )
 }
 
+/// Checks that the authenticated API key, if any, may use `requested_model`.
+///
+/// The auth layer only sees the model of JSON bodies and `?model=`, so handlers that read it
+/// elsewhere (multipart form fields, batch input lines) check it with this. `"default"`
+/// resolves to the current default model.
+pub fn validate_model_access(
+    requested_model: &str,
+    key: Option<&AuthenticatedKey>,
+    state: &MistralRs,
+) -> Result<(), AuthError> {
+    let Some(key) = key else {
+        return Ok(());
+    };
+    let model = if requested_model == "default" {
+        state.get_default_model_id().ok().flatten()
+    } else {
+        Some(requested_model.to_string())
+    };
+    match model {
+        Some(model) if !key.allows_model(&model) => Err(AuthError::ModelDenied(model)),
+        _ => Ok(()),
+    }
+}
+
 /// Sanitize error messages to remove internal implementation details like stack traces.
 /// This ensures that sensitive internal information is not exposed to API clients.
 ///
@@ -4,8 +4,8 @@ use indexmap::IndexMap;
 use mistralrs_core::{
     speech_utils, Constraint, DiffusionGenerationParams, DrySamplingParams,
     ImageGenerationResponseFormat, MessageContent, MistralRs, ModelCategory, NormalRequest,
-    Request, RequestMessage, Response, ResponseOk, SamplingParams, WebSearchOptions,
-    TERMINATE_ALL_NEXT_STEP,
+    Request, RequestMessage, RequestPriority, Response, ResponseOk, SamplingParams,
+    WebSearchOptions, TERMINATE_ALL_NEXT_STEP,
 };
 use regex::Regex;
 use rustyline::{error::ReadlineError, history::History, DefaultEditor, Editor, Helper};
@@ -170,6 +170,7 @@ fn interactive_sample_parameters() -> SamplingParams {
         logits_bias: None,
         n_choices: 1,
         dry_params: Some(DrySamplingParams::default()),
+        seed: None,
     }
 }
 
@@ -348,6 +349,13 @@ async fn text_interactive_mode(
             model_id: None,
             truncate_sequence: false,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
         sender.send(req).await.unwrap();
         let start_ttft = Instant::now();
@@ -426,6 +434,7 @@ async fn text_interactive_mode(
                 Response::CompletionChunk(_) => unreachable!(),
                 Response::ImageGeneration(_) => unreachable!(),
                 Response::Speech { .. } => unreachable!(),
+                Response::SpeechChunk { .. } => unreachable!(),
                 Response::Raw { .. } => unreachable!(),
                 Response::Embeddings { .. } => unreachable!(),
             }
@@ -718,6 +727,13 @@ async fn multimodal_interactive_mode(
             model_id: None,
             truncate_sequence: false,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
         sender.send(req).await.unwrap();
         let start_ttft = Instant::now();
@@ -796,6 +812,7 @@ async fn multimodal_interactive_mode(
                 Response::CompletionChunk(_) => unreachable!(),
                 Response::ImageGeneration(_) => unreachable!(),
                 Response::Speech { .. } => unreachable!(),
+                Response::SpeechChunk { .. } => unreachable!(),
                 Response::Raw { .. } => unreachable!(),
                 Response::Embeddings { .. } => unreachable!(),
             }
@@ -913,6 +930,13 @@ async fn diffusion_interactive_mode(mistralrs: Arc<MistralRs>, do_search: bool)
             model_id: None,
             truncate_sequence: false,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
 
         let start = Instant::now();
@@ -988,6 +1012,7 @@ async fn speech_interactive_mode(mistralrs: Arc<MistralRs>, do_search: bool) {
             id: 0,
             messages: RequestMessage::SpeechGeneration {
                 prompt: prompt.to_string(),
+                reference: None,
             },
             sampling_params: SamplingParams::deterministic(),
             response: tx,
@@ -1012,6 +1037,13 @@ async fn speech_interactive_mode(mistralrs: Arc<MistralRs>, do_search: bool) {
             model_id: None,
             truncate_sequence: false,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
 
         let start = Instant::now();
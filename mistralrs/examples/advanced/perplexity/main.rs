@@ -9,7 +9,7 @@ use clap::Parser;
 use either::Either;
 use mistralrs::{
     cross_entropy_loss, parse_isq_value, Constraint, DType, Device, MistralRs, ModelBuilder,
-    NormalRequest, Request, ResponseOk, SamplingParams, Tensor,
+    NormalRequest, Request, RequestPriority, ResponseOk, SamplingParams, Tensor,
 };
 use tokio::sync::mpsc::channel;
 
@@ -66,6 +66,13 @@ async fn process_chunk(runner: &MistralRs, chunk: Vec<u32>) -> anyhow::Result<(T
         truncate_sequence: false,
         session_id: None,
         files: None,
+        input_files: None,
+        priority: RequestPriority::Normal,
+        timeout: None,
+        trace_context: None,
+        user: None,
+        prediction: None,
+        agentic_permit: None,
     }));
 
     runner.get_sender(None)?.send(request).await?;
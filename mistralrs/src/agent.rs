@@ -288,6 +288,7 @@ impl<'a> AgentStream<'a> {
                                                         total_time_sec: 0.0,
                                                         total_prompt_time_sec: 0.0,
                                                         total_completion_time_sec: 0.0,
+                                                        completion_tokens_details: None,
                                                     },
                                                     agentic_tool_calls: None,
                                                     files: None,
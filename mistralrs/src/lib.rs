@@ -242,6 +242,8 @@ pub use messages::{
     EmbeddingRequest, EmbeddingRequestBuilder, EmbeddingRequestInput, MultimodalMessages,
     RequestBuilder, RequestLike, TextMessageRole, TextMessages,
 };
+pub use mistralrs_core::{init_otlp_exporter, OtlpExporterConfig, TraceContext};
+pub use mistralrs_core::{AdmissionLimits, QueueDepth};
 pub use mistralrs_core::{
     AgentPermission, AgentToolApproval, AgentToolApprovalAsyncCallback, AgentToolApprovalCallback,
     AgentToolApprovalDecision, AgentToolApprovalFuture, AgentToolApprovalHandler, AgentToolKind,
@@ -257,9 +259,9 @@ pub use mistralrs_core::{
 };
 pub use mistralrs_core::{
     MultimodalToolCallback, SearchCallback, SearchResult, ToolCallContext, ToolCallback,
-    ToolCallbackKind, ToolOutput,
+    ToolCallbackKind, ToolCancellation, ToolOutput,
 };
-pub use mistralrs_core::{SerializedSession, SerializedVideo};
+pub use mistralrs_core::{SerializedSession, SerializedVideo, SessionInfo};
 pub use model::{best_device, Model};
 pub use model_builder_trait::{AnyModelBuilder, MultiModelBuilder};
 pub use multimodal_model::{MultimodalModelBuilder, UqffMultimodalModelBuilder};
@@ -279,11 +281,13 @@ pub mod core;
 // ========== Response Types ==========
 pub use mistralrs_core::{
     ChatCompletionChunkResponse, ChatCompletionResponse, Choice, ChunkChoice, CompletionResponse,
-    Delta, Logprobs, Response, ResponseMessage, TopLogprob, Usage,
+    CompletionTokensDetails, Delta, Logprobs, Response, ResponseMessage, TopLogprob, Usage,
 };
 
 // ========== Request Types ==========
-pub use mistralrs_core::{Constraint, LlguidanceGrammar, MessageContent, NormalRequest, Request};
+pub use mistralrs_core::{
+    Constraint, LlguidanceGrammar, MessageContent, NormalRequest, Request, RequestPriority,
+};
 
 // ========== Sampling ==========
 pub use mistralrs_core::{DrySamplingParams, ModelGenerationDefaults, SamplingParams, StopTokens};
@@ -315,7 +319,7 @@ pub use mistralrs_core::ModelCategory;
 pub use mistralrs_core::{SearchEmbeddingModel, SearchFunctionParameters};
 
 // ========== Speech Types ==========
-pub use mistralrs_core::{speech_utils, SpeechLoaderType};
+pub use mistralrs_core::{speech_utils, SpeechLoaderType, SpeechReference};
 
 // ========== AnyMoe Types ==========
 pub use mistralrs_core::{AnyMoeConfig, AnyMoeExpertType};
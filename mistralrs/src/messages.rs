@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fmt::Display, future::Future, sync::Arc};
+use std::{collections::HashMap, fmt::Display, future::Future, sync::Arc, time::Duration};
 
 use super::*;
 use either::Either;
@@ -65,6 +65,26 @@ pub trait RequestLike {
     fn truncate_sequence(&self) -> bool {
         false
     }
+    /// Scheduling class of this request.
+    fn priority(&self) -> RequestPriority {
+        RequestPriority::Normal
+    }
+    /// Time the request may run, tool rounds included, before it finishes as canceled.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+    /// Trace to record this request's spans in, see [`init_otlp_exporter`].
+    fn trace_context(&self) -> Option<TraceContext> {
+        None
+    }
+    /// Caller identity, recorded in the engine's audit log when one is configured.
+    fn user(&self) -> Option<String> {
+        None
+    }
+    /// Take the predicted output, if any.
+    fn take_prediction(&mut self) -> Option<String> {
+        None
+    }
     /// Apply any deferred model-specific media prefixes.
     ///
     /// Called automatically by [`Model`](crate::Model) before sending the request.
@@ -488,6 +508,11 @@ pub struct RequestBuilder {
     tool_dispatch_url: Option<String>,
     enable_thinking: Option<bool>,
     truncate_sequence: bool,
+    priority: RequestPriority,
+    timeout: Option<Duration>,
+    trace_context: Option<TraceContext>,
+    user: Option<String>,
+    prediction: Option<String>,
     files: Option<Vec<RequestedFile>>,
     pending_prefixes: Vec<PendingMediaPrefix>,
 }
@@ -522,6 +547,11 @@ impl From<TextMessages> for RequestBuilder {
             tool_dispatch_url: None,
             enable_thinking: None,
             truncate_sequence: false,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
             files: None,
             pending_prefixes: Vec::new(),
         }
@@ -552,6 +582,11 @@ impl From<MultimodalMessages> for RequestBuilder {
             tool_dispatch_url: None,
             enable_thinking: None,
             truncate_sequence: false,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
             files: None,
             pending_prefixes: value.pending_prefixes,
         }
@@ -583,6 +618,11 @@ impl RequestBuilder {
             tool_dispatch_url: None,
             enable_thinking: None,
             truncate_sequence: false,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
             files: None,
             pending_prefixes: Vec::new(),
         }
@@ -967,6 +1007,12 @@ impl RequestBuilder {
         self
     }
 
+    /// Seed sampling so the same request reproduces the same output.
+    pub fn set_sampler_seed(mut self, seed: u64) -> Self {
+        self.sampling_params.seed = Some(seed);
+        self
+    }
+
     /// Enable extended thinking (chain-of-thought) for models that support it.
     pub fn enable_thinking(mut self, enable_thinking: bool) -> Self {
         self.enable_thinking = Some(enable_thinking);
@@ -979,6 +1025,41 @@ impl RequestBuilder {
         self
     }
 
+    /// Scheduling class. Higher classes are admitted first and preempted last.
+    pub fn with_priority(mut self, priority: RequestPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Stop generating and any running tool call once `timeout` has elapsed. The response then
+    /// has `finish_reason: "canceled"`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Record this request's spans as part of an existing trace, e.g. one parsed with
+    /// [`TraceContext::from_traceparent`]. Has no effect unless an exporter was started with
+    /// [`init_otlp_exporter`].
+    pub fn with_trace_context(mut self, trace_context: TraceContext) -> Self {
+        self.trace_context = Some(trace_context);
+        self
+    }
+
+    /// Attribute this request to `user` in the engine's audit log, if one is configured.
+    pub fn with_user(mut self, user: impl ToString) -> Self {
+        self.user = Some(user.to_string());
+        self
+    }
+
+    /// Text the response is expected to mostly repeat, e.g. the current contents of a file
+    /// being edited. Matching spans are verified several tokens per forward pass, and the
+    /// response's `usage.completion_tokens_details` reports how much of it was accepted.
+    pub fn with_prediction(mut self, prediction: impl ToString) -> Self {
+        self.prediction = Some(prediction.to_string());
+        self
+    }
+
     /// Require an output file by name. Surfaced to the model and returned in `files` (or as an error placeholder).
     pub fn require_file(mut self, name: impl Into<String>) -> Self {
         self.files
@@ -1141,6 +1222,26 @@ impl RequestLike for RequestBuilder {
         self.truncate_sequence
     }
 
+    fn priority(&self) -> RequestPriority {
+        self.priority
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn trace_context(&self) -> Option<TraceContext> {
+        self.trace_context
+    }
+
+    fn user(&self) -> Option<String> {
+        self.user.clone()
+    }
+
+    fn take_prediction(&mut self) -> Option<String> {
+        self.prediction.take()
+    }
+
     fn take_files(&mut self) -> Option<Vec<RequestedFile>> {
         self.files.take()
     }
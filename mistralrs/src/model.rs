@@ -157,6 +157,13 @@ impl Model {
             truncate_sequence,
             session_id: request.session_id().map(|s| s.to_string()),
             files: request.take_files(),
+            input_files: None,
+            priority: request.priority(),
+            timeout: request.timeout(),
+            trace_context: request.trace_context(),
+            user: request.user(),
+            prediction: request.take_prediction(),
+            agentic_permit: None,
         }));
 
         self.runner.get_sender(model_id)?.send(request).await?;
@@ -218,6 +225,13 @@ impl Model {
             truncate_sequence,
             session_id: request.session_id().map(|s| s.to_string()),
             files: request.take_files(),
+            input_files: None,
+            priority: request.priority(),
+            timeout: request.timeout(),
+            trace_context: request.trace_context(),
+            user: request.user(),
+            prediction: request.take_prediction(),
+            agentic_permit: None,
         }));
 
         self.runner.get_sender(model_id)?.send(request).await?;
@@ -296,6 +310,13 @@ impl Model {
             truncate_sequence,
             session_id: request.session_id().map(|s| s.to_string()),
             files: request.take_files(),
+            input_files: None,
+            priority: request.priority(),
+            timeout: request.timeout(),
+            trace_context: request.trace_context(),
+            user: request.user(),
+            prediction: None,
+            agentic_permit: None,
         }));
 
         self.runner.get_sender(model_id)?.send(request).await?;
@@ -466,6 +487,13 @@ impl Model {
             truncate_sequence: false,
             session_id: None,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
 
         self.runner.get_sender(model_id)?.send(request).await?;
@@ -506,6 +534,21 @@ impl Model {
         &self,
         prompt: impl ToString,
         model_id: Option<&str>,
+    ) -> crate::error::Result<(Arc<Vec<f32>>, usize, usize)> {
+        self.generate_speech_with_reference(prompt, None, model_id)
+            .await
+    }
+
+    /// Generate audio in the voice of `reference`, a recording with its transcript. The
+    /// transcript should use the same speaker tags as the prompt. If `model_id` is `None`,
+    /// the request is sent to the default model.
+    ///
+    /// This returns: (pcm, sampling rate, channels)
+    pub async fn generate_speech_with_reference(
+        &self,
+        prompt: impl ToString,
+        reference: Option<SpeechReference>,
+        model_id: Option<&str>,
     ) -> crate::error::Result<(Arc<Vec<f32>>, usize, usize)> {
         let (tx, mut rx) = channel(1);
 
@@ -513,6 +556,7 @@ impl Model {
             id: 0,
             messages: RequestMessage::SpeechGeneration {
                 prompt: prompt.to_string(),
+                reference,
             },
             sampling_params: SamplingParams::deterministic(),
             response: tx,
@@ -537,6 +581,13 @@ impl Model {
             truncate_sequence: false,
             session_id: None,
             files: None,
+            input_files: None,
+            priority: RequestPriority::Normal,
+            timeout: None,
+            trace_context: None,
+            user: None,
+            prediction: None,
+            agentic_permit: None,
         }));
 
         self.runner.get_sender(model_id)?.send(request).await?;
@@ -621,6 +672,13 @@ impl Model {
                     truncate_sequence,
                     session_id: None,
                     files: None,
+                    input_files: None,
+                    priority: RequestPriority::Normal,
+                    timeout: None,
+                    trace_context: None,
+                    user: None,
+                    prediction: None,
+                    agentic_permit: None,
                 }));
 
                 runner
@@ -940,6 +998,22 @@ impl Model {
         Ok(self.runner.list_session_ids(model_id)?)
     }
 
+    /// All stored agentic sessions with their last update time, most recent first.
+    pub fn list_sessions(
+        &self,
+        model_id: Option<&str>,
+    ) -> crate::error::Result<Vec<mistralrs_core::SessionInfo>> {
+        Ok(self.runner.list_sessions(model_id)?)
+    }
+
+    /// Current queue depth of `model_id`, as checked against its admission limits.
+    pub fn queue_depth(
+        &self,
+        model_id: Option<&str>,
+    ) -> crate::error::Result<mistralrs_core::QueueDepth> {
+        Ok(self.runner.queue_depth(model_id)?)
+    }
+
     /// MCP-provided tools registered for `model_id`. Excludes built-ins (search, code exec). Returns `(name, description)` per tool.
     pub fn list_mcp_tools(
         &self,
@@ -2,8 +2,8 @@
 
 use candle_core::Device;
 use mistralrs_core::{
-    AddModelConfig, DefaultSchedulerMethod, EngineConfig, IsqType, Pipeline, SchedulerConfig,
-    SearchCallback, SearchEmbeddingModel, ToolCallbackWithTool,
+    AddModelConfig, AdmissionLimits, DefaultSchedulerMethod, EngineConfig, IsqType, Pipeline,
+    SchedulerConfig, SearchCallback, SearchEmbeddingModel, ToolCallbackWithTool,
 };
 use std::{collections::HashMap, path::PathBuf, sync::Arc};
 use tokio::sync::Mutex;
@@ -330,6 +330,9 @@ pub(crate) fn build_engine_config(
         no_prefix_cache: prefix_cache_n.is_none(),
         prefix_cache_n: prefix_cache_n.unwrap_or(16),
         disable_eos_stop: false,
+        session_storage: None,
+        admission_limits: AdmissionLimits::default(),
+        audit_log: None,
     }
 }
 